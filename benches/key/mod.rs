@@ -6,7 +6,7 @@ use pgp::composed::{
     Deserializable, KeyType, SecretKey, SecretKeyParamsBuilder, SignedSecretKey,
     SubkeyParamsBuilder,
 };
-use pgp::crypto::{HashAlgorithm, SymmetricKeyAlgorithm};
+use pgp::crypto::{ECCCurve, HashAlgorithm, SymmetricKeyAlgorithm};
 use pgp::ser::Serialize;
 use pgp::types::CompressionAlgorithm;
 
@@ -99,18 +99,18 @@ fn bench_secret_key_rsa_2048_self_sign(b: &mut Bencher) {
 
 #[bench]
 fn bench_secret_key_x25519_generate(b: &mut Bencher) {
-    b.iter(|| black_box(build_key(KeyType::EdDSA, KeyType::ECDH)));
+    b.iter(|| black_box(build_key(KeyType::EdDSA, KeyType::ECDH(ECCCurve::Curve25519))));
 }
 
 #[bench]
 fn bench_secret_key_x25519_self_sign(b: &mut Bencher) {
-    let key = build_key(KeyType::EdDSA, KeyType::ECDH);
+    let key = build_key(KeyType::EdDSA, KeyType::ECDH(ECCCurve::Curve25519));
     b.iter(|| black_box(key.clone().sign(|| "".into()).unwrap()));
 }
 
 #[bench]
 fn bench_secret_key_parse_armored_x25519(b: &mut Bencher) {
-    let key = build_key(KeyType::EdDSA, KeyType::ECDH)
+    let key = build_key(KeyType::EdDSA, KeyType::ECDH(ECCCurve::Curve25519))
         .sign(|| "".into())
         .unwrap();
     let bytes = key.to_armored_bytes(None).unwrap();
@@ -0,0 +1,246 @@
+//! # rPGP for WASM
+//!
+//! `wasm-bindgen` bindings for rPGP, to use it from JavaScript in Node.js and the browser.
+//!
+//! Keys and messages are passed as ASCII armored strings, binary data as `Uint8Array`.
+//! The expensive operations, key generation, encryption, decryption and signing, return
+//! a `Promise`.
+
+#![deny(clippy::all, rust_2018_idioms)]
+
+use std::fmt;
+
+use js_sys::{Array, Uint8Array};
+use pgp::composed::{
+    Deserializable, KeyType, Message, SecretKeyParamsBuilder, SignedPublicKey, SignedSecretKey,
+    SubkeyParamsBuilder, VerificationResult,
+};
+use pgp::crypto::{ECCCurve, HashAlgorithm, SymmetricKeyAlgorithm};
+use pgp::types::KeyTrait;
+use rand::thread_rng;
+use wasm_bindgen::prelude::*;
+
+/// A newly generated key, as armored strings.
+#[wasm_bindgen]
+pub struct KeyPair {
+    secret_key: String,
+    public_key: String,
+}
+
+#[wasm_bindgen]
+impl KeyPair {
+    #[wasm_bindgen(getter, js_name = secretKey)]
+    pub fn secret_key(&self) -> String {
+        self.secret_key.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = publicKey)]
+    pub fn public_key(&self) -> String {
+        self.public_key.clone()
+    }
+}
+
+/// Information about a parsed key.
+#[wasm_bindgen]
+pub struct KeyInfo {
+    fingerprint: String,
+    key_id: String,
+    user_ids: Vec<String>,
+    is_secret: bool,
+}
+
+#[wasm_bindgen]
+impl KeyInfo {
+    /// The fingerprint of the primary key, as upper case hex.
+    #[wasm_bindgen(getter)]
+    pub fn fingerprint(&self) -> String {
+        self.fingerprint.clone()
+    }
+
+    /// The key id of the primary key, as upper case hex.
+    #[wasm_bindgen(getter, js_name = keyId)]
+    pub fn key_id(&self) -> String {
+        self.key_id.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = userIds)]
+    pub fn user_ids(&self) -> Array {
+        self.user_ids.iter().map(JsValue::from).collect()
+    }
+
+    #[wasm_bindgen(getter, js_name = isSecret)]
+    pub fn is_secret(&self) -> bool {
+        self.is_secret
+    }
+}
+
+/// Generates an Ed25519 signing key with a Curve25519 encryption subkey, protected by the
+/// given passphrase.
+#[wasm_bindgen(js_name = generateKey)]
+pub async fn generate_key(
+    user_id: String,
+    passphrase: Option<String>,
+) -> Result<KeyPair, JsValue> {
+    let subkey = SubkeyParamsBuilder::default()
+        .key_type(KeyType::ECDH(ECCCurve::Curve25519))
+        .can_encrypt(true)
+        .passphrase(passphrase.clone())
+        .build()
+        .map_err(js_err)?;
+    let params = SecretKeyParamsBuilder::default()
+        .key_type(KeyType::EdDSA)
+        .can_create_certificates(true)
+        .can_sign(true)
+        .primary_user_id(user_id)
+        .passphrase(passphrase.clone())
+        .subkey(subkey)
+        .build()
+        .map_err(js_err)?;
+
+    let key = params
+        .generate()
+        .map_err(js_err)?
+        .sign(|| passphrase.unwrap_or_default())
+        .map_err(js_err)?;
+
+    Ok(KeyPair {
+        secret_key: key.to_armored_string(None).map_err(js_err)?,
+        public_key: key
+            .signed_public_key()
+            .to_armored_string(None)
+            .map_err(js_err)?,
+    })
+}
+
+/// Parses an armored public or secret key.
+#[wasm_bindgen(js_name = parseKey)]
+pub fn parse_key(armored: &str) -> Result<KeyInfo, JsValue> {
+    let (key, is_secret) = match parse_secret_key(armored) {
+        Ok(key) => (key.signed_public_key(), true),
+        Err(_) => (parse_public_key(armored)?, false),
+    };
+
+    Ok(KeyInfo {
+        fingerprint: hex::encode_upper(key.fingerprint()),
+        key_id: hex::encode_upper(key.key_id()),
+        user_ids: key
+            .details
+            .users
+            .iter()
+            .map(|user| user.id.id().to_string())
+            .collect(),
+        is_secret,
+    })
+}
+
+/// Encrypts `data` to the given armored public keys, returns the armored message.
+#[wasm_bindgen]
+pub async fn encrypt(data: Vec<u8>, public_keys: Array) -> Result<String, JsValue> {
+    let keys = public_keys
+        .iter()
+        .map(|key| {
+            let armored = key
+                .as_string()
+                .ok_or_else(|| js_err("public keys must be armored strings"))?;
+            parse_public_key(&armored)
+        })
+        .collect::<Result<Vec<_>, JsValue>>()?;
+    let keys = keys.iter().collect::<Vec<_>>();
+
+    Message::new_literal_bytes("", &data)
+        .encrypt_to_recipients(&mut thread_rng(), SymmetricKeyAlgorithm::AES128, &keys)
+        .and_then(|msg| msg.to_armored_string(None))
+        .map_err(js_err)
+}
+
+/// Decrypts the armored `message` using the armored secret key, returns the content.
+#[wasm_bindgen]
+pub async fn decrypt(
+    message: String,
+    secret_key: String,
+    passphrase: Option<String>,
+) -> Result<Uint8Array, JsValue> {
+    let msg = parse_message(&message)?;
+    let key = parse_secret_key(&secret_key)?;
+
+    let (mut decrypter, _) = msg
+        .decrypt(String::new, move || passphrase.unwrap_or_default(), &[&key])
+        .map_err(js_err)?;
+    let decrypted = decrypter
+        .next()
+        .ok_or_else(|| js_err("no message found"))?
+        .map_err(js_err)?;
+
+    content(&decrypted)
+}
+
+/// Signs `data` using the armored secret key, returns the armored signed message.
+#[wasm_bindgen]
+pub async fn sign(
+    data: Vec<u8>,
+    secret_key: String,
+    passphrase: Option<String>,
+) -> Result<String, JsValue> {
+    let key = parse_secret_key(&secret_key)?;
+
+    Message::new_literal_bytes("", &data)
+        .sign(
+            &key,
+            move || passphrase.unwrap_or_default(),
+            HashAlgorithm::SHA2_256,
+        )
+        .and_then(|msg| msg.to_armored_string(None))
+        .map_err(js_err)
+}
+
+/// Verifies the armored signed `message` using the armored public key, returns the content.
+/// Throws if there is no valid signature by the key.
+#[wasm_bindgen]
+pub fn verify(message: &str, public_key: &str) -> Result<Uint8Array, JsValue> {
+    let msg = parse_message(message)?;
+    let key = parse_public_key(public_key)?;
+
+    let results = msg.verify_signatures(&[&key]).map_err(js_err)?;
+    let valid = results
+        .iter()
+        .any(|res| matches!(res, VerificationResult::Valid(_)));
+    if !valid {
+        return Err(js_err("no valid signature found"));
+    }
+
+    content(&msg)
+}
+
+fn parse_public_key(armored: &str) -> Result<SignedPublicKey, JsValue> {
+    let (key, _headers) = SignedPublicKey::from_string(armored).map_err(js_err)?;
+    key.verify().map_err(js_err)?;
+
+    Ok(key)
+}
+
+fn parse_secret_key(armored: &str) -> Result<SignedSecretKey, JsValue> {
+    let (key, _headers) = SignedSecretKey::from_string(armored).map_err(js_err)?;
+    key.verify().map_err(js_err)?;
+
+    Ok(key)
+}
+
+fn parse_message(armored: &str) -> Result<Message, JsValue> {
+    let (msg, _headers) = Message::from_string(armored).map_err(js_err)?;
+
+    Ok(msg)
+}
+
+fn content(msg: &Message) -> Result<Uint8Array, JsValue> {
+    let data = msg
+        .get_content()
+        .map_err(js_err)?
+        .ok_or_else(|| js_err("no content found"))?;
+
+    Ok(Uint8Array::from(&data[..]))
+}
+
+/// Converts an error into a JS `Error`.
+fn js_err(err: impl fmt::Display) -> JsValue {
+    js_sys::Error::new(&err.to_string()).into()
+}
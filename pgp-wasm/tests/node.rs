@@ -0,0 +1,41 @@
+//! Run with `wasm-pack test --node`.
+
+use js_sys::Array;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::*;
+
+use pgp_wasm::{decrypt, encrypt, generate_key, parse_key, sign, verify};
+
+#[wasm_bindgen_test]
+async fn test_roundtrip() {
+    let key = generate_key("Me <me@mail.com>".into(), Some("secret".into()))
+        .await
+        .unwrap();
+
+    let info = parse_key(&key.public_key()).unwrap();
+    assert!(!info.is_secret());
+    assert_eq!(info.fingerprint().len(), 40);
+    assert_eq!(info.user_ids().get(0).as_string().unwrap(), "Me <me@mail.com>");
+    assert!(parse_key(&key.secret_key()).unwrap().is_secret());
+
+    let recipients: Array = std::iter::once(JsValue::from(key.public_key())).collect();
+    let encrypted = encrypt(b"hello world".to_vec(), recipients).await.unwrap();
+    let decrypted = decrypt(encrypted.clone(), key.secret_key(), Some("secret".into()))
+        .await
+        .unwrap();
+    assert_eq!(decrypted.to_vec(), b"hello world");
+    assert!(decrypt(encrypted, key.secret_key(), Some("wrong".into()))
+        .await
+        .is_err());
+
+    let signed = sign(b"hello world".to_vec(), key.secret_key(), Some("secret".into()))
+        .await
+        .unwrap();
+    let content = verify(&signed, &key.public_key()).unwrap();
+    assert_eq!(content.to_vec(), b"hello world");
+
+    let other = generate_key("Other <other@mail.com>".into(), None)
+        .await
+        .unwrap();
+    assert!(verify(&signed, &other.public_key()).is_err());
+}
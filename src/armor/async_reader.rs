@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::armor::{BlockType, Dearmor};
+use crate::errors::Result;
+
+/// Parses an ascii armored block from an async reader, and writes the decoded data to
+/// `output`, like `parse`.
+///
+/// This is a buffering helper: the whole armored input is read into memory first, as
+/// decoding needs to look ahead in it, and is then decoded synchronously. Only writing the
+/// decoded data to `output` is done without blocking the executor.
+pub async fn parse_async<R, W>(
+    mut input: R,
+    output: &mut W,
+) -> Result<(BlockType, BTreeMap<String, String>)>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut armored = Vec::new();
+    input.read_to_end(&mut armored).await?;
+
+    let mut dearmor = Dearmor::new(Cursor::new(armored));
+    let mut buf = vec![0u8; 8 * 1024];
+    loop {
+        let read = dearmor.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        output.write_all(&buf[..read]).await?;
+    }
+    output.flush().await?;
+
+    let typ = dearmor
+        .typ
+        .ok_or_else(|| format_err!("missing ascii armor header"))?;
+
+    Ok((typ, dearmor.headers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::File;
+
+    #[tokio::test]
+    async fn test_parse_async() {
+        let mut armored = Vec::new();
+        File::open("./tests/openpgp/pubring.asc")
+            .unwrap()
+            .read_to_end(&mut armored)
+            .unwrap();
+
+        let mut expected = Vec::new();
+        let (typ, headers) = crate::armor::parse(Cursor::new(&armored), &mut expected).unwrap();
+
+        let mut actual = Vec::new();
+        let res = parse_async(&armored[..], &mut actual).await.unwrap();
+
+        assert_eq!(res, (typ, headers));
+        assert_eq!(actual, expected);
+    }
+}
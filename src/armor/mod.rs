@@ -2,8 +2,12 @@
 //!
 //! Armor module provides implementation of ASCII Armor as specified in RFC 4880.
 
+#[cfg(feature = "async")]
+mod async_reader;
 mod reader;
 mod writer;
 
+#[cfg(feature = "async")]
+pub use self::async_reader::*;
 pub use self::reader::*;
 pub use self::writer::*;
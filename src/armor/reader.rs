@@ -315,13 +315,19 @@ const CAPACITY: usize = 1024 * 32;
 
 impl<R: Read + Seek> Dearmor<R> {
     pub fn new(input: R) -> Self {
+        Dearmor::with_capacity(input, CAPACITY)
+    }
+
+    /// Creates a `Dearmor` with a read buffer of the given capacity.
+    /// The armor headers must fit into the buffer.
+    pub fn with_capacity(input: R, capacity: usize) -> Self {
         Dearmor {
             typ: None,
             headers: BTreeMap::new(),
             checksum: None,
             current_part: Part::Header,
             base_decoder: None,
-            inner: Some(BufReader::with_capacity(CAPACITY, input)),
+            inner: Some(BufReader::with_capacity(capacity, input)),
             done: false,
             crc: Default::default(),
         }
@@ -338,6 +344,7 @@ impl<R: Read + Seek> Dearmor<R> {
 
             let consumed = match header_parser(b.buffer()) {
                 Ok((remaining, (typ, header))) => {
+                    debug!("armor header: {:?} ({} headers)", typ, header.len());
                     self.typ = Some(typ);
                     self.headers = header;
                     self.current_part = Part::Body;
@@ -498,6 +505,26 @@ impl<R: Read + Seek> Read for Dearmor<R> {
     }
 }
 
+/// Decodes the armored `input` into `output`, returning the block type and the headers.
+///
+/// The data is decoded incrementally, so memory usage does not depend on the input size.
+pub fn parse<R: Read + Seek, W: Write>(
+    input: R,
+    output: &mut W,
+) -> Result<(BlockType, BTreeMap<String, String>)> {
+    let _span = debug_span!("dearmor").entered();
+
+    let mut dearmor = Dearmor::new(input);
+    let len = io::copy(&mut dearmor, output)?;
+    debug!("dearmored {} bytes", len);
+
+    let typ = dearmor
+        .typ
+        .ok_or_else(|| format_err!("missing ascii armor header"))?;
+
+    Ok((typ, dearmor.headers))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -507,15 +534,12 @@ mod tests {
 
     // helper function to parse all data at once
     pub fn parse<R: Read + Seek>(
-        mut input: R,
+        input: R,
     ) -> Result<(BlockType, BTreeMap<String, String>, Vec<u8>)> {
-        let mut dearmor = Dearmor::new(input.by_ref());
-
-        // estimate size
         let mut bytes = Vec::new();
-        dearmor.read_to_end(&mut bytes)?;
+        let (typ, headers) = super::parse(input, &mut bytes)?;
 
-        Ok((dearmor.typ.unwrap(), dearmor.headers, bytes))
+        Ok((typ, headers, bytes))
     }
 
     #[test]
@@ -731,6 +755,44 @@ y5Zgv9TWZlmW9FDTp4XVgn5zQTEN1LdL7vNXWV9aOvfrqPk5ClBkxhndgq7j6MFs
         assert_eq!(typ, (BlockType::PrivateKeyPKCS1(PKCS1Type::RSA)));
     }
 
+    #[test]
+    fn test_parse_streaming() {
+        // a writer that only counts the data, to not hold the decoded data in memory
+        #[derive(Default)]
+        struct Counter {
+            len: usize,
+            non_zero: bool,
+        }
+
+        impl Write for Counter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.len += buf.len();
+                self.non_zero |= buf.iter().any(|b| *b != 0);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let encoded = base64::encode(&vec![0u8; 1024 * 1024]);
+        let mut armored = "-----BEGIN PGP MESSAGE-----\n\n".to_string();
+        for line in encoded.as_bytes().chunks(64) {
+            armored += str::from_utf8(line).unwrap();
+            armored += "\n";
+        }
+        armored += "-----END PGP MESSAGE-----\n";
+
+        let mut counter = Counter::default();
+        let (typ, headers) = super::parse(Cursor::new(armored), &mut counter).unwrap();
+
+        assert_eq!(typ, BlockType::Message);
+        assert!(headers.is_empty());
+        assert_eq!(counter.len, 1024 * 1024);
+        assert!(!counter.non_zero);
+    }
+
     #[test]
     fn test_dearmor_small_stream() {
         let mut map = BTreeMap::new();
@@ -0,0 +1,602 @@
+//! Secret keys as stored by `gpg-agent` in `private-keys-v1.d`.
+//!
+//! Ref: https://github.com/gpg/gnupg/blob/master/agent/keyformat.txt
+
+use std::fmt;
+
+use aes::Aes128;
+use block_modes::block_padding::NoPadding;
+use block_modes::{BlockMode, Cbc};
+
+use crate::crypto::hash::HashAlgorithm;
+use crate::errors::Result;
+use crate::packet;
+use crate::types::{hash_iterated, Mpi, PlainSecretParams, PublicParams, SecretParams};
+
+/// The only supported protection mode.
+const PROTECTION_SHA1_AES_CBC: &[u8] = b"openpgp-s2k3-sha1-aes-cbc";
+
+/// The largest iteration count, that the OpenPGP S2K count encoding allows.
+const MAX_S2K_COUNT: usize = 65_011_712;
+
+/// A secret key, as stored by `gpg-agent`.
+///
+/// Agent keys only contain the raw key material, so they need to be combined with the matching
+/// public key, see `SignedSecretKey::from_agent_keys`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AgentKey {
+    /// The `(private-key ..)` or `(protected-private-key ..)` expression.
+    key: Sexp,
+}
+
+impl AgentKey {
+    /// Parses a key file, either in the canonical S-expression format or in the extended
+    /// key format, used by newer versions of GnuPG.
+    pub fn from_bytes(input: &[u8]) -> Result<Self> {
+        let key = match extended_key_value(input)? {
+            Some(value) => Sexp::parse(value.as_bytes())?,
+            None => Sexp::parse(input)?,
+        };
+
+        match key.name() {
+            Some(b"private-key") | Some(b"protected-private-key") => {}
+            _ => bail!("not a private key"),
+        }
+        ensure!(
+            key.algorithm_list().and_then(Sexp::name).is_some(),
+            "missing key algorithm"
+        );
+
+        Ok(AgentKey { key })
+    }
+
+    /// Is the key material protected by a passphrase.
+    pub fn is_protected(&self) -> bool {
+        self.key.name() == Some(&b"protected-private-key"[..])
+    }
+
+    /// Checks if this is the secret key for the given public key.
+    pub fn matches(&self, public_params: &PublicParams) -> bool {
+        let params = self.params();
+        let eq = |name: &str, value: &Mpi| find_mpi(params, name).as_ref() == Some(value);
+
+        match (self.algorithm(), public_params) {
+            (b"rsa", PublicParams::RSA { n, e }) => eq("n", n) && eq("e", e),
+            (b"dsa", PublicParams::DSA { p, q, g, y }) => {
+                eq("p", p) && eq("q", q) && eq("g", g) && eq("y", y)
+            }
+            (b"elg", PublicParams::Elgamal { p, g, y }) => eq("p", p) && eq("g", g) && eq("y", y),
+            (b"ecc", PublicParams::ECDSA { p, .. })
+            | (b"ecc", PublicParams::ECDH { p, .. })
+            | (b"ecdsa", PublicParams::ECDSA { p, .. })
+            | (b"ecdh", PublicParams::ECDH { p, .. }) => eq("q", p),
+            (b"ecc", PublicParams::EdDSA { q, .. }) | (b"eddsa", PublicParams::EdDSA { q, .. }) => {
+                eq("q", q)
+            }
+            _ => false,
+        }
+    }
+
+    /// Converts this into a secret key packet for the given public key.
+    /// The secret key material is stored unprotected.
+    pub fn to_secret_key<F>(
+        &self,
+        public: &packet::PublicKey,
+        key_pw: F,
+    ) -> Result<packet::SecretKey>
+    where
+        F: FnOnce() -> String,
+    {
+        let secret_params = self.secret_params(public.public_params(), key_pw)?;
+
+        Ok(packet::SecretKey {
+            details: public.clone(),
+            secret_params: SecretParams::Plain(secret_params),
+        })
+    }
+
+    /// Converts this into a secret subkey packet for the given public subkey.
+    /// The secret key material is stored unprotected.
+    pub fn to_secret_subkey<F>(
+        &self,
+        public: &packet::PublicSubkey,
+        key_pw: F,
+    ) -> Result<packet::SecretSubkey>
+    where
+        F: FnOnce() -> String,
+    {
+        let secret_params = self.secret_params(public.public_params(), key_pw)?;
+
+        Ok(packet::SecretSubkey {
+            details: public.clone(),
+            secret_params: SecretParams::Plain(secret_params),
+        })
+    }
+
+    fn secret_params<F>(
+        &self,
+        public_params: &PublicParams,
+        key_pw: F,
+    ) -> Result<PlainSecretParams>
+    where
+        F: FnOnce() -> String,
+    {
+        ensure!(
+            self.matches(public_params),
+            "agent key does not match the public key"
+        );
+
+        let unprotected;
+        let params = if self.is_protected() {
+            unprotected = self.unprotect(key_pw)?;
+            &unprotected[..]
+        } else {
+            self.params()
+        };
+
+        let mpi = |name: &str| {
+            find_mpi(params, name).ok_or_else(|| format_err!("missing secret parameter {}", name))
+        };
+
+        let secret_params = match public_params {
+            PublicParams::RSA { .. } => PlainSecretParams::RSA {
+                d: mpi("d")?,
+                p: mpi("p")?,
+                q: mpi("q")?,
+                u: mpi("u")?,
+            },
+            PublicParams::DSA { .. } => PlainSecretParams::DSA(mpi("x")?),
+            PublicParams::Elgamal { .. } => PlainSecretParams::Elgamal(mpi("x")?),
+            PublicParams::ECDSA { .. } => PlainSecretParams::ECDSA(mpi("d")?),
+            PublicParams::ECDH { .. } => PlainSecretParams::ECDH(mpi("d")?),
+            PublicParams::EdDSA { .. } => PlainSecretParams::EdDSA(mpi("d")?),
+        };
+
+        Ok(secret_params)
+    }
+
+    /// Returns the name of the algorithm, e.g. `rsa` or `ecc`.
+    fn algorithm(&self) -> &[u8] {
+        self.key
+            .algorithm_list()
+            .and_then(Sexp::name)
+            .expect("checked when parsing")
+    }
+
+    /// Returns the parameters of the key, excluding the algorithm name.
+    fn params(&self) -> &[Sexp] {
+        let list = self
+            .key
+            .algorithm_list()
+            .and_then(Sexp::as_list)
+            .expect("checked when parsing");
+
+        &list[1..]
+    }
+
+    /// Decrypts the protected parameters and returns all parameters of the key.
+    fn unprotect<F>(&self, key_pw: F) -> Result<Vec<Sexp>>
+    where
+        F: FnOnce() -> String,
+    {
+        let params = self.params();
+        let pos = params
+            .iter()
+            .position(|p| p.name() == Some(&b"protected"[..]))
+            .ok_or_else(|| format_err!("missing protected parameters"))?;
+        let protected = params[pos].as_list().expect("named sexp is a list");
+
+        // (protected <mode> ((<hash> <salt> <count>) <iv>) <ciphertext>)
+        ensure_eq!(protected.len(), 4, "invalid protected parameters");
+        let mode = protected[1]
+            .as_atom()
+            .ok_or_else(|| format_err!("invalid protection mode"))?;
+        if mode != PROTECTION_SHA1_AES_CBC {
+            unsupported_err!("protection mode {}", String::from_utf8_lossy(mode));
+        }
+
+        let (s2k, iv) = match protected[2].as_list() {
+            Some([s2k, iv]) => (s2k.as_list(), iv.as_atom()),
+            _ => bail!("invalid protection parameters"),
+        };
+        let (salt, count) = match s2k {
+            Some([Sexp::Atom(hash), Sexp::Atom(salt), Sexp::Atom(count)]) if hash == b"sha1" => {
+                (salt, std::str::from_utf8(count)?.parse::<usize>()?)
+            }
+            _ => bail!("invalid protection parameters"),
+        };
+        ensure!(count <= MAX_S2K_COUNT, "S2K count {} is too large", count);
+        let iv = iv.ok_or_else(|| format_err!("invalid protection parameters"))?;
+        let ciphertext = protected[3]
+            .as_atom()
+            .ok_or_else(|| format_err!("invalid protected parameters"))?;
+
+        // derive the key, the same way as S2K mode 3
+        let mut hasher = HashAlgorithm::SHA1.new_hasher()?;
+        hash_iterated(&mut *hasher, salt, key_pw().as_bytes(), count);
+        let key = hasher.finish();
+
+        let cipher = Cbc::<Aes128, NoPadding>::new_var(&key[..16], iv)
+            .map_err(|_| format_err!("invalid protection parameters"))?;
+        let mut plaintext = ciphertext.to_vec();
+        cipher.decrypt(&mut plaintext)?;
+
+        // ((<secret params>) (hash sha1 <hash>)), followed by padding
+        let plaintext = Sexp::parse(&plaintext).map_err(|_| format_err!("invalid passphrase"))?;
+        let (secret, hash) = match plaintext.as_list() {
+            Some([Sexp::List(secret), hash]) => (secret, hash),
+            _ => bail!("invalid passphrase"),
+        };
+
+        let mut unprotected = params[..pos].to_vec();
+        unprotected.extend_from_slice(secret);
+        unprotected.extend_from_slice(&params[pos + 1..]);
+
+        // the hash covers the unprotected algorithm list
+        let mut list = vec![Sexp::Atom(self.algorithm().to_vec())];
+        list.extend_from_slice(&unprotected);
+        let expected = match hash.as_list() {
+            Some([Sexp::Atom(name), Sexp::Atom(algorithm), Sexp::Atom(value)])
+                if name == b"hash" && algorithm == b"sha1" =>
+            {
+                value
+            }
+            _ => bail!("invalid passphrase"),
+        };
+        ensure!(
+            HashAlgorithm::SHA1.digest(&Sexp::List(list).to_canonical())? == *expected,
+            "invalid passphrase"
+        );
+
+        Ok(unprotected)
+    }
+}
+
+impl fmt::Debug for AgentKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AgentKey")
+            .field("algorithm", &String::from_utf8_lossy(self.algorithm()))
+            .field("protected", &self.is_protected())
+            .finish()
+    }
+}
+
+/// Finds the `(name value)` parameter and returns its value as MPI.
+fn find_mpi(params: &[Sexp], name: &str) -> Option<Mpi> {
+    params
+        .iter()
+        .find(|p| p.name() == Some(name.as_bytes()))
+        .and_then(|p| match p.as_list() {
+            Some([_, Sexp::Atom(value)]) => Some(Mpi::from_raw_slice(value)),
+            _ => None,
+        })
+}
+
+/// Extracts the value of the `Key` entry, if this is a key in the extended key format.
+fn extended_key_value(input: &[u8]) -> Result<Option<String>> {
+    if input.iter().find(|c| !c.is_ascii_whitespace()) == Some(&b'(') {
+        return Ok(None);
+    }
+
+    let input = std::str::from_utf8(input)?;
+    let mut lines = input.lines().skip_while(|line| {
+        line.split(':')
+            .next()
+            .map_or(true, |name| !name.eq_ignore_ascii_case("key"))
+    });
+
+    let first = match lines.next() {
+        Some(line) => line
+            .splitn(2, ':')
+            .nth(1)
+            .ok_or_else(|| format_err!("invalid key entry"))?,
+        None => bail!("missing key entry"),
+    };
+    let mut value = first.trim().to_string();
+    // continuation lines start with a space
+    for line in lines.take_while(|line| line.starts_with(|c| c == ' ' || c == '\t')) {
+        value.push('\n');
+        value.push_str(line.trim());
+    }
+
+    Ok(Some(value))
+}
+
+/// An S-expression, as used by libgcrypt.
+/// Ref: https://people.csail.mit.edu/rivest/Sexp.txt
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Sexp {
+    Atom(Vec<u8>),
+    List(Vec<Sexp>),
+}
+
+impl Sexp {
+    /// Parses a single expression, in either the canonical or the advanced format.
+    /// Any trailing data is ignored.
+    fn parse(input: &[u8]) -> Result<Self> {
+        let mut parser = SexpParser { input, pos: 0 };
+        parser.skip_whitespace();
+        parser.parse_list()
+    }
+
+    fn as_atom(&self) -> Option<&[u8]> {
+        match self {
+            Sexp::Atom(value) => Some(value),
+            Sexp::List(_) => None,
+        }
+    }
+
+    fn as_list(&self) -> Option<&[Sexp]> {
+        match self {
+            Sexp::Atom(_) => None,
+            Sexp::List(list) => Some(list),
+        }
+    }
+
+    /// Returns the name of a list, which is its first element.
+    fn name(&self) -> Option<&[u8]> {
+        self.as_list()
+            .and_then(|list| list.first())
+            .and_then(Sexp::as_atom)
+    }
+
+    /// Returns the `(<algorithm> ..)` list of a `(private-key ..)` expression.
+    fn algorithm_list(&self) -> Option<&Sexp> {
+        self.as_list()
+            .and_then(|list| list.get(1))
+            .filter(|l| l.as_list().is_some())
+    }
+
+    fn to_canonical(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write_canonical(&mut out);
+        out
+    }
+
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        match self {
+            Sexp::Atom(value) => {
+                out.extend_from_slice(value.len().to_string().as_bytes());
+                out.push(b':');
+                out.extend_from_slice(value);
+            }
+            Sexp::List(list) => {
+                out.push(b'(');
+                for el in list {
+                    el.write_canonical(out);
+                }
+                out.push(b')');
+            }
+        }
+    }
+}
+
+struct SexpParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SexpParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<u8> {
+        let c = self
+            .peek()
+            .ok_or_else(|| format_err!("unexpected end of s-expression"))?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, |c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<Sexp> {
+        ensure_eq!(self.next()?, b'(', "expected list");
+
+        let mut list = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b')') => {
+                    self.pos += 1;
+                    return Ok(Sexp::List(list));
+                }
+                Some(b'(') => list.push(self.parse_list()?),
+                Some(b'[') => {
+                    // display hints are ignored
+                    self.pos += 1;
+                    self.parse_atom()?;
+                    self.skip_whitespace();
+                    ensure_eq!(self.next()?, b']', "invalid display hint");
+                }
+                Some(_) => list.push(Sexp::Atom(self.parse_atom()?)),
+                None => bail!("unexpected end of s-expression"),
+            }
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Vec<u8>> {
+        self.skip_whitespace();
+
+        let mut len = None;
+        while let Some(c) = self.peek().filter(u8::is_ascii_digit) {
+            let next = len
+                .unwrap_or(0usize)
+                .checked_mul(10)
+                .and_then(|len| len.checked_add(usize::from(c - b'0')))
+                .filter(|len| *len <= self.input.len())
+                .ok_or_else(|| format_err!("unexpected end of s-expression"))?;
+            len = Some(next);
+            self.pos += 1;
+        }
+
+        match (self.next()?, len) {
+            (b':', Some(len)) => {
+                let end = self
+                    .pos
+                    .checked_add(len)
+                    .filter(|end| *end <= self.input.len())
+                    .ok_or_else(|| format_err!("unexpected end of s-expression"))?;
+                let value = self.input[self.pos..end].to_vec();
+                self.pos = end;
+                Ok(value)
+            }
+            (b'#', _) => {
+                let value = self.take_until(b'#')?;
+                let value: Vec<u8> = value
+                    .iter()
+                    .filter(|c| !c.is_ascii_whitespace())
+                    .copied()
+                    .collect();
+                hex::decode(value).map_err(|_| format_err!("invalid hex value"))
+            }
+            (b'|', _) => {
+                let value = self.take_until(b'|')?;
+                let value: Vec<u8> = value
+                    .iter()
+                    .filter(|c| !c.is_ascii_whitespace())
+                    .copied()
+                    .collect();
+                Ok(base64::decode(value)?)
+            }
+            (b'"', _) => self.parse_quoted(),
+            (c, None) if is_token_char(c) => {
+                let start = self.pos - 1;
+                while self.peek().map_or(false, is_token_char) {
+                    self.pos += 1;
+                }
+                Ok(self.input[start..self.pos].to_vec())
+            }
+            (c, _) => bail!("unexpected character {:?} in s-expression", c as char),
+        }
+    }
+
+    fn take_until(&mut self, end: u8) -> Result<&'a [u8]> {
+        let start = self.pos;
+        let len = self.input[start..]
+            .iter()
+            .position(|c| *c == end)
+            .ok_or_else(|| format_err!("unexpected end of s-expression"))?;
+        self.pos += len + 1;
+
+        Ok(&self.input[start..start + len])
+    }
+
+    fn parse_quoted(&mut self) -> Result<Vec<u8>> {
+        let mut value = Vec::new();
+        loop {
+            match self.next()? {
+                b'"' => return Ok(value),
+                b'\\' => match self.next()? {
+                    b'n' => value.push(b'\n'),
+                    b'r' => value.push(b'\r'),
+                    b't' => value.push(b'\t'),
+                    b'\n' => {
+                        // line continuation
+                        if self.peek() == Some(b'\r') {
+                            self.pos += 1;
+                        }
+                    }
+                    b'x' => {
+                        let hex = [self.next()?, self.next()?];
+                        let c = hex::decode(hex).map_err(|_| format_err!("invalid escape"))?;
+                        value.extend_from_slice(&c);
+                    }
+                    c @ b'0'..=b'7' => {
+                        let mut n = u32::from(c - b'0');
+                        for _ in 0..2 {
+                            match self.next()? {
+                                c @ b'0'..=b'7' => n = n * 8 + u32::from(c - b'0'),
+                                _ => bail!("invalid escape"),
+                            }
+                        }
+                        ensure!(n <= 0xff, "invalid escape");
+                        value.push(n as u8);
+                    }
+                    c => value.push(c),
+                },
+                c => value.push(c),
+            }
+        }
+    }
+}
+
+fn is_token_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"-./_:*+=".contains(&c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sexp_canonical() {
+        let input = b"(11:private-key(3:rsa(1:n3:\x00\x01\x02)(1:e1:\x03)))";
+        let sexp = Sexp::parse(&input[..]).unwrap();
+
+        assert_eq!(sexp.name(), Some(&b"private-key"[..]));
+        assert_eq!(
+            sexp.algorithm_list(),
+            Some(&Sexp::List(vec![
+                Sexp::Atom(b"rsa".to_vec()),
+                Sexp::List(vec![Sexp::Atom(b"n".to_vec()), Sexp::Atom(vec![0, 1, 2])]),
+                Sexp::List(vec![Sexp::Atom(b"e".to_vec()), Sexp::Atom(vec![3])]),
+            ]))
+        );
+        assert_eq!(sexp.to_canonical(), &input[..]);
+    }
+
+    #[test]
+    fn test_sexp_advanced() {
+        let input = b"(private-key (ecc (curve \"Ed25519\")[hint]\n (q #0001\n 02#) (d |AwQ=|)\n (x 3:abc) (y \"a\\x41\\101\\\"\")))";
+        let sexp = Sexp::parse(&input[..]).unwrap();
+
+        let canonical = Sexp::parse(
+            &b"(11:private-key(3:ecc(5:curve7:Ed25519)(1:q3:\x00\x01\x02)(1:d2:\x03\x04)(1:x3:abc)(1:y4:aAA\")))"[..],
+        )
+        .unwrap();
+        assert_eq!(sexp, canonical);
+    }
+
+    #[test]
+    fn test_sexp_invalid() {
+        assert!(Sexp::parse(b"(3:rsa").is_err());
+        assert!(Sexp::parse(b"(5:rsa)").is_err());
+        assert!(Sexp::parse(b"(rsa #0g#)").is_err());
+        assert!(Sexp::parse(b"rsa").is_err());
+        assert!(Sexp::parse(b"(99999999999999999999999:rsa)").is_err());
+        assert!(Sexp::parse(b"(3:rs)").is_err());
+    }
+
+    #[test]
+    fn test_protected_s2k_count() {
+        let input = b"(protected-private-key (rsa (n #01#) (e #03#) (protected \
+            openpgp-s2k3-sha1-aes-cbc ((sha1 #0102030405060708# \"4294967296\") \
+            #00000000000000000000000000000000#) #00000000000000000000000000000000#)))";
+        let key = AgentKey::from_bytes(&input[..]).unwrap();
+        assert!(key.is_protected());
+
+        match key.unprotect(|| "".into()) {
+            Err(crate::errors::Error::Message(ref msg)) => {
+                assert_eq!(msg, "S2K count 4294967296 is too large")
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_extended_key_value() {
+        let input = "Created: 20200101T000000\nkey: (private-key (ecc (curve Ed25519)\n  (q #40#)))\nLabel: test\n";
+        assert_eq!(
+            extended_key_value(input.as_bytes()).unwrap(),
+            Some("(private-key (ecc (curve Ed25519)\n(q #40#)))".to_string())
+        );
+        assert_eq!(extended_key_value(b" (3:rsa)").unwrap(), None);
+        assert!(extended_key_value(b"Created: 20200101T000000\nKey\n").is_err());
+    }
+}
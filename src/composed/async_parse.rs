@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::composed::shared::skip_invalid;
+use crate::composed::Deserializable;
+use crate::errors::Result;
+use crate::packet::AsyncPacketParser;
+
+/// Parses a list of compositions in raw byte format from an async reader, like
+/// `Deserializable::from_bytes_many`.
+///
+/// This is a buffering helper: packets are read without blocking the executor, but all of
+/// them are kept in memory until the end of the input, before the compositions are built.
+/// Use `AsyncPacketParser::next_packet` directly to process packets as they arrive.
+pub async fn from_async_reader<T, R>(input: R) -> Result<Vec<T>>
+where
+    T: Deserializable,
+    R: AsyncRead + Unpin,
+{
+    let mut parser = AsyncPacketParser::new(input);
+    let mut packets = Vec::new();
+    while let Some(p) = parser.next_packet().await {
+        packets.extend(skip_invalid(p));
+    }
+
+    T::from_packets(packets.into_iter()).collect()
+}
+
+/// Parses an armor encoded list of compositions from an async reader, like
+/// `Deserializable::from_armor_many`.
+///
+/// This is a buffering helper: the whole armored input is read into memory, and then parsed
+/// synchronously. Use `AsyncPacketParser` on the decoded data to stream packets.
+pub async fn from_armor_async<T, R>(mut input: R) -> Result<(Vec<T>, BTreeMap<String, String>)>
+where
+    T: Deserializable,
+    R: AsyncRead + Unpin,
+{
+    let mut armored = Vec::new();
+    input.read_to_end(&mut armored).await?;
+
+    let (el, headers) = T::from_armor_many(Cursor::new(armored))?;
+    let el = el.collect::<Result<_>>()?;
+
+    Ok((el, headers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::File;
+    use std::io::Read;
+
+    use crate::composed::SignedPublicKey;
+    use crate::ser::Serialize;
+
+    #[tokio::test]
+    async fn test_from_async_reader() {
+        let mut armored = Vec::new();
+        File::open("./tests/autocrypt/alice@autocrypt.example.pub.asc")
+            .unwrap()
+            .read_to_end(&mut armored)
+            .unwrap();
+        let (key, headers) =
+            SignedPublicKey::from_armor_single(Cursor::new(&armored)).unwrap();
+
+        let (keys, headers2) = from_armor_async::<SignedPublicKey, _>(&armored[..])
+            .await
+            .unwrap();
+        assert_eq!(keys, vec![key.clone()]);
+        assert_eq!(headers, headers2);
+
+        let bytes = key.to_bytes().unwrap();
+        let keys = from_async_reader::<SignedPublicKey, _>(&bytes[..])
+            .await
+            .unwrap();
+        assert_eq!(keys, vec![key]);
+    }
+}
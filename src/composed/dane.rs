@@ -0,0 +1,69 @@
+use crate::composed::shared::Deserializable;
+use crate::composed::signed_key::SignedPublicKey;
+use crate::crypto::HashAlgorithm;
+use crate::errors::Result;
+
+/// Returns the owner name of the DNS OPENPGPKEY record for `email`.
+///
+/// The label is the SHA2-256 hash of the local part, truncated to 28 octets, e.g.
+/// `c93f1e400f26708f98cb19d936620da35eec8f72e57f9eec01c1afd6._openpgpkey.example.com.` for
+/// `hugh@example.com`. The local part is used as is, without any case folding.
+/// Ref: https://tools.ietf.org/html/rfc7929#section-3
+pub fn openpgpkey_name(email: &str) -> Result<String> {
+    let at = email
+        .rfind('@')
+        .ok_or_else(|| format_err!("invalid email address {:?}", email))?;
+    let local_part = &email[..at];
+    let domain = email[at + 1..].trim_end_matches('.');
+    ensure!(
+        !local_part.is_empty() && !domain.is_empty(),
+        "invalid email address {:?}",
+        email
+    );
+
+    let digest = HashAlgorithm::SHA2_256.digest(local_part.as_bytes())?;
+
+    Ok(format!(
+        "{}._openpgpkey.{}.",
+        hex::encode(&digest[..28]),
+        domain
+    ))
+}
+
+/// Parses the data of an OPENPGPKEY record, fetched for `email`.
+///
+/// The key must be valid and have a user id with the email address.
+pub fn openpgpkey_from_record(data: &[u8], email: &str) -> Result<SignedPublicKey> {
+    let key = SignedPublicKey::from_bytes(data)?;
+    key.verify()?;
+    ensure!(
+        key.emails()
+            .iter()
+            .any(|other| other.eq_ignore_ascii_case(email)),
+        "no user id with email {:?} found",
+        email
+    );
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openpgpkey_name() {
+        // example from RFC 7929
+        assert_eq!(
+            openpgpkey_name("hugh@example.com").unwrap(),
+            "c93f1e400f26708f98cb19d936620da35eec8f72e57f9eec01c1afd6._openpgpkey.example.com."
+        );
+        assert_eq!(
+            openpgpkey_name("hugh@example.com.").unwrap(),
+            openpgpkey_name("hugh@example.com").unwrap()
+        );
+        assert!(openpgpkey_name("example.com").is_err());
+        assert!(openpgpkey_name("@example.com").is_err());
+        assert!(openpgpkey_name("hugh@").is_err());
+    }
+}
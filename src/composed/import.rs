@@ -0,0 +1,285 @@
+use std::fmt;
+use std::io::{Cursor, Read};
+
+use chrono::{DateTime, SubsecRound, Utc};
+use rsa::{PublicKeyParts, RSAPrivateKey, RSAPublicKey};
+use smallvec::SmallVec;
+
+use crate::armor::{BlockType, Dearmor, PKCS1Type};
+use crate::composed::ssh::{parse_ssh_private_key, parse_ssh_public_key};
+use crate::composed::{KeyDetails, SecretKey, SignedSecretKey};
+use crate::crypto::{self, ECCCurve, PublicKeyAlgorithm};
+use crate::errors::Result;
+use crate::packet::{self, KeyFlags, UserId};
+use crate::types::{Mpi, PlainSecretParams, PublicParams, SecretParams};
+
+/// DER prefix of an Ed25519 PKCS#8 private key, followed by the 32 byte secret.
+const ED25519_PKCS8_PREFIX: &[u8] = &[
+    0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+
+/// DER prefix of an Ed25519 SubjectPublicKeyInfo, followed by the 32 byte public key.
+const ED25519_SPKI_PREFIX: &[u8] = &[
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+macro_rules! ec_public_key {
+    ($c:ident, $curve:expr, $key:expr) => {{
+        use $c::elliptic_curve::sec1::ToEncodedPoint;
+
+        let p = $key.to_encoded_point(false);
+
+        PublicParams::ECDSA {
+            curve: $curve,
+            p: Mpi::from_raw_slice(p.as_bytes()),
+        }
+    }};
+}
+
+macro_rules! ec_secret_key {
+    ($c:ident, $curve:expr, $key:expr) => {{
+        let secret = $key;
+
+        (
+            ec_public_key!($c, $curve, secret.public_key()),
+            PlainSecretParams::ECDSA(Mpi::from_raw_slice(&secret.to_bytes())),
+        )
+    }};
+}
+
+/// Key material from a PEM encoded PKCS#1, SEC1 or PKCS#8 key, or an OpenSSH key, which can
+/// be converted into an OpenPGP key.
+///
+/// Elliptic curve keys on the NIST curves are imported as ECDSA keys.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ImportedKey {
+    public_params: PublicParams,
+    secret_params: Option<PlainSecretParams>,
+}
+
+impl fmt::Debug for ImportedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImportedKey")
+            .field("public_params", &self.public_params)
+            .field("has_secret_key", &self.has_secret_key())
+            .finish()
+    }
+}
+
+impl ImportedKey {
+    /// Parses a PEM encoded key, or an OpenSSH public key in the `authorized_keys` format.
+    ///
+    /// OpenSSH private keys need to be unencrypted.
+    pub fn from_string(input: &str) -> Result<Self> {
+        let input = input.trim();
+        if !input.starts_with("-----") {
+            return Ok(ImportedKey {
+                public_params: parse_ssh_public_key(input)?,
+                secret_params: None,
+            });
+        }
+
+        let mut dearmor = Dearmor::new(Cursor::new(input));
+        let mut der = Vec::new();
+        dearmor.read_to_end(&mut der)?;
+        let typ = dearmor
+            .typ
+            .ok_or_else(|| format_err!("dearmor failed to retrieve armor type"))?;
+
+        let (public_params, secret_params) = match typ {
+            BlockType::PublicKeyPKCS1(PKCS1Type::RSA) => {
+                let key = RSAPublicKey::from_pkcs1(&der)?;
+                (rsa_public_params(&key), None)
+            }
+            BlockType::PublicKeyPKCS8 => (public_key_from_spki(&der)?, None),
+            BlockType::PrivateKeyPKCS1(PKCS1Type::RSA) => {
+                let key = RSAPrivateKey::from_pkcs1(&der)?;
+                let (public_params, secret_params) = crypto::rsa::key_params(&key);
+                (public_params, Some(secret_params))
+            }
+            BlockType::PrivateKeyPKCS1(PKCS1Type::EC) => {
+                let (public_params, secret_params) = ec_key_from_sec1(&der)?;
+                (public_params, Some(secret_params))
+            }
+            BlockType::PrivateKeyPKCS8 => {
+                let (public_params, secret_params) = key_from_pkcs8(&der)?;
+                (public_params, Some(secret_params))
+            }
+            BlockType::PrivateKeyOpenssh => {
+                let (public_params, secret_params) = parse_ssh_private_key(&der)?;
+                (public_params, Some(secret_params))
+            }
+            _ => unsupported_err!("key format {}", typ),
+        };
+
+        Ok(ImportedKey {
+            public_params,
+            secret_params,
+        })
+    }
+
+    pub fn algorithm(&self) -> PublicKeyAlgorithm {
+        match self.public_params {
+            PublicParams::RSA { .. } => PublicKeyAlgorithm::RSA,
+            PublicParams::DSA { .. } => PublicKeyAlgorithm::DSA,
+            PublicParams::ECDSA { .. } => PublicKeyAlgorithm::ECDSA,
+            PublicParams::ECDH { .. } => PublicKeyAlgorithm::ECDH,
+            PublicParams::Elgamal { .. } => PublicKeyAlgorithm::Elgamal,
+            PublicParams::EdDSA { .. } => PublicKeyAlgorithm::EdDSA,
+        }
+    }
+
+    pub fn public_params(&self) -> &PublicParams {
+        &self.public_params
+    }
+
+    /// Returns `true` if the secret key material is available.
+    pub fn has_secret_key(&self) -> bool {
+        self.secret_params.is_some()
+    }
+
+    /// Creates the public key packet, using `created_at` as creation time of the key.
+    pub fn to_public_key(&self, created_at: DateTime<Utc>) -> packet::PublicKey {
+        packet::PublicKey {
+            packet_version: Default::default(),
+            version: Default::default(),
+            algorithm: self.algorithm(),
+            created_at: created_at.trunc_subsecs(0),
+            expiration: None,
+            public_params: self.public_params.clone(),
+        }
+    }
+
+    /// Creates the unprotected secret key packet, using `created_at` as creation time of the
+    /// key. Fails if only the public key is available.
+    pub fn to_secret_key(&self, created_at: DateTime<Utc>) -> Result<packet::SecretKey> {
+        let secret_params = self
+            .secret_params
+            .clone()
+            .ok_or_else(|| format_err!("missing secret key material"))?;
+
+        Ok(packet::SecretKey {
+            details: self.to_public_key(created_at),
+            secret_params: SecretParams::Plain(secret_params),
+        })
+    }
+
+    /// Wraps the key into a self signed OpenPGP key with the primary user id `user_id`, that
+    /// can be used for certifications and signing.
+    pub fn to_signed_secret_key(
+        &self,
+        user_id: &str,
+        created_at: DateTime<Utc>,
+    ) -> Result<SignedSecretKey> {
+        let primary_key = self.to_secret_key(created_at)?;
+
+        let mut keyflags = KeyFlags::default();
+        keyflags.set_certify(true);
+        keyflags.set_sign(true);
+
+        let details = KeyDetails::new(
+            UserId::from_str(Default::default(), user_id),
+            Vec::new(),
+            Vec::new(),
+            keyflags,
+            SmallVec::new(),
+            SmallVec::new(),
+            SmallVec::new(),
+            SmallVec::new(),
+            None,
+        );
+
+        SecretKey::new(primary_key, details, Vec::new(), Vec::new()).sign(String::new)
+    }
+}
+
+/// Builds the public parameters of an Ed25519 key, from the raw 32 byte public key.
+pub(crate) fn ed25519_public_params(q: &[u8]) -> Result<PublicParams> {
+    ensure_eq!(q.len(), 32, "invalid Ed25519 public key");
+
+    let mut p = Vec::with_capacity(33);
+    p.push(0x40);
+    p.extend_from_slice(q);
+
+    Ok(PublicParams::EdDSA {
+        curve: ECCCurve::Ed25519,
+        q: p.into(),
+    })
+}
+
+fn rsa_public_params(key: &RSAPublicKey) -> PublicParams {
+    PublicParams::RSA {
+        n: key.n().into(),
+        e: key.e().into(),
+    }
+}
+
+/// Parses a DER encoded SubjectPublicKeyInfo, as found in `PUBLIC KEY` PEM blocks.
+fn public_key_from_spki(der: &[u8]) -> Result<PublicParams> {
+    if der.len() == ED25519_SPKI_PREFIX.len() + 32 && der.starts_with(ED25519_SPKI_PREFIX) {
+        return ed25519_public_params(&der[ED25519_SPKI_PREFIX.len()..]);
+    }
+    #[cfg(feature = "p256")]
+    if let Ok(key) = <p256::PublicKey as p256::pkcs8::DecodePublicKey>::from_public_key_der(der) {
+        return Ok(ec_public_key!(p256, ECCCurve::P256, key));
+    }
+    #[cfg(feature = "p384")]
+    if let Ok(key) = <p384::PublicKey as p384::pkcs8::DecodePublicKey>::from_public_key_der(der) {
+        return Ok(ec_public_key!(p384, ECCCurve::P384, key));
+    }
+    #[cfg(feature = "p521")]
+    if let Ok(key) = <p521::PublicKey as p521::pkcs8::DecodePublicKey>::from_public_key_der(der) {
+        return Ok(ec_public_key!(p521, ECCCurve::P521, key));
+    }
+
+    let key = RSAPublicKey::from_pkcs8(der)?;
+
+    Ok(rsa_public_params(&key))
+}
+
+/// Parses a DER encoded SEC1 private key, as found in `EC PRIVATE KEY` PEM blocks.
+fn ec_key_from_sec1(der: &[u8]) -> Result<(PublicParams, PlainSecretParams)> {
+    #[cfg(feature = "p256")]
+    if let Ok(key) = p256::SecretKey::from_sec1_der(der) {
+        return Ok(ec_secret_key!(p256, ECCCurve::P256, key));
+    }
+    #[cfg(feature = "p384")]
+    if let Ok(key) = p384::SecretKey::from_sec1_der(der) {
+        return Ok(ec_secret_key!(p384, ECCCurve::P384, key));
+    }
+    #[cfg(feature = "p521")]
+    if let Ok(key) = p521::SecretKey::from_sec1_der(der) {
+        return Ok(ec_secret_key!(p521, ECCCurve::P521, key));
+    }
+
+    unsupported_err!("EC private key")
+}
+
+/// Parses a DER encoded PKCS#8 private key, as found in `PRIVATE KEY` PEM blocks.
+fn key_from_pkcs8(der: &[u8]) -> Result<(PublicParams, PlainSecretParams)> {
+    if der.len() == ED25519_PKCS8_PREFIX.len() + 32 && der.starts_with(ED25519_PKCS8_PREFIX) {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&der[ED25519_PKCS8_PREFIX.len()..])?;
+        let public = ed25519_dalek::PublicKey::from(&secret);
+
+        return Ok((
+            ed25519_public_params(public.as_bytes())?,
+            PlainSecretParams::EdDSA(Mpi::from_raw_slice(secret.as_bytes())),
+        ));
+    }
+    #[cfg(feature = "p256")]
+    if let Ok(key) = <p256::SecretKey as p256::pkcs8::DecodePrivateKey>::from_pkcs8_der(der) {
+        return Ok(ec_secret_key!(p256, ECCCurve::P256, key));
+    }
+    #[cfg(feature = "p384")]
+    if let Ok(key) = <p384::SecretKey as p384::pkcs8::DecodePrivateKey>::from_pkcs8_der(der) {
+        return Ok(ec_secret_key!(p384, ECCCurve::P384, key));
+    }
+    #[cfg(feature = "p521")]
+    if let Ok(key) = <p521::SecretKey as p521::pkcs8::DecodePrivateKey>::from_pkcs8_der(der) {
+        return Ok(ec_secret_key!(p521, ECCCurve::P521, key));
+    }
+
+    let key = RSAPrivateKey::from_pkcs8(der)?;
+
+    Ok(crypto::rsa::key_params(&key))
+}
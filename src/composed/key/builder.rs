@@ -5,15 +5,21 @@ use rand::{thread_rng, CryptoRng, Rng};
 use smallvec::SmallVec;
 
 use crate::composed::{KeyDetails, SecretKey, SecretSubkey};
-use crate::crypto::{ecdh, eddsa, rsa, HashAlgorithm, PublicKeyAlgorithm, SymmetricKeyAlgorithm};
+use crate::crypto::{
+    ecdh, ecdsa, eddsa, rsa, AeadAlgorithm, ECCCurve, HashAlgorithm, PublicKeyAlgorithm,
+    SymmetricKeyAlgorithm,
+};
 use crate::errors::Result;
 use crate::packet::{self, KeyFlags, UserAttribute, UserId};
-use crate::types::{self, CompressionAlgorithm, PublicParams, RevocationKey};
+use crate::types::{self, CompressionAlgorithm, PublicParams, RevocationKey, S2kParams};
 
 #[derive(Debug, PartialEq, Eq, Builder)]
 #[builder(build_fn(validate = "Self::validate"))]
 pub struct SecretKeyParams {
     key_type: KeyType,
+    /// Public exponent of RSA keys, defaults to 65537.
+    #[builder(default)]
+    rsa_public_exponent: Option<u32>,
 
     // -- Keyflags
     #[builder(default)]
@@ -22,6 +28,8 @@ pub struct SecretKeyParams {
     can_create_certificates: bool,
     #[builder(default)]
     can_encrypt: bool,
+    #[builder(default)]
+    can_authenticate: bool,
 
     // -- Preferences
     /// List of symmetric algorithms that indicate which algorithms the key holder prefers to use.
@@ -33,6 +41,10 @@ pub struct SecretKeyParams {
     /// List of compression algorithms that indicate which algorithms the key holder prefers to use.
     #[builder(default)]
     preferred_compression_algorithms: SmallVec<[CompressionAlgorithm; 8]>,
+    /// List of AEAD algorithms that indicate which algorithms the key holder prefers to use.
+    #[builder(default)]
+    preferred_aead_algorithms: SmallVec<[AeadAlgorithm; 2]>,
+    /// Designated revoker, which is allowed to issue revocations for this key.
     #[builder(default)]
     revocation_key: Option<RevocationKey>,
 
@@ -45,6 +57,10 @@ pub struct SecretKeyParams {
     user_attributes: Vec<UserAttribute>,
     #[builder(default)]
     passphrase: Option<String>,
+    /// Parameters used to protect the secret key material, if a passphrase is set.
+    #[builder(default)]
+    s2k: S2kParams,
+    /// Creation time of the key, defaults to now.
     #[builder(default = "chrono::Utc::now().trunc_subsecs(0)")]
     created_at: chrono::DateTime<chrono::Utc>,
     #[builder(default)]
@@ -59,8 +75,14 @@ pub struct SecretKeyParams {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
 pub struct SubkeyParams {
+    /// The key type of the subkey, which can differ from the primary key, e.g. in the size of
+    /// RSA keys.
     key_type: KeyType,
+    /// Public exponent of RSA keys, defaults to 65537.
+    #[builder(default)]
+    rsa_public_exponent: Option<u32>,
 
     #[builder(default)]
     can_sign: bool,
@@ -68,6 +90,8 @@ pub struct SubkeyParams {
     can_create_certificates: bool,
     #[builder(default)]
     can_encrypt: bool,
+    #[builder(default)]
+    can_authenticate: bool,
 
     #[builder(default)]
     user_ids: Vec<UserId>,
@@ -75,42 +99,124 @@ pub struct SubkeyParams {
     user_attributes: Vec<UserAttribute>,
     #[builder(default)]
     passphrase: Option<String>,
+    /// Parameters used to protect the secret key material, if a passphrase is set.
+    #[builder(default)]
+    s2k: S2kParams,
+    /// Creation time of the key, defaults to now.
     #[builder(default = "chrono::Utc::now().trunc_subsecs(0)")]
     created_at: chrono::DateTime<chrono::Utc>,
     #[builder(default)]
     packet_version: types::Version,
     #[builder(default)]
     version: types::KeyVersion,
+    /// Expiration of the subkey relative to its creation time, independent of the primary key.
     #[builder(default)]
     expiration: Option<Duration>,
 }
 
-impl SecretKeyParamsBuilder {
-    fn validate(&self) -> std::result::Result<(), String> {
-        match self.key_type {
-            Some(KeyType::Rsa(size)) => {
-                if size < 2048 {
-                    return Err("Keys with less than 2048bits are considered insecure".into());
-                }
-            }
-            Some(KeyType::EdDSA) => {
-                if let Some(can_encrypt) = self.can_encrypt {
-                    if can_encrypt {
-                        return Err("EdDSA can only be used for signing keys".into());
-                    }
-                }
-            }
-            Some(KeyType::ECDH) => {
-                if let Some(can_sign) = self.can_sign {
-                    if can_sign {
-                        return Err("ECDH can only be used for encryption keys".into());
-                    }
-                }
+/// Formats a validation error, naming the offending field and the violated constraint.
+fn invalid(field: &str, constraint: impl std::fmt::Display) -> String {
+    format!("invalid `{}`: {}", field, constraint)
+}
+
+/// Checks that a key type was set, and the size and public exponent of RSA keys.
+fn validate_key_type(
+    key_type: Option<KeyType>,
+    rsa_public_exponent: Option<Option<u32>>,
+) -> std::result::Result<KeyType, String> {
+    let key_type = key_type.ok_or_else(|| invalid("key_type", "a key type must be set"))?;
+
+    if let KeyType::Rsa(size) = key_type {
+        if size == 0 {
+            return Err(invalid("key_type", "RSA keys need a non-zero bit size"));
+        }
+        if size < 2048 {
+            return Err(invalid(
+                "key_type",
+                format!(
+                    "RSA keys with less than 2048 bits are considered insecure, got {}",
+                    size
+                ),
+            ));
+        }
+    }
+
+    if let Some(Some(exponent)) = rsa_public_exponent {
+        match key_type {
+            KeyType::Rsa(_) => {}
+            _ => {
+                return Err(invalid(
+                    "rsa_public_exponent",
+                    format!("can only be set for RSA keys, not for {:?}", key_type),
+                ))
             }
-            _ => {}
         }
+        if exponent < 3 || exponent % 2 == 0 {
+            return Err(invalid(
+                "rsa_public_exponent",
+                format!("must be odd and at least 3, got {}", exponent),
+            ));
+        }
+    }
 
-        Ok(())
+    Ok(key_type)
+}
+
+/// Checks that the requested capabilities are supported by the key type.
+fn validate_key_flags(
+    key_type: KeyType,
+    can_sign: Option<bool>,
+    can_encrypt: Option<bool>,
+    can_authenticate: Option<bool>,
+) -> std::result::Result<(), String> {
+    match key_type {
+        KeyType::EdDSA | KeyType::Ed448 | KeyType::ECDSA(_) if can_encrypt == Some(true) => {
+            Err(invalid(
+                "can_encrypt",
+                format!("{:?} keys can only be used for signing", key_type),
+            ))
+        }
+        KeyType::ECDH(_) if can_sign == Some(true) => Err(invalid(
+            "can_sign",
+            format!("{:?} keys can only be used for encryption", key_type),
+        )),
+        KeyType::ECDH(_) if can_authenticate == Some(true) => Err(invalid(
+            "can_authenticate",
+            format!("{:?} keys can only be used for encryption", key_type),
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl SecretKeyParamsBuilder {
+    fn validate(&self) -> std::result::Result<(), String> {
+        let key_type = validate_key_type(self.key_type, self.rsa_public_exponent)?;
+
+        // the primary key signs the self-signatures and binding signatures
+        if let KeyType::ECDH(_) = key_type {
+            return Err(invalid(
+                "key_type",
+                format!(
+                    "{:?} can not be used for the primary key, which needs to sign",
+                    key_type
+                ),
+            ));
+        }
+        validate_key_flags(
+            key_type,
+            self.can_sign,
+            self.can_encrypt,
+            self.can_authenticate,
+        )?;
+
+        match self.primary_user_id {
+            Some(ref id) if id.trim().is_empty() => Err(invalid(
+                "primary_user_id",
+                "the User ID must not be empty",
+            )),
+            Some(_) => Ok(()),
+            None => Err(invalid("primary_user_id", "a User ID must be set")),
+        }
     }
 
     pub fn user_id<VALUE: Into<String>>(&mut self, value: VALUE) -> &mut Self {
@@ -122,6 +228,15 @@ impl SecretKeyParamsBuilder {
         self
     }
 
+    pub fn user_attribute<VALUE: Into<UserAttribute>>(&mut self, value: VALUE) -> &mut Self {
+        if let Some(ref mut user_attributes) = self.user_attributes {
+            user_attributes.push(value.into());
+        } else {
+            self.user_attributes = Some(vec![value.into()]);
+        }
+        self
+    }
+
     pub fn subkey<VALUE: Into<SubkeyParams>>(&mut self, value: VALUE) -> &mut Self {
         if let Some(ref mut subkeys) = self.subkeys {
             subkeys.push(value.into());
@@ -130,17 +245,86 @@ impl SecretKeyParamsBuilder {
         }
         self
     }
+
+    /// Creates a builder for a key with a certification only primary key and separate
+    /// subkeys for signing, encryption and authentication, which is the layout recommended
+    /// by GnuPG.
+    ///
+    /// `signing` is the key type of the primary key and of the signing and authentication
+    /// subkeys, `encryption` the key type of the encryption subkey.
+    pub fn split_usage<VALUE: Into<String>>(
+        signing: KeyType,
+        encryption: KeyType,
+        primary_user_id: VALUE,
+    ) -> Result<Self> {
+        let mut builder = Self::default();
+        builder
+            .key_type(signing)
+            .can_create_certificates(true)
+            .primary_user_id(primary_user_id.into())
+            .subkey(
+                SubkeyParamsBuilder::default()
+                    .key_type(signing)
+                    .can_sign(true)
+                    .build()?,
+            )
+            .subkey(
+                SubkeyParamsBuilder::default()
+                    .key_type(encryption)
+                    .can_encrypt(true)
+                    .build()?,
+            )
+            .subkey(
+                SubkeyParamsBuilder::default()
+                    .key_type(signing)
+                    .can_authenticate(true)
+                    .build()?,
+            );
+
+        Ok(builder)
+    }
+}
+
+impl SubkeyParamsBuilder {
+    fn validate(&self) -> std::result::Result<(), String> {
+        let key_type = validate_key_type(self.key_type, self.rsa_public_exponent)?;
+        validate_key_flags(
+            key_type,
+            self.can_sign,
+            self.can_encrypt,
+            self.can_authenticate,
+        )?;
+
+        if self.can_create_certificates == Some(true) {
+            return Err(invalid(
+                "can_create_certificates",
+                "only the primary key can certify User IDs and subkeys",
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 impl SecretKeyParams {
+    /// Generates the key, using the thread local random number generator.
     pub fn generate(self) -> Result<SecretKey> {
         let mut rng = thread_rng();
         self.generate_with_rng(&mut rng)
     }
 
+    /// Generates the key, using `rng` for all randomness, including the subkeys and the
+    /// parameters to protect the secret key material.
+    ///
+    /// Generation is deterministic for a seeded `rng` and a fixed `created_at`.
     pub fn generate_with_rng<R: Rng + CryptoRng>(self, rng: &mut R) -> Result<SecretKey> {
         let passphrase = self.passphrase;
-        let (public_params, secret_params) = self.key_type.generate_with_rng(rng, passphrase)?;
+        let (public_params, secret_params) = self.key_type.generate_with_params(
+            rng,
+            self.rsa_public_exponent,
+            passphrase,
+            &self.s2k,
+        )?;
         let primary_key = packet::SecretKey {
             details: packet::PublicKey {
                 packet_version: self.packet_version,
@@ -158,6 +342,7 @@ impl SecretKeyParams {
         keyflags.set_encrypt_comms(self.can_encrypt);
         keyflags.set_encrypt_storage(self.can_encrypt);
         keyflags.set_sign(self.can_sign);
+        keyflags.set_authentication(self.can_authenticate);
 
         Ok(SecretKey::new(
             primary_key,
@@ -172,96 +357,131 @@ impl SecretKeyParams {
                 self.preferred_symmetric_algorithms,
                 self.preferred_hash_algorithms,
                 self.preferred_compression_algorithms,
+                self.preferred_aead_algorithms,
                 self.revocation_key,
             ),
             Default::default(),
             self.subkeys
                 .into_iter()
-                .map(|subkey| {
-                    let passphrase = subkey.passphrase;
-                    let (public_params, secret_params) = subkey.key_type.generate(passphrase)?;
-                    let mut keyflags = KeyFlags::default();
-                    keyflags.set_certify(subkey.can_create_certificates);
-                    keyflags.set_encrypt_comms(subkey.can_encrypt);
-                    keyflags.set_encrypt_storage(subkey.can_encrypt);
-                    keyflags.set_sign(subkey.can_sign);
-
-                    Ok(SecretSubkey::new(
-                        packet::SecretSubkey {
-                            details: packet::PublicSubkey {
-                                packet_version: subkey.packet_version,
-                                version: subkey.version,
-                                algorithm: subkey.key_type.to_alg(),
-                                created_at: subkey.created_at,
-                                expiration: subkey.expiration.map(|v| v.as_secs() as u16),
-                                public_params,
-                            },
-                            secret_params,
-                        },
-                        keyflags,
-                    ))
-                })
+                .map(|subkey| subkey.generate_with_rng(rng))
                 .collect::<Result<Vec<_>>>()?,
         ))
     }
 }
 
+impl SubkeyParams {
+    /// Generates the subkey, using the thread local random number generator.
+    pub fn generate(self) -> Result<SecretSubkey> {
+        let mut rng = thread_rng();
+        self.generate_with_rng(&mut rng)
+    }
+
+    /// Generates the subkey, using `rng` for all randomness.
+    pub fn generate_with_rng<R: Rng + CryptoRng>(self, rng: &mut R) -> Result<SecretSubkey> {
+        let passphrase = self.passphrase;
+        let (public_params, secret_params) = self.key_type.generate_with_params(
+            rng,
+            self.rsa_public_exponent,
+            passphrase,
+            &self.s2k,
+        )?;
+        let mut keyflags = KeyFlags::default();
+        keyflags.set_certify(self.can_create_certificates);
+        keyflags.set_encrypt_comms(self.can_encrypt);
+        keyflags.set_encrypt_storage(self.can_encrypt);
+        keyflags.set_sign(self.can_sign);
+        keyflags.set_authentication(self.can_authenticate);
+
+        Ok(SecretSubkey::new(
+            packet::SecretSubkey {
+                details: packet::PublicSubkey {
+                    packet_version: self.packet_version,
+                    version: self.version,
+                    algorithm: self.key_type.to_alg(),
+                    created_at: self.created_at,
+                    expiration: self.expiration.map(|v| v.as_secs() as u16),
+                    public_params,
+                },
+                secret_params,
+            },
+            keyflags,
+        )
+        .with_expiration(self.expiration))
+    }
+}
+
 #[derive(Clone, Debug, Copy, PartialEq, Eq)]
 pub enum KeyType {
     /// Encryption & Signing with RSA an the given bitsize.
     Rsa(u32),
-    /// Encrypting with Curve25519
-    ECDH,
+    /// Encrypting with Curve25519, Curve448 or the given NIST curve
+    ECDH(ECCCurve),
     /// Signing with Curve25519
     EdDSA,
+    /// Signing with Ed448
+    Ed448,
+    /// Signing with the given NIST curve
+    ECDSA(ECCCurve),
 }
 
 impl KeyType {
     pub fn to_alg(self) -> PublicKeyAlgorithm {
         match self {
             KeyType::Rsa(_) => PublicKeyAlgorithm::RSA,
-            KeyType::ECDH => PublicKeyAlgorithm::ECDH,
-            KeyType::EdDSA => PublicKeyAlgorithm::EdDSA,
+            KeyType::ECDH(_) => PublicKeyAlgorithm::ECDH,
+            KeyType::EdDSA | KeyType::Ed448 => PublicKeyAlgorithm::EdDSA,
+            KeyType::ECDSA(_) => PublicKeyAlgorithm::ECDSA,
         }
     }
 
     pub fn generate(
         self,
         passphrase: Option<String>,
+        s2k_params: &S2kParams,
     ) -> Result<(PublicParams, types::SecretParams)> {
         let mut rng = thread_rng();
-        self.generate_with_rng(&mut rng, passphrase)
+        self.generate_with_rng(&mut rng, passphrase, s2k_params)
     }
 
     pub fn generate_with_rng<R: Rng + CryptoRng>(
         self,
         rng: &mut R,
         passphrase: Option<String>,
+        s2k_params: &S2kParams,
+    ) -> Result<(PublicParams, types::SecretParams)> {
+        self.generate_with_params(rng, None, passphrase, s2k_params)
+    }
+
+    fn generate_with_params<R: Rng + CryptoRng>(
+        self,
+        rng: &mut R,
+        rsa_public_exponent: Option<u32>,
+        passphrase: Option<String>,
+        s2k_params: &S2kParams,
     ) -> Result<(PublicParams, types::SecretParams)> {
         let (pub_params, plain) = match self {
-            KeyType::Rsa(bit_size) => rsa::generate_key(rng, bit_size as usize)?,
-            KeyType::ECDH => ecdh::generate_key(rng),
-            KeyType::EdDSA => eddsa::generate_key(rng),
+            KeyType::Rsa(bit_size) => match rsa_public_exponent {
+                Some(exponent) => {
+                    rsa::generate_key_with_exponent(rng, bit_size as usize, exponent)?
+                }
+                None => rsa::generate_key(rng, bit_size as usize)?,
+            },
+            KeyType::ECDH(ref curve) => ecdh::generate_key(rng, curve)?,
+            KeyType::EdDSA => eddsa::generate_key(rng, &ECCCurve::Ed25519)?,
+            KeyType::Ed448 => eddsa::generate_key(rng, &ECCCurve::Ed448)?,
+            KeyType::ECDSA(ref curve) => ecdsa::generate_key(rng, curve)?,
         };
 
         let secret = match passphrase {
             Some(passphrase) => {
-                // TODO: make configurable
-                let s2k = types::StringToKey::new_default(rng);
-                let alg = SymmetricKeyAlgorithm::AES256;
-                // encrypted, sha1 checksum
-                let id = 254;
-
                 // TODO: derive from key itself
                 let version = types::KeyVersion::default();
 
-                types::SecretParams::Encrypted(plain.encrypt(
+                types::SecretParams::Encrypted(plain.encrypt_with_params(
                     rng,
                     &passphrase,
-                    alg,
-                    s2k,
+                    s2k_params,
                     version,
-                    id,
                 )?)
             }
             None => types::SecretParams::Plain(plain),
@@ -271,12 +491,46 @@ impl KeyType {
     }
 }
 
+/// Generates a signed EdDSA key for `user_id` without a passphrase, as a fixture for tests.
+/// If `encryption_subkey` is set, an ECDH Curve25519 subkey is added.
+#[cfg(test)]
+pub(crate) fn test_key<R: CryptoRng + Rng>(
+    rng: &mut R,
+    user_id: &str,
+    encryption_subkey: bool,
+) -> crate::composed::SignedSecretKey {
+    let mut params = SecretKeyParamsBuilder::default();
+    params
+        .key_type(KeyType::EdDSA)
+        .can_create_certificates(true)
+        .can_sign(true)
+        .primary_user_id(user_id.into());
+    if encryption_subkey {
+        params.subkey(
+            SubkeyParamsBuilder::default()
+                .key_type(KeyType::ECDH(ECCCurve::Curve25519))
+                .can_encrypt(true)
+                .build()
+                .unwrap(),
+        );
+    }
+
+    params
+        .build()
+        .unwrap()
+        .generate_with_rng(rng)
+        .expect("failed to generate secret key")
+        .sign(|| "".into())
+        .expect("failed to sign key")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use crate::composed::{Deserializable, SignedPublicKey, SignedSecretKey};
-    use crate::types::SecretKeyTrait;
+    use crate::composed::{Deserializable, PublicComponentKey, SignedPublicKey, SignedSecretKey};
+    use crate::ser::Serialize;
+    use crate::types::{KeyTrait, SecretKeyTrait};
 
     use rand::SeedableRng;
     use rand_chacha::ChaCha8Rng;
@@ -434,7 +688,7 @@ mod tests {
             ])
             .subkey(
                 SubkeyParamsBuilder::default()
-                    .key_type(KeyType::ECDH)
+                    .key_type(KeyType::ECDH(ECCCurve::Curve25519))
                     .can_encrypt(true)
                     .passphrase(None)
                     .build()
@@ -479,4 +733,703 @@ mod tests {
             SignedPublicKey::from_string(&armor).expect("failed to parse public key");
         signed_key2.verify().expect("invalid public key");
     }
+
+    #[test]
+    fn key_gen_s2k_params() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+
+        let s2k = S2kParams {
+            hash: HashAlgorithm::SHA2_512,
+            salt: None,
+            count: 240,
+            cipher: SymmetricKeyAlgorithm::AES128,
+        };
+
+        let key = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::EdDSA)
+            .can_sign(true)
+            .primary_user_id("Me-S2K <me-s2k@mail.com>".into())
+            .passphrase(Some("hello".into()))
+            .s2k(s2k.clone())
+            .build()
+            .unwrap()
+            .generate_with_rng(rng)
+            .expect("failed to generate secret key");
+
+        let signed_key = key.sign(|| "hello".into()).expect("failed to sign key");
+        let armor = signed_key
+            .to_armored_string(None)
+            .expect("failed to serialize key");
+        let (signed_key, _headers) =
+            SignedSecretKey::from_string(&armor).expect("failed to parse key");
+        signed_key.verify().expect("invalid key");
+
+        match signed_key.primary_key.secret_params() {
+            types::SecretParams::Encrypted(params) => {
+                assert_eq!(params.encryption_algorithm(), SymmetricKeyAlgorithm::AES128);
+                assert_eq!(params.string_to_key().hash(), HashAlgorithm::SHA2_512);
+                assert_eq!(params.string_to_key().count(), Some(33_554_432));
+            }
+            types::SecretParams::Plain(_) | types::SecretParams::Stub(_) => {
+                panic!("expected an encrypted key")
+            }
+        }
+
+        signed_key
+            .unlock(|| "hello".into(), |_| Ok(()))
+            .expect("failed to unlock key");
+
+        // locking an unprotected key
+        let mut primary_key = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::EdDSA)
+            .can_sign(true)
+            .primary_user_id("Me-S2K <me-s2k@mail.com>".into())
+            .build()
+            .unwrap()
+            .generate_with_rng(rng)
+            .expect("failed to generate secret key")
+            .sign(|| "".into())
+            .expect("failed to sign key")
+            .primary_key;
+
+        primary_key
+            .lock(rng, "world", &s2k)
+            .expect("failed to lock key");
+        assert!(primary_key.secret_params().is_encrypted());
+        assert!(primary_key.lock(rng, "world", &s2k).is_err());
+        primary_key
+            .unlock(|| "world".into(), |_| Ok(()))
+            .expect("failed to unlock key");
+    }
+
+    #[test]
+    fn key_gen_add_subkey() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+
+        let key = test_key(rng, "Me-Sub <me-sub@mail.com>", false);
+        assert!(key.secret_subkeys.is_empty());
+
+        let key = key
+            .generate_subkey_with_rng(
+                rng,
+                SubkeyParamsBuilder::default()
+                    .key_type(KeyType::ECDH(ECCCurve::Curve25519))
+                    .can_encrypt(true)
+                    .build()
+                    .unwrap(),
+                || "".into(),
+            )
+            .expect("failed to add encryption subkey")
+            .generate_subkey_with_rng(
+                rng,
+                SubkeyParamsBuilder::default()
+                    .key_type(KeyType::EdDSA)
+                    .can_sign(true)
+                    .build()
+                    .unwrap(),
+                || "".into(),
+            )
+            .expect("failed to add signing subkey");
+
+        let armor = key
+            .to_armored_string(None)
+            .expect("failed to serialize key");
+        let (key, _headers) = SignedSecretKey::from_string(&armor).expect("failed to parse key");
+        key.verify().expect("invalid key");
+        assert_eq!(key.secret_subkeys.len(), 2);
+
+        // only the signing subkey carries a back signature
+        let enc_sig = &key.secret_subkeys[0].signatures[0];
+        assert!(enc_sig.embedded_signature().is_none());
+
+        let sign_subkey = &key.secret_subkeys[1];
+        let backsig = sign_subkey.signatures[0]
+            .embedded_signature()
+            .expect("missing back signature");
+        backsig
+            .verify_primary_key_binding(&key.primary_key, &sign_subkey.key)
+            .expect("invalid back signature");
+        assert!(backsig
+            .verify_primary_key_binding(&key.primary_key, &key.secret_subkeys[0].key)
+            .is_err());
+
+        // re-binding the public subkey keeps its back signature
+        let public_subkey = sign_subkey.public_key();
+        public_subkey
+            .clone()
+            .sign(&key, || "".into())
+            .expect("failed to bind subkey")
+            .verify(&key.primary_key)
+            .expect("invalid subkey");
+        assert!(public_subkey
+            .with_backsig(None)
+            .sign(&key, || "".into())
+            .is_err());
+
+        // a binding of the signing subkey without back signature is rejected
+        let hashed_subpackets = sign_subkey.signatures[0]
+            .hashed_subpackets()
+            .filter(|p| match p {
+                packet::Subpacket::EmbeddedSignature(_) => false,
+                _ => true,
+            })
+            .cloned()
+            .collect();
+        let binding = packet::SignatureConfigBuilder::default()
+            .typ(packet::SignatureType::SubkeyBinding)
+            .pub_alg(key.algorithm())
+            .hashed_subpackets(hashed_subpackets)
+            .unhashed_subpackets(vec![packet::Subpacket::Issuer(key.key_id())])
+            .build()
+            .unwrap()
+            .sign_key_binding(&key.primary_key, || "".into(), &sign_subkey.key)
+            .expect("failed to sign binding");
+        binding
+            .verify_key_binding(&key.primary_key, &sign_subkey.key)
+            .expect("invalid binding");
+
+        let forged = crate::composed::SignedSecretSubKey {
+            key: sign_subkey.key.clone(),
+            signatures: vec![binding],
+        };
+        assert!(forged.verify(&key.primary_key).is_err());
+    }
+
+    #[test]
+    fn key_gen_add_user_id() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+
+        let key = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::EdDSA)
+            .can_create_certificates(true)
+            .can_sign(true)
+            .primary_user_id("Me <me@mail.com>".into())
+            .preferred_hash_algorithms(smallvec![HashAlgorithm::SHA2_512])
+            .preferred_aead_algorithms(smallvec![AeadAlgorithm::Ocb, AeadAlgorithm::Eax])
+            .build()
+            .unwrap()
+            .generate_with_rng(rng)
+            .expect("failed to generate secret key")
+            .sign(|| "".into())
+            .expect("failed to sign key");
+
+        let key = key
+            .add_user_id(
+                UserId::from_str(Default::default(), "Me Other <other@mail.com>"),
+                false,
+                || "".into(),
+            )
+            .expect("failed to add user id")
+            .add_user_id(
+                UserId::from_str(Default::default(), "Me New <new@mail.com>"),
+                true,
+                || "".into(),
+            )
+            .expect("failed to add primary user id");
+
+        let armor = key
+            .to_armored_string(None)
+            .expect("failed to serialize key");
+        let (key, _headers) = SignedSecretKey::from_string(&armor).expect("failed to parse key");
+        key.verify().expect("invalid key");
+
+        let users = &key.details.users;
+        assert_eq!(users.len(), 3);
+        let primary = users
+            .iter()
+            .filter(|u| u.is_primary())
+            .map(|u| u.id.id())
+            .collect::<Vec<_>>();
+        assert_eq!(primary, vec!["Me New <new@mail.com>"]);
+
+        for user in users {
+            let sig = &user.signatures[0];
+            assert!(sig.key_flags().sign());
+            assert_eq!(sig.preferred_hash_algs(), &[HashAlgorithm::SHA2_512][..]);
+            assert_eq!(
+                sig.preferred_aead_algs(),
+                &[AeadAlgorithm::Ocb, AeadAlgorithm::Eax][..]
+            );
+        }
+        assert_eq!(users[2].signatures[0].typ(), packet::SignatureType::CertPositive);
+    }
+
+    #[test]
+    fn key_gen_deterministic() {
+        let created_at = chrono::Utc::now().trunc_subsecs(0);
+        let generate = |seed: u64| {
+            SecretKeyParamsBuilder::default()
+                .key_type(KeyType::EdDSA)
+                .can_create_certificates(true)
+                .can_sign(true)
+                .primary_user_id("Me <me@mail.com>".into())
+                .passphrase(Some("hello".into()))
+                .created_at(created_at)
+                .subkey(
+                    SubkeyParamsBuilder::default()
+                        .key_type(KeyType::ECDH(ECCCurve::Curve25519))
+                        .can_encrypt(true)
+                        .passphrase(Some("hello".into()))
+                        .created_at(created_at)
+                        .build()
+                        .unwrap(),
+                )
+                .build()
+                .unwrap()
+                .generate_with_rng(&mut ChaCha8Rng::seed_from_u64(seed))
+                .expect("failed to generate secret key")
+        };
+
+        let key = generate(42);
+        assert_eq!(key, generate(42));
+        assert_ne!(key, generate(43));
+
+        let signed_key = key.sign(|| "hello".into()).expect("failed to sign key");
+        let other_key = generate(42)
+            .sign(|| "hello".into())
+            .expect("failed to sign key");
+        assert_eq!(signed_key.fingerprint(), other_key.fingerprint());
+        assert_eq!(
+            signed_key.secret_subkeys[0].key,
+            other_key.secret_subkeys[0].key
+        );
+    }
+
+    #[test]
+    fn key_gen_created_at() {
+        use chrono::TimeZone;
+
+        use crate::composed::Message;
+
+        let created_at = chrono::Utc.timestamp(1_500_000_000, 0);
+        let signed_at = created_at + chrono::Duration::hours(1);
+        let generate = || {
+            SecretKeyParamsBuilder::default()
+                .key_type(KeyType::EdDSA)
+                .can_create_certificates(true)
+                .can_sign(true)
+                .primary_user_id("Me <me@mail.com>".into())
+                .created_at(created_at)
+                .subkey(
+                    SubkeyParamsBuilder::default()
+                        .key_type(KeyType::EdDSA)
+                        .can_sign(true)
+                        .created_at(created_at)
+                        .build()
+                        .unwrap(),
+                )
+                .build()
+                .unwrap()
+                .generate_with_rng(&mut ChaCha8Rng::seed_from_u64(0))
+                .expect("failed to generate secret key")
+        };
+
+        assert!(generate()
+            .sign_at(|| "".into(), created_at - chrono::Duration::hours(1))
+            .is_err());
+
+        let key = generate()
+            .sign_at(|| "".into(), signed_at)
+            .expect("failed to sign key");
+        key.verify().expect("invalid key");
+        assert_eq!(key.created_at(), &created_at);
+
+        // the whole key is reproducible
+        let other_key = generate()
+            .sign_at(|| "".into(), signed_at)
+            .expect("failed to sign key");
+        assert_eq!(key, other_key);
+
+        assert_eq!(key.details.users[0].signatures[0].created(), Some(&signed_at));
+        let binding = &key.secret_subkeys[0].signatures[0];
+        assert_eq!(binding.created(), Some(&signed_at));
+        assert_eq!(binding.embedded_signature().unwrap().created(), Some(&signed_at));
+
+        let signature = Message::new_literal("hello.txt", "hello")
+            .sign_at(&key, || "".into(), HashAlgorithm::SHA2_256, signed_at)
+            .expect("failed to sign message")
+            .into_signature();
+        assert_eq!(signature.signature.created(), Some(&signed_at));
+    }
+
+    #[test]
+    fn key_gen_user_attribute() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+
+        // the smallest possible JPEG, a start of image marker followed by the end of image
+        let image = vec![0xFF, 0xD8, 0xFF, 0xD9];
+        assert!(UserAttribute::new_image(Default::default(), vec![0x89, 0x50]).is_err());
+
+        let key = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::EdDSA)
+            .can_create_certificates(true)
+            .can_sign(true)
+            .primary_user_id("Me <me@mail.com>".into())
+            .user_attribute(UserAttribute::new_image(Default::default(), image.clone()).unwrap())
+            .build()
+            .unwrap()
+            .generate_with_rng(rng)
+            .expect("failed to generate secret key")
+            .sign(|| "".into())
+            .expect("failed to sign key");
+
+        let armor = key
+            .to_armored_string(None)
+            .expect("failed to serialize key");
+        let (key, _headers) = SignedSecretKey::from_string(&armor).expect("failed to parse key");
+        key.verify().expect("invalid key");
+
+        let attrs = &key.details.user_attributes;
+        assert_eq!(attrs.len(), 1);
+        assert_eq!(attrs[0].signatures.len(), 1);
+        match &attrs[0].attr {
+            UserAttribute::Image { header, data, .. } => {
+                assert_eq!(&header[..2], &[0x01, 0x01]);
+                assert_eq!(data, &image);
+            }
+            attr => panic!("unexpected attribute {:?}", attr),
+        }
+    }
+
+    #[test]
+    fn key_gen_rsa_params() {
+        let subkey = SubkeyParamsBuilder::default()
+            .key_type(KeyType::Rsa(3072))
+            .rsa_public_exponent(Some(3))
+            .can_encrypt(true)
+            .build()
+            .unwrap();
+
+        let params = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::Rsa(2048))
+            .can_create_certificates(true)
+            .can_sign(true)
+            .primary_user_id("Me <me@mail.com>".into())
+            .subkey(subkey)
+            .build()
+            .unwrap();
+        assert_eq!(params.subkeys[0].key_type, KeyType::Rsa(3072));
+        assert_eq!(params.subkeys[0].rsa_public_exponent, Some(3));
+
+        assert!(SubkeyParamsBuilder::default()
+            .key_type(KeyType::Rsa(1024))
+            .build()
+            .is_err());
+        assert!(SubkeyParamsBuilder::default()
+            .key_type(KeyType::Rsa(2048))
+            .rsa_public_exponent(Some(4))
+            .build()
+            .is_err());
+        assert!(SecretKeyParamsBuilder::default()
+            .key_type(KeyType::EdDSA)
+            .rsa_public_exponent(Some(3))
+            .primary_user_id("Me <me@mail.com>".into())
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn key_gen_validation_errors() {
+        let err = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::Rsa(0))
+            .primary_user_id("Me <me@mail.com>".into())
+            .build()
+            .unwrap_err();
+        assert_eq!(err, "invalid `key_type`: RSA keys need a non-zero bit size");
+
+        let err = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::ECDH(ECCCurve::Curve25519))
+            .can_encrypt(true)
+            .primary_user_id("Me <me@mail.com>".into())
+            .build()
+            .unwrap_err();
+        assert!(err.starts_with("invalid `key_type`: "), "{}", err);
+
+        let err = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::EdDSA)
+            .can_sign(true)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, "invalid `primary_user_id`: a User ID must be set");
+
+        let err = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::EdDSA)
+            .can_sign(true)
+            .primary_user_id(" ".into())
+            .build()
+            .unwrap_err();
+        assert_eq!(err, "invalid `primary_user_id`: the User ID must not be empty");
+
+        let err = SubkeyParamsBuilder::default()
+            .key_type(KeyType::ECDSA(ECCCurve::P256))
+            .can_encrypt(true)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "invalid `can_encrypt`: ECDSA(P256) keys can only be used for signing"
+        );
+
+        let err = SubkeyParamsBuilder::default().build().unwrap_err();
+        assert_eq!(err, "invalid `key_type`: a key type must be set");
+    }
+
+    #[test]
+    fn key_gen_split_usage() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+
+        let key = SecretKeyParamsBuilder::split_usage(
+            KeyType::EdDSA,
+            KeyType::ECDH(ECCCurve::Curve25519),
+            "Me <me@mail.com>",
+        )
+        .unwrap()
+        .build()
+        .unwrap()
+        .generate_with_rng(rng)
+        .expect("failed to generate secret key")
+        .sign(|| "".into())
+        .expect("failed to sign key");
+        key.verify().expect("invalid key");
+
+        let flags = key.details.users[0].signatures[0].key_flags();
+        assert!(flags.certify());
+        assert!(!flags.sign());
+        assert!(!flags.encrypt_comms());
+        assert!(!flags.authentication());
+
+        let public_key = key.signed_public_key();
+        let subkeys = &public_key.public_subkeys;
+        assert_eq!(subkeys.len(), 3);
+        match public_key.signing_key() {
+            Some(PublicComponentKey::Subkey(subkey)) => assert_eq!(subkey, &subkeys[0]),
+            key => panic!("unexpected signing key {:?}", key),
+        }
+        assert_eq!(public_key.encryption_subkey(), Some(&subkeys[1]));
+        match public_key.authentication_key() {
+            Some(PublicComponentKey::Subkey(subkey)) => assert_eq!(subkey, &subkeys[2]),
+            key => panic!("unexpected authentication key {:?}", key),
+        }
+
+        assert!(SecretKeyParamsBuilder::split_usage(
+            KeyType::ECDH(ECCCurve::Curve25519),
+            KeyType::ECDH(ECCCurve::Curve25519),
+            "Me <me@mail.com>",
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn key_gen_subkey_expiration() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+
+        let key = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::EdDSA)
+            .can_create_certificates(true)
+            .can_sign(true)
+            .primary_user_id("Me <me@mail.com>".into())
+            .subkey(
+                SubkeyParamsBuilder::default()
+                    .key_type(KeyType::ECDH(ECCCurve::Curve25519))
+                    .can_encrypt(true)
+                    .expiration(Some(Duration::from_secs(60 * 60 * 24)))
+                    .build()
+                    .unwrap(),
+            )
+            .subkey(
+                SubkeyParamsBuilder::default()
+                    .key_type(KeyType::EdDSA)
+                    .can_sign(true)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap()
+            .generate_with_rng(rng)
+            .expect("failed to generate secret key")
+            .sign(|| "".into())
+            .expect("failed to sign key");
+
+        let armor = key
+            .to_armored_string(None)
+            .expect("failed to serialize key");
+        let (key, _headers) = SignedSecretKey::from_string(&armor).expect("failed to parse key");
+        key.verify().expect("invalid key");
+
+        assert_eq!(key.expires_at(), None);
+        let subkey = &key.secret_subkeys[0];
+        assert_eq!(
+            subkey.expires_at(&key.primary_key),
+            Some(*subkey.created_at() + chrono::Duration::days(1))
+        );
+        assert!(subkey.signatures[0].key_flags().encrypt_comms());
+        assert_eq!(key.secret_subkeys[1].expires_at(&key.primary_key), None);
+    }
+
+    #[test]
+    fn key_gen_designated_revoker() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+        let revoker = test_key(rng, "Revoker <revoker@mail.com>", false);
+        let other = test_key(rng, "Other <other@mail.com>", false);
+        let mut key = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::EdDSA)
+            .can_create_certificates(true)
+            .can_sign(true)
+            .primary_user_id("Me <me@mail.com>".into())
+            .revocation_key(Some(RevocationKey::new(
+                types::RevocationKeyClass::Default,
+                PublicKeyAlgorithm::EdDSA,
+                revoker.fingerprint().as_bytes(),
+            )))
+            .build()
+            .unwrap()
+            .generate_with_rng(rng)
+            .expect("failed to generate secret key")
+            .sign(|| "".into())
+            .expect("failed to sign key");
+
+        assert_eq!(key.details.revocation_keys().len(), 1);
+        assert!(!key.is_revoked_by(&revoker.primary_key).unwrap());
+        assert!(key.is_revoked_by(&other.primary_key).is_err());
+
+        let sig = revoker
+            .revoke_key(
+                &key.primary_key,
+                packet::RevocationCode::KeyCompromised,
+                "lost it",
+                || "".into(),
+            )
+            .expect("failed to create revocation");
+        key.details.revocation_signatures.push(sig);
+
+        // a revocation claiming to be issued by the revoker, that does not verify
+        let forged = packet::SignatureConfigBuilder::default()
+            .typ(packet::SignatureType::KeyRevocation)
+            .pub_alg(other.primary_key.algorithm())
+            .hashed_subpackets(vec![packet::Subpacket::SignatureCreationTime(
+                chrono::Utc::now().trunc_subsecs(0),
+            )])
+            .unhashed_subpackets(vec![packet::Subpacket::Issuer(revoker.key_id())])
+            .build()
+            .unwrap()
+            .sign_key(&other.primary_key, || "".into(), &key.primary_key)
+            .expect("failed to create revocation");
+        let mut forged_key = key.clone();
+        forged_key.details.revocation_signatures = vec![forged.clone()];
+        assert!(!forged_key.is_revoked_by(&revoker.primary_key).unwrap());
+        key.details.revocation_signatures.insert(0, forged);
+
+        let armor = key
+            .to_armored_string(None)
+            .expect("failed to serialize key");
+        let (key, _headers) = SignedSecretKey::from_string(&armor).expect("failed to parse key");
+
+        // only verifiable given the revoker key
+        key.verify().expect("invalid key");
+        assert!(key.is_revoked_by(&revoker.primary_key).unwrap());
+        assert!(key.is_revoked_by(&other.primary_key).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "p256")]
+    fn key_gen_ecdsa_p256() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..10 {
+            gen_ecdsa(rng, ECCCurve::P256);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "p384")]
+    fn key_gen_ecdsa_p384() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..10 {
+            gen_ecdsa(rng, ECCCurve::P384);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "p521")]
+    fn key_gen_ecdsa_p521() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..10 {
+            gen_ecdsa(rng, ECCCurve::P521);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "k256")]
+    fn key_gen_ecdsa_secp256k1() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..10 {
+            gen_ecdsa(rng, ECCCurve::Secp256k1);
+        }
+    }
+
+    fn gen_ecdsa<R: Rng + CryptoRng>(rng: &mut R, curve: ECCCurve) {
+        let _ = pretty_env_logger::try_init();
+
+        let key_params = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::ECDSA(curve))
+            .can_create_certificates(true)
+            .can_sign(true)
+            .primary_user_id("Me-X <me-ecdsa@mail.com>".into())
+            .passphrase(None)
+            .preferred_symmetric_algorithms(smallvec![
+                SymmetricKeyAlgorithm::AES256,
+                SymmetricKeyAlgorithm::AES192,
+                SymmetricKeyAlgorithm::AES128,
+            ])
+            .preferred_hash_algorithms(smallvec![
+                HashAlgorithm::SHA2_256,
+                HashAlgorithm::SHA2_384,
+                HashAlgorithm::SHA2_512,
+            ])
+            .preferred_compression_algorithms(smallvec![
+                CompressionAlgorithm::ZLIB,
+                CompressionAlgorithm::ZIP,
+            ])
+            .subkey(
+                SubkeyParamsBuilder::default()
+                    .key_type(KeyType::ECDH(curve))
+                    .can_encrypt(true)
+                    .passphrase(None)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        let key = key_params
+            .generate_with_rng(rng)
+            .expect("failed to generate secret key");
+
+        let signed_key = key.sign(|| "".into()).expect("failed to sign key");
+
+        let armor = signed_key
+            .to_armored_string(None)
+            .expect("failed to serialize key");
+
+        let (signed_key2, _headers) =
+            SignedSecretKey::from_string(&armor).expect("failed to parse key");
+        signed_key2.verify().expect("invalid key");
+
+        assert_eq!(signed_key, signed_key2);
+
+        let public_key = signed_key.public_key();
+
+        let public_signed_key = public_key
+            .sign(&signed_key, || "".into())
+            .expect("failed to sign public key");
+
+        public_signed_key.verify().expect("invalid public key");
+
+        let armor = public_signed_key
+            .to_armored_string(None)
+            .expect("failed to serialize public key");
+
+        let (signed_key2, _headers) =
+            SignedPublicKey::from_string(&armor).expect("failed to parse public key");
+        signed_key2.verify().expect("invalid public key");
+    }
 }
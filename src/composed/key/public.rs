@@ -1,6 +1,6 @@
 use std::io;
 
-use chrono::{self, SubsecRound};
+use chrono::{DateTime, SubsecRound, Utc};
 use rand::{CryptoRng, Rng};
 use smallvec::SmallVec;
 
@@ -8,7 +8,7 @@ use crate::composed::{KeyDetails, SignedPublicKey, SignedPublicSubKey};
 use crate::crypto::{HashAlgorithm, PublicKeyAlgorithm};
 use crate::errors::Result;
 use crate::packet::{self, KeyFlags, SignatureConfigBuilder, SignatureType, Subpacket};
-use crate::types::{KeyId, KeyTrait, Mpi, PublicKeyTrait, SecretKeyTrait};
+use crate::types::{Fingerprint, KeyId, KeyTrait, Mpi, PublicKeyTrait, SecretKeyTrait};
 
 /// User facing interface to work with a public key.
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -22,6 +22,9 @@ pub struct PublicKey {
 pub struct PublicSubkey {
     key: packet::PublicSubkey,
     keyflags: KeyFlags,
+    /// The primary key binding, made by the subkey, that signing subkeys must embed in their
+    /// binding signature. It can only be created using the secret subkey.
+    backsig: Option<packet::Signature>,
 }
 
 impl PublicKey {
@@ -58,7 +61,7 @@ impl PublicKey {
 }
 
 impl KeyTrait for PublicKey {
-    fn fingerprint(&self) -> Vec<u8> {
+    fn fingerprint(&self) -> Fingerprint {
         self.primary_key.fingerprint()
     }
 
@@ -87,23 +90,64 @@ impl PublicKeyTrait for PublicKey {
 
 impl PublicSubkey {
     pub fn new(key: packet::PublicSubkey, keyflags: KeyFlags) -> Self {
-        PublicSubkey { key, keyflags }
+        PublicSubkey {
+            key,
+            keyflags,
+            backsig: None,
+        }
+    }
+
+    /// Sets the primary key binding, that is embedded when signing a signing subkey.
+    pub fn with_backsig(mut self, backsig: Option<packet::Signature>) -> Self {
+        self.backsig = backsig;
+        self
     }
 
     pub fn sign<F>(self, sec_key: &impl SecretKeyTrait, key_pw: F) -> Result<SignedPublicSubKey>
     where
         F: (FnOnce() -> String) + Clone,
     {
+        self.sign_at(sec_key, key_pw, Utc::now())
+    }
+
+    /// Creates the subkey binding signature, using `created_at` as signature creation time.
+    pub fn sign_at<F>(
+        self,
+        sec_key: &impl SecretKeyTrait,
+        key_pw: F,
+        created_at: DateTime<Utc>,
+    ) -> Result<SignedPublicSubKey>
+    where
+        F: (FnOnce() -> String) + Clone,
+    {
+        let created_at = created_at.trunc_subsecs(0);
+        ensure!(
+            created_at >= *self.key.created_at(),
+            "signature creation time is before the subkey creation time"
+        );
+
         let key = self.key;
-        let hashed_subpackets = vec![
-            Subpacket::SignatureCreationTime(chrono::Utc::now().trunc_subsecs(0)),
+        let mut hashed_subpackets = vec![
+            Subpacket::SignatureCreationTime(created_at),
             Subpacket::KeyFlags(self.keyflags.into()),
             Subpacket::IssuerFingerprint(
                 Default::default(),
-                SmallVec::from_slice(&sec_key.fingerprint()),
+                SmallVec::from_slice(sec_key.fingerprint().as_bytes()),
             ),
         ];
 
+        if self.keyflags.sign() {
+            match self.backsig {
+                Some(backsig) => {
+                    hashed_subpackets.push(Subpacket::EmbeddedSignature(Box::new(backsig)))
+                }
+                None => bail!(
+                    "signing subkey {:?} is missing its primary key binding",
+                    key.key_id()
+                ),
+            }
+        }
+
         let config = SignatureConfigBuilder::default()
             .typ(SignatureType::SubkeyBinding)
             .pub_alg(sec_key.algorithm())
@@ -118,7 +162,7 @@ impl PublicSubkey {
 }
 
 impl KeyTrait for PublicSubkey {
-    fn fingerprint(&self) -> Vec<u8> {
+    fn fingerprint(&self) -> Fingerprint {
         self.key.fingerprint()
     }
 
@@ -1,11 +1,14 @@
-use chrono::{self, SubsecRound};
+use std::time::Duration;
+
+use chrono::{DateTime, SubsecRound, Utc};
 use smallvec::SmallVec;
 
+use crate::composed::signed_key::secret::key_expiration_subpackets;
 use crate::composed::{KeyDetails, PublicSubkey, SignedSecretKey, SignedSecretSubKey};
 use crate::crypto::PublicKeyAlgorithm;
 use crate::errors::Result;
 use crate::packet::{self, KeyFlags, SignatureConfigBuilder, SignatureType, Subpacket};
-use crate::types::{KeyId, KeyTrait, SecretKeyTrait};
+use crate::types::{Fingerprint, KeyId, KeyTrait, SecretKeyTrait};
 
 /// User facing interface to work with a secret key.
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -20,6 +23,7 @@ pub struct SecretKey {
 pub struct SecretSubkey {
     key: packet::SecretSubkey,
     keyflags: KeyFlags,
+    expiration: Option<Duration>,
 }
 
 impl SecretKey {
@@ -41,17 +45,35 @@ impl SecretKey {
     where
         F: (FnOnce() -> String) + Clone,
     {
+        self.sign_at(key_pw, Utc::now())
+    }
+
+    /// Creates all self signatures, using `created_at` as signature creation time.
+    ///
+    /// `created_at` must not be before the creation time of the primary key or any subkey.
+    pub fn sign_at<F>(self, key_pw: F, created_at: DateTime<Utc>) -> Result<SignedSecretKey>
+    where
+        F: (FnOnce() -> String) + Clone,
+    {
+        let created_at = created_at.trunc_subsecs(0);
         let primary_key = self.primary_key;
-        let details = self.details.sign(&primary_key, key_pw.clone())?;
+        ensure!(
+            created_at >= *primary_key.created_at(),
+            "signature creation time is before the key creation time"
+        );
+
+        let details = self
+            .details
+            .sign_at(&primary_key, key_pw.clone(), created_at)?;
         let public_subkeys = self
             .public_subkeys
             .into_iter()
-            .map(|k| k.sign(&primary_key, key_pw.clone()))
+            .map(|k| k.sign_at(&primary_key, key_pw.clone(), created_at))
             .collect::<Result<Vec<_>>>()?;
         let secret_subkeys = self
             .secret_subkeys
             .into_iter()
-            .map(|k| k.sign(&primary_key, key_pw.clone()))
+            .map(|k| k.sign_at(&primary_key, key_pw.clone(), created_at))
             .collect::<Result<Vec<_>>>()?;
 
         Ok(SignedSecretKey {
@@ -64,7 +86,7 @@ impl SecretKey {
 }
 
 impl KeyTrait for SecretKey {
-    fn fingerprint(&self) -> Vec<u8> {
+    fn fingerprint(&self) -> Fingerprint {
         self.primary_key.fingerprint()
     }
 
@@ -79,23 +101,78 @@ impl KeyTrait for SecretKey {
 
 impl SecretSubkey {
     pub fn new(key: packet::SecretSubkey, keyflags: KeyFlags) -> Self {
-        SecretSubkey { key, keyflags }
+        SecretSubkey {
+            key,
+            keyflags,
+            expiration: None,
+        }
     }
 
+    /// Sets the expiration of the subkey, relative to its creation time, which is stored in
+    /// the subkey binding signature.
+    pub fn with_expiration(mut self, expiration: Option<Duration>) -> Self {
+        self.expiration = expiration;
+        self
+    }
+
+    /// Creates the subkey binding signature, using `sec_key` as primary key.
+    ///
+    /// Signing subkeys additionally get an embedded primary key binding signature,
+    /// for which the subkey is unlocked using the same `key_pw`.
     pub fn sign<F>(self, sec_key: &impl SecretKeyTrait, key_pw: F) -> Result<SignedSecretSubKey>
     where
         F: (FnOnce() -> String) + Clone,
     {
+        self.sign_at(sec_key, key_pw, Utc::now())
+    }
+
+    /// Creates the subkey binding signature, using `created_at` as signature creation time.
+    pub fn sign_at<F>(
+        self,
+        sec_key: &impl SecretKeyTrait,
+        key_pw: F,
+        created_at: DateTime<Utc>,
+    ) -> Result<SignedSecretSubKey>
+    where
+        F: (FnOnce() -> String) + Clone,
+    {
+        let created_at = created_at.trunc_subsecs(0);
+        ensure!(
+            created_at >= *self.key.created_at(),
+            "signature creation time is before the subkey creation time"
+        );
+
         let key = self.key;
-        let hashed_subpackets = vec![
-            Subpacket::SignatureCreationTime(chrono::Utc::now().trunc_subsecs(0)),
+        let mut hashed_subpackets = vec![
+            Subpacket::SignatureCreationTime(created_at),
             Subpacket::KeyFlags(self.keyflags.into()),
             Subpacket::IssuerFingerprint(
                 Default::default(),
-                SmallVec::from_slice(&sec_key.fingerprint()),
+                SmallVec::from_slice(sec_key.fingerprint().as_bytes()),
             ),
         ];
 
+        if let Some(expiration) = self.expiration {
+            let expiration = chrono::Duration::from_std(expiration)
+                .map_err(|_| format_err!("expiration out of range"))?;
+            let created_at = key.created_at();
+            hashed_subpackets.extend(key_expiration_subpackets(
+                created_at,
+                Some(*created_at + expiration),
+            )?);
+        }
+
+        if self.keyflags.sign() {
+            let backsig = SignatureConfigBuilder::default()
+                .typ(SignatureType::KeyBinding)
+                .pub_alg(key.algorithm())
+                .hashed_subpackets(vec![Subpacket::SignatureCreationTime(created_at)])
+                .unhashed_subpackets(vec![Subpacket::Issuer(key.key_id())])
+                .build()?
+                .sign_primary_key_binding(&key, key_pw.clone(), sec_key)?;
+            hashed_subpackets.push(Subpacket::EmbeddedSignature(Box::new(backsig)));
+        }
+
         let config = SignatureConfigBuilder::default()
             .typ(SignatureType::SubkeyBinding)
             .pub_alg(sec_key.algorithm())
@@ -109,7 +186,7 @@ impl SecretSubkey {
 }
 
 impl KeyTrait for SecretSubkey {
-    fn fingerprint(&self) -> Vec<u8> {
+    fn fingerprint(&self) -> Fingerprint {
         self.key.fingerprint()
     }
 
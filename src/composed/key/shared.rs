@@ -1,7 +1,8 @@
-use chrono::{self, SubsecRound};
+use chrono::{DateTime, SubsecRound, Utc};
 use smallvec::SmallVec;
 
 use crate::composed::SignedKeyDetails;
+use crate::crypto::aead::AeadAlgorithm;
 use crate::crypto::hash::HashAlgorithm;
 use crate::crypto::sym::SymmetricKeyAlgorithm;
 use crate::errors::Result;
@@ -19,6 +20,7 @@ pub struct KeyDetails {
     preferred_symmetric_algorithms: SmallVec<[SymmetricKeyAlgorithm; 8]>,
     preferred_hash_algorithms: SmallVec<[HashAlgorithm; 8]>,
     preferred_compression_algorithms: SmallVec<[CompressionAlgorithm; 8]>,
+    preferred_aead_algorithms: SmallVec<[AeadAlgorithm; 2]>,
     revocation_key: Option<RevocationKey>,
 }
 
@@ -32,6 +34,7 @@ impl KeyDetails {
         preferred_symmetric_algorithms: SmallVec<[SymmetricKeyAlgorithm; 8]>,
         preferred_hash_algorithms: SmallVec<[HashAlgorithm; 8]>,
         preferred_compression_algorithms: SmallVec<[CompressionAlgorithm; 8]>,
+        preferred_aead_algorithms: SmallVec<[AeadAlgorithm; 2]>,
         revocation_key: Option<RevocationKey>,
     ) -> Self {
         KeyDetails {
@@ -42,6 +45,7 @@ impl KeyDetails {
             preferred_symmetric_algorithms,
             preferred_hash_algorithms,
             preferred_compression_algorithms,
+            preferred_aead_algorithms,
             revocation_key,
         }
     }
@@ -50,10 +54,26 @@ impl KeyDetails {
     where
         F: (FnOnce() -> String) + Clone,
     {
+        self.sign_at(key, key_pw, Utc::now())
+    }
+
+    /// Certifies all user ids and user attributes, using `created_at` as signature
+    /// creation time.
+    pub fn sign_at<F>(
+        self,
+        key: &impl SecretKeyTrait,
+        key_pw: F,
+        created_at: DateTime<Utc>,
+    ) -> Result<SignedKeyDetails>
+    where
+        F: (FnOnce() -> String) + Clone,
+    {
+        let created_at = created_at.trunc_subsecs(0);
         let keyflags: SmallVec<[u8; 1]> = self.keyflags.into();
         let preferred_symmetric_algorithms = self.preferred_symmetric_algorithms;
         let preferred_hash_algorithms = self.preferred_hash_algorithms;
         let preferred_compression_algorithms = self.preferred_compression_algorithms;
+        let preferred_aead_algorithms = self.preferred_aead_algorithms;
         let revocation_key = self.revocation_key;
 
         let mut users = vec![];
@@ -63,16 +83,21 @@ impl KeyDetails {
             let id = self.primary_user_id;
             let mut hashed_subpackets = vec![
                 Subpacket::IsPrimary(true),
-                Subpacket::SignatureCreationTime(chrono::Utc::now().trunc_subsecs(0)),
+                Subpacket::SignatureCreationTime(created_at),
                 Subpacket::KeyFlags(keyflags.clone()),
                 Subpacket::PreferredSymmetricAlgorithms(preferred_symmetric_algorithms.clone()),
                 Subpacket::PreferredHashAlgorithms(preferred_hash_algorithms.clone()),
                 Subpacket::PreferredCompressionAlgorithms(preferred_compression_algorithms.clone()),
                 Subpacket::IssuerFingerprint(
                     Default::default(),
-                    SmallVec::from_slice(&key.fingerprint()),
+                    SmallVec::from_slice(key.fingerprint().as_bytes()),
                 ),
             ];
+            if !preferred_aead_algorithms.is_empty() {
+                hashed_subpackets.push(Subpacket::PreferredAeadAlgorithms(
+                    preferred_aead_algorithms.clone(),
+                ));
+            }
             if let Some(rkey) = revocation_key {
                 hashed_subpackets.push(Subpacket::RevocationKey(rkey));
             }
@@ -95,24 +120,31 @@ impl KeyDetails {
             self.user_ids
                 .into_iter()
                 .map(|id| {
+                    let mut hashed_subpackets = vec![
+                        Subpacket::SignatureCreationTime(created_at),
+                        Subpacket::KeyFlags(keyflags.clone()),
+                        Subpacket::PreferredSymmetricAlgorithms(
+                            preferred_symmetric_algorithms.clone(),
+                        ),
+                        Subpacket::PreferredHashAlgorithms(preferred_hash_algorithms.clone()),
+                        Subpacket::PreferredCompressionAlgorithms(
+                            preferred_compression_algorithms.clone(),
+                        ),
+                        Subpacket::IssuerFingerprint(
+                            Default::default(),
+                            SmallVec::from_slice(key.fingerprint().as_bytes()),
+                        ),
+                    ];
+                    if !preferred_aead_algorithms.is_empty() {
+                        hashed_subpackets.push(Subpacket::PreferredAeadAlgorithms(
+                            preferred_aead_algorithms.clone(),
+                        ));
+                    }
+
                     let config = SignatureConfigBuilder::default()
                         .typ(SignatureType::CertGeneric)
                         .pub_alg(key.algorithm())
-                        .hashed_subpackets(vec![
-                            Subpacket::SignatureCreationTime(chrono::Utc::now().trunc_subsecs(0)),
-                            Subpacket::KeyFlags(keyflags.clone()),
-                            Subpacket::PreferredSymmetricAlgorithms(
-                                preferred_symmetric_algorithms.clone(),
-                            ),
-                            Subpacket::PreferredHashAlgorithms(preferred_hash_algorithms.clone()),
-                            Subpacket::PreferredCompressionAlgorithms(
-                                preferred_compression_algorithms.clone(),
-                            ),
-                            Subpacket::IssuerFingerprint(
-                                Default::default(),
-                                SmallVec::from_slice(&key.fingerprint()),
-                            ),
-                        ])
+                        .hashed_subpackets(hashed_subpackets)
                         .unhashed_subpackets(vec![Subpacket::Issuer(key.key_id())])
                         .build()?;
 
@@ -126,7 +158,7 @@ impl KeyDetails {
         let user_attributes = self
             .user_attributes
             .into_iter()
-            .map(|u| u.sign(key, key_pw.clone()))
+            .map(|u| u.sign_at(key, key_pw.clone(), created_at))
             .collect::<Result<Vec<_>>>()?;
 
         Ok(SignedKeyDetails {
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use crate::armor;
+use crate::composed::signed_key::{
+    from_armor_many, from_bytes_many, from_slice_many, PublicOrSecret,
+};
+use crate::errors::Result;
+use crate::ser::Serialize;
+use crate::types::{Fingerprint, KeyId, KeyTrait, SignedUser};
+
+/// A collection of public and secret keys, as found in keyrings like `pubring.gpg` or
+/// `secring.gpg`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Keyring {
+    keys: Vec<PublicOrSecret>,
+}
+
+impl Keyring {
+    pub fn new() -> Self {
+        Keyring::default()
+    }
+
+    /// Parses a binary keyring.
+    pub fn from_bytes(bytes: impl io::Read) -> Result<Self> {
+        let keys = from_bytes_many(bytes).collect::<Result<_>>()?;
+
+        Ok(Keyring { keys })
+    }
+
+    /// Parses a binary keyring, directly from the given slice.
+    ///
+    /// Keys that fail to parse are skipped, as large keyrings like SKS dumps are expected to
+    /// contain some.
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let keys = from_slice_many(bytes)
+            .filter_map(|key| match key {
+                Ok(key) => Some(key),
+                Err(err) => {
+                    warn!("skipping key: {:?}", err);
+                    None
+                }
+            })
+            .collect();
+
+        Ok(Keyring { keys })
+    }
+
+    /// Parses the binary keyring file at `path`, by memory-mapping it.
+    ///
+    /// This avoids reading large keyrings, like SKS dumps, into memory first. Keys that fail to
+    /// parse are skipped, like in `from_slice`.
+    #[cfg(feature = "mmap")]
+    #[allow(unsafe_code)]
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        // Safety: the mapping is only read while parsing, and dropped afterwards. The file
+        // must not be modified concurrently.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+
+        Keyring::from_slice(&mmap)
+    }
+
+    /// Parses an ascii armored keyring.
+    pub fn from_armor<R: io::Read + io::Seek>(input: R) -> Result<Self> {
+        let (keys, _headers) = from_armor_many(input)?;
+        let keys = keys.collect::<Result<_>>()?;
+
+        Ok(Keyring { keys })
+    }
+
+    /// Parses an ascii armored keyring.
+    pub fn from_string(input: &str) -> Result<Self> {
+        Keyring::from_armor(io::Cursor::new(input))
+    }
+
+    pub fn keys(&self) -> &[PublicOrSecret] {
+        &self.keys
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PublicOrSecret> {
+        self.keys.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Adds a key to the keyring, replacing an existing key with the same fingerprint.
+    /// Returns the replaced key, if any.
+    pub fn add(&mut self, key: PublicOrSecret) -> Option<PublicOrSecret> {
+        let fingerprint = key.fingerprint();
+        match self
+            .keys
+            .iter_mut()
+            .find(|k| k.fingerprint() == fingerprint)
+        {
+            Some(existing) => Some(std::mem::replace(existing, key)),
+            None => {
+                self.keys.push(key);
+                None
+            }
+        }
+    }
+
+    /// Removes the key with the given primary key fingerprint.
+    pub fn remove(&mut self, fingerprint: &Fingerprint) -> Option<PublicOrSecret> {
+        let pos = self
+            .keys
+            .iter()
+            .position(|k| &k.fingerprint() == fingerprint)?;
+
+        Some(self.keys.remove(pos))
+    }
+
+    /// Finds the key that has either a primary key or a subkey with the given key id.
+    pub fn get_by_key_id(&self, key_id: &KeyId) -> Option<&PublicOrSecret> {
+        self.keys.iter().find(|k| {
+            &k.key_id() == key_id || subkeys(k).iter().any(|(id, _)| id == key_id)
+        })
+    }
+
+    /// Finds the key that has either a primary key or a subkey with the given fingerprint.
+    pub fn get_by_fingerprint(&self, fingerprint: &Fingerprint) -> Option<&PublicOrSecret> {
+        self.keys.iter().find(|k| {
+            &k.fingerprint() == fingerprint || subkeys(k).iter().any(|(_, fp)| fp == fingerprint)
+        })
+    }
+
+    /// Finds all keys with a user id that contains the given string, ignoring case.
+    pub fn find_by_user_id(&self, query: &str) -> Vec<&PublicOrSecret> {
+        let query = query.to_lowercase();
+        self.find_by_user(|user| user.id.id().to_lowercase().contains(&query))
+    }
+
+    /// Finds all keys with a user id for the given email address, ignoring case.
+    pub fn find_by_email(&self, email: &str) -> Vec<&PublicOrSecret> {
+        self.find_by_user(|user| {
+            extract_email(user.id.id()).map_or(false, |e| e.eq_ignore_ascii_case(email))
+        })
+    }
+
+    fn find_by_user<P>(&self, predicate: P) -> Vec<&PublicOrSecret>
+    where
+        P: Fn(&SignedUser) -> bool,
+    {
+        self.keys
+            .iter()
+            .filter(|k| users(k).iter().any(&predicate))
+            .collect()
+    }
+
+    pub fn to_armored_writer(
+        &self,
+        writer: &mut impl io::Write,
+        headers: Option<&BTreeMap<String, String>>,
+    ) -> Result<()> {
+        let typ = if self.keys.iter().any(PublicOrSecret::is_secret) {
+            armor::BlockType::PrivateKey
+        } else {
+            armor::BlockType::PublicKey
+        };
+
+        armor::write(self, typ, writer, headers)
+    }
+
+    pub fn to_armored_string(&self, headers: Option<&BTreeMap<String, String>>) -> Result<String> {
+        let mut buf = Vec::new();
+        self.to_armored_writer(&mut buf, headers)?;
+
+        Ok(::std::str::from_utf8(&buf)?.to_string())
+    }
+}
+
+impl Serialize for Keyring {
+    fn to_writer<W: io::Write>(&self, writer: &mut W) -> Result<()> {
+        for key in &self.keys {
+            key.to_writer(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl IntoIterator for Keyring {
+    type Item = PublicOrSecret;
+    type IntoIter = std::vec::IntoIter<PublicOrSecret>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.keys.into_iter()
+    }
+}
+
+impl std::iter::FromIterator<PublicOrSecret> for Keyring {
+    fn from_iter<I: IntoIterator<Item = PublicOrSecret>>(iter: I) -> Self {
+        let mut keyring = Keyring::new();
+        for key in iter {
+            keyring.add(key);
+        }
+
+        keyring
+    }
+}
+
+fn users(key: &PublicOrSecret) -> &[SignedUser] {
+    match key {
+        PublicOrSecret::Public(k) => &k.details.users,
+        PublicOrSecret::Secret(k) => &k.details.users,
+    }
+}
+
+/// Returns the key ids and fingerprints of all subkeys.
+fn subkeys(key: &PublicOrSecret) -> Vec<(KeyId, Fingerprint)> {
+    match key {
+        PublicOrSecret::Public(k) => k
+            .public_subkeys
+            .iter()
+            .map(|s| (s.key_id(), s.fingerprint()))
+            .collect(),
+        PublicOrSecret::Secret(k) => k
+            .public_subkeys
+            .iter()
+            .map(|s| (s.key_id(), s.fingerprint()))
+            .chain(
+                k.secret_subkeys
+                    .iter()
+                    .map(|s| (s.key_id(), s.fingerprint())),
+            )
+            .collect(),
+    }
+}
+
+/// Extracts the email address from a user id like `Name <mail@example.com>`.
+fn extract_email(id: &str) -> Option<&str> {
+    match (id.rfind('<'), id.rfind('>')) {
+        (Some(start), Some(end)) if start < end => Some(&id[start + 1..end]),
+        _ if id.contains('@') => Some(id.trim()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_email() {
+        assert_eq!(extract_email("Me <me@mail.com>"), Some("me@mail.com"));
+        assert_eq!(extract_email("me@mail.com"), Some("me@mail.com"));
+        assert_eq!(extract_email("Me (<3) <me@mail.com>"), Some("me@mail.com"));
+        assert_eq!(extract_email("Me"), None);
+    }
+}
@@ -0,0 +1,349 @@
+use std::fmt;
+use std::io::Read;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rand::{thread_rng, CryptoRng, Rng};
+
+use crate::composed::message::types::{negotiate_aead, Esk, Message};
+use crate::composed::signed_key::secret::signature_expiration_subpackets;
+use crate::composed::signed_key::{SignedPublicKey, SignedSecretKey};
+use crate::crypto::{HashAlgorithm, SymmetricKeyAlgorithm};
+use crate::errors::Result;
+use crate::packet::{
+    LiteralData, Notation, PublicKeyEncryptedSessionKey, Subpacket, SymKeyEncryptedSessionKey,
+};
+use crate::types::{CompressionAlgorithm, KeyTrait, StringToKey};
+
+enum Plaintext {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// Builds a message from plaintext, nesting the layers in the usual order.
+///
+/// The literal data is signed first, by all signers, then the signed message is compressed,
+/// and finally encrypted to all recipients and passwords, using a single session key.
+///
+/// ```ignore
+/// let msg = MessageBuilder::from_text("hello world\n")
+///     .file_name("hello.txt")
+///     .sign(&alice, || "".into(), HashAlgorithm::SHA2_256)
+///     .compression(CompressionAlgorithm::ZLIB)
+///     .recipient(&bob)
+///     .build()?;
+/// ```
+pub struct MessageBuilder<'a> {
+    plaintext: Plaintext,
+    file_name: String,
+    date: Option<DateTime<Utc>>,
+    signers: Vec<(&'a SignedSecretKey, Box<dyn FnOnce() -> String + 'a>, HashAlgorithm)>,
+    notations: Vec<Notation>,
+    signature_expiration: Option<Duration>,
+    compression: Option<CompressionAlgorithm>,
+    sym_alg: SymmetricKeyAlgorithm,
+    recipients: Vec<&'a SignedPublicKey>,
+    passwords: Vec<Box<dyn FnOnce() -> String + 'a>>,
+}
+
+impl fmt::Debug for MessageBuilder<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageBuilder")
+            .field("file_name", &self.file_name)
+            .field("date", &self.date)
+            .field(
+                "signers",
+                &self.signers.iter().map(|(key, _, hash)| (key, hash)).collect::<Vec<_>>(),
+            )
+            .field("notations", &self.notations)
+            .field("signature_expiration", &self.signature_expiration)
+            .field("compression", &self.compression)
+            .field("sym_alg", &self.sym_alg)
+            .field("recipients", &self.recipients)
+            .field("passwords", &self.passwords.len())
+            .finish()
+    }
+}
+
+impl<'a> MessageBuilder<'a> {
+    fn new(plaintext: Plaintext) -> Self {
+        MessageBuilder {
+            plaintext,
+            file_name: String::new(),
+            date: None,
+            signers: Vec::new(),
+            notations: Vec::new(),
+            signature_expiration: None,
+            compression: None,
+            sym_alg: SymmetricKeyAlgorithm::default(),
+            recipients: Vec::new(),
+            passwords: Vec::new(),
+        }
+    }
+
+    /// Creates a message from the given text. Normalizes line endings.
+    pub fn from_text(text: &str) -> Self {
+        Self::new(Plaintext::Text(text.to_owned()))
+    }
+
+    /// Creates a message from the given binary data.
+    pub fn from_bytes(data: &[u8]) -> Self {
+        Self::new(Plaintext::Binary(data.to_vec()))
+    }
+
+    /// Creates a message from the binary data read from `reader`.
+    pub fn from_reader(mut reader: impl Read) -> Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        Ok(Self::new(Plaintext::Binary(data)))
+    }
+
+    /// Sets the file name of the literal data, empty by default.
+    pub fn file_name(mut self, file_name: &str) -> Self {
+        self.file_name = file_name.to_owned();
+        self
+    }
+
+    /// Sets the date of the literal data, defaults to the current time.
+    pub fn date(mut self, date: DateTime<Utc>) -> Self {
+        self.date = Some(date);
+        self
+    }
+
+    /// Signs the message using the provided key. Can be called multiple times, to sign the
+    /// message with several keys.
+    pub fn sign<F>(mut self, key: &'a SignedSecretKey, key_pw: F, hash_alg: HashAlgorithm) -> Self
+    where
+        F: FnOnce() -> String + 'a,
+    {
+        self.signers.push((key, Box::new(key_pw), hash_alg));
+        self
+    }
+
+    /// Adds the notation to the signatures.
+    pub fn notation(mut self, notation: Notation) -> Self {
+        self.notations.push(notation);
+        self
+    }
+
+    /// Lets the signatures expire `expiration` after its creation, signatures do not expire by
+    /// default.
+    pub fn signature_expiration(mut self, expiration: Duration) -> Self {
+        self.signature_expiration = Some(expiration);
+        self
+    }
+
+    /// Compresses the message using the given algorithm, the message is not compressed by
+    /// default.
+    pub fn compression(mut self, alg: CompressionAlgorithm) -> Self {
+        self.compression = Some(alg);
+        self
+    }
+
+    /// Sets the symmetric algorithm used for encryption.
+    pub fn sym_algorithm(mut self, alg: SymmetricKeyAlgorithm) -> Self {
+        self.sym_alg = alg;
+        self
+    }
+
+    /// Encrypts the message to the given recipient, using the key returned by
+    /// `SignedPublicKey::encryption_key`.
+    pub fn recipient(mut self, key: &'a SignedPublicKey) -> Self {
+        self.recipients.push(key);
+        self
+    }
+
+    /// Encrypts the message using the given password.
+    pub fn password<F>(mut self, msg_pw: F) -> Self
+    where
+        F: FnOnce() -> String + 'a,
+    {
+        self.passwords.push(Box::new(msg_pw));
+        self
+    }
+
+    /// Builds the message, using [`thread_rng`] for RNG.
+    ///
+    /// [`thread_rng`]: rand::thread_rng
+    pub fn build(self) -> Result<Message> {
+        self.build_with_rng(&mut thread_rng())
+    }
+
+    /// Builds the message.
+    pub fn build_with_rng<R: CryptoRng + Rng>(self, rng: &mut R) -> Result<Message> {
+        let literal = match self.plaintext {
+            Plaintext::Text(ref text) => LiteralData::from_str(&self.file_name, text),
+            Plaintext::Binary(ref data) => LiteralData::from_bytes(&self.file_name, data),
+        };
+        let literal = match self.date {
+            Some(date) => literal.with_created(date),
+            None => literal,
+        };
+        let mut msg = Message::Literal(literal);
+
+        if !self.signers.is_empty() {
+            let mut subpackets: Vec<_> =
+                self.notations.into_iter().map(Subpacket::Notation).collect();
+            subpackets.extend(signature_expiration_subpackets(self.signature_expiration)?);
+            let created_at = Utc::now();
+
+            for (i, (key, key_pw, hash_alg)) in self.signers.into_iter().enumerate() {
+                let subpackets = subpackets.clone();
+                msg = if i == 0 {
+                    msg.sign_with_subpackets(key, key_pw, hash_alg, created_at, subpackets)?
+                } else {
+                    msg.add_signature_with_subpackets(
+                        key,
+                        key_pw,
+                        hash_alg,
+                        created_at,
+                        subpackets,
+                    )?
+                };
+            }
+        }
+
+        if let Some(alg) = self.compression {
+            msg = msg.compress(alg)?;
+        }
+
+        if self.recipients.is_empty() && self.passwords.is_empty() {
+            return Ok(msg);
+        }
+
+        let alg = self.sym_alg;
+        let session_key = alg.new_session_key(rng);
+        let mut esk = Vec::with_capacity(self.recipients.len() + self.passwords.len());
+
+        for key in &self.recipients {
+            let pkey = key
+                .encryption_key()
+                .ok_or_else(|| format_err!("no encryption key found for {:?}", key.key_id()))?;
+            let pkes =
+                PublicKeyEncryptedSessionKey::from_session_key(rng, &session_key, alg, &pkey)?;
+            esk.push(Esk::PublicKeyEncryptedSessionKey(pkes));
+        }
+
+        for msg_pw in self.passwords {
+            let password = msg_pw();
+            let s2k = StringToKey::new_default(rng);
+            let skesk = SymKeyEncryptedSessionKey::encrypt(|| password, &session_key, s2k, alg)?;
+            esk.push(Esk::SymKeyEncryptedSessionKey(skesk));
+        }
+
+        let aead = negotiate_aead(alg, &self.recipients);
+
+        msg.encrypt_symmetric(rng, esk, alg, aead, session_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use chrono::TimeZone;
+
+    use crate::composed::{Deserializable, VerificationResult};
+
+    fn secret_key(name: &str) -> SignedSecretKey {
+        let f = fs::File::open(format!("./tests/autocrypt/{}@autocrypt.example.sec.asc", name))
+            .unwrap();
+        SignedSecretKey::from_armor_single(f).unwrap().0
+    }
+
+    #[test]
+    fn test_build_literal() {
+        let date = Utc.ymd(2020, 1, 1).and_hms(12, 0, 0);
+        let msg = MessageBuilder::from_bytes(b"hello")
+            .file_name("hello.bin")
+            .date(date)
+            .build()
+            .unwrap();
+
+        let literal = msg.literal_data().unwrap().unwrap();
+        assert!(literal.is_binary());
+        assert_eq!(literal.file_name(), "hello.bin");
+        assert_eq!(literal.created(), &date);
+        assert_eq!(literal.data(), b"hello");
+    }
+
+    #[test]
+    fn test_build_multiple_signers() {
+        let alice = secret_key("alice");
+        let bob = secret_key("bob");
+        let alice_pub = alice.signed_public_key();
+        let bob_pub = bob.signed_public_key();
+
+        let msg = MessageBuilder::from_text("hello world\n")
+            .sign(&alice, || "".into(), HashAlgorithm::SHA2_256)
+            .sign(&bob, || "".into(), HashAlgorithm::SHA2_256)
+            .build()
+            .unwrap();
+
+        let armored = msg.to_armored_string(None).unwrap();
+        let parsed = Message::from_string(&armored).unwrap().0;
+        assert_eq!(
+            parsed.verify_signatures(&[&alice_pub, &bob_pub]).unwrap(),
+            vec![
+                VerificationResult::Valid(bob.key_id()),
+                VerificationResult::Valid(alice.key_id()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_signed_compressed_encrypted() {
+        let alice = secret_key("alice");
+        let bob = secret_key("bob");
+        let alice_pub = alice.signed_public_key();
+        let bob_pub = bob.signed_public_key();
+
+        let msg = MessageBuilder::from_text("hello world\n")
+            .file_name("hello.txt")
+            .sign(&alice, || "".into(), HashAlgorithm::SHA2_256)
+            .notation(Notation::new("test@example.org", "builder"))
+            .signature_expiration(Duration::from_secs(24 * 60 * 60))
+            .compression(CompressionAlgorithm::ZLIB)
+            .recipient(&bob_pub)
+            .password(|| "secret".into())
+            .build()
+            .unwrap();
+
+        let armored = msg.to_armored_string(None).unwrap();
+        let parsed = Message::from_string(&armored).unwrap().0;
+
+        let (decrypted, results, ids) = parsed
+            .decrypt_and_verify(|| "".into(), || "".into(), &[&bob], &[&alice_pub])
+            .unwrap();
+        assert_eq!(ids.len(), 1);
+        assert_eq!(results, vec![VerificationResult::Valid(alice.key_id())]);
+        assert_eq!(decrypted.get_content().unwrap().unwrap(), b"hello world\r\n");
+
+        let decrypted = parsed
+            .decrypt_with_password(|| "secret".into())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let decrypted = decrypted.decompress().unwrap();
+        assert!(decrypted.is_one_pass_signed());
+        decrypted.verify(&alice_pub).unwrap();
+
+        match decrypted {
+            Message::Signed { signature, .. } => {
+                assert_eq!(
+                    signature.notations(),
+                    vec![&Notation::new("test@example.org", "builder")]
+                );
+                assert_eq!(
+                    signature.signature_expiration_time().map(|t| t.timestamp()),
+                    Some(24 * 60 * 60)
+                );
+            }
+            _ => panic!("invalid message"),
+        }
+    }
+}
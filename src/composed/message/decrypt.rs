@@ -1,14 +1,15 @@
 use std::boxed::Box;
-use std::io::Cursor;
 
 use num_traits::FromPrimitive;
+use zeroize::Zeroize;
 
+use crate::composed::message::parser::MessageParser;
 use crate::composed::message::types::{Edata, Message};
-use crate::composed::shared::Deserializable;
+use crate::composed::shared::skip_invalid;
 use crate::crypto::{checksum, ecdh, rsa, SymmetricKeyAlgorithm};
-use crate::errors::Result;
-use crate::packet::SymKeyEncryptedSessionKey;
-use crate::types::{KeyTrait, Mpi, SecretKeyRepr, SecretKeyTrait, Tag};
+use crate::errors::{Error, Result};
+use crate::packet::{PacketParser, SymKeyEncryptedSessionKey};
+use crate::types::{KeyTrait, Mpi, Policy, SecretKeyRepr, SecretKeyTrait, Tag};
 
 pub fn decrypt_session_key<F>(
     locked_key: &(impl SecretKeyTrait + KeyTrait),
@@ -18,46 +19,37 @@ pub fn decrypt_session_key<F>(
 where
     F: FnOnce() -> String,
 {
-    debug!("decrypting session key");
+    let _span = debug_span!("decrypt_session_key", key_id = ?locked_key.key_id()).entered();
 
     let mut key: Vec<u8> = Vec::new();
     let mut alg: Option<SymmetricKeyAlgorithm> = None;
     locked_key.unlock(key_pw, |priv_key| {
-        let decrypted_key = match *priv_key {
-            SecretKeyRepr::RSA(ref priv_key) => {
-                rsa::decrypt(priv_key, mpis, &locked_key.fingerprint())?
-            }
+        let mut decrypted_key = match *priv_key {
+            SecretKeyRepr::RSA(ref priv_key) => rsa::decrypt(priv_key, mpis)?,
             SecretKeyRepr::DSA(_) => bail!("DSA is only used for signing"),
-            SecretKeyRepr::ECDSA => bail!("ECDSA is only used for signing"),
+            SecretKeyRepr::ECDSA(_) => bail!("ECDSA is only used for signing"),
             SecretKeyRepr::ECDH(ref priv_key) => {
                 ecdh::decrypt(priv_key, mpis, &locked_key.fingerprint())?
             }
             SecretKeyRepr::EdDSA(_) => unimplemented_err!("EdDSA"),
         };
-        let algorithm = SymmetricKeyAlgorithm::from_u8(decrypted_key[0])
-            .ok_or_else(|| format_err!("invalid symmetric key algorithm"))?;
-        alg = Some(algorithm);
-        debug!("alg: {:?}", alg);
-
-        let (k, checksum) = match *priv_key {
-            SecretKeyRepr::ECDH(_) => {
-                let dec_len = decrypted_key.len();
-                (
-                    &decrypted_key[1..dec_len - 2],
-                    &decrypted_key[dec_len - 2..],
-                )
-            }
-            _ => {
-                let key_size = algorithm.key_size();
-                (
-                    &decrypted_key[1..=key_size],
-                    &decrypted_key[key_size + 1..key_size + 3],
-                )
-            }
+        let is_ecdh = match *priv_key {
+            SecretKeyRepr::ECDH(_) => true,
+            _ => false,
         };
 
-        key = k.to_vec();
-        checksum::simple(checksum, k)?;
+        // all failures result in the same error, to not give away why a session key
+        // was rejected
+        let res = split_session_key(&decrypted_key, is_ecdh).and_then(|(a, k, checksum)| {
+            checksum::simple(checksum, k).ok()?;
+            Some((a, k.to_vec()))
+        });
+        decrypted_key.zeroize();
+
+        let (algorithm, k) = res.ok_or_else(|| format_err!("invalid session key"))?;
+        alg = Some(algorithm);
+        debug!("alg: {:?}", alg);
+        key = k;
 
         Ok(())
     })?;
@@ -65,6 +57,29 @@ where
     Ok((key, alg.expect("failed to unlock")))
 }
 
+/// Splits a decrypted session key into the symmetric algorithm, the key and its checksum.
+/// For ECDH the size of the key is given by the padding, otherwise by the algorithm.
+fn split_session_key(
+    decrypted_key: &[u8],
+    is_ecdh: bool,
+) -> Option<(SymmetricKeyAlgorithm, &[u8], &[u8])> {
+    let algorithm = SymmetricKeyAlgorithm::from_u8(*decrypted_key.first()?)?;
+    let key_size = if is_ecdh {
+        decrypted_key.len().checked_sub(3)?
+    } else {
+        algorithm.key_size()
+    };
+    if decrypted_key.len() < key_size + 3 {
+        return None;
+    }
+
+    Some((
+        algorithm,
+        &decrypted_key[1..=key_size],
+        &decrypted_key[key_size + 1..key_size + 3],
+    ))
+}
+
 pub fn decrypt_session_key_with_password<F>(
     packet: &SymKeyEncryptedSessionKey,
     msg_pw: F,
@@ -72,7 +87,7 @@ pub fn decrypt_session_key_with_password<F>(
 where
     F: FnOnce() -> String,
 {
-    debug!("decrypting session key");
+    let _span = debug_span!("decrypt_session_key_with_password").entered();
 
     let key = packet
         .s2k()
@@ -103,7 +118,13 @@ pub struct MessageDecrypter<'a> {
     // position in the edata slice
     pos: usize,
     // the current msgs that are already decrypted
-    current_msgs: Option<Box<dyn Iterator<Item = Result<Message>>>>,
+    current_msgs: Option<Box<dyn Iterator<Item = Result<Message>> + Send>>,
+    // decrypt packets without a modification detection code
+    allow_unprotected: bool,
+    // restricts the symmetric algorithms, if set
+    policy: Option<Policy>,
+    // scratch buffer for decrypting the packets, reused across packets
+    buf: Vec<u8>,
 }
 
 impl<'a> MessageDecrypter<'a> {
@@ -114,8 +135,33 @@ impl<'a> MessageDecrypter<'a> {
             edata,
             pos: 0,
             current_msgs: None,
+            allow_unprotected: false,
+            policy: None,
+            buf: Vec::new(),
         }
     }
+
+    /// Allows decrypting legacy `SymEncryptedData` packets.
+    ///
+    /// These are not integrity protected, so modifications by an attacker go unnoticed, and
+    /// are refused with `Error::MissingMdc` by default.
+    pub fn allow_unprotected(mut self, allow: bool) -> Self {
+        self.allow_unprotected = allow;
+        self
+    }
+
+    /// Refuses to decrypt packets using symmetric algorithms, that `policy` rejects.
+    /// Unprotected packets are decrypted, if the policy does not require a MDC.
+    pub fn with_policy(mut self, policy: &Policy) -> Self {
+        self.allow_unprotected = !policy.requires_mdc();
+        self.policy = Some(policy.clone());
+        self
+    }
+}
+
+/// Parses the messages contained in a decrypted packet.
+fn parse_messages(plaintext: &[u8]) -> Vec<Result<Message>> {
+    MessageParser::new(PacketParser::new(plaintext).filter_map(skip_invalid)).collect()
 }
 
 impl<'a> Iterator for MessageDecrypter<'a> {
@@ -131,20 +177,38 @@ impl<'a> Iterator for MessageDecrypter<'a> {
             let packet = &self.edata[self.pos];
             self.pos += 1;
 
-            let mut res = packet.data()[..].to_vec();
-            let protected = packet.tag() == Tag::SymEncryptedProtectedData;
-
-            debug!("decrypting protected = {:?}", protected);
+            let _span =
+                debug_span!("decrypt", tag = ?packet.tag(), length = packet.data().len()).entered();
 
-            let decrypted_packet: &[u8] = if protected {
-                err_opt!(self.alg.decrypt_protected(&self.key, &mut res))
+            let msgs = if let Edata::AeadEncryptedData(packet) = packet {
+                debug!("decrypting aead {:?}", packet.aead_algorithm());
+                if let Some(ref policy) = self.policy {
+                    err_opt!(policy.check_symmetric(packet.sym_algorithm()));
+                }
+                parse_messages(&err_opt!(packet.decrypt(&self.key)))
             } else {
-                err_opt!(self.alg.decrypt(&self.key, &mut res))
+                let protected = packet.tag() == Tag::SymEncryptedProtectedData;
+
+                debug!("decrypting protected = {:?}", protected);
+                if !protected && !self.allow_unprotected {
+                    return Some(Err(Error::MissingMdc));
+                }
+                if let Some(ref policy) = self.policy {
+                    err_opt!(policy.check_symmetric(self.alg));
+                }
+
+                self.buf.clear();
+                self.buf.extend_from_slice(packet.data());
+                let plaintext = if protected {
+                    err_opt!(self.alg.decrypt_protected(&self.key, &mut self.buf))
+                } else {
+                    err_opt!(self.alg.decrypt(&self.key, &mut self.buf))
+                };
+
+                parse_messages(&self.buf[plaintext])
             };
 
-            self.current_msgs = Some(Message::from_bytes_many(Cursor::new(
-                decrypted_packet.to_vec(),
-            )));
+            self.current_msgs = Some(Box::new(msgs.into_iter()));
         };
 
         let mut msgs = self.current_msgs.take().expect("just checked");
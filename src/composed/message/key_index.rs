@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use crate::composed::message::decrypt::decrypt_session_key;
+use crate::composed::signed_key::{SignedSecretKey, SignedSecretSubKey};
+use crate::crypto::{PublicKeyAlgorithm, SymmetricKeyAlgorithm};
+use crate::errors::Result;
+use crate::packet::SecretKey;
+use crate::types::{KeyId, KeyTrait, Mpi};
+
+/// An index of the primary keys and subkeys of a list of secret keys, by their key id.
+///
+/// `Message::decrypt` builds one for every call. When decrypting many messages with the same
+/// set of keys, build it once and use `Message::decrypt_with_index` instead.
+#[derive(Debug, Clone)]
+pub struct SecretKeyIndex<'a> {
+    keys: Vec<&'a SignedSecretKey>,
+    by_key_id: HashMap<KeyId, (usize, DecryptionKey<'a>)>,
+}
+
+/// A primary key or subkey, that can decrypt session keys.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum DecryptionKey<'a> {
+    Primary(&'a SecretKey),
+    Subkey(&'a SignedSecretSubKey),
+}
+
+impl<'a> SecretKeyIndex<'a> {
+    pub fn new(keys: &[&'a SignedSecretKey]) -> Self {
+        let mut by_key_id = HashMap::new();
+
+        for (i, key) in keys.iter().enumerate() {
+            by_key_id
+                .entry(key.primary_key.key_id())
+                .or_insert((i, DecryptionKey::Primary(&key.primary_key)));
+
+            for subkey in &key.secret_subkeys {
+                by_key_id
+                    .entry(subkey.key_id())
+                    .or_insert((i, DecryptionKey::Subkey(subkey)));
+            }
+        }
+
+        SecretKeyIndex {
+            keys: keys.to_vec(),
+            by_key_id,
+        }
+    }
+
+    /// The indexed keys.
+    pub fn keys(&self) -> &[&'a SignedSecretKey] {
+        &self.keys
+    }
+
+    /// Returns `true` if one of the keys has a primary key or subkey with the given key id.
+    pub fn contains_key_id(&self, key_id: &KeyId) -> bool {
+        self.by_key_id.contains_key(key_id)
+    }
+
+    /// Returns the position of the owning key in `keys`, and the matching primary key or
+    /// subkey.
+    pub(crate) fn get(&self, key_id: &KeyId) -> Option<(usize, DecryptionKey<'a>)> {
+        self.by_key_id.get(key_id).copied()
+    }
+
+    /// Returns all primary keys and subkeys with the given algorithm.
+    pub(crate) fn by_algorithm(&self, alg: PublicKeyAlgorithm) -> Vec<DecryptionKey<'a>> {
+        let mut res = Vec::new();
+        for key in &self.keys {
+            if key.primary_key.algorithm() == alg {
+                res.push(DecryptionKey::Primary(&key.primary_key));
+            }
+            for subkey in &key.secret_subkeys {
+                if subkey.algorithm() == alg {
+                    res.push(DecryptionKey::Subkey(subkey));
+                }
+            }
+        }
+
+        res
+    }
+}
+
+impl<'a> DecryptionKey<'a> {
+    pub fn key_id(&self) -> KeyId {
+        match self {
+            DecryptionKey::Primary(key) => key.key_id(),
+            DecryptionKey::Subkey(key) => key.key_id(),
+        }
+    }
+
+    pub fn decrypt_session_key<F>(
+        &self,
+        key_pw: F,
+        mpis: &[Mpi],
+    ) -> Result<(Vec<u8>, SymmetricKeyAlgorithm)>
+    where
+        F: FnOnce() -> String,
+    {
+        match self {
+            DecryptionKey::Primary(key) => decrypt_session_key(*key, key_pw, mpis),
+            DecryptionKey::Subkey(key) => decrypt_session_key(*key, key_pw, mpis),
+        }
+    }
+}
@@ -1,5 +1,11 @@
+mod builder;
 mod decrypt;
+mod key_index;
 mod parser;
 mod types;
 
+pub use self::builder::*;
+pub use self::decrypt::MessageDecrypter;
+pub use self::key_index::*;
+pub use self::parser::MAX_NESTING_DEPTH;
 pub use self::types::*;
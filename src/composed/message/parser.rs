@@ -1,23 +1,79 @@
 use std::boxed::Box;
+use std::io::Read;
 use std::iter::Peekable;
 
 use try_from::TryInto;
 
 use crate::composed::message::Message;
+use crate::composed::shared::skip_invalid;
 use crate::composed::Deserializable;
-use crate::errors::Result;
-use crate::packet::Packet;
+use crate::errors::{Error, Result};
+use crate::packet::{Packet, PacketParser};
 use crate::types::Tag;
 
+/// How deep signed and compressed messages may be nested by default, to limit the recursion
+/// when parsing and processing crafted messages.
+pub const MAX_NESTING_DEPTH: usize = 32;
+
+/// The number of levels a message may still be nested, out of the configured maximum.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Nesting {
+    max: usize,
+    left: usize,
+}
+
+impl Nesting {
+    pub(crate) fn new(max: usize) -> Self {
+        Nesting { max, left: max }
+    }
+
+    /// Enters a nested message, failing if the maximum depth is exceeded.
+    pub(crate) fn enter(self) -> Result<Self> {
+        if self.left == 0 {
+            return Err(Error::NestingTooDeep(self.max));
+        }
+
+        Ok(Nesting {
+            max: self.max,
+            left: self.left - 1,
+        })
+    }
+}
+
+impl Default for Nesting {
+    fn default() -> Self {
+        Nesting::new(MAX_NESTING_DEPTH)
+    }
+}
+
 pub struct MessageParser<I: Sized + Iterator<Item = Packet>> {
     source: Peekable<I>,
+    nesting: Nesting,
 }
 
-fn next<I: Iterator<Item = Packet>>(packets: &mut Peekable<I>) -> Option<Result<Message>> {
+impl<I: Sized + Iterator<Item = Packet>> MessageParser<I> {
+    pub(crate) fn new(source: I) -> Self {
+        MessageParser::with_nesting(source, Nesting::default())
+    }
+
+    /// Creates a parser, that fails on messages nested deeper than `nesting` allows.
+    pub(crate) fn with_nesting(source: I, nesting: Nesting) -> Self {
+        MessageParser {
+            source: source.peekable(),
+            nesting,
+        }
+    }
+}
+
+/// Parses the next message, which may contain as many nested levels as `nesting` allows.
+fn next<I: Iterator<Item = Packet>>(
+    packets: &mut Peekable<I>,
+    nesting: Nesting,
+) -> Option<Result<Message>> {
     while let Some(packet) = packets.by_ref().next() {
         // for packet in packets.by_ref() {
-        debug!("{:?}: ", packet);
         let tag = packet.tag();
+        debug!("{:?}", tag);
         match tag {
             Tag::LiteralData => {
                 return match packet.try_into() {
@@ -51,6 +107,7 @@ fn next<I: Iterator<Item = Packet>>(packets: &mut Peekable<I>) -> Option<Result<
                         while let Some(true) = packets.peek().map(|p| {
                             p.tag() == Tag::SymEncryptedData
                                 || p.tag() == Tag::SymEncryptedProtectedData
+                                || p.tag() == Tag::AeadEncryptedData
                         }) {
                             edata.push(packets.next().expect("peeked").try_into().expect("peeked"));
                         }
@@ -61,8 +118,9 @@ fn next<I: Iterator<Item = Packet>>(packets: &mut Peekable<I>) -> Option<Result<
                 };
             }
             //    Encrypted Data :- Symmetrically Encrypted Data Packet |
-            //          Symmetrically Encrypted Integrity Protected Data Packet
-            Tag::SymEncryptedData | Tag::SymEncryptedProtectedData => {
+            //          Symmetrically Encrypted Integrity Protected Data Packet |
+            //          AEAD Encrypted Data Packet
+            Tag::SymEncryptedData | Tag::SymEncryptedProtectedData | Tag::AeadEncryptedData => {
                 return match packet.try_into() {
                     Ok(p) => {
                         let esk = Vec::new();
@@ -72,6 +130,7 @@ fn next<I: Iterator<Item = Packet>>(packets: &mut Peekable<I>) -> Option<Result<
                         while let Some(true) = packets.peek().map(|p| {
                             p.tag() == Tag::SymEncryptedData
                                 || p.tag() == Tag::SymEncryptedProtectedData
+                                || p.tag() == Tag::AeadEncryptedData
                         }) {
                             edata.push(packets.next().expect("peeked").try_into().expect("peeked"));
                         }
@@ -84,7 +143,7 @@ fn next<I: Iterator<Item = Packet>>(packets: &mut Peekable<I>) -> Option<Result<
             Tag::Signature => {
                 return match packet.try_into() {
                     Ok(signature) => {
-                        let m = next(packets.by_ref());
+                        let m = next(packets.by_ref(), err_opt!(nesting.enter()));
                         let message = if let Some(Err(err)) = m {
                             return Some(Err(err));
                         } else {
@@ -104,7 +163,7 @@ fn next<I: Iterator<Item = Packet>>(packets: &mut Peekable<I>) -> Option<Result<
                 return match packet.try_into() {
                     Ok(p) => {
                         let one_pass_signature = Some(p);
-                        let m = next(packets.by_ref());
+                        let m = next(packets.by_ref(), err_opt!(nesting.enter()));
                         let message = if let Some(Err(err)) = m {
                             return Some(Err(err));
                         } else {
@@ -146,7 +205,24 @@ impl<I: Sized + Iterator<Item = Packet>> Iterator for MessageParser<I> {
     type Item = Result<Message>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        next(self.source.by_ref())
+        next(self.source.by_ref(), self.nesting)
+    }
+}
+
+impl Message {
+    /// Parse a single byte encoded message, whose signatures are nested at most `max_depth`
+    /// levels deep, instead of `MAX_NESTING_DEPTH`.
+    pub fn from_bytes_with_max_depth(bytes: impl Read, max_depth: usize) -> Result<Self> {
+        Message::from_bytes_nested(bytes, Nesting::new(max_depth))
+    }
+
+    /// Parse a single byte encoded message, that may be nested as deep as `nesting` allows.
+    pub(crate) fn from_bytes_nested(bytes: impl Read, nesting: Nesting) -> Result<Self> {
+        let packets = PacketParser::new(bytes).filter_map(skip_invalid);
+
+        MessageParser::with_nesting(packets, nesting)
+            .next()
+            .ok_or_else(|| Error::NoMatchingPacket)?
     }
 }
 
@@ -156,8 +232,6 @@ impl Deserializable for Message {
     fn from_packets<'a>(
         packets: impl Iterator<Item = Packet> + 'a,
     ) -> Box<dyn Iterator<Item = Result<Self>> + 'a> {
-        Box::new(MessageParser {
-            source: packets.peekable(),
-        })
+        Box::new(MessageParser::new(packets))
     }
 }
@@ -1,7 +1,11 @@
 use std::boxed::Box;
 use std::collections::BTreeMap;
-use std::io;
+use std::io::{self, Read};
+use std::iter;
+use std::time::Duration;
 
+#[cfg(feature = "bzip2")]
+use bzip2::write::BzEncoder;
 use chrono::{self, SubsecRound};
 use flate2::write::{DeflateEncoder, ZlibEncoder};
 use flate2::Compression;
@@ -11,20 +15,25 @@ use try_from::TryFrom;
 
 use crate::armor;
 use crate::composed::message::decrypt::*;
+use crate::composed::message::key_index::SecretKeyIndex;
+use crate::composed::message::parser::Nesting;
 use crate::composed::shared::Deserializable;
-use crate::composed::signed_key::SignedSecretKey;
+use crate::composed::signed_key::secret::signature_expiration_subpackets;
+use crate::composed::signed_key::validity::is_self_signature;
+use crate::composed::signed_key::{PublicComponentKey, SignedPublicKey, SignedSecretKey, Validity};
 use crate::composed::StandaloneSignature;
-use crate::crypto::{HashAlgorithm, SymmetricKeyAlgorithm};
-use crate::errors::{Error, Result};
+use crate::crypto::{AeadAlgorithm, HashAlgorithm, SymmetricKeyAlgorithm};
+use crate::errors::{Algorithm, Error, Result};
 use crate::packet::{
-    write_packet, CompressedData, LiteralData, OnePassSignature, Packet,
-    PublicKeyEncryptedSessionKey, Signature, SignatureConfig, SignatureType, Subpacket,
-    SymEncryptedData, SymEncryptedProtectedData, SymKeyEncryptedSessionKey,
+    write_packet, AeadEncryptedData, CompressedData, LiteralData, LiteralDataReader, Notation,
+    OnePassSignature, Packet, PublicKeyEncryptedSessionKey, RevocationCode, Signature,
+    SignatureConfig, SignatureType, Subpacket, SymEncryptedData, SymEncryptedProtectedData,
+    SymKeyEncryptedSessionKey, DEFAULT_AEAD_CHUNK_SIZE,
 };
 use crate::ser::Serialize;
 use crate::types::{
-    CompressionAlgorithm, KeyId, KeyTrait, KeyVersion, PublicKeyTrait, SecretKeyTrait, StringToKey,
-    Tag,
+    CompressionAlgorithm, KeyId, KeyTrait, KeyVersion, Policy, PublicKeyTrait, SecretKeyTrait,
+    StringToKey, Tag,
 };
 
 /// An [OpenPGP message](https://tools.ietf.org/html/rfc4880.html#section-11.3)
@@ -46,6 +55,26 @@ pub enum Message {
     },
 }
 
+/// The result of checking a single signature of a message, see `Message::verify_signatures`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationResult {
+    /// The signature is valid and was issued by the key with the given id.
+    Valid(KeyId),
+    /// The signature was issued by the key with the given id, but is not valid.
+    Invalid(KeyId),
+    /// None of the keys issued the signature, contains the issuer if it is known.
+    UnknownIssuer(Option<KeyId>),
+    /// The signature is valid, but the key with the given id, or its primary key, was revoked
+    /// when the signature was made. Hard revocations, e.g. of compromised keys, apply to
+    /// signatures made at any time.
+    Revoked {
+        key_id: KeyId,
+        code: Option<RevocationCode>,
+        reason: Option<String>,
+        revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+    },
+}
+
 /// Encrypted Session Key
 ///
 /// Public-Key Encrypted Session Key Packet |
@@ -103,11 +132,13 @@ impl From<Esk> for Packet {
 
 /// Encrypted Data
 /// Symmetrically Encrypted Data Packet |
-/// Symmetrically Encrypted Integrity Protected Data Packet
+/// Symmetrically Encrypted Integrity Protected Data Packet |
+/// AEAD Encrypted Data Packet
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Edata {
     SymEncryptedData(SymEncryptedData),
     SymEncryptedProtectedData(SymEncryptedProtectedData),
+    AeadEncryptedData(AeadEncryptedData),
 }
 
 impl Serialize for Edata {
@@ -115,6 +146,7 @@ impl Serialize for Edata {
         match self {
             Edata::SymEncryptedData(d) => write_packet(writer, d),
             Edata::SymEncryptedProtectedData(d) => write_packet(writer, d),
+            Edata::AeadEncryptedData(d) => write_packet(writer, d),
         }
     }
 }
@@ -122,7 +154,8 @@ impl Serialize for Edata {
 impl_try_from_into!(
     Edata,
     SymEncryptedData => SymEncryptedData,
-    SymEncryptedProtectedData => SymEncryptedProtectedData
+    SymEncryptedProtectedData => SymEncryptedProtectedData,
+    AeadEncryptedData => AeadEncryptedData
 );
 
 impl TryFrom<Packet> for Edata {
@@ -132,6 +165,7 @@ impl TryFrom<Packet> for Edata {
         match other {
             Packet::SymEncryptedData(d) => Ok(Edata::SymEncryptedData(d)),
             Packet::SymEncryptedProtectedData(d) => Ok(Edata::SymEncryptedProtectedData(d)),
+            Packet::AeadEncryptedData(d) => Ok(Edata::AeadEncryptedData(d)),
             _ => Err(format_err!("not a valid edata packet: {:?}", other)),
         }
     }
@@ -142,6 +176,7 @@ impl From<Edata> for Packet {
         match other {
             Edata::SymEncryptedData(d) => Packet::SymEncryptedData(d),
             Edata::SymEncryptedProtectedData(d) => Packet::SymEncryptedProtectedData(d),
+            Edata::AeadEncryptedData(d) => Packet::AeadEncryptedData(d),
         }
     }
 }
@@ -151,6 +186,7 @@ impl Edata {
         match self {
             Edata::SymEncryptedData(d) => d.data(),
             Edata::SymEncryptedProtectedData(d) => d.data(),
+            Edata::AeadEncryptedData(d) => d.data(),
         }
     }
 
@@ -158,6 +194,7 @@ impl Edata {
         match self {
             Edata::SymEncryptedData(_) => Tag::SymEncryptedData,
             Edata::SymEncryptedProtectedData(_) => Tag::SymEncryptedProtectedData,
+            Edata::AeadEncryptedData(_) => Tag::AeadEncryptedData,
         }
     }
 }
@@ -209,6 +246,16 @@ impl Message {
 
     /// Compresses the message.
     pub fn compress(&self, alg: CompressionAlgorithm) -> Result<Self> {
+        self.compress_with_level(alg, Compression::default().level())
+    }
+
+    /// Compresses the message, using the given compression level.
+    ///
+    /// The level ranges from `0`, no compression, to `9`, best compression, and is ignored
+    /// for uncompressed data.
+    pub fn compress_with_level(&self, alg: CompressionAlgorithm, level: u32) -> Result<Self> {
+        ensure!(level <= 9, "invalid compression level {}", level);
+
         let data = match alg {
             CompressionAlgorithm::Uncompressed => {
                 let mut data = Vec::new();
@@ -216,17 +263,28 @@ impl Message {
                 data
             }
             CompressionAlgorithm::ZIP => {
-                let mut enc = DeflateEncoder::new(Vec::new(), Compression::default());
+                let mut enc = DeflateEncoder::new(Vec::new(), Compression::new(level));
                 self.to_writer(&mut enc)?;
                 enc.finish()?
             }
             CompressionAlgorithm::ZLIB => {
-                let mut enc = ZlibEncoder::new(Vec::new(), Compression::default());
+                let mut enc = ZlibEncoder::new(Vec::new(), Compression::new(level));
+                self.to_writer(&mut enc)?;
+                enc.finish()?
+            }
+            #[cfg(feature = "bzip2")]
+            CompressionAlgorithm::BZip2 => {
+                let mut enc = BzEncoder::new(Vec::new(), bzip2::Compression::new(level.max(1)));
                 self.to_writer(&mut enc)?;
                 enc.finish()?
             }
-            CompressionAlgorithm::BZip2 => unimplemented_err!("BZip2"),
-            CompressionAlgorithm::Private10 => unsupported_err!("Private10 should not be used"),
+            #[cfg(not(feature = "bzip2"))]
+            CompressionAlgorithm::BZip2 => {
+                return Err(Error::UnsupportedAlgorithm(Algorithm::Compression(alg)));
+            }
+            CompressionAlgorithm::Private10 => {
+                return Err(Error::UnsupportedAlgorithm(Algorithm::Compression(alg)));
+            }
         };
 
         Ok(Message::Compressed(CompressedData::from_compressed(
@@ -248,6 +306,21 @@ impl Message {
         rng: &mut R,
         alg: SymmetricKeyAlgorithm,
         pkeys: &[&impl PublicKeyTrait],
+    ) -> Result<Self> {
+        self.encrypt_to_keys_aead(rng, alg, AeadAlgorithm::None, pkeys)
+    }
+
+    /// Encrypt the message to the list of passed in public keys, using an AEAD Encrypted Data
+    /// Packet with the given AEAD algorithm.
+    ///
+    /// `AeadAlgorithm::None` uses a Symmetrically Encrypted Integrity Protected Data Packet
+    /// instead.
+    pub fn encrypt_to_keys_aead<R: CryptoRng + Rng>(
+        &self,
+        rng: &mut R,
+        alg: SymmetricKeyAlgorithm,
+        aead: AeadAlgorithm,
+        pkeys: &[&impl PublicKeyTrait],
     ) -> Result<Self> {
         // 1. Generate a session key.
         let session_key = alg.new_session_key(rng);
@@ -263,7 +336,33 @@ impl Message {
             .collect::<Result<_>>()?;
 
         // 3. Encrypt (sym) the data using the session key.
-        self.encrypt_symmetric(rng, esk, alg, session_key)
+        self.encrypt_symmetric(rng, esk, alg, aead, session_key)
+    }
+
+    /// Encrypt the message to the list of recipients.
+    ///
+    /// For each recipient the key returned by `SignedPublicKey::encryption_key` is used,
+    /// fails if a recipient has no usable encryption key.
+    ///
+    /// If all recipients prefer a common AEAD algorithm, that can be used with `alg`, the
+    /// data is encrypted using AEAD, otherwise integrity protected CFB mode is used.
+    pub fn encrypt_to_recipients<R: CryptoRng + Rng>(
+        &self,
+        rng: &mut R,
+        alg: SymmetricKeyAlgorithm,
+        recipients: &[&SignedPublicKey],
+    ) -> Result<Self> {
+        let pkeys = recipients
+            .iter()
+            .map(|key| {
+                key.encryption_key()
+                    .ok_or_else(|| format_err!("no encryption key found for {:?}", key.key_id()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let pkeys: Vec<_> = pkeys.iter().collect();
+        let aead = negotiate_aead(alg, recipients);
+
+        self.encrypt_to_keys_aead(rng, alg, aead, &pkeys)
     }
 
     /// Encrytp the message using the given password.
@@ -274,6 +373,26 @@ impl Message {
         alg: SymmetricKeyAlgorithm,
         msg_pw: F,
     ) -> Result<Self>
+    where
+        R: Rng + CryptoRng,
+        F: FnOnce() -> String + Clone,
+    {
+        self.encrypt_with_password_aead(rng, s2k, alg, AeadAlgorithm::None, msg_pw)
+    }
+
+    /// Encrypt the message using the given password, using an AEAD Encrypted Data Packet with
+    /// the given AEAD algorithm.
+    ///
+    /// `AeadAlgorithm::None` uses a Symmetrically Encrypted Integrity Protected Data Packet
+    /// instead.
+    pub fn encrypt_with_password_aead<R, F>(
+        &self,
+        rng: &mut R,
+        s2k: StringToKey,
+        alg: SymmetricKeyAlgorithm,
+        aead: AeadAlgorithm,
+        msg_pw: F,
+    ) -> Result<Self>
     where
         R: Rng + CryptoRng,
         F: FnOnce() -> String + Clone,
@@ -290,22 +409,34 @@ impl Message {
         )?);
 
         // 3. Encrypt (sym) the data using the session key.
-        self.encrypt_symmetric(rng, vec![skesk], alg, session_key)
+        self.encrypt_symmetric(rng, vec![skesk], alg, aead, session_key)
     }
 
     /// Symmetrically encrypts oneself using the provided `session_key`.
-    fn encrypt_symmetric<R: CryptoRng + Rng>(
+    pub(crate) fn encrypt_symmetric<R: CryptoRng + Rng>(
         &self,
         rng: &mut R,
         esk: Vec<Esk>,
         alg: SymmetricKeyAlgorithm,
+        aead: AeadAlgorithm,
         session_key: Vec<u8>,
     ) -> Result<Self> {
         let data = self.to_bytes()?;
 
-        let edata = vec![Edata::SymEncryptedProtectedData(
-            SymEncryptedProtectedData::encrypt_with_rng(rng, alg, &session_key, &data)?,
-        )];
+        let edata = if aead == AeadAlgorithm::None {
+            vec![Edata::SymEncryptedProtectedData(
+                SymEncryptedProtectedData::encrypt_with_rng(rng, alg, &session_key, &data)?,
+            )]
+        } else {
+            vec![Edata::AeadEncryptedData(AeadEncryptedData::encrypt_with_rng(
+                rng,
+                alg,
+                aead,
+                DEFAULT_AEAD_CHUNK_SIZE,
+                &session_key,
+                &data,
+            )?)]
+        };
 
         Ok(Message::Encrypted { esk, edata })
     }
@@ -317,20 +448,189 @@ impl Message {
         key_pw: F,
         hash_algorithm: HashAlgorithm,
     ) -> Result<Self>
+    where
+        F: FnOnce() -> String,
+    {
+        self.sign_at(key, key_pw, hash_algorithm, chrono::Utc::now())
+    }
+
+    /// Sign this message using the provided key, with `created_at` as signature creation time.
+    pub fn sign_at<F>(
+        self,
+        key: &impl SecretKeyTrait,
+        key_pw: F,
+        hash_algorithm: HashAlgorithm,
+        created_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Self>
+    where
+        F: FnOnce() -> String,
+    {
+        self.sign_at_with_notations(key, key_pw, hash_algorithm, created_at, &[])
+    }
+
+    /// Sign this message using the provided key, adding the notations to the hashed area of
+    /// the signature.
+    pub fn sign_with_notations<F>(
+        self,
+        key: &impl SecretKeyTrait,
+        key_pw: F,
+        hash_algorithm: HashAlgorithm,
+        notations: &[Notation],
+    ) -> Result<Self>
+    where
+        F: FnOnce() -> String,
+    {
+        self.sign_at_with_notations(key, key_pw, hash_algorithm, chrono::Utc::now(), notations)
+    }
+
+    /// Sign this message using the provided key, with a signature that expires
+    /// `signature_expiration` after its creation.
+    pub fn sign_with_expiration<F>(
+        self,
+        key: &impl SecretKeyTrait,
+        key_pw: F,
+        hash_algorithm: HashAlgorithm,
+        signature_expiration: Duration,
+    ) -> Result<Self>
+    where
+        F: FnOnce() -> String,
+    {
+        let subpackets = signature_expiration_subpackets(Some(signature_expiration))?;
+        self.sign_with_subpackets(key, key_pw, hash_algorithm, chrono::Utc::now(), subpackets)
+    }
+
+    /// Sign this message using the provided key, with `created_at` as signature creation time,
+    /// adding the notations to the hashed area of the signature.
+    pub fn sign_at_with_notations<F>(
+        self,
+        key: &impl SecretKeyTrait,
+        key_pw: F,
+        hash_algorithm: HashAlgorithm,
+        created_at: chrono::DateTime<chrono::Utc>,
+        notations: &[Notation],
+    ) -> Result<Self>
+    where
+        F: FnOnce() -> String,
+    {
+        let subpackets = notations.iter().cloned().map(Subpacket::Notation).collect();
+        self.sign_with_subpackets(key, key_pw, hash_algorithm, created_at, subpackets)
+    }
+
+    /// Sign this message using the provided key, with `created_at` as signature creation time,
+    /// adding `subpackets` to the hashed area of the signature.
+    pub(crate) fn sign_with_subpackets<F>(
+        self,
+        key: &impl SecretKeyTrait,
+        key_pw: F,
+        hash_algorithm: HashAlgorithm,
+        created_at: chrono::DateTime<chrono::Utc>,
+        subpackets: Vec<Subpacket>,
+    ) -> Result<Self>
+    where
+        F: FnOnce() -> String,
+    {
+        let (ops, signature) = self.create_signature(
+            key,
+            key_pw,
+            hash_algorithm,
+            created_at,
+            subpackets,
+            Nesting::default(),
+        )?;
+
+        Ok(Message::Signed {
+            message: Some(Box::new(self)),
+            one_pass_signature: Some(ops),
+            signature,
+        })
+    }
+
+    /// Adds a signature by another key to this one pass signed message.
+    ///
+    /// The new signature covers the same data as the existing ones, instead of signing them,
+    /// so that all signatures can be verified independently of each other.
+    pub fn add_signature<F>(
+        self,
+        key: &impl SecretKeyTrait,
+        key_pw: F,
+        hash_algorithm: HashAlgorithm,
+    ) -> Result<Self>
+    where
+        F: FnOnce() -> String,
+    {
+        self.add_signature_with_subpackets(
+            key,
+            key_pw,
+            hash_algorithm,
+            chrono::Utc::now(),
+            Vec::new(),
+        )
+    }
+
+    /// Adds a signature by another key to this one pass signed message, with `created_at` as
+    /// signature creation time, adding `subpackets` to the hashed area of the signature.
+    pub(crate) fn add_signature_with_subpackets<F>(
+        self,
+        key: &impl SecretKeyTrait,
+        key_pw: F,
+        hash_algorithm: HashAlgorithm,
+        created_at: chrono::DateTime<chrono::Utc>,
+        subpackets: Vec<Subpacket>,
+    ) -> Result<Self>
+    where
+        F: FnOnce() -> String,
+    {
+        let (ops, signature) = match self {
+            Message::Signed {
+                message: Some(ref message),
+                one_pass_signature: Some(ref ops),
+                ..
+            } => message.covered_by(ops)?.create_signature(
+                key,
+                key_pw,
+                hash_algorithm,
+                created_at,
+                subpackets,
+                Nesting::default().enter()?,
+            )?,
+            _ => bail!("only one pass signed messages can have additional signatures"),
+        };
+
+        Ok(Message::Signed {
+            message: Some(Box::new(self)),
+            one_pass_signature: Some(ops.with_last(false)),
+            signature,
+        })
+    }
+
+    /// Creates a signature over this message, and the matching one pass signature.
+    fn create_signature<F>(
+        &self,
+        key: &impl SecretKeyTrait,
+        key_pw: F,
+        hash_algorithm: HashAlgorithm,
+        created_at: chrono::DateTime<chrono::Utc>,
+        subpackets: Vec<Subpacket>,
+        nesting: Nesting,
+    ) -> Result<(OnePassSignature, Signature)>
     where
         F: FnOnce() -> String,
     {
         let key_id = key.key_id();
         let algorithm = key.algorithm();
-        let hashed_subpackets = vec![
-            Subpacket::IssuerFingerprint(KeyVersion::V4, SmallVec::from_slice(&key.fingerprint())),
-            Subpacket::SignatureCreationTime(chrono::Utc::now().trunc_subsecs(0)),
+        let mut hashed_subpackets = vec![
+            Subpacket::IssuerFingerprint(
+                KeyVersion::V4,
+                SmallVec::from_slice(key.fingerprint().as_bytes()),
+            ),
+            Subpacket::SignatureCreationTime(created_at.trunc_subsecs(0)),
         ];
-        let unhashed_subpackets = vec![Subpacket::Issuer(key_id.clone())];
+        hashed_subpackets.extend(subpackets);
+        let unhashed_subpackets = vec![Subpacket::Issuer(key_id)];
 
-        let (typ, signature) = match self {
-            Message::Literal(ref l) => {
-                let typ = if l.is_binary() {
+        let (typ, signature) = match self.signed_literal(nesting)? {
+            Some((binary, data)) => {
+                let typ = if binary {
                     SignatureType::Binary
                 } else {
                     SignatureType::Text
@@ -344,9 +644,9 @@ impl Message {
                     hashed_subpackets,
                     unhashed_subpackets,
                 );
-                (typ, signature_config.sign(key, key_pw, l.data())?)
+                (typ, signature_config.sign(key, key_pw, data)?)
             }
-            _ => {
+            None => {
                 let typ = SignatureType::Binary;
                 let signature_config = SignatureConfig::new_v4(
                     Default::default(),
@@ -366,11 +666,69 @@ impl Message {
         };
         let ops = OnePassSignature::from_details(typ, hash_algorithm, algorithm, key_id);
 
-        Ok(Message::Signed {
-            message: Some(Box::new(self)),
-            one_pass_signature: Some(ops),
-            signature,
-        })
+        Ok((ops, signature))
+    }
+
+    /// Returns the message covered by a signature with the given one pass signature, when this
+    /// message is the one it encloses.
+    ///
+    /// One pass signatures that are not marked as last are grouped with the following one, and
+    /// cover the same data.
+    fn covered_by<'a>(&'a self, ops: &OnePassSignature) -> Result<&'a Message> {
+        if ops.is_last() {
+            return Ok(self);
+        }
+
+        match self {
+            Message::Signed {
+                message: Some(message),
+                one_pass_signature: Some(ops),
+                ..
+            } => message.covered_by(ops),
+            _ => bail!("missing grouped one pass signature"),
+        }
+    }
+
+    /// Returns the message covered by a signature enclosing this message, which may or may not
+    /// have a one pass signature.
+    fn covered_by_signature<'a>(&'a self, ops: Option<&OnePassSignature>) -> Result<&'a Message> {
+        match ops {
+            Some(ops) => self.covered_by(ops),
+            None => Ok(self),
+        }
+    }
+
+    /// Returns a reader over the literal data, that a signature over this message covers, and
+    /// whether it is binary.
+    ///
+    /// Compressed messages are decompressed, as signatures are always calculated over the
+    /// contained literal data, so that they can be verified in a single pass.
+    fn signed_literal(&self, nesting: Nesting) -> Result<Option<(bool, Box<dyn io::Read + '_>)>> {
+        match self {
+            Message::Literal(data) => Ok(Some((data.is_binary(), Box::new(data.data())))),
+            Message::Compressed(data) => {
+                let nesting = nesting.enter()?;
+
+                // hash the literal data while decompressing it, without holding it in memory
+                if let Ok(reader) = LiteralDataReader::from_literal_packet(data.decompress()?) {
+                    return Ok(Some((reader.is_binary(), Box::new(reader))));
+                }
+
+                // anything else, like nested compression, is parsed
+                let msg = Message::from_bytes_nested(data.decompress()?, nesting)?;
+                let (binary, content) = match msg.signed_literal(nesting)? {
+                    Some((binary, mut reader)) => {
+                        let mut content = Vec::new();
+                        reader.read_to_end(&mut content)?;
+                        (binary, content)
+                    }
+                    None => return Ok(None),
+                };
+
+                Ok(Some((binary, Box::new(io::Cursor::new(content)))))
+            }
+            _ => Ok(None),
+        }
     }
 
     /// Convert the message to a standalone signature according to the cleartext framework.
@@ -384,27 +742,60 @@ impl Message {
     /// Verify this message.
     /// For signed messages this verifies the signature and for compressed messages
     /// they are decompressed and checked for signatures to verify.
+    ///
+    /// Only the outermost signature is checked, using `key` itself. Use `verify_signatures`
+    /// to check all signatures against signed keys, including their signing subkeys.
     pub fn verify(&self, key: &impl PublicKeyTrait) -> Result<()> {
+        self.verify_checked(key, &|_| Ok(()), Nesting::default())
+    }
+
+    /// Verify this message like `verify`, rejecting signatures that `policy` does not accept.
+    ///
+    /// The size of `key` is not checked, use `SignedPublicKey::verify_with_policy` for that.
+    pub fn verify_with_policy(&self, policy: &Policy, key: &impl PublicKeyTrait) -> Result<()> {
+        self.verify_checked(key, &|sig| sig.check_policy(policy), Nesting::default())
+    }
+
+    /// Verify this message like `verify`, rejecting signatures that are expired at `time`.
+    ///
+    /// The expiration of `key` is not checked, use `SignedPublicKey::verify_at` for that.
+    pub fn verify_at(
+        &self,
+        key: &impl PublicKeyTrait,
+        time: &chrono::DateTime<chrono::Utc>,
+    ) -> Result<()> {
+        self.verify_checked(key, &|sig| sig.check_expiration(time), Nesting::default())
+    }
+
+    /// Verifies the outermost signature, after running `check` on it.
+    fn verify_checked<F>(
+        &self,
+        key: &impl PublicKeyTrait,
+        check: &F,
+        nesting: Nesting,
+    ) -> Result<()>
+    where
+        F: Fn(&Signature) -> Result<()>,
+    {
         match self {
             Message::Signed {
-                signature, message, ..
+                signature,
+                message,
+                one_pass_signature,
             } => {
                 if let Some(message) = message {
-                    match **message {
-                        Message::Literal(ref data) => signature.verify(key, data.data()),
-                        _ => {
-                            let data = &message.to_bytes()?;
-                            let cursor = io::Cursor::new(data);
-                            signature.verify(key, cursor)
-                        }
-                    }
+                    check(signature)?;
+                    message
+                        .covered_by_signature(one_pass_signature.as_ref())?
+                        .verify_signature(signature, key, nesting.enter()?)
                 } else {
                     unimplemented_err!("no message, what to do?");
                 }
             }
             Message::Compressed(data) => {
-                let msg = Message::from_bytes(data.decompress()?)?;
-                msg.verify(key)
+                let nesting = nesting.enter()?;
+                let msg = Message::from_bytes_nested(data.decompress()?, nesting)?;
+                msg.verify_checked(key, check, nesting)
             }
             // Nothing to do for others.
             // TODO: should this return an error?
@@ -412,6 +803,92 @@ impl Message {
         }
     }
 
+    /// Verifies `signature` over this message, using `key`.
+    fn verify_signature(
+        &self,
+        signature: &Signature,
+        key: &impl PublicKeyTrait,
+        nesting: Nesting,
+    ) -> Result<()> {
+        match self.signed_literal(nesting)? {
+            Some((_, data)) => signature.verify(key, data),
+            None => {
+                let data = &self.to_bytes()?;
+                let cursor = io::Cursor::new(data);
+                signature.verify(key, cursor)
+            }
+        }
+    }
+
+    /// Checks all signatures of this message, including nested ones, against the given keys.
+    ///
+    /// Returns one result per signature, starting with the outermost. Compressed messages are
+    /// decompressed, other messages have no signatures. Signatures by keys, that were revoked
+    /// at the time of signing, are reported as `VerificationResult::Revoked`.
+    pub fn verify_signatures(&self, keys: &[&SignedPublicKey]) -> Result<Vec<VerificationResult>> {
+        self.verify_signatures_nested(keys, Nesting::default())
+    }
+
+    fn verify_signatures_nested(
+        &self,
+        keys: &[&SignedPublicKey],
+        nesting: Nesting,
+    ) -> Result<Vec<VerificationResult>> {
+        match self {
+            Message::Signed {
+                signature,
+                message,
+                one_pass_signature,
+            } => {
+                let message = match message {
+                    Some(message) => message,
+                    None => unimplemented_err!("no message, what to do?"),
+                };
+
+                let nesting = nesting.enter()?;
+                let covered = message.covered_by_signature(one_pass_signature.as_ref())?;
+                let mut results = vec![covered.verification_result(signature, keys, nesting)?];
+                results.extend(message.verify_signatures_nested(keys, nesting)?);
+
+                Ok(results)
+            }
+            Message::Compressed(data) => {
+                let nesting = nesting.enter()?;
+                let msg = Message::from_bytes_nested(data.decompress()?, nesting)?;
+                msg.verify_signatures_nested(keys, nesting)
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Searches the keys for the issuer of `signature` and verifies it over this message.
+    fn verification_result(
+        &self,
+        signature: &Signature,
+        keys: &[&SignedPublicKey],
+        nesting: Nesting,
+    ) -> Result<VerificationResult> {
+        let candidates = keys.iter().flat_map(|signed_key| {
+            iter::once(PublicComponentKey::Primary(&signed_key.primary_key))
+                .chain(signed_key.public_subkeys.iter().map(PublicComponentKey::Subkey))
+                .map(move |key| (*signed_key, key))
+        });
+
+        let mut issuer = None;
+        for (signed_key, key) in candidates.filter(|(_, key)| is_self_signature(signature, key)) {
+            if self.verify_signature(signature, &key, nesting).is_ok() {
+                return Ok(revocation_result(signed_key, &key, signature)
+                    .unwrap_or_else(|| VerificationResult::Valid(key.key_id())));
+            }
+            issuer = Some(key.key_id());
+        }
+
+        Ok(match issuer {
+            Some(key_id) => VerificationResult::Invalid(key_id),
+            None => VerificationResult::UnknownIssuer(signature.issuer().cloned()),
+        })
+    }
+
     /// Returns a list of [KeyId]s that the message is encrypted to. For non encrypted messages this list is empty.
     pub fn get_recipients(&self) -> Vec<&KeyId> {
         match self {
@@ -428,12 +905,31 @@ impl Message {
 
     /// Decrypt the message using the given key.
     /// Returns a message decrypter, and a list of [KeyId]s that are valid recipients of this message.
+    ///
+    /// If none of the keys is a recipient, but the message is password protected, it is
+    /// decrypted using the password returned by `msg_pw`, and the list of [KeyId]s is empty.
     pub fn decrypt<'a, F, G>(
         &'a self,
-        msg_pw: F, // TODO: remove
+        msg_pw: F,
         key_pw: G,
         keys: &[&SignedSecretKey],
     ) -> Result<(MessageDecrypter<'a>, Vec<KeyId>)>
+    where
+        F: FnOnce() -> String + Clone,
+        G: FnOnce() -> String + Clone,
+    {
+        self.decrypt_with_index(msg_pw, key_pw, &SecretKeyIndex::new(keys))
+    }
+
+    /// Decrypt the message using the keys in the given index, see `decrypt`.
+    ///
+    /// The index can be reused, to decrypt many messages with a large set of keys.
+    pub fn decrypt_with_index<'a, F, G>(
+        &'a self,
+        msg_pw: F,
+        key_pw: G,
+        index: &SecretKeyIndex<'_>,
+    ) -> Result<(MessageDecrypter<'a>, Vec<KeyId>)>
     where
         F: FnOnce() -> String + Clone,
         G: FnOnce() -> String + Clone,
@@ -443,82 +939,58 @@ impl Message {
                 bail!("not encrypted");
             }
             Message::Signed { message, .. } => match message {
-                Some(message) => message.as_ref().decrypt(msg_pw, key_pw, keys),
+                Some(message) => message.as_ref().decrypt_with_index(msg_pw, key_pw, index),
                 None => bail!("not encrypted"),
             },
             Message::Encrypted { esk, edata, .. } => {
-                let valid_keys = keys
-                    .iter()
-                    .filter_map(|key| {
-                        // search for a packet with a key id that we have and that key.
-                        let mut packet = None;
-                        let mut encoding_key = None;
-                        let mut encoding_subkey = None;
-
-                        for esk_packet in esk.iter().filter_map(|k| match k {
-                            Esk::PublicKeyEncryptedSessionKey(k) => Some(k),
-                            _ => None,
-                        }) {
-                            debug!("esk packet: {:?}", esk_packet);
-                            debug!("{:?}", key.key_id());
-                            debug!(
-                                "{:?}",
-                                key.secret_subkeys
-                                    .iter()
-                                    .map(KeyTrait::key_id)
-                                    .collect::<Vec<_>>()
-                            );
-
-                            // find the key with the matching key id
-
-                            if &key.primary_key.key_id() == esk_packet.id() {
-                                encoding_key = Some(&key.primary_key);
-                            }
-
-                            if encoding_key.is_none() {
-                                encoding_subkey = key.secret_subkeys.iter().find_map(|subkey| {
-                                    if &subkey.key_id() == esk_packet.id() {
-                                        Some(subkey)
-                                    } else {
-                                        None
-                                    }
-                                });
-                            }
-
-                            if encoding_key.is_some() || encoding_subkey.is_some() {
-                                packet = Some(esk_packet);
-                                break;
-                            }
+                let _span = debug_span!("decrypt_message", esk = esk.len(), edata = edata.len())
+                    .entered();
+
+                let pkesks = esk.iter().filter_map(|k| match k {
+                    Esk::PublicKeyEncryptedSessionKey(k) => Some(k),
+                    _ => None,
+                });
+
+                // find the packets with a key id that we have, using only the first
+                // packet for each key
+                let mut used_keys = Vec::new();
+                let mut valid_keys = Vec::new();
+                for esk_packet in pkesks.clone() {
+                    debug!("esk packet for {:?}", esk_packet.id());
+                    if let Some((pos, key)) = index.get(esk_packet.id()) {
+                        if !used_keys.contains(&pos) {
+                            used_keys.push(pos);
+                            valid_keys.push((esk_packet, key));
                         }
+                    }
+                }
 
-                        if let Some(packet) = packet {
-                            Some((packet, encoding_key, encoding_subkey))
-                        } else {
-                            None
+                if valid_keys.is_empty() {
+                    // the recipients of packets with the wildcard key id are unknown, so all
+                    // keys with a matching algorithm are tried
+                    for esk_packet in pkesks.filter(|k| k.id().is_wildcard()) {
+                        for key in index.by_algorithm(esk_packet.algorithm()) {
+                            valid_keys.push((esk_packet, key));
                         }
-                    })
-                    .collect::<Vec<_>>();
+                    }
+                }
 
                 if valid_keys.is_empty() {
+                    // messages without a matching public key packet might be password protected
+                    if esk.iter().any(|e| e.tag() == Tag::SymKeyEncryptedSessionKey) {
+                        return Ok((self.decrypt_with_password(msg_pw)?, Vec::new()));
+                    }
+
                     return Err(Error::MissingKey);
                 }
 
                 let session_keys = valid_keys
                     .iter()
-                    .map(|(packet, encoding_key, encoding_subkey)| {
-                        if let Some(ek) = encoding_key {
-                            Ok((
-                                ek.key_id(),
-                                decrypt_session_key(ek, key_pw.clone(), packet.mpis())?,
-                            ))
-                        } else if let Some(ek) = encoding_subkey {
-                            Ok((
-                                ek.key_id(),
-                                decrypt_session_key(ek, key_pw.clone(), packet.mpis())?,
-                            ))
-                        } else {
-                            unreachable!("either a key or a subkey were found");
-                        }
+                    .map(|(packet, key)| {
+                        Ok((
+                            key.key_id(),
+                            key.decrypt_session_key(key_pw.clone(), packet.mpis())?,
+                        ))
                     })
                     .filter(|res| match res {
                         Ok(_) => true,
@@ -549,8 +1021,32 @@ impl Message {
         }
     }
 
-    /// Decrypt the message using the given key.
-    /// Returns a message decrypter, and a list of [KeyId]s that are valid recipients of this message.
+    /// Decrypt the message using the given keys, and verify the signatures of the decrypted
+    /// message against `verification_keys`.
+    ///
+    /// Returns the decrypted message, the results of `verify_signatures` and the list of
+    /// [KeyId]s that are valid recipients of this message.
+    pub fn decrypt_and_verify<F, G>(
+        &self,
+        msg_pw: F,
+        key_pw: G,
+        keys: &[&SignedSecretKey],
+        verification_keys: &[&SignedPublicKey],
+    ) -> Result<(Message, Vec<VerificationResult>, Vec<KeyId>)>
+    where
+        F: FnOnce() -> String + Clone,
+        G: FnOnce() -> String + Clone,
+    {
+        let (mut decrypter, ids) = self.decrypt(msg_pw, key_pw, keys)?;
+        let message = decrypter
+            .next()
+            .ok_or_else(|| format_err!("no message found"))??;
+        let results = message.verify_signatures(verification_keys)?;
+
+        Ok((message, results, ids))
+    }
+
+    /// Decrypt the message using the given password.
     pub fn decrypt_with_password<'a, F>(&'a self, msg_pw: F) -> Result<MessageDecrypter<'a>>
     where
         F: FnOnce() -> String + Clone,
@@ -564,6 +1060,9 @@ impl Message {
                 None => bail!("not encrypted"),
             },
             Message::Encrypted { esk, edata, .. } => {
+                let _span = debug_span!("decrypt_message_with_password", edata = edata.len())
+                    .entered();
+
                 // TODO: handle multiple passwords
                 let skesk = esk.iter().find_map(|esk| match esk {
                     Esk::SymKeyEncryptedSessionKey(k) => Some(k),
@@ -618,8 +1117,35 @@ impl Message {
         }
     }
 
-    /// Returns the underlying content and `None` if the message is encrypted.
-    pub fn get_content(&self) -> Result<Option<Vec<u8>>> {
+    /// Returns the literal data of the message, including the file name, creation time and
+    /// data mode. Compressed messages are decompressed, returns `None` if the message is
+    /// encrypted.
+    pub fn literal_data(&self) -> Result<Option<LiteralData>> {
+        self.literal_data_nested(Nesting::default())
+    }
+
+    fn literal_data_nested(&self, nesting: Nesting) -> Result<Option<LiteralData>> {
+        match self {
+            Message::Literal(data) => Ok(Some(data.clone())),
+            Message::Compressed(data) => {
+                let nesting = nesting.enter()?;
+                let msg = Message::from_bytes_nested(data.decompress()?, nesting)?;
+                msg.literal_data_nested(nesting)
+            }
+            Message::Signed { message, .. } => match message {
+                Some(message) => message.literal_data_nested(nesting.enter()?),
+                None => Ok(None),
+            },
+            Message::Encrypted { .. } => Ok(None),
+        }
+    }
+
+    /// Returns the underlying content and `None` if the message is encrypted.
+    pub fn get_content(&self) -> Result<Option<Vec<u8>>> {
+        self.get_content_nested(Nesting::default())
+    }
+
+    fn get_content_nested(&self, nesting: Nesting) -> Result<Option<Vec<u8>>> {
         match self {
             Message::Literal(ref data) => Ok(Some(data.data().to_vec())),
             Message::Signed { message, .. } => Ok(message
@@ -627,13 +1153,36 @@ impl Message {
                 .and_then(|m| m.get_literal())
                 .map(|l| l.data().to_vec())),
             Message::Compressed(data) => {
-                let msg = Message::from_bytes(data.decompress()?)?;
-                msg.get_content()
+                let nesting = nesting.enter()?;
+                if let Ok(mut reader) = LiteralDataReader::from_literal_packet(data.decompress()?) {
+                    let mut content = Vec::new();
+                    reader.read_to_end(&mut content)?;
+                    return Ok(Some(content));
+                }
+
+                let msg = Message::from_bytes_nested(data.decompress()?, nesting)?;
+                msg.get_content_nested(nesting)
             }
             Message::Encrypted { .. } => Ok(None),
         }
     }
 
+    /// Returns a reader over the underlying content and `None` if the message is encrypted.
+    ///
+    /// Compressed data is decompressed on the fly, so the content is never held in memory
+    /// completely. Signatures are not verified.
+    pub fn content_reader(&self) -> Result<Option<Box<dyn io::Read + '_>>> {
+        match self {
+            Message::Literal(ref data) => Ok(Some(Box::new(data.data()))),
+            Message::Signed { message, .. } => match message {
+                Some(message) => message.content_reader(),
+                None => Ok(None),
+            },
+            Message::Compressed(data) => Ok(Some(Box::new(data.literal_reader()?))),
+            Message::Encrypted { .. } => Ok(None),
+        }
+    }
+
     pub fn to_armored_writer(
         &self,
         writer: &mut impl io::Write,
@@ -655,16 +1204,86 @@ impl Message {
     }
 }
 
+/// Returns the revocation of `key`, or of its primary key, that is in effect at the creation
+/// time of `signature`.
+fn revocation_result(
+    signed_key: &SignedPublicKey,
+    key: &PublicComponentKey<'_>,
+    signature: &Signature,
+) -> Option<VerificationResult> {
+    let time = signature.created().cloned().unwrap_or_else(chrono::Utc::now);
+    let validity = signed_key.validity_at(&time);
+    let key_id = key.key_id();
+    let subkey = validity
+        .subkeys
+        .into_iter()
+        .find(|(id, _)| *id == key_id)
+        .map(|(_, validity)| validity);
+
+    iter::once(validity.primary)
+        .chain(subkey)
+        .find_map(|validity| match validity {
+            Validity::Revoked {
+                code,
+                reason,
+                revoked_at,
+            } => Some(VerificationResult::Revoked {
+                key_id,
+                code,
+                reason,
+                revoked_at,
+            }),
+            _ => None,
+        })
+}
+
+/// Picks the first AEAD algorithm preferred by the first recipient, which all other
+/// recipients prefer as well and which can be used with `alg`.
+///
+/// Returns `AeadAlgorithm::None` if there is no such algorithm.
+pub(crate) fn negotiate_aead(
+    alg: SymmetricKeyAlgorithm,
+    recipients: &[&SignedPublicKey],
+) -> AeadAlgorithm {
+    match alg {
+        SymmetricKeyAlgorithm::AES128
+        | SymmetricKeyAlgorithm::AES192
+        | SymmetricKeyAlgorithm::AES256 => {}
+        _ => return AeadAlgorithm::None,
+    }
+
+    let first = match recipients.first() {
+        Some(first) => first,
+        None => return AeadAlgorithm::None,
+    };
+
+    first
+        .details
+        .preferred_aead_algs()
+        .iter()
+        .filter(|aead| **aead == AeadAlgorithm::Eax)
+        .find(|aead| {
+            recipients
+                .iter()
+                .all(|key| key.details.preferred_aead_algs().contains(*aead))
+        })
+        .copied()
+        .unwrap_or(AeadAlgorithm::None)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use rand::thread_rng;
     use std::fs;
-    use std::io::Cursor;
+    use std::io::{Cursor, Read};
 
-    use crate::composed::{Deserializable, Message, SignedSecretKey};
+    use crate::composed::{
+        Deserializable, Message, SecretKeyIndex, SignedPublicKey, SignedSecretKey,
+    };
     use crate::crypto::SymmetricKeyAlgorithm;
-    use crate::types::{CompressionAlgorithm, SecretKeyTrait};
+    use crate::packet::DataMode;
+    use crate::types::{Argon2Params, CompressionAlgorithm, SecretKeyTrait};
 
     #[test]
     fn test_compression_zlib() {
@@ -689,6 +1308,17 @@ mod tests {
         assert_eq!(&lit_msg, &uncompressed_msg);
     }
 
+    #[test]
+    #[cfg(feature = "bzip2")]
+    fn test_compression_bzip2() {
+        let lit_msg = Message::new_literal("hello-bzip2.txt", "hello world");
+
+        let compressed_msg = lit_msg.compress(CompressionAlgorithm::BZip2).unwrap();
+        let uncompressed_msg = compressed_msg.decompress().unwrap();
+
+        assert_eq!(&lit_msg, &uncompressed_msg);
+    }
+
     #[test]
     fn test_compression_uncompressed() {
         let lit_msg = Message::new_literal("hello.txt", "hello world");
@@ -702,6 +1332,39 @@ mod tests {
         assert_eq!(&lit_msg, &uncompressed_msg);
     }
 
+    #[test]
+    fn test_compression_level() {
+        let lit_msg = Message::new_literal("hello.txt", &"hello world ".repeat(100));
+
+        let fast = lit_msg.compress_with_level(CompressionAlgorithm::ZLIB, 0).unwrap();
+        let best = lit_msg.compress_with_level(CompressionAlgorithm::ZLIB, 9).unwrap();
+        assert!(best.to_bytes().unwrap().len() < fast.to_bytes().unwrap().len());
+
+        assert_eq!(&lit_msg, &fast.decompress().unwrap());
+        assert_eq!(&lit_msg, &best.decompress().unwrap());
+
+        assert!(lit_msg
+            .compress_with_level(CompressionAlgorithm::ZIP, 10)
+            .is_err());
+    }
+
+    #[test]
+    fn test_content_reader() {
+        let content = "hello world ".repeat(1000);
+        let lit_msg = Message::new_literal("hello.txt", &content);
+        let compressed_msg = lit_msg.compress(CompressionAlgorithm::ZLIB).unwrap();
+
+        for msg in &[lit_msg, compressed_msg] {
+            let mut data = Vec::new();
+            msg.content_reader()
+                .unwrap()
+                .unwrap()
+                .read_to_end(&mut data)
+                .unwrap();
+            assert_eq!(data, msg.get_content().unwrap().unwrap());
+        }
+    }
+
     #[test]
     fn test_rsa_encryption() {
         use rand::SeedableRng;
@@ -812,6 +1475,672 @@ mod tests {
         assert_eq!(compressed_msg, decrypted);
     }
 
+    #[test]
+    fn test_password_encryption_argon2() {
+        let mut rng = thread_rng();
+
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        let params = Argon2Params {
+            passes: 1,
+            parallelism: 1,
+            memory_exp: 10,
+        };
+        let s2k = StringToKey::new_argon2(&mut rng, params);
+
+        let encrypted = lit_msg
+            .encrypt_with_password(&mut rng, s2k, SymmetricKeyAlgorithm::AES256, || {
+                "secret".into()
+            })
+            .unwrap();
+
+        let armored = encrypted.to_armored_bytes(None).unwrap();
+        let parsed = Message::from_armor_single(Cursor::new(&armored)).unwrap().0;
+        match parsed {
+            Message::Encrypted { ref esk, .. } => match esk[0] {
+                Esk::SymKeyEncryptedSessionKey(ref skesk) => {
+                    assert_eq!(skesk.s2k().argon2(), Some(&params))
+                }
+                _ => panic!("invalid esk"),
+            },
+            _ => panic!("not encrypted"),
+        }
+
+        let decrypted = parsed
+            .decrypt_with_password(|| "secret".into())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(lit_msg, decrypted);
+    }
+
+    /// Encrypts a message with `alg` to a key and to a password, and decrypts both again.
+    fn cipher_roundtrip(alg: SymmetricKeyAlgorithm) {
+        let (skey, _headers) = SignedSecretKey::from_armor_single(
+            fs::File::open("./tests/opengpg-interop/testcases/messages/gnupg-v1-001-decrypt.asc")
+                .unwrap(),
+        )
+        .unwrap();
+
+        // subkey[0] is the encryption key
+        let pkey = skey.secret_subkeys[0].public_key();
+        let mut rng = thread_rng();
+
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        let compressed_msg = lit_msg.compress(CompressionAlgorithm::ZLIB).unwrap();
+
+        let encrypted = compressed_msg
+            .encrypt_to_keys(&mut rng, alg, &[&pkey][..])
+            .unwrap();
+        let armored = encrypted.to_armored_bytes(None).unwrap();
+        let parsed = Message::from_armor_single(Cursor::new(&armored)).unwrap().0;
+        let decrypted = parsed
+            .decrypt(|| "".into(), || "test".into(), &[&skey])
+            .unwrap()
+            .0
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(compressed_msg, decrypted);
+
+        let s2k = StringToKey::new_default(&mut rng);
+        let encrypted = compressed_msg
+            .encrypt_with_password(&mut rng, s2k, alg, || "secret".into())
+            .unwrap();
+        match encrypted {
+            Message::Encrypted { ref esk, .. } => match esk[0] {
+                Esk::SymKeyEncryptedSessionKey(ref skesk) => assert_eq!(skesk.sym_algorithm(), alg),
+                _ => panic!("invalid esk"),
+            },
+            _ => panic!("not encrypted"),
+        }
+        let armored = encrypted.to_armored_bytes(None).unwrap();
+        let parsed = Message::from_armor_single(Cursor::new(&armored)).unwrap().0;
+        let decrypted = parsed
+            .decrypt_with_password(|| "secret".into())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(compressed_msg, decrypted);
+    }
+
+    #[test]
+    #[cfg(feature = "twofish")]
+    fn test_twofish_encryption() {
+        cipher_roundtrip(SymmetricKeyAlgorithm::Twofish);
+    }
+
+    #[test]
+    #[cfg(feature = "cast5")]
+    fn test_cast5_encryption() {
+        cipher_roundtrip(SymmetricKeyAlgorithm::CAST5);
+    }
+
+    #[test]
+    #[cfg(feature = "blowfish")]
+    fn test_blowfish_encryption() {
+        cipher_roundtrip(SymmetricKeyAlgorithm::Blowfish);
+    }
+
+    #[test]
+    fn test_aead_encryption() {
+        let (skey, _headers) = SignedSecretKey::from_armor_single(
+            fs::File::open("./tests/autocrypt/alice@autocrypt.example.sec.asc").unwrap(),
+        )
+        .unwrap();
+
+        // subkey[0] is the encryption key
+        let pkey = skey.secret_subkeys[0].public_key();
+        let mut rng = thread_rng();
+
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        for aead in &[AeadAlgorithm::Eax] {
+            let encrypted = lit_msg
+                .encrypt_to_keys_aead(&mut rng, SymmetricKeyAlgorithm::AES256, *aead, &[&pkey])
+                .unwrap();
+            let s2k = StringToKey::new_default(&mut rng);
+            let encrypted_pw = lit_msg
+                .encrypt_with_password_aead(
+                    &mut rng,
+                    s2k,
+                    SymmetricKeyAlgorithm::AES128,
+                    *aead,
+                    || "secret".into(),
+                )
+                .unwrap();
+
+            for msg in &[&encrypted, &encrypted_pw] {
+                match msg {
+                    Message::Encrypted { edata, .. } => {
+                        assert_eq!(edata[0].tag(), Tag::AeadEncryptedData)
+                    }
+                    _ => panic!("not encrypted"),
+                }
+            }
+
+            let armored = encrypted.to_armored_bytes(None).unwrap();
+            let parsed = Message::from_armor_single(Cursor::new(&armored)).unwrap().0;
+            let decrypted = parsed
+                .decrypt(|| "".into(), || "".into(), &[&skey])
+                .unwrap()
+                .0
+                .next()
+                .unwrap()
+                .unwrap();
+            assert_eq!(lit_msg, decrypted);
+
+            let armored = encrypted_pw.to_armored_bytes(None).unwrap();
+            let parsed = Message::from_armor_single(Cursor::new(&armored)).unwrap().0;
+            let decrypted = parsed
+                .decrypt_with_password(|| "secret".into())
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap();
+            assert_eq!(lit_msg, decrypted);
+        }
+    }
+
+    #[test]
+    fn test_negotiate_aead() {
+        let key = |name: &str| {
+            let f = fs::File::open(format!("./tests/autocrypt/{}@autocrypt.example.pub.asc", name))
+                .unwrap();
+            SignedPublicKey::from_armor_single(f).unwrap().0
+        };
+        let with_prefs = |mut key: SignedPublicKey, prefs: &[AeadAlgorithm]| {
+            for user in &mut key.details.users {
+                for sig in &mut user.signatures {
+                    sig.config
+                        .hashed_subpackets
+                        .insert(0, Subpacket::PreferredAeadAlgorithms(prefs.into()));
+                }
+            }
+            key
+        };
+
+        let alice = with_prefs(key("alice"), &[AeadAlgorithm::Ocb, AeadAlgorithm::Eax]);
+        let bob = with_prefs(key("bob"), &[AeadAlgorithm::Eax]);
+        let carol = key("carol");
+
+        let aes = SymmetricKeyAlgorithm::AES128;
+        assert_eq!(negotiate_aead(aes, &[&alice]), AeadAlgorithm::Eax);
+        assert_eq!(negotiate_aead(aes, &[&alice, &bob]), AeadAlgorithm::Eax);
+        assert_eq!(negotiate_aead(aes, &[&alice, &carol]), AeadAlgorithm::None);
+        assert_eq!(
+            negotiate_aead(SymmetricKeyAlgorithm::TripleDES, &[&alice]),
+            AeadAlgorithm::None
+        );
+
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        let encrypted = lit_msg
+            .encrypt_to_recipients(&mut thread_rng(), aes, &[&alice, &bob])
+            .unwrap();
+        match encrypted {
+            Message::Encrypted { edata, .. } => assert_eq!(edata[0].tag(), Tag::AeadEncryptedData),
+            _ => panic!("not encrypted"),
+        }
+    }
+
+    #[test]
+    fn test_decrypted_literal_data() {
+        let mut rng = thread_rng();
+
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        let s2k = StringToKey::new_default(&mut rng);
+        let encrypted = lit_msg
+            .compress(CompressionAlgorithm::ZLIB)
+            .unwrap()
+            .encrypt_with_password(&mut rng, s2k, SymmetricKeyAlgorithm::AES128, || {
+                "secret".into()
+            })
+            .unwrap();
+        assert!(encrypted.literal_data().unwrap().is_none());
+
+        let decrypted = encrypted
+            .decrypt_with_password(|| "secret".into())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let literal = decrypted.literal_data().unwrap().unwrap();
+
+        assert_eq!(literal.file_name(), "hello.txt");
+        assert_eq!(literal.mode(), DataMode::Utf8);
+        assert_eq!(literal.created(), lit_msg.get_literal().unwrap().created());
+        assert_eq!(literal.data(), b"hello world\r\n");
+    }
+
+    #[test]
+    fn test_unprotected_decryption() {
+        let key = [7u8; 16];
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        let ciphertext = SymmetricKeyAlgorithm::AES128
+            .encrypt(&key, &lit_msg.to_bytes().unwrap())
+            .unwrap();
+        let edata = vec![Edata::SymEncryptedData(
+            SymEncryptedData::from_slice(Default::default(), &ciphertext).unwrap(),
+        )];
+
+        let mut decrypter =
+            MessageDecrypter::new(key.to_vec(), SymmetricKeyAlgorithm::AES128, &edata);
+        match decrypter.next() {
+            Some(Err(Error::MissingMdc)) => {}
+            res => panic!("unexpected result {:?}", res),
+        }
+
+        let mut decrypter =
+            MessageDecrypter::new(key.to_vec(), SymmetricKeyAlgorithm::AES128, &edata)
+                .allow_unprotected(true);
+        assert_eq!(decrypter.next().unwrap().unwrap(), lit_msg);
+    }
+
+    #[test]
+    fn test_decryption_policy() {
+        let key = [7u8; 16];
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        let ciphertext = SymmetricKeyAlgorithm::AES128
+            .encrypt(&key, &lit_msg.to_bytes().unwrap())
+            .unwrap();
+        let edata = vec![Edata::SymEncryptedData(
+            SymEncryptedData::from_slice(Default::default(), &ciphertext).unwrap(),
+        )];
+        let decrypter =
+            || MessageDecrypter::new(key.to_vec(), SymmetricKeyAlgorithm::AES128, &edata);
+
+        match decrypter().with_policy(&Policy::default()).next() {
+            Some(Err(Error::MissingMdc)) => {}
+            res => panic!("unexpected result {:?}", res),
+        }
+
+        let policy = Policy::legacy().reject_symmetric(SymmetricKeyAlgorithm::AES128);
+        match decrypter().with_policy(&policy).next() {
+            Some(Err(Error::PolicyViolation(_))) => {}
+            res => panic!("unexpected result {:?}", res),
+        }
+
+        let mut decrypter = decrypter().with_policy(&Policy::legacy());
+        assert_eq!(decrypter.next().unwrap().unwrap(), lit_msg);
+    }
+
+    #[test]
+    fn test_password_decryption_with_keys() {
+        let (skey, _headers) = SignedSecretKey::from_armor_single(
+            fs::File::open("./tests/autocrypt/alice@autocrypt.example.sec.asc").unwrap(),
+        )
+        .unwrap();
+        let mut rng = thread_rng();
+
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        let s2k = StringToKey::new_default(&mut rng);
+        let encrypted = lit_msg
+            .encrypt_with_password(&mut rng, s2k, SymmetricKeyAlgorithm::AES128, || {
+                "secret".into()
+            })
+            .unwrap();
+
+        for keys in &[vec![], vec![&skey]] {
+            let (mut decrypter, ids) = encrypted
+                .decrypt(|| "secret".into(), || "".into(), keys)
+                .unwrap();
+            assert!(ids.is_empty());
+            assert_eq!(decrypter.next().unwrap().unwrap(), lit_msg);
+        }
+    }
+
+    #[test]
+    fn test_encrypt_to_recipients() {
+        let key = |name: &str| {
+            let f = fs::File::open(format!("./tests/autocrypt/{}@autocrypt.example.pub.asc", name))
+                .unwrap();
+            SignedPublicKey::from_armor_single(f).unwrap().0
+        };
+        let secret_key = |name: &str| {
+            let f = fs::File::open(format!("./tests/autocrypt/{}@autocrypt.example.sec.asc", name))
+                .unwrap();
+            SignedSecretKey::from_armor_single(f).unwrap().0
+        };
+
+        let alice = key("alice");
+        let bob = key("bob");
+        let mut rng = thread_rng();
+
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        let encrypted = lit_msg
+            .encrypt_to_recipients(&mut rng, SymmetricKeyAlgorithm::AES128, &[&alice, &bob])
+            .unwrap();
+
+        let armored = encrypted.to_armored_bytes(None).unwrap();
+        let parsed = Message::from_armor_single(Cursor::new(&armored)).unwrap().0;
+
+        for name in &["alice", "bob"] {
+            let (mut decrypter, ids) = parsed
+                .decrypt(|| "".into(), || "".into(), &[&secret_key(name)])
+                .unwrap();
+            assert_eq!(ids.len(), 1);
+            assert_eq!(decrypter.next().unwrap().unwrap(), lit_msg);
+        }
+    }
+
+    #[test]
+    fn test_decrypt_with_index() {
+        let open = |name: &str| fs::File::open(format!("./tests/autocrypt/{}", name)).unwrap();
+        let alice = SignedSecretKey::from_armor_single(open("alice@autocrypt.example.sec.asc"))
+            .unwrap()
+            .0;
+        let bob = SignedSecretKey::from_armor_single(open("bob@autocrypt.example.sec.asc"))
+            .unwrap()
+            .0;
+        let carol = SignedPublicKey::from_armor_single(open("carol@autocrypt.example.pub.asc"))
+            .unwrap()
+            .0;
+
+        let index = SecretKeyIndex::new(&[&alice, &bob]);
+        assert_eq!(index.keys().len(), 2);
+        assert!(index.contains_key_id(&bob.secret_subkeys[0].key_id()));
+        assert!(!index.contains_key_id(&carol.key_id()));
+
+        let mut rng = thread_rng();
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        for recipient in &[alice.signed_public_key(), bob.signed_public_key()] {
+            let encrypted = lit_msg
+                .encrypt_to_recipients(&mut rng, SymmetricKeyAlgorithm::AES128, &[recipient])
+                .unwrap();
+
+            let (mut decrypter, ids) = encrypted
+                .decrypt_with_index(|| "".into(), || "".into(), &index)
+                .unwrap();
+            assert_eq!(ids.len(), 1);
+            assert!(recipient
+                .public_subkeys
+                .iter()
+                .any(|subkey| subkey.key_id() == ids[0]));
+            assert_eq!(decrypter.next().unwrap().unwrap(), lit_msg);
+        }
+
+        let encrypted = lit_msg
+            .encrypt_to_recipients(&mut rng, SymmetricKeyAlgorithm::AES128, &[&carol])
+            .unwrap();
+        assert!(encrypted
+            .decrypt_with_index(|| "".into(), || "".into(), &index)
+            .is_err());
+    }
+
+    #[test]
+    fn test_decrypt_and_verify() {
+        let open = |name: &str| fs::File::open(format!("./tests/autocrypt/{}", name)).unwrap();
+        let alice = SignedSecretKey::from_armor_single(open("alice@autocrypt.example.sec.asc"))
+            .unwrap()
+            .0;
+        let alice_pub = SignedPublicKey::from_armor_single(open("alice@autocrypt.example.pub.asc"))
+            .unwrap()
+            .0;
+        let bob = SignedSecretKey::from_armor_single(open("bob@autocrypt.example.sec.asc"))
+            .unwrap()
+            .0;
+        let bob_pub = SignedPublicKey::from_armor_single(open("bob@autocrypt.example.pub.asc"))
+            .unwrap()
+            .0;
+        let mut rng = thread_rng();
+
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        let signed_msg = lit_msg
+            .sign(&alice, || "".into(), HashAlgorithm::SHA2_256)
+            .unwrap();
+        let encrypted = signed_msg
+            .compress(CompressionAlgorithm::ZLIB)
+            .unwrap()
+            .encrypt_to_recipients(&mut rng, SymmetricKeyAlgorithm::AES128, &[&bob_pub])
+            .unwrap();
+
+        let (decrypted, results, ids) = encrypted
+            .decrypt_and_verify(|| "".into(), || "".into(), &[&bob], &[&bob_pub, &alice_pub])
+            .unwrap();
+        assert_eq!(decrypted.decompress().unwrap(), signed_msg);
+        assert_eq!(results, vec![VerificationResult::Valid(alice.key_id())]);
+        assert_eq!(ids.len(), 1);
+
+        let (_, results, _) = encrypted
+            .decrypt_and_verify(|| "".into(), || "".into(), &[&bob], &[&bob_pub])
+            .unwrap();
+        assert_eq!(
+            results,
+            vec![VerificationResult::UnknownIssuer(Some(alice.key_id()))]
+        );
+    }
+
+    #[test]
+    fn test_decrypt_wildcard_recipient() {
+        let open = |name: &str| fs::File::open(format!("./tests/autocrypt/{}", name)).unwrap();
+        let alice = SignedSecretKey::from_armor_single(open("alice@autocrypt.example.sec.asc"))
+            .unwrap()
+            .0;
+        let bob = SignedSecretKey::from_armor_single(open("bob@autocrypt.example.sec.asc"))
+            .unwrap()
+            .0;
+        let bob_pub = SignedPublicKey::from_armor_single(open("bob@autocrypt.example.pub.asc"))
+            .unwrap()
+            .0;
+        let mut rng = thread_rng();
+
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        let encrypted = match lit_msg
+            .encrypt_to_recipients(&mut rng, SymmetricKeyAlgorithm::AES128, &[&bob_pub])
+            .unwrap()
+        {
+            Message::Encrypted { esk, edata } => {
+                // hide the recipient, like `gpg --throw-keyids`
+                let esk = esk
+                    .into_iter()
+                    .map(|esk| match esk {
+                        Esk::PublicKeyEncryptedSessionKey(k) => {
+                            let mut body = k.to_bytes().unwrap();
+                            body[1..9].copy_from_slice(KeyId::wildcard().as_ref());
+                            Esk::PublicKeyEncryptedSessionKey(
+                                PublicKeyEncryptedSessionKey::from_slice(
+                                    k.packet_version(),
+                                    &body,
+                                )
+                                .unwrap(),
+                            )
+                        }
+                        other => other,
+                    })
+                    .collect();
+                Message::Encrypted { esk, edata }
+            }
+            _ => panic!("invalid message"),
+        };
+        assert!(encrypted.get_recipients()[0].is_wildcard());
+
+        let (mut decrypter, ids) = encrypted
+            .decrypt(|| "".into(), || "".into(), &[&alice, &bob])
+            .unwrap();
+        assert_eq!(ids, vec![bob.secret_subkeys[0].key_id()]);
+        assert_eq!(decrypter.next().unwrap().unwrap(), lit_msg);
+
+        assert!(encrypted
+            .decrypt(|| "".into(), || "".into(), &[&alice])
+            .is_err());
+    }
+
+    #[test]
+    fn test_verify_signing_subkey() {
+        use crate::composed::{KeyType, SecretKeyParamsBuilder};
+        use crate::crypto::ECCCurve;
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let key = SecretKeyParamsBuilder::split_usage(
+            KeyType::EdDSA,
+            KeyType::ECDH(ECCCurve::Curve25519),
+            "Me <me@mail.com>",
+        )
+        .unwrap()
+        .build()
+        .unwrap()
+        .generate_with_rng(&mut rng)
+        .unwrap()
+        .sign(|| "".into())
+        .unwrap();
+        let public_key = key.signed_public_key();
+        let signing_key = &key.secret_subkeys[0];
+
+        let signed_msg = Message::new_literal("hello.txt", "hello world\n")
+            .sign(signing_key, || "".into(), HashAlgorithm::SHA2_256)
+            .unwrap();
+        let armored = signed_msg.to_armored_bytes(None).unwrap();
+        let parsed = Message::from_armor_single(Cursor::new(&armored)).unwrap().0;
+
+        assert_eq!(
+            parsed.verify_signatures(&[&public_key]).unwrap(),
+            vec![VerificationResult::Valid(signing_key.key_id())]
+        );
+
+        // a second signature, by the primary key
+        let signed_twice = signed_msg
+            .sign(&key, || "".into(), HashAlgorithm::SHA2_256)
+            .unwrap();
+        let results = signed_twice.verify_signatures(&[&public_key]).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0], VerificationResult::Valid(key.key_id()));
+        assert_eq!(results[1], VerificationResult::Valid(signing_key.key_id()));
+
+        // a compromised primary key invalidates the signatures of its subkeys as well
+        let revocation = key
+            .revoke_key(&key.primary_key, RevocationCode::KeyCompromised, "stolen", || {
+                "".into()
+            })
+            .unwrap();
+        let revoked_at = revocation.created().cloned();
+        let mut revoked_key = public_key.clone();
+        revoked_key.details.revocation_signatures.push(revocation);
+
+        let results = signed_twice.verify_signatures(&[&revoked_key]).unwrap();
+        assert_eq!(
+            results[1],
+            VerificationResult::Revoked {
+                key_id: signing_key.key_id(),
+                code: Some(RevocationCode::KeyCompromised),
+                reason: Some("stolen".to_string()),
+                revoked_at,
+            }
+        );
+        match revoked_key.verify_at(&chrono::Utc::now()) {
+            Err(Error::KeyRevoked { code, .. }) => {
+                assert_eq!(code, Some(RevocationCode::KeyCompromised))
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "curve448")]
+    fn test_curve448() {
+        use crate::composed::{KeyType, SecretKeyParamsBuilder};
+        use crate::crypto::ECCCurve;
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let key = SecretKeyParamsBuilder::split_usage(
+            KeyType::Ed448,
+            KeyType::ECDH(ECCCurve::Curve448),
+            "Me <me@mail.com>",
+        )
+        .unwrap()
+        .build()
+        .unwrap()
+        .generate_with_rng(&mut rng)
+        .unwrap()
+        .sign(|| "".into())
+        .unwrap();
+        key.verify().unwrap();
+        let public_key = key.signed_public_key();
+
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        let signed_msg = lit_msg
+            .clone()
+            .sign(&key.secret_subkeys[0], || "".into(), HashAlgorithm::SHA2_512)
+            .unwrap();
+        signed_msg.verify(&public_key.public_subkeys[0]).unwrap();
+
+        // subkey[1] is the encryption key
+        let pkey = &public_key.public_subkeys[1];
+        let encrypted = lit_msg
+            .encrypt_to_keys(&mut rng, SymmetricKeyAlgorithm::AES256, &[pkey][..])
+            .unwrap();
+        let armored = encrypted.to_armored_bytes(None).unwrap();
+        let parsed = Message::from_armor_single(Cursor::new(&armored)).unwrap().0;
+        let decrypted = parsed
+            .decrypt(|| "".into(), || "".into(), &[&key])
+            .unwrap()
+            .0
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(lit_msg, decrypted);
+    }
+
+    #[test]
+    fn test_multiple_signers() {
+        let read_key = |name: &str| {
+            let f = fs::File::open(format!("./tests/autocrypt/{}@autocrypt.example.sec.asc", name))
+                .unwrap();
+            SignedSecretKey::from_armor_single(f).unwrap().0
+        };
+        let alice = read_key("alice");
+        let bob = read_key("bob");
+        let alice_pub = alice.signed_public_key();
+        let bob_pub = bob.signed_public_key();
+
+        let signed_msg = Message::new_literal("hello.txt", "hello world\n")
+            .sign(&alice, || "".into(), HashAlgorithm::SHA2_256)
+            .unwrap()
+            .add_signature(&bob, || "".into(), HashAlgorithm::SHA2_512)
+            .unwrap();
+
+        let armored = signed_msg.to_armored_bytes(None).unwrap();
+        let parsed = Message::from_armor_single(Cursor::new(&armored)).unwrap().0;
+
+        // both signatures cover the literal data, and can be checked on their own
+        parsed.verify(&bob.public_key()).unwrap();
+        assert_eq!(
+            parsed.verify_signatures(&[&alice_pub, &bob_pub]).unwrap(),
+            vec![
+                VerificationResult::Valid(bob.key_id()),
+                VerificationResult::Valid(alice.key_id()),
+            ]
+        );
+        assert_eq!(
+            parsed.verify_signatures(&[&alice_pub]).unwrap()[0],
+            VerificationResult::UnknownIssuer(Some(bob.key_id()))
+        );
+
+        match parsed {
+            Message::Signed {
+                one_pass_signature: Some(ref outer),
+                message: Some(ref message),
+                ..
+            } => {
+                assert!(!outer.is_last());
+                match **message {
+                    Message::Signed {
+                        one_pass_signature: Some(ref inner),
+                        ..
+                    } => assert!(inner.is_last()),
+                    _ => panic!("invalid message"),
+                }
+            }
+            _ => panic!("invalid message"),
+        }
+
+        // only signed messages can have additional signatures
+        assert!(Message::new_literal("hello.txt", "hello world\n")
+            .add_signature(&bob, || "".into(), HashAlgorithm::SHA2_256)
+            .is_err());
+    }
+
     #[test]
     fn test_x25519_signing_string() {
         let (skey, _headers) = SignedSecretKey::from_armor_single(
@@ -835,6 +2164,87 @@ mod tests {
         parsed.verify(&pkey).unwrap();
     }
 
+    #[test]
+    fn test_signing_notations() {
+        let (skey, _headers) = SignedSecretKey::from_armor_single(
+            fs::File::open("./tests/autocrypt/alice@autocrypt.example.sec.asc").unwrap(),
+        )
+        .unwrap();
+
+        let pkey = skey.public_key();
+        let notations = vec![
+            Notation::new("proof@metacode.biz", "dns:example.org"),
+            Notation {
+                readable: false,
+                name: "salt@example.org".into(),
+                value: "\x01\x02".into(),
+            },
+        ];
+
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        let signed_msg = lit_msg
+            .sign_with_notations(&skey, || "".into(), HashAlgorithm::SHA2_256, &notations)
+            .unwrap();
+
+        let armored = signed_msg.to_armored_bytes(None).unwrap();
+        let parsed = Message::from_armor_single(Cursor::new(&armored)).unwrap().0;
+        parsed.verify(&pkey).unwrap();
+
+        match parsed {
+            Message::Signed { signature, .. } => {
+                assert_eq!(signature.notations(), notations.iter().collect::<Vec<_>>());
+                // notations are part of the signed data
+                assert!(signature
+                    .config
+                    .hashed_subpackets
+                    .contains(&Subpacket::Notation(notations[0].clone())));
+            }
+            _ => panic!("invalid message"),
+        }
+    }
+
+    #[test]
+    fn test_signing_expiration() {
+        let (skey, _headers) = SignedSecretKey::from_armor_single(
+            fs::File::open("./tests/autocrypt/alice@autocrypt.example.sec.asc").unwrap(),
+        )
+        .unwrap();
+
+        let pkey = skey.public_key();
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        let signed_msg = lit_msg
+            .sign_with_expiration(
+                &skey,
+                || "".into(),
+                HashAlgorithm::SHA2_256,
+                Duration::from_secs(60 * 60),
+            )
+            .unwrap();
+
+        let armored = signed_msg.to_armored_bytes(None).unwrap();
+        let parsed = Message::from_armor_single(Cursor::new(&armored)).unwrap().0;
+        parsed.verify(&pkey).unwrap();
+
+        let now = chrono::Utc::now();
+        parsed.verify_at(&pkey, &now).unwrap();
+        match parsed.verify_at(&pkey, &(now + chrono::Duration::hours(2))) {
+            Err(Error::SignatureExpired(_)) => {}
+            res => panic!("unexpected result {:?}", res),
+        }
+
+        match parsed {
+            Message::Signed { signature, .. } => assert_eq!(
+                signature.signature_expiration_time().map(|t| t.timestamp()),
+                Some(60 * 60)
+            ),
+            _ => panic!("invalid message"),
+        }
+
+        assert!(Message::new_literal("hello.txt", "hello world\n")
+            .sign_with_expiration(&skey, || "".into(), HashAlgorithm::SHA2_256, Duration::new(0, 0))
+            .is_err());
+    }
+
     #[test]
     fn test_x25519_signing_bytes() {
         let (skey, _headers) = SignedSecretKey::from_armor_single(
@@ -880,6 +2290,63 @@ mod tests {
 
         let parsed = Message::from_armor_single(Cursor::new(&armored)).unwrap().0;
         parsed.verify(&pkey).unwrap();
+
+        // the one pass signature and the signature are skipped
+        let mut data = Vec::new();
+        parsed
+            .content_reader()
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"hello world\n");
+    }
+
+    #[test]
+    fn test_x25519_signing_compressed_one_pass() {
+        let (skey, _headers) = SignedSecretKey::from_armor_single(
+            fs::File::open("./tests/autocrypt/alice@autocrypt.example.sec.asc").unwrap(),
+        )
+        .unwrap();
+
+        let pkey = skey.public_key();
+
+        let lit_msg = Message::new_literal("hello.txt", "hello world\n");
+        let signed_msg = lit_msg
+            .compress(CompressionAlgorithm::ZLIB)
+            .unwrap()
+            .sign(&skey, || "".into(), HashAlgorithm::SHA2_256)
+            .unwrap();
+        assert!(signed_msg.is_one_pass_signed());
+
+        // the signature covers the literal data, not the compressed packet
+        match signed_msg {
+            Message::Signed { ref signature, .. } => {
+                assert_eq!(signature.typ(), SignatureType::Text);
+                signature.verify(&pkey, &b"hello world\n"[..]).unwrap();
+            }
+            _ => panic!("invalid message"),
+        }
+
+        let armored = signed_msg.to_armored_bytes(None).unwrap();
+        let parsed = Message::from_armor_single(Cursor::new(&armored)).unwrap().0;
+        parsed.verify(&pkey).unwrap();
+
+        // nested compression is parsed instead of streamed, and covers the same data
+        let signed_msg = Message::new_literal("hello.txt", "hello world\n")
+            .compress(CompressionAlgorithm::ZLIB)
+            .unwrap()
+            .compress(CompressionAlgorithm::ZIP)
+            .unwrap()
+            .sign(&skey, || "".into(), HashAlgorithm::SHA2_256)
+            .unwrap();
+        match signed_msg {
+            Message::Signed { ref signature, .. } => {
+                signature.verify(&pkey, &b"hello world\n"[..]).unwrap();
+            }
+            _ => panic!("invalid message"),
+        }
+        signed_msg.verify(&pkey).unwrap();
     }
 
     #[test]
@@ -954,4 +2421,58 @@ mod tests {
         let parsed = Message::from_armor_single(Cursor::new(&armored)).unwrap().0;
         parsed.verify(&pkey).unwrap();
     }
+
+    #[test]
+    fn test_nesting_depth() {
+        use crate::composed::message::MAX_NESTING_DEPTH;
+
+        let mut compressed = Message::new_literal("hello.txt", "hello world\n");
+        for _ in 0..MAX_NESTING_DEPTH {
+            compressed = compressed.compress(CompressionAlgorithm::ZLIB).unwrap();
+        }
+        assert_eq!(compressed.get_content().unwrap().unwrap(), b"hello world\n");
+
+        let too_deep = compressed.compress(CompressionAlgorithm::ZLIB).unwrap();
+        match too_deep.get_content() {
+            Err(Error::NestingTooDeep(MAX_NESTING_DEPTH)) => {}
+            res => panic!("unexpected result {:?}", res),
+        }
+        assert!(too_deep.literal_data().is_err());
+
+        // wrap a literal message in three signatures
+        let mut msg_file = fs::File::open("./tests/literal-text-signed.asc").unwrap();
+        let (msg, _) = Message::from_armor_single(&mut msg_file).unwrap();
+        let signature = match msg {
+            Message::Signed { signature, .. } => signature,
+            _ => panic!("not signed"),
+        };
+
+        let mut signed = Message::new_literal("hello.txt", "hello world\n");
+        for _ in 0..3 {
+            signed = Message::Signed {
+                message: Some(Box::new(signed)),
+                one_pass_signature: None,
+                signature: signature.clone(),
+            };
+        }
+        let bytes = signed.to_bytes().unwrap();
+
+        assert_eq!(Message::from_bytes(&bytes[..]).unwrap(), signed);
+        assert_eq!(Message::from_bytes_with_max_depth(&bytes[..], 3).unwrap(), signed);
+        match Message::from_bytes_with_max_depth(&bytes[..], 2) {
+            Err(Error::NestingTooDeep(2)) => {}
+            res => panic!("unexpected result {:?}", res),
+        }
+
+        // signatures and compression share the same depth
+        let signed_compressed = Message::Signed {
+            message: Some(Box::new(compressed)),
+            one_pass_signature: None,
+            signature,
+        };
+        match signed_compressed.literal_data() {
+            Err(Error::NestingTooDeep(MAX_NESTING_DEPTH)) => {}
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
 }
@@ -2,11 +2,25 @@ pub mod key;
 pub mod message;
 pub mod signed_key;
 
+mod agent_key;
+#[cfg(feature = "async")]
+mod async_parse;
+mod dane;
+mod import;
+mod keyring;
 mod shared;
 mod signature;
+mod ssh;
 
+pub use self::agent_key::*;
+#[cfg(feature = "async")]
+pub use self::async_parse::*;
+pub use self::dane::*;
+pub use self::import::*;
 pub use self::key::*;
+pub use self::keyring::*;
 pub use self::message::*;
 pub use self::shared::Deserializable;
 pub use self::signature::*;
 pub use self::signed_key::*;
+pub use self::ssh::*;
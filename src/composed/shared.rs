@@ -1,5 +1,7 @@
+use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::io::{Cursor, Read, Seek};
+use std::rc::Rc;
 
 use crate::armor::{self, BlockType};
 use crate::errors::{Error, Result};
@@ -12,6 +14,21 @@ pub trait Deserializable: Sized {
         el.next().ok_or_else(|| Error::NoMatchingPacket)?
     }
 
+    /// Parse a single byte encoded composition, failing on the first malformed packet,
+    /// instead of skipping it.
+    ///
+    /// The whole input is parsed, so malformed packets after the first composition are
+    /// reported as well.
+    fn from_bytes_strict(bytes: impl Read) -> Result<Self> {
+        let mut el = Self::from_bytes_many_strict(bytes);
+        let first = el.next().ok_or_else(|| Error::NoMatchingPacket)??;
+        for rest in el {
+            rest?;
+        }
+
+        Ok(first)
+    }
+
     /// Parse a single armor encoded composition.
     fn from_string(input: &str) -> Result<(Self, BTreeMap<String, String>)> {
         let (mut el, headers) = Self::from_string_many(input)?;
@@ -29,6 +46,15 @@ pub trait Deserializable: Sized {
         Self::from_armor_many(Cursor::new(input))
     }
 
+    /// Parse a single armor encoded composition, failing on the first malformed packet.
+    ///
+    /// See `from_bytes_strict` for details.
+    fn from_string_strict(input: &str) -> Result<(Self, BTreeMap<String, String>)> {
+        let (dearmor, headers) = read_armor(Cursor::new(input))?;
+
+        Ok((Self::from_bytes_strict(dearmor)?, headers))
+    }
+
     /// Armored ascii data.
     fn from_armor_single<R: Read + Seek>(input: R) -> Result<(Self, BTreeMap<String, String>)> {
         let (mut el, headers) = Self::from_armor_many(input)?;
@@ -43,55 +69,143 @@ pub trait Deserializable: Sized {
         Box<dyn Iterator<Item = Result<Self>> + 'a>,
         BTreeMap<String, String>,
     )> {
-        let mut dearmor = armor::Dearmor::new(input);
-        dearmor.read_header()?;
-        // Safe to unwrap, as read_header succeeded.
-        let typ = dearmor
-            .typ
-            .ok_or_else(|| format_err!("dearmor failed to retrieve armor type"))?;
-
-        // TODO: add typ information to the key possibly?
-        match typ {
-            // Standard PGP types
-            BlockType::PublicKey
-            | BlockType::PrivateKey
-            | BlockType::Message
-            | BlockType::MultiPartMessage(_, _)
-            | BlockType::Signature
-            | BlockType::File => {
-                let headers = dearmor.headers.clone(); // FIXME: avoid clone
-
-                // TODO: check that the result is what it actually said.
-                Ok((Self::from_bytes_many(dearmor), headers))
-            }
-            BlockType::PublicKeyPKCS1(_)
-            | BlockType::PublicKeyPKCS8
-            | BlockType::PublicKeyOpenssh
-            | BlockType::PrivateKeyPKCS1(_)
-            | BlockType::PrivateKeyPKCS8
-            | BlockType::PrivateKeyOpenssh => {
-                unimplemented_err!("key format {:?}", typ);
-            }
-        }
+        let (dearmor, headers) = read_armor(input)?;
+
+        Ok((Self::from_bytes_many(dearmor), headers))
     }
 
     /// Parse a list of compositions in raw byte format.
     fn from_bytes_many<'a>(bytes: impl Read + 'a) -> Box<dyn Iterator<Item = Result<Self>> + 'a> {
-        let packets = PacketParser::new(bytes).filter_map(|p| {
-            // for now we are skipping any packets that we failed to parse
-            if p.is_ok() {
-                p.ok()
-            } else {
-                warn!("skipping packet: {:?}", p);
-                None
-            }
-        });
+        let packets = PacketParser::new(bytes).filter_map(skip_invalid);
 
         Self::from_packets(packets)
     }
 
+    /// Parse a list of compositions in raw byte format, ending with an error at the first
+    /// malformed packet, instead of skipping it.
+    ///
+    /// Use this when the input is expected to be well formed, e.g. when verifying a
+    /// signature, rather than for keyserver dumps.
+    fn from_bytes_many_strict<'a>(
+        bytes: impl Read + 'a,
+    ) -> Box<dyn Iterator<Item = Result<Self>> + 'a>
+    where
+        Self: 'a,
+    {
+        let error = Rc::new(RefCell::new(None));
+        let packets = StrictPackets {
+            inner: PacketParser::new(bytes),
+            error: error.clone(),
+        };
+
+        Box::new(Strict {
+            inner: Self::from_packets(packets),
+            error,
+            done: false,
+        })
+    }
+
     /// Turn a list of packets into a usable representation.
     fn from_packets<'a>(
         packets: impl Iterator<Item = Packet> + 'a,
     ) -> Box<dyn Iterator<Item = Result<Self>> + 'a>;
 }
+
+/// Filters out packets that failed to parse.
+pub(crate) fn skip_invalid(packet: Result<Packet>) -> Option<Packet> {
+    // for now we are skipping any packets that we failed to parse
+    match packet {
+        Ok(packet) => Some(packet),
+        Err(err) => {
+            warn!("skipping packet: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Reads the armor header, returning the reader for the armored data.
+fn read_armor<R: Read + Seek>(
+    input: R,
+) -> Result<(armor::Dearmor<R>, BTreeMap<String, String>)> {
+    let mut dearmor = armor::Dearmor::new(input);
+    dearmor.read_header()?;
+    // Safe to unwrap, as read_header succeeded.
+    let typ = dearmor
+        .typ
+        .ok_or_else(|| format_err!("dearmor failed to retrieve armor type"))?;
+
+    // TODO: add typ information to the key possibly?
+    match typ {
+        // Standard PGP types
+        BlockType::PublicKey
+        | BlockType::PrivateKey
+        | BlockType::Message
+        | BlockType::MultiPartMessage(_, _)
+        | BlockType::Signature
+        | BlockType::File => {
+            let headers = dearmor.headers.clone(); // FIXME: avoid clone
+
+            // TODO: check that the result is what it actually said.
+            Ok((dearmor, headers))
+        }
+        BlockType::PublicKeyPKCS1(_)
+        | BlockType::PublicKeyPKCS8
+        | BlockType::PublicKeyOpenssh
+        | BlockType::PrivateKeyPKCS1(_)
+        | BlockType::PrivateKeyPKCS8
+        | BlockType::PrivateKeyOpenssh => {
+            unsupported_err!("key format {:?}, use ImportedKey instead", typ);
+        }
+    }
+}
+
+/// Passes on packets, until the first one that fails to parse, whose error is stored
+/// in `error`.
+struct StrictPackets<R> {
+    inner: PacketParser<R>,
+    error: Rc<RefCell<Option<Error>>>,
+}
+
+impl<R: Read> Iterator for StrictPackets<R> {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.borrow().is_some() {
+            return None;
+        }
+
+        match self.inner.next()? {
+            Ok(packet) => Some(packet),
+            Err(err) => {
+                *self.error.borrow_mut() = Some(err);
+                None
+            }
+        }
+    }
+}
+
+/// Ends parsing with the error stored by `StrictPackets`, once there is one.
+struct Strict<I> {
+    inner: I,
+    error: Rc<RefCell<Option<Error>>>,
+    done: bool,
+}
+
+impl<T, I: Iterator<Item = Result<T>>> Iterator for Strict<I> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let next = self.inner.next();
+        // compositions that were cut short by the malformed packet are dropped
+        if let Some(err) = self.error.borrow_mut().take() {
+            self.done = true;
+            return Some(Err(err));
+        }
+
+        next
+    }
+}
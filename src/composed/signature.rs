@@ -1,6 +1,8 @@
 use std::collections::BTreeMap;
+use std::io::Read;
 use std::iter::Peekable;
 
+use chrono::{DateTime, Utc};
 use try_from::TryInto;
 
 use crate::armor;
@@ -46,6 +48,22 @@ impl StandaloneSignature {
     pub fn verify(&self, key: &impl PublicKeyTrait, content: &[u8]) -> Result<()> {
         self.signature.verify(key, content)
     }
+
+    /// Verify this signature, rejecting it if it is expired at `time`.
+    pub fn verify_at(
+        &self,
+        key: &impl PublicKeyTrait,
+        content: &[u8],
+        time: &DateTime<Utc>,
+    ) -> Result<()> {
+        self.signature.check_expiration(time)?;
+        self.signature.verify(key, content)
+    }
+
+    /// Verify this signature as detached signature over `data`, see `Signature::verify_data`.
+    pub fn verify_data(&self, key: &impl PublicKeyTrait, data: impl Read) -> Result<()> {
+        self.signature.verify_data(key, data)
+    }
 }
 
 impl Serialize for StandaloneSignature {
@@ -88,3 +106,56 @@ fn next<I: Iterator<Item = Packet>>(
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+    use std::io::Cursor;
+
+    use chrono::Utc;
+
+    use crate::composed::SignedSecretKey;
+    use crate::crypto::HashAlgorithm;
+    use crate::packet::{SignatureConfig, SignatureType, Subpacket};
+    use crate::types::{KeyTrait, SecretKeyTrait};
+
+    #[test]
+    fn test_detached_signature() {
+        let (skey, _headers) = SignedSecretKey::from_armor_single(
+            fs::File::open("./tests/autocrypt/alice@autocrypt.example.sec.asc").unwrap(),
+        )
+        .unwrap();
+        let pkey = skey.public_key();
+
+        for typ in &[SignatureType::Binary, SignatureType::Text] {
+            let config = SignatureConfig::new_v4(
+                Default::default(),
+                *typ,
+                skey.algorithm(),
+                HashAlgorithm::SHA2_256,
+                vec![Subpacket::SignatureCreationTime(Utc::now())],
+                vec![Subpacket::Issuer(skey.key_id())],
+            );
+            let signature = config
+                .sign(&skey, || "".into(), &b"hello\r\nworld\r\n"[..])
+                .unwrap();
+
+            let armored = StandaloneSignature::new(signature)
+                .to_armored_string(None)
+                .unwrap();
+            let parsed = StandaloneSignature::from_string(&armored).unwrap().0;
+
+            parsed
+                .verify_data(&pkey, Cursor::new(b"hello\r\nworld\r\n"))
+                .unwrap();
+            assert_eq!(
+                parsed
+                    .verify_data(&pkey, Cursor::new(b"hello\nworld\n"))
+                    .is_ok(),
+                *typ == SignatureType::Text
+            );
+        }
+    }
+}
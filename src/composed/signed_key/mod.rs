@@ -112,8 +112,12 @@ pub mod parse;
 pub mod public;
 pub mod secret;
 pub mod shared;
+pub mod summary;
+pub mod validity;
 
 pub use self::parse::*;
 pub use self::public::*;
 pub use self::secret::*;
 pub use self::shared::*;
+pub use self::summary::*;
+pub use self::validity::*;
@@ -2,10 +2,10 @@ use std::collections::BTreeMap;
 use std::{io, iter};
 
 use crate::armor::{self, BlockType};
-use crate::composed::shared::Deserializable;
+use crate::composed::shared::{skip_invalid, Deserializable};
 use crate::composed::signed_key::{PublicOrSecret, SignedPublicKey, SignedSecretKey};
 use crate::errors::Result;
-use crate::packet::{Packet, PacketParser};
+use crate::packet::{Packet, PacketParser, SlicePacketParser};
 use crate::types::Tag;
 
 // TODO: can detect armored vs binary using a check if the first bit in the data is set. If it is cleared it is not a binary message, so can try to parse as armor ascii. (from gnupg source)
@@ -44,7 +44,7 @@ pub fn from_armor_many<'a, R: io::Read + io::Seek + 'a>(
         | BlockType::PrivateKeyPKCS1(_)
         | BlockType::PrivateKeyPKCS8
         | BlockType::PrivateKeyOpenssh => {
-            unimplemented_err!("key format {:?}", typ);
+            unsupported_err!("key format {:?}, use ImportedKey instead", typ);
         }
     }
 }
@@ -53,16 +53,15 @@ pub fn from_armor_many<'a, R: io::Read + io::Seek + 'a>(
 pub fn from_bytes_many<'a>(
     bytes: impl io::Read + 'a,
 ) -> Box<dyn Iterator<Item = Result<PublicOrSecret>> + 'a> {
-    let packets = PacketParser::new(bytes)
-        .filter_map(|p| {
-            // for now we are skipping any packets that we failed to parse
-            if p.is_ok() {
-                p.ok()
-            } else {
-                warn!("skipping packet: {:?}", p);
-                None
-            }
-        })
+    let packets = PacketParser::new(bytes).filter_map(skip_invalid).peekable();
+
+    Box::new(PubPrivIterator { inner: packets })
+}
+
+/// Parses a list of secret and public keys from raw bytes, without buffering the input.
+pub fn from_slice_many(bytes: &[u8]) -> Box<dyn Iterator<Item = Result<PublicOrSecret>> + '_> {
+    let packets = SlicePacketParser::new(bytes)
+        .filter_map(skip_invalid)
         .peekable();
 
     Box::new(PubPrivIterator { inner: packets })
@@ -1,3 +1,4 @@
+use std::cmp::Reverse;
 use std::collections::BTreeMap;
 use std::io;
 
@@ -6,13 +7,22 @@ use rand::{CryptoRng, Rng};
 
 use crate::armor;
 use crate::composed::key::{PublicKey, PublicSubkey};
-use crate::composed::signed_key::SignedKeyDetails;
+use crate::composed::signed_key::shared::{merge_signatures, minimize_signatures, verify_backsig};
+use crate::composed::signed_key::validity::{
+    expiration_at, is_self_signature, key_expires_at, newest_signature_at,
+    primary_self_signature_at, revocation_at, subkey_expires_at, user_validity_at,
+};
+use crate::composed::signed_key::{KeyValidity, MinimizeOptions, SignedKeyDetails, Validity};
+use crate::composed::dane::openpgpkey_name;
+use crate::composed::ssh::ssh_public_key;
 use crate::crypto::public_key::PublicKeyAlgorithm;
 use crate::crypto::HashAlgorithm;
-use crate::errors::Result;
+use crate::errors::{Error, Result};
 use crate::packet::{self, write_packet, SignatureType};
 use crate::ser::Serialize;
-use crate::types::{KeyId, KeyTrait, Mpi, PublicKeyTrait};
+use crate::types::{
+    Fingerprint, KeyId, KeyTrait, Mpi, Policy, PublicKeyTrait, PublicParams, Tag, Timestamp,
+};
 
 /// Represents a Public PGP key, which is signed and either received or ready to be transferred.
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -57,10 +67,382 @@ impl SignedPublicKey {
         }
     }
 
-    /// Get the public key expiration as a date.
+    /// Merges another copy of this key into this one, combining user ids, subkeys,
+    /// certifications and revocations. Duplicate signatures are dropped.
+    pub fn merge(mut self, other: SignedPublicKey) -> Result<Self> {
+        ensure_eq!(
+            self.fingerprint(),
+            other.fingerprint(),
+            "can not merge different keys"
+        );
+
+        self.details.merge(other.details);
+        for subkey in other.public_subkeys {
+            let fingerprint = subkey.key.fingerprint();
+            match self
+                .public_subkeys
+                .iter_mut()
+                .find(|k| k.key.fingerprint() == fingerprint)
+            {
+                Some(existing) => merge_signatures(&mut existing.signatures, subkey.signatures),
+                None => self.public_subkeys.push(subkey),
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Returns a minimal copy of this key, that only contains the newest valid self signature
+    /// of each component, for use where size limits apply, like Autocrypt or WKD.
+    pub fn minimize(&self, options: &MinimizeOptions) -> SignedPublicKey {
+        let now = Utc::now();
+        let public_subkeys = self
+            .public_subkeys
+            .iter()
+            .filter_map(|subkey| {
+                let signatures =
+                    minimize_signatures(&subkey.signatures, &self.primary_key, |sig| {
+                        sig.verify_key_binding(&self.primary_key, &subkey.key).is_ok()
+                    });
+                // only verified revocations are left at this point
+                if options.drop_revoked_subkeys
+                    && signatures
+                        .iter()
+                        .any(|sig| sig.typ() == SignatureType::SubkeyRevocation)
+                {
+                    return None;
+                }
+                if options.drop_expired_subkeys
+                    && subkey
+                        .expires_at(&self.primary_key)
+                        .map_or(false, |t| t <= now)
+                {
+                    return None;
+                }
+
+                Some(SignedPublicSubKey {
+                    key: subkey.key.clone(),
+                    signatures,
+                })
+            })
+            .filter(|subkey| !subkey.signatures.is_empty())
+            .collect();
+
+        SignedPublicKey {
+            primary_key: self.primary_key.clone(),
+            details: self.details.minimize(&self.primary_key, options),
+            public_subkeys,
+        }
+    }
+
+    /// Computes the validity of the primary key, the user ids and the subkeys at `time`.
+    ///
+    /// Only self signatures are taken into account. Revocations by designated revokers
+    /// require the key of the revoker, see `is_revoked_by`.
+    pub fn validity_at(&self, time: &DateTime<Utc>) -> KeyValidity {
+        let key = &self.primary_key;
+
+        let users: Vec<_> = self
+            .details
+            .users
+            .iter()
+            .map(|user| (user, user_validity_at(user, key, time)))
+            .collect();
+
+        let primary = if let Some(revoked) = revocation_at(
+            self.details
+                .revocation_signatures
+                .iter()
+                .filter(|sig| is_self_signature(sig, key)),
+            SignatureType::KeyRevocation,
+            time,
+            |sig| sig.verify_key(key).is_ok(),
+        ) {
+            revoked
+        } else {
+            match primary_self_signature_at(
+                &self.details.direct_signatures,
+                users.iter().filter_map(|(_, (_, sig))| *sig),
+                key,
+                time,
+            ) {
+                Some(sig) => expiration_at(sig, key.created_at(), time),
+                None => Validity::NoSelfSignature,
+            }
+        };
+        let users = users
+            .into_iter()
+            .map(|(user, (validity, _))| (user.id.id().to_string(), validity))
+            .collect();
+
+        let subkeys = self
+            .public_subkeys
+            .iter()
+            .map(|subkey| {
+                let self_sigs = || {
+                    subkey
+                        .signatures
+                        .iter()
+                        .filter(|sig| is_self_signature(sig, key))
+                };
+                let verify =
+                    |sig: &packet::Signature| sig.verify_key_binding(key, &subkey.key).is_ok();
+
+                let validity = if let Some(revoked) =
+                    revocation_at(self_sigs(), SignatureType::SubkeyRevocation, time, verify)
+                {
+                    revoked
+                } else if let Some(sig) = newest_signature_at(
+                    self_sigs().filter(|sig| sig.typ() == SignatureType::SubkeyBinding),
+                    time,
+                    verify,
+                ) {
+                    expiration_at(sig, subkey.key.created_at(), time)
+                } else {
+                    Validity::NoSelfSignature
+                };
+
+                (subkey.key_id(), validity)
+            })
+            .collect();
+
+        KeyValidity {
+            primary,
+            users,
+            subkeys,
+        }
+    }
+
+    /// The email addresses of the user ids of this key, without duplicates.
+    pub fn emails(&self) -> Vec<&str> {
+        let mut emails = Vec::new();
+        for email in self.details.users.iter().filter_map(|user| user.id.email()) {
+            if !emails.contains(&email) {
+                emails.push(email);
+            }
+        }
+
+        emails
+    }
+
+    /// Returns the best subkey for encryption at the current time.
+    ///
+    /// Only valid subkeys, that are flagged for encryption, are considered. Modern algorithms
+    /// are preferred, followed by the most recently created subkey.
+    pub fn encryption_subkey(&self) -> Option<&SignedPublicSubKey> {
+        self.live_subkeys(|flags| flags.encrypt_comms() || flags.encrypt_storage())
+            .filter(|subkey| subkey.is_encryption_key())
+            .max_by_key(|subkey| (algorithm_rank(subkey.algorithm()), *subkey.key.created_at()))
+    }
+
+    /// Returns the key used to encrypt messages to this key, at the current time.
+    ///
+    /// The `encryption_subkey` is preferred. Otherwise the primary key is used, if it is
+    /// flagged for encryption.
+    pub fn encryption_key(&self) -> Option<PublicComponentKey<'_>> {
+        if let Some(subkey) = self.encryption_subkey() {
+            return Some(PublicComponentKey::Subkey(subkey));
+        }
+
+        let key = &self.primary_key;
+        let now = Utc::now();
+        if !self.validity_at(&now).is_valid() || !key.is_encryption_key() {
+            return None;
+        }
+
+        let flags = self.primary_key_flags(&now)?;
+        if flags.encrypt_comms() || flags.encrypt_storage() {
+            Some(PublicComponentKey::Primary(key))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the best key for signing at the current time.
+    ///
+    /// Valid signing subkeys with a valid primary key binding are preferred, using the
+    /// same order as `encryption_subkey`. Otherwise the primary key is used, if it is
+    /// flagged for signing.
+    pub fn signing_key(&self) -> Option<PublicComponentKey<'_>> {
+        let key = &self.primary_key;
+        if let Some(subkey) = self
+            .live_subkeys(|flags| flags.sign())
+            .filter(|subkey| subkey.is_signing_key())
+            .filter(|subkey| {
+                subkey.signatures.iter().any(|sig| {
+                    sig.typ() == SignatureType::SubkeyBinding
+                        && sig.embedded_signature().map_or(false, |backsig| {
+                            backsig.verify_primary_key_binding(key, &subkey.key).is_ok()
+                        })
+                })
+            })
+            .max_by_key(|subkey| (algorithm_rank(subkey.algorithm()), *subkey.key.created_at()))
+        {
+            return Some(PublicComponentKey::Subkey(subkey));
+        }
+
+        let now = Utc::now();
+        if !self.validity_at(&now).is_valid() || !key.is_signing_key() {
+            return None;
+        }
+
+        if self.primary_key_flags(&now)?.sign() {
+            Some(PublicComponentKey::Primary(key))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the key used for authentication, e.g. for SSH, at the current time.
+    ///
+    /// The most recently created valid authentication subkey is preferred. Otherwise the
+    /// primary key is used, if it is flagged for authentication.
+    pub fn authentication_key(&self) -> Option<PublicComponentKey<'_>> {
+        // on equal creation times the first subkey is used, like GnuPG does
+        if let Some(subkey) = self
+            .live_subkeys(|flags| flags.authentication())
+            .min_by_key(|subkey| Reverse(*subkey.key.created_at()))
+        {
+            return Some(PublicComponentKey::Subkey(subkey));
+        }
+
+        let now = Utc::now();
+        if !self.validity_at(&now).is_valid() {
+            return None;
+        }
+
+        if self.primary_key_flags(&now)?.authentication() {
+            Some(PublicComponentKey::Primary(&self.primary_key))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the owner name and the data of the DNS OPENPGPKEY record for `email`.
+    ///
+    /// The key is minimized and only contains the user ids with that email address.
+    /// Ref: https://tools.ietf.org/html/rfc7929
+    pub fn to_openpgpkey_record(&self, email: &str) -> Result<(String, Vec<u8>)> {
+        let name = openpgpkey_name(email)?;
+
+        let mut key = self.minimize(&MinimizeOptions {
+            drop_expired_subkeys: true,
+            drop_revoked_subkeys: true,
+            drop_user_attributes: true,
+        });
+        key.details.users.retain(|user| {
+            user.id
+                .email()
+                .map_or(false, |other| other.eq_ignore_ascii_case(email))
+        });
+        ensure!(
+            !key.details.users.is_empty(),
+            "no user id with email {:?} found",
+            email
+        );
+
+        Ok((name, key.to_bytes()?))
+    }
+
+    /// Exports the authentication key as OpenSSH public key, like `gpg --export-ssh-key`.
+    pub fn to_ssh_public_key(&self) -> Result<String> {
+        let key = self
+            .authentication_key()
+            .ok_or_else(|| format_err!("no authentication key found"))?;
+        let key_id = key.key_id();
+        let comment = format!("openpgp:0x{}", hex::encode_upper(&key_id.as_ref()[4..]));
+
+        ssh_public_key(key.public_params(), &comment)
+    }
+
+    /// Returns the key flags of the primary key, as found in its newest self signature.
+    fn primary_key_flags(&self, now: &DateTime<Utc>) -> Option<packet::KeyFlags> {
+        let key = &self.primary_key;
+        let newest = self
+            .details
+            .users
+            .iter()
+            .filter_map(|user| {
+                newest_signature_at(
+                    user.signatures.iter().filter(|sig| {
+                        sig.typ() != SignatureType::CertRevocation && is_self_signature(sig, key)
+                    }),
+                    now,
+                    |sig| sig.verify_certificate(key, Tag::UserId, &user.id).is_ok(),
+                )
+            })
+            .chain(newest_signature_at(
+                self.details
+                    .direct_signatures
+                    .iter()
+                    .filter(|sig| is_self_signature(sig, key)),
+                now,
+                |sig| sig.verify_key(key).is_ok(),
+            ))
+            .max_by_key(|sig| sig.created().cloned())?;
+
+        Some(newest.key_flags())
+    }
+
+    /// Returns the subkeys that are valid at the current time, and whose newest binding
+    /// signature has key flags matching `flags`.
+    fn live_subkeys<F>(&self, flags: F) -> impl Iterator<Item = &SignedPublicSubKey>
+    where
+        F: Fn(packet::KeyFlags) -> bool,
+    {
+        let key = &self.primary_key;
+        let now = Utc::now();
+        let validity = self.validity_at(&now);
+        let primary_valid = validity.is_valid();
+
+        self.public_subkeys
+            .iter()
+            .zip(validity.subkeys)
+            .filter(move |(_, (_, validity))| primary_valid && validity.is_valid())
+            .map(|(subkey, _)| subkey)
+            .filter(move |subkey| {
+                newest_signature_at(
+                    subkey
+                        .signatures
+                        .iter()
+                        .filter(|sig| sig.typ() == SignatureType::SubkeyBinding)
+                        .filter(|sig| is_self_signature(sig, key)),
+                    &now,
+                    |sig| sig.verify_key_binding(key, &subkey.key).is_ok(),
+                )
+                .map_or(false, |sig| flags(sig.key_flags()))
+            })
+    }
+
+    /// Get the public key creation time.
+    pub fn created_at(&self) -> &DateTime<Utc> {
+        self.primary_key.created_at()
+    }
+
+    /// Get the public key creation time, as a `Timestamp`.
+    pub fn created_timestamp(&self) -> Timestamp {
+        self.created_at().into()
+    }
+
+    /// Get the public key expiration, as a `Timestamp`.
+    pub fn expires_timestamp(&self) -> Option<Timestamp> {
+        self.expires_at().map(Into::into)
+    }
+
+    /// Get the public key expiration as a date, based on the newest valid direct key
+    /// signature or self certification of a user id.
     pub fn expires_at(&self) -> Option<DateTime<Utc>> {
-        let expiration = self.details.key_expiration_time()?;
-        Some(*self.primary_key.created_at() + expiration)
+        key_expires_at(
+            &self.details.direct_signatures,
+            &self.details.users,
+            &self.primary_key,
+            self.primary_key.created_at(),
+        )
+    }
+
+    /// Checks if this key was revoked by the given designated revoker.
+    pub fn is_revoked_by(&self, revoker: &impl PublicKeyTrait) -> Result<bool> {
+        self.details.is_revoked_by(&self.primary_key, revoker)
     }
 
     fn verify_public_subkeys(&self) -> Result<()> {
@@ -78,6 +460,44 @@ impl SignedPublicKey {
         Ok(())
     }
 
+    /// Verifies this key like `verify`, and checks that the primary key is neither expired
+    /// nor revoked at `time`. Self signatures, that are expired at `time`, are ignored.
+    pub fn verify_at(&self, time: &DateTime<Utc>) -> Result<()> {
+        self.verify()?;
+        self.check_validity(time)
+    }
+
+    /// Checks that the primary key has a self signature at `time`, and is neither expired
+    /// nor revoked.
+    pub(crate) fn check_validity(&self, time: &DateTime<Utc>) -> Result<()> {
+        match self.validity_at(time).primary {
+            Validity::Valid => Ok(()),
+            Validity::Expired(expires_at) => Err(Error::KeyExpired(expires_at)),
+            Validity::Revoked {
+                code,
+                reason,
+                revoked_at,
+            } => Err(Error::KeyRevoked {
+                code,
+                reason,
+                revoked_at,
+            }),
+            Validity::NoSelfSignature => bail!("no valid self signature at {}", time),
+        }
+    }
+
+    /// Verifies this key like `verify`, and checks its key sizes and self signatures
+    /// against `policy`.
+    pub fn verify_with_policy(&self, policy: &Policy) -> Result<()> {
+        policy.check_public_params(self.primary_key.public_params())?;
+        self.details.check_policy(policy, &self.primary_key)?;
+        for subkey in &self.public_subkeys {
+            subkey.check_policy(policy)?;
+        }
+
+        self.verify()
+    }
+
     pub fn to_armored_writer(
         &self,
         writer: &mut impl io::Write,
@@ -108,10 +528,22 @@ impl SignedPublicKey {
                 .collect(),
         )
     }
+
+    /// Returns the packets of this key, in the order they are serialized: the primary key,
+    /// its signatures, User IDs and User Attributes, followed by the subkeys.
+    pub fn packets(&self) -> Vec<packet::Packet> {
+        let mut packets = vec![packet::Packet::PublicKey(self.primary_key.clone())];
+        packets.extend(self.details.packets());
+        for subkey in &self.public_subkeys {
+            packets.extend(subkey.packets());
+        }
+
+        packets
+    }
 }
 
 impl KeyTrait for SignedPublicKey {
-    fn fingerprint(&self) -> Vec<u8> {
+    fn fingerprint(&self) -> Fingerprint {
         self.primary_key.fingerprint()
     }
 
@@ -150,6 +582,78 @@ impl Serialize for SignedPublicKey {
     }
 }
 
+/// Orders algorithms by preference, when selecting between multiple keys.
+fn algorithm_rank(alg: PublicKeyAlgorithm) -> u8 {
+    match alg {
+        PublicKeyAlgorithm::EdDSA | PublicKeyAlgorithm::ECDH => 3,
+        PublicKeyAlgorithm::ECDSA => 2,
+        PublicKeyAlgorithm::RSA | PublicKeyAlgorithm::RSAEncrypt | PublicKeyAlgorithm::RSASign => 1,
+        _ => 0,
+    }
+}
+
+/// A component of a `SignedPublicKey`, either the primary key or one of its subkeys.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PublicComponentKey<'a> {
+    Primary(&'a packet::PublicKey),
+    Subkey(&'a SignedPublicSubKey),
+}
+
+impl PublicComponentKey<'_> {
+    pub fn public_params(&self) -> &PublicParams {
+        match self {
+            PublicComponentKey::Primary(k) => k.public_params(),
+            PublicComponentKey::Subkey(k) => k.key.public_params(),
+        }
+    }
+}
+
+impl KeyTrait for PublicComponentKey<'_> {
+    fn fingerprint(&self) -> Fingerprint {
+        match self {
+            PublicComponentKey::Primary(k) => k.fingerprint(),
+            PublicComponentKey::Subkey(k) => k.fingerprint(),
+        }
+    }
+
+    fn key_id(&self) -> KeyId {
+        match self {
+            PublicComponentKey::Primary(k) => k.key_id(),
+            PublicComponentKey::Subkey(k) => k.key_id(),
+        }
+    }
+
+    fn algorithm(&self) -> PublicKeyAlgorithm {
+        match self {
+            PublicComponentKey::Primary(k) => k.algorithm(),
+            PublicComponentKey::Subkey(k) => k.algorithm(),
+        }
+    }
+}
+
+impl PublicKeyTrait for PublicComponentKey<'_> {
+    fn verify_signature(&self, hash: HashAlgorithm, data: &[u8], sig: &[Mpi]) -> Result<()> {
+        match self {
+            PublicComponentKey::Primary(k) => k.verify_signature(hash, data, sig),
+            PublicComponentKey::Subkey(k) => k.verify_signature(hash, data, sig),
+        }
+    }
+
+    fn encrypt<R: Rng + CryptoRng>(&self, rng: &mut R, plain: &[u8]) -> Result<Vec<Mpi>> {
+        match self {
+            PublicComponentKey::Primary(k) => k.encrypt(rng, plain),
+            PublicComponentKey::Subkey(k) => k.encrypt(rng, plain),
+        }
+    }
+
+    fn to_writer_old(&self, writer: &mut impl io::Write) -> Result<()> {
+        match self {
+            PublicComponentKey::Primary(k) => k.to_writer_old(writer),
+            PublicComponentKey::Subkey(k) => k.to_writer_old(writer),
+        }
+    }
+}
+
 /// Represents a Public PGP SubKey.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct SignedPublicSubKey {
@@ -176,10 +680,42 @@ impl SignedPublicSubKey {
         SignedPublicSubKey { key, signatures }
     }
 
+    /// Get the subkey creation time.
+    pub fn created_at(&self) -> &DateTime<Utc> {
+        self.key.created_at()
+    }
+
+    /// Get the subkey creation time, as a `Timestamp`.
+    pub fn created_timestamp(&self) -> Timestamp {
+        self.created_at().into()
+    }
+
+    /// Get the subkey expiration, as a `Timestamp`.
+    pub fn expires_timestamp(&self, key: &impl PublicKeyTrait) -> Option<Timestamp> {
+        self.expires_at(key).map(Into::into)
+    }
+
+    /// Get the subkey expiration as a date, based on the newest binding signature by the
+    /// primary key `key`, that verifies.
+    pub fn expires_at(&self, key: &impl PublicKeyTrait) -> Option<DateTime<Utc>> {
+        subkey_expires_at(&self.signatures, key, &self.key, self.key.created_at())
+    }
+
     pub fn verify(&self, key: &impl PublicKeyTrait) -> Result<()> {
         ensure!(!self.signatures.is_empty(), "missing subkey bindings");
         for sig in &self.signatures {
             sig.verify_key_binding(key, &self.key)?;
+            verify_backsig(sig, key, &self.key)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the size and the binding signatures of this subkey against `policy`.
+    pub fn check_policy(&self, policy: &Policy) -> Result<()> {
+        policy.check_public_params(self.key.public_params())?;
+        for sig in &self.signatures {
+            sig.check_policy(policy)?;
         }
 
         Ok(())
@@ -191,14 +727,26 @@ impl SignedPublicSubKey {
             .first()
             .expect("missing signatures")
             .key_flags();
+        let backsig = self
+            .signatures
+            .first()
+            .and_then(|sig| sig.embedded_signature())
+            .cloned();
+
+        PublicSubkey::new(self.key.clone(), keyflags).with_backsig(backsig)
+    }
 
-        PublicSubkey::new(self.key.clone(), keyflags)
+    /// Returns the subkey packet, followed by its binding signatures.
+    pub fn packets(&self) -> Vec<packet::Packet> {
+        let mut packets = vec![packet::Packet::PublicSubkey(self.key.clone())];
+        packets.extend(self.signatures.iter().cloned().map(packet::Packet::Signature));
+        packets
     }
 }
 
 impl KeyTrait for SignedPublicSubKey {
     /// Returns the fingerprint of the key.
-    fn fingerprint(&self) -> Vec<u8> {
+    fn fingerprint(&self) -> Fingerprint {
         self.key.fingerprint()
     }
 
@@ -236,3 +784,314 @@ impl Serialize for SignedPublicSubKey {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use chrono::{NaiveDateTime, SubsecRound};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::composed::key::{test_key, KeyType, SecretKeyParamsBuilder, SubkeyParamsBuilder};
+    use crate::crypto::ECCCurve;
+    use crate::types::SecretKeyTrait;
+
+    #[test]
+    fn test_minimize() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+        let signer = test_key(rng, "Signer <signer@mail.com>", false);
+        let key = test_key(rng, "Me <me@mail.com>", true);
+        let public_key = key
+            .public_key()
+            .sign(&key, || "".into())
+            .expect("failed to sign public key");
+
+        let mut certified = signer
+            .certify(
+                &public_key,
+                "Me <me@mail.com>",
+                packet::CertificationType::Generic,
+                None,
+                true,
+                None,
+                || "".into(),
+            )
+            .expect("failed to certify");
+
+        // add an older self signature
+        let newest = certified.details.users[0].signatures[0].clone();
+        let created = *newest.created().expect("missing creation time");
+        let older = packet::SignatureConfigBuilder::default()
+            .typ(SignatureType::CertPositive)
+            .pub_alg(key.primary_key.algorithm())
+            .hashed_subpackets(vec![packet::Subpacket::SignatureCreationTime(
+                created - chrono::Duration::days(1),
+            )])
+            .unhashed_subpackets(vec![packet::Subpacket::Issuer(key.primary_key.key_id())])
+            .build()
+            .unwrap()
+            .sign_certificate(
+                &key.primary_key,
+                || "".into(),
+                Tag::UserId,
+                &key.details.users[0].id,
+            )
+            .expect("failed to sign certificate");
+        certified.details.users[0].signatures.push(older);
+
+        // and a newer self signature, that does not verify
+        let mut forged = newest.clone();
+        for p in &mut forged.config.hashed_subpackets {
+            if let packet::Subpacket::SignatureCreationTime(ref mut t) = p {
+                *t = created + chrono::Duration::seconds(1);
+            }
+        }
+        certified.details.users[0].signatures.push(forged);
+        assert_eq!(certified.details.users[0].signatures.len(), 4);
+
+        let minimal = certified.minimize(&Default::default());
+        minimal.verify().expect("invalid key");
+        assert_eq!(minimal.details.users.len(), 1);
+        assert_eq!(minimal.details.users[0].signatures, vec![newest]);
+        assert_eq!(minimal.public_subkeys, certified.public_subkeys);
+        assert!(minimal.to_bytes().unwrap().len() < certified.to_bytes().unwrap().len());
+
+        // revocations that do not verify are dropped
+        let mut forged = certified.public_subkeys[0].signatures[0].clone();
+        forged.config.typ = SignatureType::SubkeyRevocation;
+        let mut forged_key = certified.clone();
+        forged_key.public_subkeys[0].signatures.push(forged);
+
+        let minimal = forged_key.minimize(&MinimizeOptions {
+            drop_revoked_subkeys: true,
+            ..Default::default()
+        });
+        assert_eq!(minimal.public_subkeys, certified.public_subkeys);
+
+        // revoked subkeys are only dropped on request
+        let subkey = &certified.public_subkeys[0];
+        let revocation = packet::SignatureConfigBuilder::default()
+            .typ(SignatureType::SubkeyRevocation)
+            .pub_alg(key.primary_key.algorithm())
+            .hashed_subpackets(vec![
+                packet::Subpacket::SignatureCreationTime(Utc::now().trunc_subsecs(0)),
+                packet::Subpacket::RevocationReason(
+                    packet::RevocationCode::KeyRetired,
+                    "retired".to_string(),
+                ),
+            ])
+            .unhashed_subpackets(vec![packet::Subpacket::Issuer(key.primary_key.key_id())])
+            .build()
+            .unwrap()
+            .sign_key_binding(&key.primary_key, || "".into(), &subkey.key)
+            .expect("failed to revoke subkey");
+        certified.public_subkeys[0].signatures.push(revocation);
+
+        let minimal = certified.minimize(&Default::default());
+        assert_eq!(minimal.public_subkeys[0].signatures.len(), 2);
+
+        let minimal = certified.minimize(&MinimizeOptions {
+            drop_revoked_subkeys: true,
+            ..Default::default()
+        });
+        assert!(minimal.public_subkeys.is_empty());
+
+        // key revocations are kept
+        let revocation = key
+            .revoke_key(
+                &key.primary_key,
+                packet::RevocationCode::KeyCompromised,
+                "compromised",
+                || "".into(),
+            )
+            .expect("failed to revoke key");
+        certified.details.revocation_signatures.push(revocation.clone());
+        let minimal = certified.minimize(&Default::default());
+        assert_eq!(minimal.details.revocation_signatures, vec![revocation]);
+    }
+
+    #[test]
+    fn test_validity() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+        let key = test_key(rng, "Me <me@mail.com>", true)
+            .set_expiration(Some(Duration::from_secs(60 * 60 * 24)), || "".into())
+            .expect("failed to set expiration");
+
+        let mut public_key = key.signed_public_key();
+
+        let created_at = *key.primary_key.created_at();
+        let expires_at = created_at + chrono::Duration::days(1);
+
+        let validity = public_key.validity_at(&Utc::now());
+        assert!(validity.is_valid());
+        assert_eq!(
+            validity.users,
+            vec![("Me <me@mail.com>".to_string(), Validity::Valid)]
+        );
+        assert_eq!(
+            validity.subkeys,
+            vec![(public_key.public_subkeys[0].key_id(), Validity::Valid)]
+        );
+
+        let before = public_key.validity_at(&(created_at - chrono::Duration::days(1)));
+        assert_eq!(before.primary, Validity::NoSelfSignature);
+        assert_eq!(before.users[0].1, Validity::NoSelfSignature);
+        assert_eq!(before.subkeys[0].1, Validity::NoSelfSignature);
+
+        let after = public_key.validity_at(&(expires_at + chrono::Duration::days(1)));
+        assert_eq!(after.primary, Validity::Expired(expires_at));
+        assert_eq!(after.users[0].1, Validity::Valid);
+        assert_eq!(after.subkeys[0].1, Validity::Expired(expires_at));
+
+        public_key.verify_at(&Utc::now()).expect("invalid key");
+        key.verify_at(&Utc::now()).expect("invalid key");
+        match public_key.verify_at(&(expires_at + chrono::Duration::days(1))) {
+            Err(Error::KeyExpired(at)) => assert_eq!(at, expires_at),
+            res => panic!("unexpected result {:?}", res),
+        }
+        assert!(public_key
+            .verify_at(&(created_at - chrono::Duration::days(1)))
+            .is_err());
+
+        let revoke = |code| {
+            key.revoke_key(&key.primary_key, code, "revoked", || "".into())
+                .expect("failed to create revocation")
+        };
+        let long_ago = created_at - chrono::Duration::days(1);
+
+        // soft revocations only apply from the time they were issued
+        let mut soft = public_key.clone();
+        let sig = revoke(packet::RevocationCode::KeySuperseded);
+        let revoked_at = *sig.created().expect("missing creation time");
+        soft.details.revocation_signatures.push(sig);
+        assert_eq!(
+            soft.validity_at(&revoked_at).primary,
+            Validity::Revoked {
+                code: Some(packet::RevocationCode::KeySuperseded),
+                reason: Some("revoked".to_string()),
+                revoked_at: Some(revoked_at),
+            }
+        );
+        assert_eq!(soft.validity_at(&long_ago).primary, Validity::NoSelfSignature);
+
+        let sig = revoke(packet::RevocationCode::KeyCompromised);
+        let revoked_at = sig.created().cloned();
+        public_key.details.revocation_signatures.push(sig);
+        assert_eq!(
+            public_key.validity_at(&long_ago).primary,
+            Validity::Revoked {
+                code: Some(packet::RevocationCode::KeyCompromised),
+                reason: Some("revoked".to_string()),
+                revoked_at,
+            }
+        );
+    }
+
+    #[test]
+    fn test_direct_key_expiration() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+        let now = Utc::now().trunc_subsecs(0);
+        let created_at = now - chrono::Duration::days(2);
+        let key = SecretKeyParamsBuilder::default()
+            .key_type(KeyType::EdDSA)
+            .can_create_certificates(true)
+            .can_sign(true)
+            .primary_user_id("Me <me@mail.com>".into())
+            .created_at(created_at)
+            .build()
+            .unwrap()
+            .generate_with_rng(rng)
+            .expect("failed to generate secret key")
+            .sign_at(|| "".into(), created_at)
+            .expect("failed to sign key");
+        let mut public_key = key.signed_public_key();
+        assert_eq!(public_key.expires_at(), None);
+
+        // the newer direct key signature takes precedence over the user id certification
+        let direct = packet::SignatureConfigBuilder::default()
+            .typ(SignatureType::Key)
+            .pub_alg(key.primary_key.algorithm())
+            .hashed_subpackets(vec![
+                packet::Subpacket::SignatureCreationTime(now),
+                packet::Subpacket::KeyExpirationTime(DateTime::from_utc(
+                    NaiveDateTime::from_timestamp(60 * 60 * 24 * 3, 0),
+                    Utc,
+                )),
+            ])
+            .unhashed_subpackets(vec![packet::Subpacket::Issuer(key.primary_key.key_id())])
+            .build()
+            .unwrap()
+            .sign_key(&key.primary_key, || "".into(), &key.primary_key)
+            .expect("failed to sign direct key signature");
+        public_key.details.direct_signatures.push(direct);
+
+        let expires_at = created_at + chrono::Duration::days(3);
+        assert_eq!(public_key.expires_at(), Some(expires_at));
+        assert_eq!(public_key.validity_at(&now).primary, Validity::Valid);
+        assert_eq!(
+            public_key
+                .validity_at(&(expires_at + chrono::Duration::days(1)))
+                .primary,
+            Validity::Expired(expires_at)
+        );
+    }
+
+    #[test]
+    fn test_select_subkeys() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+        let key = test_key(rng, "Me <me@mail.com>", false);
+
+        let public_key = key.signed_public_key();
+        assert!(public_key.encryption_subkey().is_none());
+        assert_eq!(
+            public_key.signing_key(),
+            Some(PublicComponentKey::Primary(&public_key.primary_key))
+        );
+
+        let key = key
+            .generate_subkey_with_rng(
+                rng,
+                SubkeyParamsBuilder::default()
+                    .key_type(KeyType::ECDH(ECCCurve::Curve25519))
+                    .can_encrypt(true)
+                    .build()
+                    .unwrap(),
+                || "".into(),
+            )
+            .expect("failed to add encryption subkey")
+            .generate_subkey_with_rng(
+                rng,
+                SubkeyParamsBuilder::default()
+                    .key_type(KeyType::ECDH(ECCCurve::Curve25519))
+                    .can_encrypt(true)
+                    .build()
+                    .unwrap(),
+                || "".into(),
+            )
+            .expect("failed to add encryption subkey")
+            .generate_subkey_with_rng(
+                rng,
+                SubkeyParamsBuilder::default()
+                    .key_type(KeyType::EdDSA)
+                    .can_sign(true)
+                    .build()
+                    .unwrap(),
+                || "".into(),
+            )
+            .expect("failed to add signing subkey");
+
+        // the newest encryption subkey is preferred
+        let public_key = key.signed_public_key();
+        assert_eq!(
+            public_key.encryption_subkey(),
+            Some(&public_key.public_subkeys[1])
+        );
+        assert_eq!(
+            public_key.signing_key(),
+            Some(PublicComponentKey::Subkey(&public_key.public_subkeys[2]))
+        );
+    }
+}
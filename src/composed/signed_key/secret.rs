@@ -1,18 +1,29 @@
 use std::collections::BTreeMap;
 use std::io;
+use std::time::Duration;
 
-use chrono::{DateTime, Utc};
-use rand::{CryptoRng, Rng};
+use chrono::{DateTime, NaiveDateTime, SubsecRound, Utc};
+use rand::{thread_rng, CryptoRng, Rng};
+use smallvec::SmallVec;
 
 use crate::armor;
-use crate::composed::key::{PublicKey, PublicSubkey};
-use crate::composed::signed_key::{SignedKeyDetails, SignedPublicSubKey};
+use crate::composed::agent_key::AgentKey;
+use crate::composed::key::{PublicKey, PublicSubkey, SecretSubkey, SubkeyParams};
+use crate::composed::signed_key::shared::{merge_signatures, verify_backsig};
+use crate::composed::signed_key::validity::{key_expires_at, subkey_expires_at};
+use crate::composed::signed_key::{SignedKeyDetails, SignedPublicKey, SignedPublicSubKey};
 use crate::crypto::hash::HashAlgorithm;
 use crate::crypto::public_key::PublicKeyAlgorithm;
-use crate::errors::Result;
-use crate::packet::{self, write_packet, SignatureType};
+use crate::errors::{Error, Result};
+use crate::packet::{
+    self, write_packet, CertificationType, RevocationCode, Signature, SignatureConfigBuilder,
+    SignatureType, Subpacket, UserId,
+};
 use crate::ser::Serialize;
-use crate::types::{KeyId, KeyTrait, Mpi, PublicKeyTrait, SecretKeyRepr, SecretKeyTrait};
+use crate::types::{
+    Fingerprint, KeyId, KeyTrait, Mpi, Policy, PublicKeyTrait, S2kParams, SecretKeyRepr,
+    SecretKeyTrait, SignedUser, Tag, Timestamp,
+};
 
 /// Represents a secret signed PGP key.
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -76,10 +87,467 @@ impl SignedSecretKey {
         }
     }
 
-    /// Get the secret key expiration as a date.
+    /// Creates a secret key from a public key and the secret keys of `gpg-agent`, as found in
+    /// `private-keys-v1.d`.
+    ///
+    /// Subkeys without a matching agent key are kept as public subkeys. The secret key
+    /// material is stored unprotected, use `set_passphrase` to protect it.
+    pub fn from_agent_keys<F>(
+        key: SignedPublicKey,
+        agent_keys: &[AgentKey],
+        key_pw: F,
+    ) -> Result<Self>
+    where
+        F: (FnOnce() -> String) + Clone,
+    {
+        let SignedPublicKey {
+            primary_key,
+            details,
+            public_subkeys,
+        } = key;
+
+        let agent_key = agent_keys
+            .iter()
+            .find(|k| k.matches(primary_key.public_params()));
+        let primary_key = match agent_key {
+            Some(agent_key) => agent_key.to_secret_key(&primary_key, key_pw.clone())?,
+            None => return Err(Error::MissingKey),
+        };
+
+        let mut secret_subkeys = Vec::new();
+        let mut remaining_subkeys = Vec::new();
+        for subkey in public_subkeys {
+            let agent_key = agent_keys
+                .iter()
+                .find(|k| k.matches(subkey.key.public_params()));
+            match agent_key {
+                Some(agent_key) => secret_subkeys.push(SignedSecretSubKey::new(
+                    agent_key.to_secret_subkey(&subkey.key, key_pw.clone())?,
+                    subkey.signatures,
+                )),
+                None => remaining_subkeys.push(subkey),
+            }
+        }
+
+        Ok(SignedSecretKey::new(
+            primary_key,
+            details,
+            remaining_subkeys,
+            secret_subkeys,
+        ))
+    }
+
+    /// Merges another copy of this key into this one, combining user ids, subkeys,
+    /// certifications and revocations. Duplicate signatures are dropped.
+    ///
+    /// Secret key material from `other` replaces stubs and public subkeys of this key.
+    pub fn merge(mut self, other: SignedSecretKey) -> Result<Self> {
+        ensure_eq!(
+            self.fingerprint(),
+            other.fingerprint(),
+            "can not merge different keys"
+        );
+
+        let SignedSecretKey {
+            primary_key,
+            details,
+            public_subkeys,
+            secret_subkeys,
+        } = other;
+
+        if self.primary_key.secret_params().is_stub() && !primary_key.secret_params().is_stub() {
+            self.primary_key = primary_key;
+        }
+        self.details.merge(details);
+
+        for subkey in secret_subkeys {
+            let fingerprint = subkey.key.fingerprint();
+            if let Some(existing) = self
+                .secret_subkeys
+                .iter_mut()
+                .find(|k| k.key.fingerprint() == fingerprint)
+            {
+                let has_secret = !subkey.key.secret_params().is_stub();
+                if existing.key.secret_params().is_stub() && has_secret {
+                    existing.key = subkey.key;
+                }
+                merge_signatures(&mut existing.signatures, subkey.signatures);
+            } else if let Some(pos) = self
+                .public_subkeys
+                .iter()
+                .position(|k| k.key.fingerprint() == fingerprint)
+            {
+                let public_subkey = self.public_subkeys.remove(pos);
+                let mut subkey = subkey;
+                merge_signatures(&mut subkey.signatures, public_subkey.signatures);
+                self.secret_subkeys.push(subkey);
+            } else {
+                self.secret_subkeys.push(subkey);
+            }
+        }
+
+        for subkey in public_subkeys {
+            let fingerprint = subkey.key.fingerprint();
+            if let Some(existing) = self
+                .secret_subkeys
+                .iter_mut()
+                .find(|k| k.key.fingerprint() == fingerprint)
+            {
+                merge_signatures(&mut existing.signatures, subkey.signatures);
+            } else if let Some(existing) = self
+                .public_subkeys
+                .iter_mut()
+                .find(|k| k.key.fingerprint() == fingerprint)
+            {
+                merge_signatures(&mut existing.signatures, subkey.signatures);
+            } else {
+                self.public_subkeys.push(subkey);
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Get the secret key creation time.
+    pub fn created_at(&self) -> &DateTime<Utc> {
+        self.primary_key.created_at()
+    }
+
+    /// Get the secret key creation time, as a `Timestamp`.
+    pub fn created_timestamp(&self) -> Timestamp {
+        self.created_at().into()
+    }
+
+    /// Get the secret key expiration, as a `Timestamp`.
+    pub fn expires_timestamp(&self) -> Option<Timestamp> {
+        self.expires_at().map(Into::into)
+    }
+
+    /// Get the secret key expiration as a date, based on the newest valid direct key
+    /// signature or self certification of a user id.
     pub fn expires_at(&self) -> Option<DateTime<Utc>> {
-        let expiration = self.details.key_expiration_time()?;
-        Some(*self.primary_key.created_at() + expiration)
+        key_expires_at(
+            &self.details.direct_signatures,
+            &self.details.users,
+            &self.primary_key,
+            self.primary_key.created_at(),
+        )
+    }
+
+    /// Checks if this key was revoked by the given designated revoker.
+    pub fn is_revoked_by(&self, revoker: &impl PublicKeyTrait) -> Result<bool> {
+        self.details.is_revoked_by(&self.primary_key, revoker)
+    }
+
+    /// Certifies the user id `user_id` of another key, returning the key including the new
+    /// certification.
+    ///
+    /// `signers_user_id` optionally sets which of our own user ids is making the certification,
+    /// non `exportable` certifications are marked as local only, and `signature_expiration`
+    /// limits how long the certification is valid after its creation.
+    #[allow(clippy::too_many_arguments)]
+    pub fn certify<F>(
+        &self,
+        key: &SignedPublicKey,
+        user_id: &str,
+        typ: CertificationType,
+        signers_user_id: Option<&str>,
+        exportable: bool,
+        signature_expiration: Option<Duration>,
+        key_pw: F,
+    ) -> Result<SignedPublicKey>
+    where
+        F: FnOnce() -> String,
+    {
+        let mut key = key.clone();
+        let user = key
+            .details
+            .users
+            .iter_mut()
+            .find(|u| u.id.id() == user_id)
+            .ok_or_else(|| format_err!("user id {:?} not found", user_id))?;
+
+        let mut hashed_subpackets = vec![
+            Subpacket::SignatureCreationTime(Utc::now().trunc_subsecs(0)),
+            Subpacket::IssuerFingerprint(
+                Default::default(),
+                SmallVec::from_slice(self.primary_key.fingerprint().as_bytes()),
+            ),
+        ];
+        if let Some(signers_user_id) = signers_user_id {
+            hashed_subpackets.push(Subpacket::SignersUserID(signers_user_id.to_string()));
+        }
+        if !exportable {
+            hashed_subpackets.push(Subpacket::ExportableCertification(false));
+        }
+        hashed_subpackets.extend(signature_expiration_subpackets(signature_expiration)?);
+
+        let sig = SignatureConfigBuilder::default()
+            .typ(typ.into())
+            .pub_alg(self.primary_key.algorithm())
+            .hashed_subpackets(hashed_subpackets)
+            .unhashed_subpackets(vec![Subpacket::Issuer(self.primary_key.key_id())])
+            .build()?
+            .sign_certificate_third_party(
+                &self.primary_key,
+                key_pw,
+                &key.primary_key,
+                Tag::UserId,
+                &user.id,
+            )?;
+        user.signatures.push(sig);
+
+        Ok(key)
+    }
+
+    /// Creates a key revocation signature for `key`, which can either be this key itself
+    /// or a key that lists this key as designated revoker.
+    pub fn revoke_key<F>(
+        &self,
+        key: &impl PublicKeyTrait,
+        code: RevocationCode,
+        reason: &str,
+        key_pw: F,
+    ) -> Result<Signature>
+    where
+        F: FnOnce() -> String,
+    {
+        SignatureConfigBuilder::default()
+            .typ(SignatureType::KeyRevocation)
+            .pub_alg(self.primary_key.algorithm())
+            .hashed_subpackets(vec![
+                Subpacket::SignatureCreationTime(Utc::now().trunc_subsecs(0)),
+                Subpacket::RevocationReason(code, reason.to_string()),
+                Subpacket::IssuerFingerprint(
+                    Default::default(),
+                    SmallVec::from_slice(self.primary_key.fingerprint().as_bytes()),
+                ),
+            ])
+            .unhashed_subpackets(vec![Subpacket::Issuer(self.primary_key.key_id())])
+            .build()?
+            .sign_key(&self.primary_key, key_pw, key)
+    }
+
+    /// Changes the passphrase of the primary key and all secret subkeys, using the default
+    /// S2K parameters. Passing `None` as `new_pw` removes the passphrase.
+    ///
+    /// Signatures are left untouched, as they only cover the public key material.
+    pub fn set_passphrase<F>(&mut self, old_pw: F, new_pw: Option<&str>) -> Result<()>
+    where
+        F: Fn() -> String,
+    {
+        let mut rng = thread_rng();
+        self.set_passphrase_with_rng(&mut rng, old_pw, new_pw, &S2kParams::default())
+    }
+
+    /// Same as `set_passphrase`, with a custom rng and S2K parameters.
+    ///
+    /// The key is only modified if the passphrase of every key could be changed.
+    pub fn set_passphrase_with_rng<R, F>(
+        &mut self,
+        rng: &mut R,
+        old_pw: F,
+        new_pw: Option<&str>,
+        s2k_params: &S2kParams,
+    ) -> Result<()>
+    where
+        R: Rng + CryptoRng,
+        F: Fn() -> String,
+    {
+        let mut primary_key = self.primary_key.clone();
+        primary_key.set_passphrase(rng, &old_pw, new_pw, s2k_params)?;
+
+        let mut secret_subkeys = self.secret_subkeys.clone();
+        for subkey in &mut secret_subkeys {
+            subkey.key.set_passphrase(rng, &old_pw, new_pw, s2k_params)?;
+        }
+
+        self.primary_key = primary_key;
+        self.secret_subkeys = secret_subkeys;
+
+        Ok(())
+    }
+
+    /// Generates a new subkey and adds it to this key, see `add_subkey`.
+    pub fn generate_subkey<F>(self, params: SubkeyParams, key_pw: F) -> Result<Self>
+    where
+        F: (FnOnce() -> String) + Clone,
+    {
+        let mut rng = thread_rng();
+        self.generate_subkey_with_rng(&mut rng, params, key_pw)
+    }
+
+    /// Same as `generate_subkey`, with a custom rng.
+    pub fn generate_subkey_with_rng<R, F>(
+        self,
+        rng: &mut R,
+        params: SubkeyParams,
+        key_pw: F,
+    ) -> Result<Self>
+    where
+        R: Rng + CryptoRng,
+        F: (FnOnce() -> String) + Clone,
+    {
+        let subkey = params.generate_with_rng(rng)?;
+        self.add_subkey(subkey, key_pw)
+    }
+
+    /// Adds the given subkey, creating the subkey binding signature with the primary key.
+    /// Signing subkeys also get an embedded back signature.
+    pub fn add_subkey<F>(mut self, subkey: SecretSubkey, key_pw: F) -> Result<Self>
+    where
+        F: (FnOnce() -> String) + Clone,
+    {
+        let subkey = subkey.sign(&self.primary_key, key_pw)?;
+        self.secret_subkeys.push(subkey);
+
+        Ok(self)
+    }
+
+    /// Adds a new user id, with a positive self certification.
+    ///
+    /// Key flags and preferences are taken over from the self signature of the current
+    /// primary user id. If `primary` is set, the new user id becomes the primary one and the
+    /// self signatures of the previous primary user id are re-issued without the primary flag.
+    pub fn add_user_id<F>(mut self, id: UserId, primary: bool, key_pw: F) -> Result<Self>
+    where
+        F: (FnOnce() -> String) + Clone,
+    {
+        let template = {
+            let primary_user = self
+                .details
+                .users
+                .iter()
+                .find(|u| u.is_primary())
+                .or_else(|| self.details.users.first())
+                .ok_or_else(|| format_err!("missing user ids"))?;
+            primary_user
+                .signatures
+                .first()
+                .ok_or_else(|| format_err!("invalid primary user"))?
+                .config
+                .hashed_subpackets
+                .clone()
+        };
+
+        if primary {
+            let primary_key = &self.primary_key;
+            for user in self.details.users.iter_mut().filter(|u| u.is_primary()) {
+                let id = &user.id;
+                let verify = |sig: &Signature| {
+                    sig.is_primary() && is_self_certification(sig, primary_key, id)
+                };
+                user.signatures = reissue_self_signatures(&user.signatures, verify, |sig| {
+                    let hashed_subpackets = refresh_subpackets(
+                        &sig.config.hashed_subpackets,
+                        |p| !is_primary_subpacket(p),
+                        Vec::new(),
+                    );
+                    self_certification(
+                        primary_key,
+                        id,
+                        sig.typ(),
+                        hashed_subpackets,
+                        key_pw.clone(),
+                    )
+                })?;
+            }
+        }
+
+        let extra = if primary {
+            vec![Subpacket::IsPrimary(true)]
+        } else {
+            Vec::new()
+        };
+        let hashed_subpackets = refresh_subpackets(&template, |p| !is_primary_subpacket(p), extra);
+        let sig = self_certification(
+            &self.primary_key,
+            &id,
+            SignatureType::CertPositive,
+            hashed_subpackets,
+            key_pw,
+        )?;
+        self.details.users.push(SignedUser::new(id, vec![sig]));
+
+        Ok(self)
+    }
+
+    /// Sets the expiration of the key, relative to its creation time, by re-issuing the self
+    /// signatures of all user ids, the direct key signatures and the binding signatures of
+    /// all subkeys.
+    ///
+    /// Subkeys expire at the same point in time as the primary key. Passing `None` removes
+    /// the expiration.
+    pub fn set_expiration<F>(mut self, expiration: Option<Duration>, key_pw: F) -> Result<Self>
+    where
+        F: (FnOnce() -> String) + Clone,
+    {
+        let primary_key = &self.primary_key;
+        let expires_at = match expiration {
+            Some(expiration) => {
+                let expiration = chrono::Duration::from_std(expiration)
+                    .map_err(|_| format_err!("expiration out of range"))?;
+                Some(*primary_key.created_at() + expiration)
+            }
+            None => None,
+        };
+
+        let extra = key_expiration_subpackets(primary_key.created_at(), expires_at)?;
+        for user in &mut self.details.users {
+            let id = &user.id;
+            let verify = |sig: &Signature| is_self_certification(sig, primary_key, id);
+            user.signatures = reissue_self_signatures(&user.signatures, verify, |sig| {
+                let hashed_subpackets = refresh_subpackets(
+                    &sig.config.hashed_subpackets,
+                    |p| !is_key_expiration_subpacket(p),
+                    extra.clone(),
+                );
+                self_certification(
+                    primary_key,
+                    id,
+                    sig.typ(),
+                    hashed_subpackets,
+                    key_pw.clone(),
+                )
+            })?;
+        }
+
+        let verify = |sig: &Signature| {
+            sig.typ() == SignatureType::Key && sig.verify_key(primary_key).is_ok()
+        };
+        self.details.direct_signatures =
+            reissue_self_signatures(&self.details.direct_signatures, verify, |sig| {
+                let hashed_subpackets = refresh_subpackets(
+                    &sig.config.hashed_subpackets,
+                    |p| !is_key_expiration_subpacket(p),
+                    extra.clone(),
+                );
+                direct_key_signature(primary_key, hashed_subpackets, key_pw.clone())
+            })?;
+
+        for subkey in &mut self.public_subkeys {
+            let extra = key_expiration_subpackets(subkey.key.created_at(), expires_at)?;
+            subkey.signatures = reissue_binding_signatures(
+                &subkey.signatures,
+                primary_key,
+                &subkey.key,
+                &extra,
+                key_pw.clone(),
+            )?;
+        }
+
+        for subkey in &mut self.secret_subkeys {
+            let extra = key_expiration_subpackets(subkey.key.created_at(), expires_at)?;
+            subkey.signatures = reissue_binding_signatures(
+                &subkey.signatures,
+                primary_key,
+                &subkey.key,
+                &extra,
+                key_pw.clone(),
+            )?;
+        }
+
+        Ok(self)
     }
 
     fn verify_public_subkeys(&self) -> Result<()> {
@@ -106,6 +574,45 @@ impl SignedSecretKey {
         Ok(())
     }
 
+    /// Verifies this key like `verify`, and checks that the primary key is neither expired
+    /// nor revoked at `time`. Self signatures, that are expired at `time`, are ignored.
+    pub fn verify_at(&self, time: &DateTime<Utc>) -> Result<()> {
+        self.verify()?;
+        self.signed_public_key().check_validity(time)
+    }
+
+    /// Verifies this key like `verify`, and checks its key sizes and self signatures
+    /// against `policy`.
+    pub fn verify_with_policy(&self, policy: &Policy) -> Result<()> {
+        policy.check_public_params(self.primary_key.public_params())?;
+        self.details.check_policy(policy, &self.primary_key)?;
+        for subkey in &self.public_subkeys {
+            subkey.check_policy(policy)?;
+        }
+        for subkey in &self.secret_subkeys {
+            subkey.check_policy(policy)?;
+        }
+
+        self.verify()
+    }
+
+    /// Strips the secret key material, keeping all signatures, e.g. to share the key.
+    pub fn signed_public_key(&self) -> SignedPublicKey {
+        let secret_subkeys = self.secret_subkeys.iter().map(|subkey| {
+            SignedPublicSubKey::new(subkey.key.public_key(), subkey.signatures.clone())
+        });
+
+        SignedPublicKey::new(
+            self.primary_key.public_key(),
+            self.details.clone(),
+            self.public_subkeys
+                .iter()
+                .cloned()
+                .chain(secret_subkeys)
+                .collect(),
+        )
+    }
+
     pub fn to_armored_writer(
         &self,
         writer: &mut impl io::Write,
@@ -125,11 +632,27 @@ impl SignedSecretKey {
     pub fn to_armored_string(&self, headers: Option<&BTreeMap<String, String>>) -> Result<String> {
         Ok(::std::str::from_utf8(&self.to_armored_bytes(headers)?)?.to_string())
     }
+
+    /// Returns the packets of this key, in the order they are serialized: the primary key,
+    /// its signatures, User IDs and User Attributes, followed by the public and the secret
+    /// subkeys.
+    pub fn packets(&self) -> Vec<packet::Packet> {
+        let mut packets = vec![packet::Packet::SecretKey(self.primary_key.clone())];
+        packets.extend(self.details.packets());
+        for subkey in &self.public_subkeys {
+            packets.extend(subkey.packets());
+        }
+        for subkey in &self.secret_subkeys {
+            packets.extend(subkey.packets());
+        }
+
+        packets
+    }
 }
 
 impl KeyTrait for SignedSecretKey {
     /// Returns the fingerprint of the associated primary key.
-    fn fingerprint(&self) -> Vec<u8> {
+    fn fingerprint(&self) -> Fingerprint {
         self.primary_key.fingerprint()
     }
 
@@ -209,6 +732,199 @@ impl PublicKeyTrait for SignedSecretKey {
     }
 }
 
+/// Builds the hashed subpackets for a re-issued signature: a fresh creation time, followed by
+/// `extra` and all subpackets of `template` that pass `keep`.
+fn refresh_subpackets<P>(template: &[Subpacket], keep: P, extra: Vec<Subpacket>) -> Vec<Subpacket>
+where
+    P: Fn(&Subpacket) -> bool,
+{
+    let mut hashed_subpackets = vec![Subpacket::SignatureCreationTime(
+        Utc::now().trunc_subsecs(0),
+    )];
+    hashed_subpackets.extend(extra);
+    hashed_subpackets.extend(
+        template
+            .iter()
+            .filter(|p| match p {
+                Subpacket::SignatureCreationTime(_) => false,
+                _ => keep(p),
+            })
+            .cloned(),
+    );
+
+    hashed_subpackets
+}
+
+fn is_primary_subpacket(p: &Subpacket) -> bool {
+    match p {
+        Subpacket::IsPrimary(_) => true,
+        _ => false,
+    }
+}
+
+fn is_key_expiration_subpacket(p: &Subpacket) -> bool {
+    match p {
+        Subpacket::KeyExpirationTime(_) => true,
+        _ => false,
+    }
+}
+
+/// Builds the Key Expiration Time subpacket, for a key created at `created_at`.
+pub(crate) fn key_expiration_subpackets(
+    created_at: &DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<Vec<Subpacket>> {
+    match expires_at {
+        Some(expires_at) => {
+            let offset = expires_at.signed_duration_since(*created_at).num_seconds();
+            ensure!(
+                offset > 0 && offset <= i64::from(std::u32::MAX),
+                "invalid key expiration {}",
+                expires_at
+            );
+
+            Ok(vec![Subpacket::KeyExpirationTime(DateTime::from_utc(
+                NaiveDateTime::from_timestamp(offset, 0),
+                Utc,
+            ))])
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Builds the Signature Expiration Time subpacket, for a signature that expires `expiration`
+/// after its creation.
+pub(crate) fn signature_expiration_subpackets(
+    expiration: Option<Duration>,
+) -> Result<Vec<Subpacket>> {
+    match expiration {
+        Some(expiration) => {
+            let offset = expiration.as_secs();
+            ensure!(
+                offset > 0 && offset <= u64::from(std::u32::MAX),
+                "invalid signature expiration {:?}",
+                expiration
+            );
+
+            Ok(vec![Subpacket::SignatureExpirationTime(DateTime::from_utc(
+                NaiveDateTime::from_timestamp(offset as i64, 0),
+                Utc,
+            ))])
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Checks if `sig` is a valid certification of `id`, made by `key` itself.
+fn is_self_certification(sig: &Signature, key: &packet::SecretKey, id: &UserId) -> bool {
+    match sig.typ() {
+        SignatureType::CertGeneric
+        | SignatureType::CertPersona
+        | SignatureType::CertCasual
+        | SignatureType::CertPositive => sig.verify_certificate(key, Tag::UserId, id).is_ok(),
+        _ => false,
+    }
+}
+
+/// Re-issues the valid binding signatures of `subkey`, replacing the Key Expiration Time
+/// subpacket.
+fn reissue_binding_signatures<F>(
+    signatures: &[Signature],
+    key: &packet::SecretKey,
+    subkey: &impl PublicKeyTrait,
+    extra: &[Subpacket],
+    key_pw: F,
+) -> Result<Vec<Signature>>
+where
+    F: (FnOnce() -> String) + Clone,
+{
+    let verify = |sig: &Signature| {
+        sig.typ() == SignatureType::SubkeyBinding && sig.verify_key_binding(key, subkey).is_ok()
+    };
+    reissue_self_signatures(signatures, verify, |sig| {
+        let hashed_subpackets = refresh_subpackets(
+            &sig.config.hashed_subpackets,
+            |p| !is_key_expiration_subpacket(p),
+            extra.to_vec(),
+        );
+        subkey_binding(key, subkey, hashed_subpackets, key_pw.clone())
+    })
+}
+
+/// Creates a self certification over `id`, using the given hashed subpackets.
+fn self_certification<F>(
+    key: &packet::SecretKey,
+    id: &UserId,
+    typ: SignatureType,
+    hashed_subpackets: Vec<Subpacket>,
+    key_pw: F,
+) -> Result<Signature>
+where
+    F: FnOnce() -> String,
+{
+    SignatureConfigBuilder::default()
+        .typ(typ)
+        .pub_alg(key.algorithm())
+        .hashed_subpackets(hashed_subpackets)
+        .unhashed_subpackets(vec![Subpacket::Issuer(key.key_id())])
+        .build()?
+        .sign_certificate(key, key_pw, Tag::UserId, id)
+}
+
+/// Creates a subkey binding signature for `subkey`, using the given hashed subpackets.
+fn subkey_binding<F>(
+    key: &packet::SecretKey,
+    subkey: &impl PublicKeyTrait,
+    hashed_subpackets: Vec<Subpacket>,
+    key_pw: F,
+) -> Result<Signature>
+where
+    F: FnOnce() -> String,
+{
+    SignatureConfigBuilder::default()
+        .typ(SignatureType::SubkeyBinding)
+        .pub_alg(key.algorithm())
+        .hashed_subpackets(hashed_subpackets)
+        .unhashed_subpackets(vec![Subpacket::Issuer(key.key_id())])
+        .build()?
+        .sign_key_binding(key, key_pw, subkey)
+}
+
+/// Creates a direct key signature over `key`, using the given hashed subpackets.
+fn direct_key_signature<F>(
+    key: &packet::SecretKey,
+    hashed_subpackets: Vec<Subpacket>,
+    key_pw: F,
+) -> Result<Signature>
+where
+    F: FnOnce() -> String,
+{
+    SignatureConfigBuilder::default()
+        .typ(SignatureType::Key)
+        .pub_alg(key.algorithm())
+        .hashed_subpackets(hashed_subpackets)
+        .unhashed_subpackets(vec![Subpacket::Issuer(key.key_id())])
+        .build()?
+        .sign_key(key, key_pw, key)
+}
+
+/// Re-issues the signatures in `signatures` using `f`, if they pass `verify`. All other
+/// signatures, like revocations or third party certifications, are kept as they are.
+fn reissue_self_signatures<V, G>(
+    signatures: &[Signature],
+    verify: V,
+    mut f: G,
+) -> Result<Vec<Signature>>
+where
+    V: Fn(&Signature) -> bool,
+    G: FnMut(&Signature) -> Result<Signature>,
+{
+    signatures
+        .iter()
+        .map(|sig| if verify(sig) { f(sig) } else { Ok(sig.clone()) })
+        .collect()
+}
+
 /// Represents a composed secret PGP SubKey.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct SignedSecretSubKey {
@@ -235,20 +951,59 @@ impl SignedSecretSubKey {
         SignedSecretSubKey { key, signatures }
     }
 
+    /// Get the subkey creation time.
+    pub fn created_at(&self) -> &DateTime<Utc> {
+        self.key.created_at()
+    }
+
+    /// Get the subkey creation time, as a `Timestamp`.
+    pub fn created_timestamp(&self) -> Timestamp {
+        self.created_at().into()
+    }
+
+    /// Get the subkey expiration, as a `Timestamp`.
+    pub fn expires_timestamp(&self, key: &impl PublicKeyTrait) -> Option<Timestamp> {
+        self.expires_at(key).map(Into::into)
+    }
+
+    /// Get the subkey expiration as a date, based on the newest binding signature by the
+    /// primary key `key`, that verifies.
+    pub fn expires_at(&self, key: &impl PublicKeyTrait) -> Option<DateTime<Utc>> {
+        subkey_expires_at(&self.signatures, key, &self.key, self.key.created_at())
+    }
+
     pub fn verify(&self, key: &impl PublicKeyTrait) -> Result<()> {
         ensure!(!self.signatures.is_empty(), "missing subkey bindings");
 
         for sig in &self.signatures {
             sig.verify_key_binding(key, &self.key)?;
+            verify_backsig(sig, key, &self.key)?;
         }
 
         Ok(())
     }
+
+    /// Checks the size and the binding signatures of this subkey against `policy`.
+    pub fn check_policy(&self, policy: &Policy) -> Result<()> {
+        policy.check_public_params(self.key.public_params())?;
+        for sig in &self.signatures {
+            sig.check_policy(policy)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the subkey packet, followed by its binding signatures.
+    pub fn packets(&self) -> Vec<packet::Packet> {
+        let mut packets = vec![packet::Packet::SecretSubkey(self.key.clone())];
+        packets.extend(self.signatures.iter().cloned().map(packet::Packet::Signature));
+        packets
+    }
 }
 
 impl KeyTrait for SignedSecretSubKey {
     /// Returns the fingerprint of the key.
-    fn fingerprint(&self) -> Vec<u8> {
+    fn fingerprint(&self) -> Fingerprint {
         self.key.fingerprint()
     }
 
@@ -297,8 +1052,13 @@ impl SecretKeyTrait for SignedSecretSubKey {
             .first()
             .expect("invalid signed subkey")
             .key_flags();
+        let backsig = self
+            .signatures
+            .first()
+            .and_then(|sig| sig.embedded_signature())
+            .cloned();
 
-        PublicSubkey::new(self.key.public_key(), keyflags)
+        PublicSubkey::new(self.key.public_key(), keyflags).with_backsig(backsig)
     }
 }
 
@@ -315,3 +1075,295 @@ impl PublicKeyTrait for SignedSecretSubKey {
         self.key.to_writer_old(writer)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::composed::key::{test_key, KeyType, SubkeyParamsBuilder};
+    use crate::composed::Deserializable;
+    use crate::crypto::ECCCurve;
+
+    #[test]
+    fn test_merge() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+        let key = test_key(rng, "Me <me@mail.com>", false);
+
+        let with_user = key
+            .clone()
+            .add_user_id(
+                UserId::from_str(Default::default(), "Me Other <other@mail.com>"),
+                false,
+                || "".into(),
+            )
+            .expect("failed to add user id");
+        let with_subkey = key
+            .clone()
+            .generate_subkey_with_rng(
+                rng,
+                SubkeyParamsBuilder::default()
+                    .key_type(KeyType::ECDH(ECCCurve::Curve25519))
+                    .can_encrypt(true)
+                    .build()
+                    .unwrap(),
+                || "".into(),
+            )
+            .expect("failed to add subkey");
+
+        let merged = with_user
+            .clone()
+            .merge(with_subkey.clone())
+            .expect("failed to merge");
+        merged.verify().expect("invalid key");
+        assert_eq!(merged.details.users.len(), 2);
+        assert_eq!(merged.details.users[0].signatures.len(), 1);
+        assert_eq!(merged.secret_subkeys, with_subkey.secret_subkeys);
+
+        // merging again does not duplicate anything
+        let merged2 = merged
+            .clone()
+            .merge(with_user)
+            .expect("failed to merge")
+            .merge(with_subkey)
+            .expect("failed to merge");
+        assert_eq!(merged, merged2);
+
+        // the secret key material replaces a public subkey
+        let mut public_subkey = merged.clone();
+        let subkey = public_subkey.secret_subkeys.remove(0);
+        public_subkey.public_subkeys.push(SignedPublicSubKey::new(
+            subkey.key.public_key(),
+            subkey.signatures,
+        ));
+        let merged3 = public_subkey.merge(merged.clone()).expect("failed to merge");
+        assert!(merged3.public_subkeys.is_empty());
+        assert_eq!(merged3, merged);
+
+        let other = test_key(rng, "Me <me@mail.com>", false);
+        assert!(merged.merge(other).is_err());
+    }
+
+    #[test]
+    fn test_certify() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+        let signer = test_key(rng, "Signer <signer@mail.com>", false);
+        let key = test_key(rng, "Me <me@mail.com>", false);
+        let public_key = key
+            .public_key()
+            .sign(&key, || "".into())
+            .expect("failed to sign public key");
+
+        assert!(signer
+            .certify(
+                &public_key,
+                "Unknown <unknown@mail.com>",
+                CertificationType::Casual,
+                None,
+                true,
+                None,
+                || "".into(),
+            )
+            .is_err());
+
+        let certified = signer
+            .certify(
+                &public_key,
+                "Me <me@mail.com>",
+                CertificationType::Casual,
+                Some("Signer <signer@mail.com>"),
+                false,
+                Some(Duration::from_secs(24 * 60 * 60)),
+                || "".into(),
+            )
+            .expect("failed to certify");
+
+        let armor = certified
+            .to_armored_string(None)
+            .expect("failed to serialize key");
+        let (certified, _headers) =
+            SignedPublicKey::from_string(&armor).expect("failed to parse key");
+        certified.verify().expect("invalid key");
+
+        let user = &certified.details.users[0];
+        assert_eq!(user.signatures.len(), 2);
+        user.verify_third_party(&certified.primary_key, &signer.primary_key)
+            .expect("invalid certification");
+
+        let sig = &user.signatures[1];
+        assert_eq!(sig.typ(), SignatureType::CertCasual);
+        assert_eq!(sig.signers_userid(), Some("Signer <signer@mail.com>"));
+        assert!(!sig.exportable_certification());
+        assert_eq!(
+            sig.signature_expiration_time().map(|t| t.timestamp()),
+            Some(24 * 60 * 60)
+        );
+    }
+
+    #[test]
+    fn test_foreign_user_id() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+        let signer = test_key(rng, "Signer <signer@mail.com>", false);
+        let mut key = test_key(rng, "Me <me@mail.com>", false).signed_public_key();
+
+        // a user id that only carries a certification by another key
+        let id = UserId::from_str(Default::default(), "Admin <admin@mail.com>");
+        let sig = SignatureConfigBuilder::default()
+            .typ(SignatureType::CertPositive)
+            .pub_alg(signer.primary_key.algorithm())
+            .hashed_subpackets(vec![Subpacket::SignatureCreationTime(
+                Utc::now().trunc_subsecs(0),
+            )])
+            .unhashed_subpackets(vec![Subpacket::Issuer(signer.primary_key.key_id())])
+            .build()
+            .unwrap()
+            .sign_certificate_third_party(
+                &signer.primary_key,
+                || "".into(),
+                &key.primary_key,
+                Tag::UserId,
+                &id,
+            )
+            .expect("failed to sign certification");
+        let user = SignedUser::new(id, vec![sig]);
+        user.verify_third_party(&key.primary_key, &signer.primary_key)
+            .expect("invalid certification");
+        assert!(user.verify(&key.primary_key).is_err());
+
+        key.verify().expect("invalid key");
+        key.details.users.push(user);
+        assert!(key.verify().is_err());
+    }
+
+    #[test]
+    fn test_set_expiration() {
+        let rng = &mut ChaCha8Rng::seed_from_u64(0);
+
+        let key = test_key(rng, "Me <me@mail.com>", true);
+        assert_eq!(key.expires_at(), None);
+
+        // third party certifications and revocations must not be re-issued
+        let mut key = key;
+        let signer = test_key(rng, "Signer <signer@mail.com>", false);
+        let certified = signer
+            .certify(
+                &key.signed_public_key(),
+                "Me <me@mail.com>",
+                CertificationType::Casual,
+                None,
+                true,
+                None,
+                || "".into(),
+            )
+            .expect("failed to certify");
+        let third_party = certified.details.users[0].signatures[1].clone();
+        let creation_time = Subpacket::SignatureCreationTime(Utc::now().trunc_subsecs(0));
+        let revocation = SignatureConfigBuilder::default()
+            .typ(SignatureType::CertRevocation)
+            .pub_alg(key.primary_key.algorithm())
+            .hashed_subpackets(vec![creation_time.clone()])
+            .unhashed_subpackets(vec![Subpacket::Issuer(key.primary_key.key_id())])
+            .build()
+            .unwrap()
+            .sign_certificate(
+                &key.primary_key,
+                || "".into(),
+                Tag::UserId,
+                &key.details.users[0].id,
+            )
+            .expect("failed to revoke user id");
+        let direct = SignatureConfigBuilder::default()
+            .typ(SignatureType::Key)
+            .pub_alg(key.primary_key.algorithm())
+            .hashed_subpackets(vec![creation_time])
+            .unhashed_subpackets(vec![Subpacket::Issuer(key.primary_key.key_id())])
+            .build()
+            .unwrap()
+            .sign_key(&key.primary_key, || "".into(), &key.primary_key)
+            .expect("failed to sign direct key signature");
+        key.details.users[0].signatures.push(third_party.clone());
+        key.details.users[0].signatures.push(revocation.clone());
+        key.details.direct_signatures.push(direct);
+
+        let key = key
+            .set_expiration(Some(Duration::from_secs(60 * 60 * 24)), || "".into())
+            .expect("failed to set expiration");
+
+        let armor = key
+            .to_armored_string(None)
+            .expect("failed to serialize key");
+        let (key, _headers) = SignedSecretKey::from_string(&armor).expect("failed to parse key");
+        key.verify().expect("invalid key");
+
+        let expires_at = *key.created_at() + chrono::Duration::days(1);
+        assert_eq!(key.created_at(), key.primary_key.created_at());
+        assert_eq!(key.expires_at(), Some(expires_at));
+        let subkey = &key.secret_subkeys[0];
+        assert_eq!(subkey.created_at(), subkey.key.created_at());
+        assert_eq!(subkey.expires_at(&key.primary_key), Some(expires_at));
+        assert_eq!(subkey.signatures.len(), 1);
+        assert!(subkey.signatures[0].key_expiration_time().is_some());
+        assert!(subkey.signatures[0].key_flags().encrypt_comms());
+
+        let user = &key.details.users[0];
+        assert_eq!(user.signatures.len(), 3);
+        assert!(user.signatures[0].key_expiration_time().is_some());
+        assert_eq!(user.signatures[1], third_party);
+        assert_eq!(user.signatures[2], revocation);
+        let direct = &key.details.direct_signatures[0];
+        direct
+            .verify_key(&key.primary_key)
+            .expect("invalid direct key signature");
+        assert_eq!(
+            direct.key_expiration_time().map(|t| t.timestamp()),
+            Some(60 * 60 * 24)
+        );
+
+        // signatures that do not verify are ignored
+        let forge = |sig: &Signature| {
+            let mut sig = sig.clone();
+            sig.config.hashed_subpackets = sig
+                .config
+                .hashed_subpackets
+                .into_iter()
+                .map(|p| match p {
+                    Subpacket::KeyExpirationTime(_) => {
+                        Subpacket::KeyExpirationTime(DateTime::from_utc(
+                            NaiveDateTime::from_timestamp(60 * 60 * 24 * 10, 0),
+                            Utc,
+                        ))
+                    }
+                    p => p,
+                })
+                .collect();
+            sig
+        };
+        let mut forged = key.clone();
+        let sig = forge(&forged.details.users[0].signatures[0]);
+        forged.details.users[0].signatures.push(sig);
+        let sig = forge(&forged.details.direct_signatures[0]);
+        forged.details.direct_signatures.push(sig);
+        let sig = forge(&forged.secret_subkeys[0].signatures[0]);
+        forged.secret_subkeys[0].signatures.push(sig);
+        assert_eq!(forged.expires_at(), Some(expires_at));
+        assert_eq!(
+            forged.secret_subkeys[0].expires_at(&forged.primary_key),
+            Some(expires_at)
+        );
+        let public_key = forged.signed_public_key();
+        assert_eq!(public_key.expires_at(), Some(expires_at));
+        assert_eq!(public_key.summary().subkeys[0].expires_at, Some(expires_at));
+
+        let key = key
+            .set_expiration(None, || "".into())
+            .expect("failed to remove expiration");
+        key.verify().expect("invalid key");
+        assert_eq!(key.expires_at(), None);
+        assert_eq!(key.secret_subkeys[0].expires_at(&key.primary_key), None);
+        assert!(key.secret_subkeys[0].signatures[0]
+            .key_expiration_time()
+            .is_none());
+    }
+}
@@ -5,12 +5,100 @@ use chrono::Duration;
 use smallvec::SmallVec;
 
 use crate::composed::key::KeyDetails;
+use crate::composed::signed_key::validity::is_self_signature;
 use crate::composed::signed_key::{SignedPublicKey, SignedSecretKey};
+use crate::crypto::aead::AeadAlgorithm;
 use crate::crypto::public_key::PublicKeyAlgorithm;
 use crate::errors::Result;
-use crate::packet;
+use crate::packet::{self, SignatureType};
 use crate::ser::Serialize;
-use crate::types::{KeyId, KeyTrait, PublicKeyTrait, SignedUser, SignedUserAttribute};
+use crate::types::{
+    Fingerprint, KeyId, KeyTrait, Policy, PublicKeyTrait, RevocationKey, SignedUser,
+    SignedUserAttribute, Tag,
+};
+
+/// Checks the primary key binding (back signature), that the binding signature of a signing
+/// subkey must embed. Without it, anyone could bind another key's signing subkey to their
+/// own primary key, and claim its signatures.
+pub(crate) fn verify_backsig(
+    sig: &packet::Signature,
+    key: &impl PublicKeyTrait,
+    subkey: &impl PublicKeyTrait,
+) -> Result<()> {
+    if sig.typ() != SignatureType::SubkeyBinding || !sig.key_flags().sign() {
+        return Ok(());
+    }
+
+    match sig.embedded_signature() {
+        Some(backsig) => backsig.verify_primary_key_binding(key, subkey),
+        None => bail!(
+            "missing primary key binding for signing subkey {:?}",
+            subkey.key_id()
+        ),
+    }
+}
+
+/// Adds the signatures from `other`, which are not yet part of `signatures`.
+///
+/// Signatures are compared by their signed parts, as the unhashed subpackets can be
+/// modified by anyone.
+pub(crate) fn merge_signatures(
+    signatures: &mut Vec<packet::Signature>,
+    other: Vec<packet::Signature>,
+) {
+    for sig in other {
+        let exists = signatures.iter().any(|s| {
+            s.signature == sig.signature
+                && s.signed_hash_value == sig.signed_hash_value
+                && s.config.hashed_subpackets == sig.config.hashed_subpackets
+        });
+        if !exists {
+            signatures.push(sig);
+        }
+    }
+}
+
+/// Drops all third party certifications, all self signatures that fail `verify` and all but
+/// the newest remaining self signature.
+///
+/// Valid self issued revocations are always kept, so that a revoked component stays revoked.
+pub(crate) fn minimize_signatures<F>(
+    signatures: &[packet::Signature],
+    key: &impl KeyTrait,
+    verify: F,
+) -> Vec<packet::Signature>
+where
+    F: Fn(&packet::Signature) -> bool,
+{
+    let (revocations, others): (Vec<_>, Vec<_>) = signatures
+        .iter()
+        .filter(|sig| is_self_signature(sig, key) && verify(sig))
+        .partition(|sig| match sig.typ() {
+            SignatureType::KeyRevocation
+            | SignatureType::SubkeyRevocation
+            | SignatureType::CertRevocation => true,
+            _ => false,
+        });
+
+    others
+        .into_iter()
+        .max_by_key(|sig| sig.created().cloned())
+        .into_iter()
+        .chain(revocations)
+        .cloned()
+        .collect()
+}
+
+/// Options for exporting a minimal version of a key, see `SignedPublicKey::minimize`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MinimizeOptions {
+    /// Drop subkeys that have expired.
+    pub drop_expired_subkeys: bool,
+    /// Drop subkeys that have been revoked.
+    pub drop_revoked_subkeys: bool,
+    /// Drop all user attributes, like photo ids.
+    pub drop_user_attributes: bool,
+}
 
 /// Shared details between secret and public keys.
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -76,6 +164,18 @@ impl SignedKeyDetails {
         }
     }
 
+    /// Returns the preferred AEAD algorithms, as listed in the self signature of the primary
+    /// user id.
+    pub fn preferred_aead_algs(&self) -> &[AeadAlgorithm] {
+        self.users
+            .iter()
+            .find(|u| u.is_primary())
+            .or_else(|| self.users.first())
+            .and_then(|u| u.signatures.first())
+            .map(|sig| sig.preferred_aead_algs())
+            .unwrap_or(&[])
+    }
+
     fn verify_users(&self, key: &impl PublicKeyTrait) -> Result<()> {
         for user in &self.users {
             user.verify(key)?;
@@ -92,14 +192,87 @@ impl SignedKeyDetails {
         Ok(())
     }
 
+    /// Returns the designated revokers, as listed in the self signatures of this key.
+    pub fn revocation_keys(&self) -> Vec<&RevocationKey> {
+        let mut keys: Vec<&RevocationKey> = Vec::new();
+        let sigs = self
+            .direct_signatures
+            .iter()
+            .chain(self.users.iter().flat_map(|user| &user.signatures));
+        for key in sigs.filter_map(|sig| sig.revocation_key()) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+
+        keys
+    }
+
+    /// Checks if the given signature was issued by one of the designated revokers.
+    fn is_designated_revocation(&self, sig: &packet::Signature) -> bool {
+        self.revocation_keys().iter().any(|rkey| {
+            let fp = &rkey.fingerprint[..];
+            if let Some(issuer_fp) = sig.issuer_fingerprint() {
+                return issuer_fp == fp;
+            }
+
+            // V4 key ids are the lower 64 bits of the fingerprint
+            match (sig.issuer(), fp.len()) {
+                (Some(issuer), 20) => issuer.as_ref() == &fp[12..],
+                _ => false,
+            }
+        })
+    }
+
     fn verify_revocation_signatures(&self, key: &impl PublicKeyTrait) -> Result<()> {
+        let key_id = key.key_id();
         for sig in &self.revocation_signatures {
+            if sig.issuer() != Some(&key_id) && self.is_designated_revocation(sig) {
+                // can only be verified using the revoker key, see `is_revoked_by`
+                debug!("skipping revocation by designated revoker {:?}", sig.issuer());
+                continue;
+            }
             sig.verify_key(key)?;
         }
 
         Ok(())
     }
 
+    /// Checks if `key` was revoked by the designated revoker `revoker`.
+    ///
+    /// Revocations claiming to be issued by `revoker`, that do not verify, are skipped.
+    pub fn is_revoked_by(
+        &self,
+        key: &impl PublicKeyTrait,
+        revoker: &impl PublicKeyTrait,
+    ) -> Result<bool> {
+        let fingerprint = revoker.fingerprint();
+        ensure!(
+            self.revocation_keys()
+                .iter()
+                .any(|rkey| &rkey.fingerprint[..] == fingerprint.as_bytes()),
+            "{:?} is not a designated revoker",
+            revoker.key_id()
+        );
+
+        let revoker_id = revoker.key_id();
+        for sig in &self.revocation_signatures {
+            let issued_by_revoker = match sig.issuer_fingerprint() {
+                Some(fp) => fp == fingerprint.as_bytes(),
+                None => sig.issuer() == Some(&revoker_id),
+            };
+            if !issued_by_revoker {
+                continue;
+            }
+            match sig.verify_key_third_party(revoker, key) {
+                Ok(()) => return Ok(true),
+                Err(err) => warn!("skipping invalid revocation by {:?}: {:?}", revoker_id, err),
+            }
+        }
+
+        Ok(false)
+    }
+
     fn verify_direct_signatures(&self, key: &impl PublicKeyTrait) -> Result<()> {
         for sig in &self.direct_signatures {
             sig.verify_key(key)?;
@@ -108,6 +281,23 @@ impl SignedKeyDetails {
         Ok(())
     }
 
+    /// Checks the self signatures of `key` against `policy`. Signatures by other keys are
+    /// skipped, as `verify` can not check them either.
+    pub fn check_policy(&self, policy: &Policy, key: &impl PublicKeyTrait) -> Result<()> {
+        let sigs = self
+            .users
+            .iter()
+            .flat_map(|user| &user.signatures)
+            .chain(self.user_attributes.iter().flat_map(|attr| &attr.signatures))
+            .chain(&self.revocation_signatures)
+            .chain(&self.direct_signatures);
+        for sig in sigs.filter(|sig| is_self_signature(sig, key)) {
+            sig.check_policy(policy)?;
+        }
+
+        Ok(())
+    }
+
     pub fn verify(&self, key: &impl PublicKeyTrait) -> Result<()> {
         self.verify_users(key)?;
         self.verify_attributes(key)?;
@@ -117,6 +307,76 @@ impl SignedKeyDetails {
         Ok(())
     }
 
+    /// Merges the user ids, user attributes and signatures of another copy of the same key.
+    pub fn merge(&mut self, other: SignedKeyDetails) {
+        merge_signatures(&mut self.revocation_signatures, other.revocation_signatures);
+        merge_signatures(&mut self.direct_signatures, other.direct_signatures);
+
+        for user in other.users {
+            match self.users.iter_mut().find(|u| u.id.id() == user.id.id()) {
+                Some(existing) => merge_signatures(&mut existing.signatures, user.signatures),
+                None => self.users.push(user),
+            }
+        }
+
+        for attr in other.user_attributes {
+            // compare the content, ignoring the packet framing
+            let content = attr.attr.to_bytes().ok();
+            match self
+                .user_attributes
+                .iter_mut()
+                .find(|a| a.attr.to_bytes().ok() == content)
+            {
+                Some(existing) => merge_signatures(&mut existing.signatures, attr.signatures),
+                None => self.user_attributes.push(attr),
+            }
+        }
+    }
+
+    /// Strips third party certifications and all but the newest self signature of each
+    /// component. Components without any self signature are dropped.
+    pub fn minimize(
+        &self,
+        key: &impl PublicKeyTrait,
+        options: &MinimizeOptions,
+    ) -> SignedKeyDetails {
+        let users = self
+            .users
+            .iter()
+            .map(|user| SignedUser {
+                id: user.id.clone(),
+                signatures: minimize_signatures(&user.signatures, key, |sig| {
+                    sig.verify_certificate(key, Tag::UserId, &user.id).is_ok()
+                }),
+            })
+            .filter(|user| !user.signatures.is_empty())
+            .collect();
+
+        let user_attributes = if options.drop_user_attributes {
+            Vec::new()
+        } else {
+            self.user_attributes
+                .iter()
+                .map(|attr| SignedUserAttribute {
+                    attr: attr.attr.clone(),
+                    signatures: minimize_signatures(&attr.signatures, key, |sig| {
+                        sig.verify_certificate(key, Tag::UserAttribute, &attr.attr).is_ok()
+                    }),
+                })
+                .filter(|attr| !attr.signatures.is_empty())
+                .collect()
+        };
+
+        SignedKeyDetails {
+            revocation_signatures: self.revocation_signatures.clone(),
+            direct_signatures: minimize_signatures(&self.direct_signatures, key, |sig| {
+                sig.verify_key(key).is_ok()
+            }),
+            users,
+            user_attributes,
+        }
+    }
+
     pub fn as_unsigned(&self) -> KeyDetails {
         let primary_user = if let Some(user) = self.users.iter().find(|u| u.is_primary()) {
             user
@@ -136,6 +396,7 @@ impl SignedKeyDetails {
         let preferred_hash_algorithms = SmallVec::from_slice(primary_sig.preferred_hash_algs());
         let preferred_compression_algorithms =
             SmallVec::from_slice(primary_sig.preferred_compression_algs());
+        let preferred_aead_algorithms = SmallVec::from_slice(primary_sig.preferred_aead_algs());
         let revocation_key = primary_sig.revocation_key().cloned();
 
         KeyDetails::new(
@@ -153,9 +414,30 @@ impl SignedKeyDetails {
             preferred_symmetric_algorithms,
             preferred_hash_algorithms,
             preferred_compression_algorithms,
+            preferred_aead_algorithms,
             revocation_key,
         )
     }
+
+    /// Returns the signature, User ID and User Attribute packets, in the order they are
+    /// serialized.
+    pub fn packets(&self) -> Vec<packet::Packet> {
+        let mut packets: Vec<packet::Packet> = self
+            .revocation_signatures
+            .iter()
+            .chain(&self.direct_signatures)
+            .cloned()
+            .map(packet::Packet::Signature)
+            .collect();
+        for user in &self.users {
+            packets.extend(user.packets());
+        }
+        for attr in &self.user_attributes {
+            packets.extend(attr.packets());
+        }
+
+        packets
+    }
 }
 
 impl Serialize for SignedKeyDetails {
@@ -262,7 +544,7 @@ impl Serialize for PublicOrSecret {
 
 impl KeyTrait for PublicOrSecret {
     /// Returns the fingerprint of the key.
-    fn fingerprint(&self) -> Vec<u8> {
+    fn fingerprint(&self) -> Fingerprint {
         match self {
             PublicOrSecret::Public(k) => k.fingerprint(),
             PublicOrSecret::Secret(k) => k.fingerprint(),
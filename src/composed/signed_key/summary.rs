@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+
+use crate::composed::signed_key::{SignedPublicKey, SignedPublicSubKey};
+use crate::crypto::PublicKeyAlgorithm;
+use crate::types::{Fingerprint, KeyId, KeyTrait, PublicKeyTrait};
+
+/// A summary of the metadata of a public key, e.g. to cache parsed keys.
+///
+/// With the `serde` feature enabled this can be serialized, fingerprints are encoded as hex
+/// strings and times as seconds since the unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeySummary {
+    pub fingerprint: Fingerprint,
+    pub key_id: KeyId,
+    pub algorithm: PublicKeyAlgorithm,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde_timestamp"))]
+    pub created_at: DateTime<Utc>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde_timestamp::option"))]
+    pub expires_at: Option<DateTime<Utc>>,
+    pub user_ids: Vec<String>,
+    pub subkeys: Vec<SubkeySummary>,
+}
+
+/// A summary of the metadata of a public subkey.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubkeySummary {
+    pub fingerprint: Fingerprint,
+    pub key_id: KeyId,
+    pub algorithm: PublicKeyAlgorithm,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde_timestamp"))]
+    pub created_at: DateTime<Utc>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde_timestamp::option"))]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl SignedPublicKey {
+    /// Summarizes the metadata of this key.
+    pub fn summary(&self) -> KeySummary {
+        KeySummary {
+            fingerprint: self.fingerprint(),
+            key_id: self.key_id(),
+            algorithm: self.algorithm(),
+            created_at: *self.created_at(),
+            expires_at: self.expires_at(),
+            user_ids: self
+                .details
+                .users
+                .iter()
+                .map(|user| user.id.id().to_string())
+                .collect(),
+            subkeys: self
+                .public_subkeys
+                .iter()
+                .map(|subkey| SubkeySummary::new(&self.primary_key, subkey))
+                .collect(),
+        }
+    }
+}
+
+impl SubkeySummary {
+    fn new(key: &impl PublicKeyTrait, subkey: &SignedPublicSubKey) -> Self {
+        SubkeySummary {
+            fingerprint: subkey.fingerprint(),
+            key_id: subkey.key_id(),
+            algorithm: subkey.algorithm(),
+            created_at: *subkey.created_at(),
+            expires_at: subkey.expires_at(key),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::packet::{RevocationCode, Signature, SignatureType};
+use crate::types::{KeyId, KeyTrait, PublicKeyTrait, SignedUser, Tag};
+
+/// The validity of a single component of a key, at a given point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Validity {
+    /// The component has a valid self signature and is neither expired nor revoked.
+    Valid,
+    /// The component expired at the given time.
+    Expired(DateTime<Utc>),
+    /// The component was revoked.
+    Revoked {
+        code: Option<RevocationCode>,
+        reason: Option<String>,
+        /// The creation time of the revocation signature.
+        revoked_at: Option<DateTime<Utc>>,
+    },
+    /// There is no valid self signature, that was created before the given time.
+    NoSelfSignature,
+}
+
+impl Validity {
+    pub fn is_valid(&self) -> bool {
+        *self == Validity::Valid
+    }
+}
+
+/// The validity of all components of a key, see `SignedPublicKey::validity_at`.
+///
+/// The validity of user ids and subkeys is computed on their own, they are only usable
+/// if the primary key is valid as well.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyValidity {
+    pub primary: Validity,
+    /// The validity of each user id, in the order they are stored in the key.
+    pub users: Vec<(String, Validity)>,
+    /// The validity of each subkey, in the order they are stored in the key.
+    pub subkeys: Vec<(KeyId, Validity)>,
+}
+
+impl KeyValidity {
+    /// Returns `true` if the primary key is valid.
+    pub fn is_valid(&self) -> bool {
+        self.primary.is_valid()
+    }
+}
+
+/// Checks if the signature was issued by `key`. Signatures without an issuer are assumed
+/// to be self signatures.
+pub(crate) fn is_self_signature(sig: &Signature, key: &impl KeyTrait) -> bool {
+    match sig.issuer_fingerprint() {
+        Some(fp) => fp == key.fingerprint().as_bytes(),
+        None => sig.issuer().map_or(true, |issuer| issuer == &key.key_id()),
+    }
+}
+
+/// Hard revocations invalidate a key at all times, even before they were issued, as the
+/// key material might have been compromised.
+fn is_hard_revocation(sig: &Signature) -> bool {
+    match sig.revocation_reason_code() {
+        Some(RevocationCode::KeySuperseded)
+        | Some(RevocationCode::KeyRetired)
+        | Some(RevocationCode::CertUserIdInvalid) => false,
+        _ => true,
+    }
+}
+
+fn created_before(sig: &Signature, time: &DateTime<Utc>) -> bool {
+    sig.created().map_or(false, |created| created <= time)
+}
+
+/// Returns the first revocation in `signatures`, that is in effect at `time`, as validity.
+///
+/// `verify` checks the cryptographic validity of a signature.
+pub(crate) fn revocation_at<'a, I, F>(
+    signatures: I,
+    typ: SignatureType,
+    time: &DateTime<Utc>,
+    verify: F,
+) -> Option<Validity>
+where
+    I: IntoIterator<Item = &'a Signature>,
+    F: Fn(&Signature) -> bool,
+{
+    signatures
+        .into_iter()
+        .filter(|sig| sig.typ() == typ)
+        .filter(|sig| {
+            (typ != SignatureType::CertRevocation && is_hard_revocation(sig))
+                || created_before(sig, time)
+        })
+        .find(|sig| verify(sig))
+        .map(|sig| Validity::Revoked {
+            code: sig.revocation_reason_code().cloned(),
+            reason: sig.revocation_reason_string().map(ToString::to_string),
+            revoked_at: sig.created().cloned(),
+        })
+}
+
+/// Returns the newest signature in `signatures`, that was created before `time`, is not
+/// expired at `time` and is valid.
+///
+/// `verify` checks the cryptographic validity of a signature.
+pub(crate) fn newest_signature_at<'a, I, F>(
+    signatures: I,
+    time: &DateTime<Utc>,
+    verify: F,
+) -> Option<&'a Signature>
+where
+    I: IntoIterator<Item = &'a Signature>,
+    F: Fn(&Signature) -> bool,
+{
+    signatures
+        .into_iter()
+        .filter(|sig| created_before(sig, time))
+        .filter(|sig| sig.check_expiration(time).is_ok())
+        .filter(|sig| verify(sig))
+        .max_by_key(|sig| sig.created().cloned())
+}
+
+/// Computes the expiration time of a key created at `created_at`, based on its self
+/// signature. Returns `None` if the key never expires.
+fn expiration_time(sig: &Signature, created_at: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match sig.key_expiration_time()?.timestamp() {
+        // zero means the key never expires
+        offset if offset > 0 => Some(*created_at + Duration::seconds(offset)),
+        _ => None,
+    }
+}
+
+/// Computes the expiration of a key created at `created_at`, based on its self signature.
+pub(crate) fn expiration_at(
+    sig: &Signature,
+    created_at: &DateTime<Utc>,
+    time: &DateTime<Utc>,
+) -> Validity {
+    match expiration_time(sig, created_at) {
+        Some(expires_at) if expires_at <= *time => Validity::Expired(expires_at),
+        _ => Validity::Valid,
+    }
+}
+
+fn is_certification(typ: SignatureType) -> bool {
+    match typ {
+        SignatureType::CertGeneric
+        | SignatureType::CertPersona
+        | SignatureType::CertCasual
+        | SignatureType::CertPositive => true,
+        _ => false,
+    }
+}
+
+/// Computes the validity of the user id `user` of the primary key `key` at `time`, together
+/// with its newest valid self certification.
+pub(crate) fn user_validity_at<'a>(
+    user: &'a SignedUser,
+    key: &impl PublicKeyTrait,
+    time: &DateTime<Utc>,
+) -> (Validity, Option<&'a Signature>) {
+    let self_sigs = || {
+        user.signatures
+            .iter()
+            .filter(|sig| is_self_signature(sig, key))
+    };
+    let verify = |sig: &Signature| sig.verify_certificate(key, Tag::UserId, &user.id).is_ok();
+
+    if let Some(revoked) = revocation_at(self_sigs(), SignatureType::CertRevocation, time, verify)
+    {
+        return (revoked, None);
+    }
+
+    match newest_signature_at(
+        self_sigs().filter(|sig| is_certification(sig.typ())),
+        time,
+        verify,
+    ) {
+        Some(sig) => (Validity::Valid, Some(sig)),
+        None => (Validity::NoSelfSignature, None),
+    }
+}
+
+/// Returns the self signature of the primary key `key`, that is in effect at `time`.
+///
+/// This is the newest of the valid direct key signatures and `user_signatures`, the newest
+/// valid self certifications of the user ids that are not revoked, see `user_validity_at`.
+pub(crate) fn primary_self_signature_at<'a, I>(
+    direct_signatures: &'a [Signature],
+    user_signatures: I,
+    key: &impl PublicKeyTrait,
+    time: &DateTime<Utc>,
+) -> Option<&'a Signature>
+where
+    I: IntoIterator<Item = &'a Signature>,
+{
+    let direct = newest_signature_at(
+        direct_signatures
+            .iter()
+            .filter(|sig| is_self_signature(sig, key)),
+        time,
+        |sig| sig.verify_key(key).is_ok(),
+    );
+
+    direct
+        .into_iter()
+        .chain(user_signatures)
+        .max_by_key(|sig| sig.created().cloned())
+}
+
+/// Computes the current expiration of the primary key `key` created at `created_at`, based on
+/// its self signature in effect, see `primary_self_signature_at`. Returns `None` if the key
+/// never expires.
+pub(crate) fn key_expires_at(
+    direct_signatures: &[Signature],
+    users: &[SignedUser],
+    key: &impl PublicKeyTrait,
+    created_at: &DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let now = Utc::now();
+    let user_signatures = users
+        .iter()
+        .filter_map(|user| user_validity_at(user, key, &now).1);
+    let sig = primary_self_signature_at(direct_signatures, user_signatures, key, &now)?;
+
+    expiration_time(sig, created_at)
+}
+
+/// Computes the current expiration of the subkey `subkey` created at `created_at`, based on
+/// its newest valid binding signature by the primary key `key`. Returns `None` if the subkey
+/// never expires.
+pub(crate) fn subkey_expires_at(
+    signatures: &[Signature],
+    key: &impl PublicKeyTrait,
+    subkey: &impl PublicKeyTrait,
+    created_at: &DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let binding = newest_signature_at(
+        signatures
+            .iter()
+            .filter(|sig| sig.typ() == SignatureType::SubkeyBinding)
+            .filter(|sig| is_self_signature(sig, key)),
+        &Utc::now(),
+        |sig| sig.verify_key_binding(key, subkey).is_ok(),
+    )?;
+
+    expiration_time(binding, created_at)
+}
@@ -0,0 +1,237 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use nom::be_u32;
+use num_bigint::BigUint;
+use rsa::RSAPrivateKey;
+
+use crate::composed::import::ed25519_public_params;
+use crate::crypto::{self, ecc_curve::ECCCurve};
+use crate::errors::Result;
+use crate::types::{Mpi, PlainSecretParams, PublicParams};
+
+/// Magic bytes at the start of an OpenSSH private key.
+const OPENSSH_MAGIC: &[u8] = b"openssh-key-v1\0";
+
+/// Formats the given public key as an OpenSSH public key, in the format used by
+/// `authorized_keys`, e.g. `ssh-ed25519 AAAA... comment`.
+///
+/// Supported are RSA, ECDSA on the NIST curves and EdDSA on Ed25519.
+pub fn ssh_public_key(params: &PublicParams, comment: &str) -> Result<String> {
+    let mut blob = Vec::new();
+    let typ = match params {
+        PublicParams::RSA { n, e } => {
+            write_string(&mut blob, b"ssh-rsa")?;
+            write_mpint(&mut blob, e.as_bytes())?;
+            write_mpint(&mut blob, n.as_bytes())?;
+            "ssh-rsa"
+        }
+        PublicParams::ECDSA { curve, p } => {
+            let (typ, name) = match curve {
+                ECCCurve::P256 => ("ecdsa-sha2-nistp256", "nistp256"),
+                ECCCurve::P384 => ("ecdsa-sha2-nistp384", "nistp384"),
+                ECCCurve::P521 => ("ecdsa-sha2-nistp521", "nistp521"),
+                _ => unsupported_err!("curve {} for SSH", curve.to_string()),
+            };
+            write_string(&mut blob, typ.as_bytes())?;
+            write_string(&mut blob, name.as_bytes())?;
+            write_string(&mut blob, p.as_bytes())?;
+            typ
+        }
+        PublicParams::EdDSA { curve, q } => {
+            ensure_eq!(curve, &ECCCurve::Ed25519, "invalid curve for SSH");
+            let q = q.as_bytes();
+            ensure!(q.len() == 33 && q[0] == 0x40, "invalid Q");
+
+            write_string(&mut blob, b"ssh-ed25519")?;
+            write_string(&mut blob, &q[1..])?;
+            "ssh-ed25519"
+        }
+        _ => unsupported_err!("public key algorithm for SSH"),
+    };
+
+    let mut line = format!("{} {}", typ, base64::encode(&blob));
+    if !comment.is_empty() {
+        line.push(' ');
+        line.push_str(comment);
+    }
+
+    Ok(line)
+}
+
+/// Parses an OpenSSH public key in the format used by `authorized_keys`.
+pub(crate) fn parse_ssh_public_key(line: &str) -> Result<PublicParams> {
+    let mut parts = line.split_whitespace();
+    let typ = parts
+        .next()
+        .ok_or_else(|| format_err!("missing key type"))?;
+    let blob = base64::decode(parts.next().ok_or_else(|| format_err!("missing key"))?)?;
+
+    let (input, blob_typ) = ssh_string(&blob)?;
+    ensure_eq!(blob_typ, typ.as_bytes(), "inconsistent key type");
+    let (_, params) = ssh_public_params(blob_typ, input)?;
+
+    Ok(params)
+}
+
+/// Parses an unencrypted OpenSSH private key, as written by `ssh-keygen`.
+pub(crate) fn parse_ssh_private_key(data: &[u8]) -> Result<(PublicParams, PlainSecretParams)> {
+    ensure!(data.starts_with(OPENSSH_MAGIC), "invalid OpenSSH private key");
+    let input = &data[OPENSSH_MAGIC.len()..];
+
+    let (input, cipher) = ssh_string(input)?;
+    if cipher != b"none" {
+        unsupported_err!("encrypted OpenSSH private keys");
+    }
+    let (input, _kdf) = ssh_string(input)?;
+    let (input, _kdf_options) = ssh_string(input)?;
+    let (input, count) = be_u32(input)?;
+    ensure_eq!(count, 1, "only single OpenSSH private keys are supported");
+    let (input, _public_key) = ssh_string(input)?;
+    let (_, private) = ssh_string(input)?;
+
+    let (input, check1) = be_u32(private)?;
+    let (input, check2) = be_u32(input)?;
+    ensure_eq!(check1, check2, "invalid OpenSSH private key");
+
+    let (input, typ) = ssh_string(input)?;
+    match typ {
+        b"ssh-rsa" => {
+            let (input, n) = ssh_string(input)?;
+            let (input, e) = ssh_string(input)?;
+            let (input, d) = ssh_string(input)?;
+            let (input, _iqmp) = ssh_string(input)?;
+            let (input, p) = ssh_string(input)?;
+            let (_, q) = ssh_string(input)?;
+
+            // OpenSSH stores q^-1 mod p, so the OpenPGP parameters are derived again
+            let key = RSAPrivateKey::from_components(
+                BigUint::from_bytes_be(n),
+                BigUint::from_bytes_be(e),
+                BigUint::from_bytes_be(d),
+                vec![BigUint::from_bytes_be(p), BigUint::from_bytes_be(q)],
+            );
+            key.validate()?;
+
+            Ok(crypto::rsa::key_params(&key))
+        }
+        b"ssh-ed25519" => {
+            let (input, q) = ssh_string(input)?;
+            let (_, secret) = ssh_string(input)?;
+            // the secret key is stored together with the public key
+            ensure_eq!(secret.len(), 64, "invalid Ed25519 secret key");
+
+            Ok((
+                ed25519_public_params(q)?,
+                PlainSecretParams::EdDSA(Mpi::from_raw_slice(&secret[..32])),
+            ))
+        }
+        _ => {
+            let (input, params) = ssh_public_params(typ, input)?;
+            let (_, d) = ssh_string(input)?;
+
+            Ok((params, PlainSecretParams::ECDSA(Mpi::from_raw_slice(d))))
+        }
+    }
+}
+
+/// Parses the fields of a public key of type `typ`, as found in the public key blob.
+fn ssh_public_params<'a>(typ: &[u8], input: &'a [u8]) -> Result<(&'a [u8], PublicParams)> {
+    match typ {
+        b"ssh-rsa" => {
+            let (input, e) = ssh_string(input)?;
+            let (input, n) = ssh_string(input)?;
+
+            Ok((
+                input,
+                PublicParams::RSA {
+                    n: Mpi::from_raw_slice(n),
+                    e: Mpi::from_raw_slice(e),
+                },
+            ))
+        }
+        b"ssh-ed25519" => {
+            let (input, q) = ssh_string(input)?;
+
+            Ok((input, ed25519_public_params(q)?))
+        }
+        _ => {
+            let curve = match typ {
+                b"ecdsa-sha2-nistp256" => ECCCurve::P256,
+                b"ecdsa-sha2-nistp384" => ECCCurve::P384,
+                b"ecdsa-sha2-nistp521" => ECCCurve::P521,
+                _ => unsupported_err!("SSH key type {}", String::from_utf8_lossy(typ)),
+            };
+            let (input, _curve_name) = ssh_string(input)?;
+            let (input, p) = ssh_string(input)?;
+
+            Ok((
+                input,
+                PublicParams::ECDSA {
+                    curve,
+                    p: Mpi::from_raw_slice(p),
+                },
+            ))
+        }
+    }
+}
+
+// Parses a length prefixed string, as defined in RFC 4251.
+named!(ssh_string, length_data!(be_u32));
+
+/// Writes a length prefixed string, as defined in RFC 4251.
+fn write_string(writer: &mut Vec<u8>, data: &[u8]) -> Result<()> {
+    writer.write_u32::<BigEndian>(data.len() as u32)?;
+    writer.extend_from_slice(data);
+
+    Ok(())
+}
+
+/// Writes a positive multiple precision integer, as defined in RFC 4251.
+fn write_mpint(writer: &mut Vec<u8>, data: &[u8]) -> Result<()> {
+    // leading zeros are not allowed, but positive numbers need a zero in front of
+    // a set most significant bit
+    let start = data.iter().position(|b| *b != 0).unwrap_or_else(|| data.len());
+    let data = &data[start..];
+    if data.first().map_or(false, |b| b & 0x80 != 0) {
+        writer.write_u32::<BigEndian>(data.len() as u32 + 1)?;
+        writer.push(0);
+        writer.extend_from_slice(data);
+    } else {
+        write_string(writer, data)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::types::Mpi;
+
+    #[test]
+    fn test_write_mpint() {
+        let mut buf = Vec::new();
+        write_mpint(&mut buf, &[]).unwrap();
+        write_mpint(&mut buf, &[0x00, 0x7f]).unwrap();
+        write_mpint(&mut buf, &[0x80]).unwrap();
+        assert_eq!(
+            buf,
+            vec![0, 0, 0, 0, 0, 0, 0, 1, 0x7f, 0, 0, 0, 2, 0, 0x80]
+        );
+    }
+
+    #[test]
+    fn test_ssh_public_key_ed25519() {
+        let mut q = vec![0x40];
+        q.extend_from_slice(&[1u8; 32]);
+        let params = PublicParams::EdDSA {
+            curve: ECCCurve::Ed25519,
+            q: Mpi::from_raw(q),
+        };
+
+        assert_eq!(
+            ssh_public_key(&params, "me").unwrap(),
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEB me"
+        );
+    }
+}
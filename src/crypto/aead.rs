@@ -1,3 +1,11 @@
+use aes::{Aes128, Aes192, Aes256};
+use eax::aead::{Aead, NewAead, Payload};
+use eax::Eax;
+use generic_array::GenericArray;
+
+use crate::crypto::SymmetricKeyAlgorithm;
+use crate::errors::{Error, Result};
+
 /// Available AEAD algorithms.
 #[derive(Debug, PartialEq, Eq, Copy, Clone, FromPrimitive)]
 #[repr(u8)]
@@ -6,6 +14,7 @@ pub enum AeadAlgorithm {
     None = 0,
     Eax = 1,
     Ocb = 2,
+    Gcm = 3,
 }
 
 impl Default for AeadAlgorithm {
@@ -13,3 +22,144 @@ impl Default for AeadAlgorithm {
         AeadAlgorithm::None
     }
 }
+
+macro_rules! eax {
+    ($cipher:ty, $key:expr, $nonce:expr, $method:ident, $payload:expr) => {{
+        Eax::<$cipher>::new(GenericArray::from_slice($key))
+            .$method(GenericArray::from_slice($nonce), $payload)
+            .map_err(|_| Error::AeadError)
+    }};
+}
+
+impl AeadAlgorithm {
+    /// Nonce size in bytes, which is also the size of the IV of an AEAD encrypted data packet.
+    pub fn nonce_size(self) -> usize {
+        match self {
+            AeadAlgorithm::Eax => 16,
+            AeadAlgorithm::Ocb => 15,
+            AeadAlgorithm::Gcm => 12,
+            AeadAlgorithm::None => 0,
+        }
+    }
+
+    /// Size of the authentication tag in bytes.
+    pub fn tag_size(self) -> usize {
+        match self {
+            AeadAlgorithm::None => 0,
+            _ => 16,
+        }
+    }
+
+    /// Encrypts the plaintext, returning the ciphertext followed by the authentication tag.
+    pub fn encrypt(
+        self,
+        sym_alg: SymmetricKeyAlgorithm,
+        key: &[u8],
+        nonce: &[u8],
+        associated_data: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>> {
+        self.check_params(sym_alg, key, nonce)?;
+
+        let payload = Payload {
+            msg: plaintext,
+            aad: associated_data,
+        };
+
+        match (self, sym_alg) {
+            (AeadAlgorithm::Eax, SymmetricKeyAlgorithm::AES128) => {
+                eax!(Aes128, key, nonce, encrypt, payload)
+            }
+            (AeadAlgorithm::Eax, SymmetricKeyAlgorithm::AES192) => {
+                eax!(Aes192, key, nonce, encrypt, payload)
+            }
+            (AeadAlgorithm::Eax, SymmetricKeyAlgorithm::AES256) => {
+                eax!(Aes256, key, nonce, encrypt, payload)
+            }
+            _ => unsupported_err!("AEAD {:?} with {:?}", self, sym_alg),
+        }
+    }
+
+    /// Decrypts the ciphertext, which is followed by the authentication tag, and verifies
+    /// the tag.
+    pub fn decrypt(
+        self,
+        sym_alg: SymmetricKeyAlgorithm,
+        key: &[u8],
+        nonce: &[u8],
+        associated_data: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>> {
+        self.check_params(sym_alg, key, nonce)?;
+        ensure!(ciphertext.len() >= self.tag_size(), "missing authentication tag");
+
+        let payload = Payload {
+            msg: ciphertext,
+            aad: associated_data,
+        };
+
+        match (self, sym_alg) {
+            (AeadAlgorithm::Eax, SymmetricKeyAlgorithm::AES128) => {
+                eax!(Aes128, key, nonce, decrypt, payload)
+            }
+            (AeadAlgorithm::Eax, SymmetricKeyAlgorithm::AES192) => {
+                eax!(Aes192, key, nonce, decrypt, payload)
+            }
+            (AeadAlgorithm::Eax, SymmetricKeyAlgorithm::AES256) => {
+                eax!(Aes256, key, nonce, decrypt, payload)
+            }
+            _ => unsupported_err!("AEAD {:?} with {:?}", self, sym_alg),
+        }
+    }
+
+    fn check_params(self, sym_alg: SymmetricKeyAlgorithm, key: &[u8], nonce: &[u8]) -> Result<()> {
+        ensure_eq!(key.len(), sym_alg.key_size(), "invalid key length");
+        ensure_eq!(nonce.len(), self.nonce_size(), "invalid nonce length");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_aead_roundtrip() {
+        let mut rng = XorShiftRng::from_seed([
+            0x3, 0x8, 0x3, 0xe, 0x3, 0x8, 0x3, 0xe, 0x3, 0x8, 0x3, 0xe, 0x3, 0x8, 0x3, 0xe,
+        ]);
+
+        for aead in &[AeadAlgorithm::Eax] {
+            for sym in &[
+                SymmetricKeyAlgorithm::AES128,
+                SymmetricKeyAlgorithm::AES192,
+                SymmetricKeyAlgorithm::AES256,
+            ] {
+                let key = sym.new_session_key(&mut rng);
+                let mut nonce = vec![0u8; aead.nonce_size()];
+                rng.fill(&mut nonce[..]);
+
+                for size in &[0, 1, 16, 17, 100] {
+                    let mut plaintext = vec![0u8; *size];
+                    rng.fill(&mut plaintext[..]);
+
+                    let mut ciphertext =
+                        aead.encrypt(*sym, &key, &nonce, b"ad", &plaintext).unwrap();
+                    assert_eq!(ciphertext.len(), size + 16);
+                    assert_eq!(
+                        aead.decrypt(*sym, &key, &nonce, b"ad", &ciphertext).unwrap(),
+                        plaintext
+                    );
+
+                    assert!(aead.decrypt(*sym, &key, &nonce, b"da", &ciphertext).is_err());
+                    ciphertext[0] ^= 1;
+                    assert!(aead.decrypt(*sym, &key, &nonce, b"ad", &ciphertext).is_err());
+                }
+            }
+        }
+    }
+}
@@ -3,10 +3,12 @@ use std::io;
 
 use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
 use sha1::{Digest, Sha1};
+use subtle::ConstantTimeEq;
 
 use crate::errors::Result;
 
 /// Two octet checksum: sum of all octets mod 65535.
+/// The comparison runs in constant time, as the data is usually secret.
 #[inline]
 pub fn simple(actual: &[u8], data: &[u8]) -> Result<()> {
     // Then a two-octet checksum is appended, which is equal to the
@@ -14,9 +16,8 @@ pub fn simple(actual: &[u8], data: &[u8]) -> Result<()> {
     // identifier, modulo 65536.
     let expected_checksum = calculate_simple(data);
 
-    ensure_eq!(
-        &actual[..2],
-        &expected_checksum.to_be_bytes()[..],
+    ensure!(
+        actual.len() >= 2 && bool::from(actual[..2].ct_eq(&expected_checksum.to_be_bytes())),
         "invalid simple checksum"
     );
 
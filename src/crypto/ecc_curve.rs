@@ -1,9 +1,14 @@
+use rand::{CryptoRng, Rng};
+use zeroize::Zeroize;
+
 use crate::crypto::public_key::PublicKeyAlgorithm;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ECCCurve {
     Curve25519,
     Ed25519,
+    Curve448,
+    Ed448,
     P256,
     P384,
     P521,
@@ -19,6 +24,8 @@ impl ECCCurve {
         match *self {
             ECCCurve::Curve25519 => "Curve25519",
             ECCCurve::Ed25519 => "Ed25519",
+            ECCCurve::Curve448 => "X448",
+            ECCCurve::Ed448 => "Ed448",
             ECCCurve::P256 => "NIST P-256",
             ECCCurve::P384 => "NIST P-384",
             ECCCurve::P521 => "NIST P-521",
@@ -34,6 +41,8 @@ impl ECCCurve {
         match *self {
             ECCCurve::Curve25519 => "1.3.6.1.4.1.3029.1.5.1",
             ECCCurve::Ed25519 => "1.3.6.1.4.1.11591.15.1",
+            ECCCurve::Curve448 => "1.3.101.111",
+            ECCCurve::Ed448 => "1.3.101.113",
             ECCCurve::P256 => "1.2.840.10045.3.1.7",
             ECCCurve::P384 => "1.3.132.0.34",
             ECCCurve::P521 => "1.3.132.0.35",
@@ -49,6 +58,8 @@ impl ECCCurve {
         match *self {
             ECCCurve::Curve25519 => 255,
             ECCCurve::Ed25519 => 255,
+            ECCCurve::Curve448 => 448,
+            ECCCurve::Ed448 => 448,
             ECCCurve::P256 => 256,
             ECCCurve::P384 => 384,
             ECCCurve::P521 => 521,
@@ -64,6 +75,8 @@ impl ECCCurve {
         match *self {
             ECCCurve::Curve25519 => Some("cv25519"),
             ECCCurve::Ed25519 => Some("ed25519"),
+            ECCCurve::Curve448 => Some("cv448"),
+            ECCCurve::Ed448 => Some("ed448"),
             ECCCurve::P256 => Some("nistp256"),
             ECCCurve::P384 => Some("nistp384"),
             ECCCurve::P521 => Some("nistp521"),
@@ -79,6 +92,8 @@ impl ECCCurve {
         match *self {
             ECCCurve::Curve25519 => Some(PublicKeyAlgorithm::ECDH),
             ECCCurve::Ed25519 => Some(PublicKeyAlgorithm::EdDSA),
+            ECCCurve::Curve448 => Some(PublicKeyAlgorithm::ECDH),
+            ECCCurve::Ed448 => Some(PublicKeyAlgorithm::EdDSA),
             ECCCurve::P256 => None,
             ECCCurve::P384 => None,
             ECCCurve::P521 => None,
@@ -89,6 +104,16 @@ impl ECCCurve {
         }
     }
 
+    /// Size in bytes of a secret scalar on this curve.
+    pub fn secret_key_length(&self) -> usize {
+        match *self {
+            // Ed448 secret keys have an additional octet
+            // Ref: https://tools.ietf.org/html/rfc8032#section-5.2.5
+            ECCCurve::Ed448 => 57,
+            _ => (self.nbits() as usize + 7) / 8,
+        }
+    }
+
     pub fn oid(&self) -> Vec<u8> {
         // the OID String is turned into bytes
         // with the first two numbers combined
@@ -116,6 +141,12 @@ pub fn ecc_curve_from_oid(oid: &[u8]) -> Option<ECCCurve> {
     if ECCCurve::Ed25519.oid().as_slice() == oid {
         return Some(ECCCurve::Ed25519);
     }
+    if ECCCurve::Curve448.oid().as_slice() == oid {
+        return Some(ECCCurve::Curve448);
+    }
+    if ECCCurve::Ed448.oid().as_slice() == oid {
+        return Some(ECCCurve::Ed448);
+    }
     if ECCCurve::P256.oid().as_slice() == oid {
         return Some(ECCCurve::P256);
     }
@@ -140,6 +171,32 @@ pub fn ecc_curve_from_oid(oid: &[u8]) -> Option<ECCCurve> {
     None
 }
 
+/// Generates a random secret scalar for the given curve.
+/// Candidates that are rejected by `from_slice`, e.g. because they are not smaller than the
+/// group order, are discarded.
+pub(crate) fn generate_secret_scalar<R, K, F>(rng: &mut R, curve: &ECCCurve, from_slice: F) -> K
+where
+    R: Rng + CryptoRng,
+    F: Fn(&[u8]) -> Option<K>,
+{
+    let size = curve.secret_key_length();
+    // the top bits that exceed the size of the curve are always zero
+    let mask = 0xFFu8 >> (size * 8 - curve.nbits() as usize);
+
+    let mut bytes = vec![0u8; size];
+    let secret = loop {
+        rng.fill(&mut bytes[..]);
+        bytes[0] &= mask;
+
+        if let Some(secret) = from_slice(&bytes) {
+            break secret;
+        }
+    };
+    bytes.zeroize();
+
+    secret
+}
+
 fn asn1_der_object_id_val_enc(val: u32) -> Vec<u8> {
     let mut val = val;
     let mut acc = Vec::new();
@@ -171,16 +228,32 @@ mod tests {
             vec![0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07]
         );
         assert_eq!(ECCCurve::P384.oid(), vec![0x2B, 0x81, 0x04, 0x00, 0x22]);
+        assert_eq!(ECCCurve::Curve448.oid(), vec![0x2B, 0x65, 0x6F]);
+        assert_eq!(ECCCurve::Ed448.oid(), vec![0x2B, 0x65, 0x71]);
     }
 
     #[test]
     fn test_ecc_curve_from_oid() {
         let one = vec![0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
         assert_eq!(ecc_curve_from_oid(one.as_slice()).unwrap(), ECCCurve::P256);
+        assert_eq!(
+            ecc_curve_from_oid(&[0x2B, 0x65, 0x71]),
+            Some(ECCCurve::Ed448)
+        );
 
         assert_eq!(ecc_curve_from_oid(vec![1, 2, 3].as_slice()), None);
     }
 
+    #[test]
+    fn test_secret_key_length() {
+        assert_eq!(ECCCurve::Curve25519.secret_key_length(), 32);
+        assert_eq!(ECCCurve::P256.secret_key_length(), 32);
+        assert_eq!(ECCCurve::P384.secret_key_length(), 48);
+        assert_eq!(ECCCurve::P521.secret_key_length(), 66);
+        assert_eq!(ECCCurve::Curve448.secret_key_length(), 56);
+        assert_eq!(ECCCurve::Ed448.secret_key_length(), 57);
+    }
+
     #[test]
     fn test_asn1_der_object_id_val_enc() {
         assert_eq!(asn1_der_object_id_val_enc(840), vec![0x86, 0x48]);
@@ -3,9 +3,13 @@ use rand::{CryptoRng, Rng};
 use x25519_dalek::{PublicKey, StaticSecret};
 use zeroize::Zeroize;
 
-use crate::crypto::{aes_kw, ECCCurve, HashAlgorithm, PublicKeyAlgorithm, SymmetricKeyAlgorithm};
-use crate::errors::Result;
-use crate::types::{ECDHSecretKey, Mpi, PlainSecretParams, PublicParams};
+use crate::crypto::ecc_curve::generate_secret_scalar;
+use crate::crypto::{
+    aes_kw, ecc_curve_from_oid, ECCCurve, HashAlgorithm, PublicKeyAlgorithm,
+    SymmetricKeyAlgorithm,
+};
+use crate::errors::{Algorithm, Error, Result};
+use crate::types::{ECDHSecretKey, Fingerprint, Mpi, PlainSecretParams, PublicParams};
 
 /// 20 octets representing "Anonymous Sender    ".
 const ANON_SENDER: [u8; 20] = [
@@ -13,32 +17,126 @@ const ANON_SENDER: [u8; 20] = [
     0x20, 0x20, 0x20, 0x20,
 ];
 
+/// Generates a NIST curve key pair, returning the public point and the secret scalar.
+macro_rules! generate_nist {
+    ($rng:expr, $curve:expr, $c:ident) => {{
+        use $c::elliptic_curve::sec1::ToEncodedPoint;
+
+        let secret =
+            generate_secret_scalar($rng, &$curve, |b| $c::SecretKey::from_slice(b).ok());
+        let p = secret.public_key().to_encoded_point(false);
+
+        (
+            Mpi::from_raw_slice(p.as_bytes()),
+            Mpi::from_raw_slice(&secret.to_bytes()),
+        )
+    }};
+}
+
+/// Derives the shared secret between the secret scalar and the public point.
+macro_rules! derive_nist {
+    ($c:ident, $secret:expr, $public:expr) => {{
+        let secret = $c::SecretKey::from_slice($secret)
+            .map_err(|_| format_err!("invalid secret point"))?;
+        let public = $c::PublicKey::from_sec1_bytes($public)
+            .map_err(|_| format_err!("invalid public point"))?;
+
+        $c::ecdh::diffie_hellman(secret.to_nonzero_scalar(), public.as_affine())
+            .raw_secret_bytes()
+            .to_vec()
+    }};
+}
+
+/// Generates an ephemeral key and derives the shared secret with the given public point.
+/// Returns the shared secret and the ephemeral public point.
+macro_rules! encrypt_nist {
+    ($rng:expr, $curve:expr, $c:ident, $public:expr) => {{
+        use $c::elliptic_curve::sec1::ToEncodedPoint;
+
+        let their_public = $c::PublicKey::from_sec1_bytes($public)
+            .map_err(|_| format_err!("invalid public key"))?;
+        let our_secret =
+            generate_secret_scalar($rng, &$curve, |b| $c::SecretKey::from_slice(b).ok());
+
+        let shared =
+            $c::ecdh::diffie_hellman(our_secret.to_nonzero_scalar(), their_public.as_affine());
+        let our_public = our_secret.public_key().to_encoded_point(false);
+
+        (
+            shared.raw_secret_bytes().to_vec(),
+            our_public.as_bytes().to_vec(),
+        )
+    }};
+}
+
+/// The KDF hash and key wrap algorithm used for newly generated keys on the given curve.
+/// Ref: https://tools.ietf.org/html/rfc6637#section-13
+pub fn default_kdf_params(curve: &ECCCurve) -> (HashAlgorithm, SymmetricKeyAlgorithm) {
+    match *curve {
+        ECCCurve::P384 => (HashAlgorithm::SHA2_384, SymmetricKeyAlgorithm::AES192),
+        ECCCurve::P521 | ECCCurve::Curve448 => {
+            (HashAlgorithm::SHA2_512, SymmetricKeyAlgorithm::AES256)
+        }
+        _ => (HashAlgorithm::SHA2_256, SymmetricKeyAlgorithm::AES128),
+    }
+}
+
 /// Generate an ECDH KeyPair.
-/// Currently only support ED25519.
-pub fn generate_key<R: Rng + CryptoRng>(rng: &mut R) -> (PublicParams, PlainSecretParams) {
-    let secret = StaticSecret::new(rng);
-    let public = PublicKey::from(&secret);
-
-    // public key
-    let mut p = Vec::with_capacity(33);
-    p.push(0x40);
-    p.extend_from_slice(&public.as_bytes()[..]);
-
-    // secret key
-    let q = secret.to_bytes().iter().cloned().rev().collect::<Vec<u8>>();
-
-    // TODO: make these configurable and/or check for good defaults
-    let hash = HashAlgorithm::default();
-    let alg_sym = SymmetricKeyAlgorithm::AES128;
-    (
+/// Supports Curve25519, Curve448 and the NIST curves P-256, P-384 and P-521.
+pub fn generate_key<R: Rng + CryptoRng>(
+    rng: &mut R,
+    curve: &ECCCurve,
+) -> Result<(PublicParams, PlainSecretParams)> {
+    let (p, q) = match *curve {
+        ECCCurve::Curve25519 => {
+            let secret = StaticSecret::new(rng);
+            let public = PublicKey::from(&secret);
+
+            // public key
+            let mut p = Vec::with_capacity(33);
+            p.push(0x40);
+            p.extend_from_slice(&public.as_bytes()[..]);
+
+            // secret key
+            let q = secret.to_bytes().iter().cloned().rev().collect::<Vec<u8>>();
+
+            (p.into(), Mpi::from_raw(q))
+        }
+        #[cfg(feature = "curve448")]
+        ECCCurve::Curve448 => {
+            let secret = generate_secret_scalar(rng, curve, x448::Secret::from_bytes);
+            let public = x448::PublicKey::from(&secret);
+
+            // public key
+            let mut p = Vec::with_capacity(57);
+            p.push(0x40);
+            p.extend_from_slice(&public.as_bytes()[..]);
+
+            // secret key, unlike Curve25519 stored in its native byte order
+            let q = Mpi::from_raw_slice(&secret.as_bytes()[..]);
+
+            (p.into(), q)
+        }
+        #[cfg(feature = "p256")]
+        ECCCurve::P256 => generate_nist!(rng, ECCCurve::P256, p256),
+        #[cfg(feature = "p384")]
+        ECCCurve::P384 => generate_nist!(rng, ECCCurve::P384, p384),
+        #[cfg(feature = "p521")]
+        ECCCurve::P521 => generate_nist!(rng, ECCCurve::P521, p521),
+        _ => return Err(Error::UnsupportedAlgorithm(Algorithm::Curve(*curve))),
+    };
+
+    let (hash, alg_sym) = default_kdf_params(curve);
+
+    Ok((
         PublicParams::ECDH {
-            curve: ECCCurve::Curve25519,
-            p: p.into(),
+            curve: *curve,
+            p,
             hash,
             alg_sym,
         },
-        PlainSecretParams::ECDH(Mpi::from_raw(q)),
-    )
+        PlainSecretParams::ECDH(q),
+    ))
 }
 
 /// Build param for ECDH algorithm (as defined in RFC 6637)
@@ -71,53 +169,99 @@ pub fn build_ecdh_param(
 }
 
 /// ECDH decryption.
-pub fn decrypt(priv_key: &ECDHSecretKey, mpis: &[Mpi], fingerprint: &[u8]) -> Result<Vec<u8>> {
+pub fn decrypt(
+    priv_key: &ECDHSecretKey,
+    mpis: &[Mpi],
+    fingerprint: &Fingerprint,
+) -> Result<Vec<u8>> {
     debug!("ECDH decrypt");
 
-    let param = build_ecdh_param(&priv_key.oid, priv_key.alg_sym, priv_key.hash, fingerprint);
+    let param = build_ecdh_param(
+        &priv_key.oid,
+        priv_key.alg_sym,
+        priv_key.hash,
+        fingerprint.as_bytes(),
+    );
 
-    // 33 = 0x40 + 32bits
     ensure_eq!(mpis.len(), 3);
-    ensure_eq!(mpis[0].len(), 33, "invalid public point");
-    ensure_eq!(priv_key.secret.len(), 32, "invalid secret point");
 
     // encrypted and wrapped value derived from the session key
     let encrypted_session_key = mpis[2].as_bytes();
 
-    let their_public = {
-        // public part of the ephemeral key (removes 0x40 prefix)
-        let ephemeral_public_key = &mpis[0].as_bytes()[1..];
-
-        // create montgomery point
-        let mut ephemeral_public_key_arr = [0u8; 32];
-        ephemeral_public_key_arr[..].copy_from_slice(ephemeral_public_key);
-
-        x25519_dalek::PublicKey::from(ephemeral_public_key_arr)
-    };
-
-    let our_secret = {
-        // private key of the recipient.
-        let private_key = &priv_key.secret[..];
-
-        // create scalar and reverse to little endian
-        let mut private_key_le = private_key.iter().rev().cloned().collect::<Vec<u8>>();
-        let mut private_key_arr = [0u8; 32];
-        private_key_arr[..].copy_from_slice(&private_key_le);
-        private_key_le.zeroize();
-
-        x25519_dalek::StaticSecret::from(private_key_arr)
+    // public part of the ephemeral key
+    let ephemeral_public_key = mpis[0].as_bytes();
+    let curve = ecc_curve_from_oid(&priv_key.oid).ok_or_else(|| format_err!("invalid curve"))?;
+
+    let mut shared_secret = match curve {
+        ECCCurve::Curve25519 => {
+            // 33 = 0x40 + 32bits
+            ensure_eq!(ephemeral_public_key.len(), 33, "invalid public point");
+            ensure_eq!(priv_key.secret.len(), 32, "invalid secret point");
+
+            let their_public = {
+                // create montgomery point (removes 0x40 prefix)
+                let mut ephemeral_public_key_arr = [0u8; 32];
+                ephemeral_public_key_arr[..].copy_from_slice(&ephemeral_public_key[1..]);
+
+                x25519_dalek::PublicKey::from(ephemeral_public_key_arr)
+            };
+
+            let our_secret = {
+                // private key of the recipient.
+                let private_key = &priv_key.secret[..];
+
+                // create scalar and reverse to little endian
+                let mut private_key_le = private_key.iter().rev().cloned().collect::<Vec<u8>>();
+                let mut private_key_arr = [0u8; 32];
+                private_key_arr[..].copy_from_slice(&private_key_le);
+                private_key_le.zeroize();
+
+                x25519_dalek::StaticSecret::from(private_key_arr)
+            };
+
+            // derive shared secret
+            our_secret
+                .diffie_hellman(&their_public)
+                .as_bytes()
+                .to_vec()
+        }
+        #[cfg(feature = "curve448")]
+        ECCCurve::Curve448 => {
+            // 57 = 0x40 + 56bits
+            ensure_eq!(ephemeral_public_key.len(), 57, "invalid public point");
+            ensure_eq!(ephemeral_public_key[0], 0x40, "invalid public point");
+            ensure_eq!(priv_key.secret.len(), 56, "invalid secret point");
+
+            let their_public = x448::PublicKey::from_bytes(&ephemeral_public_key[1..])
+                .ok_or_else(|| format_err!("invalid public point"))?;
+            let our_secret = x448::Secret::from_bytes(&priv_key.secret)
+                .ok_or_else(|| format_err!("invalid secret point"))?;
+
+            // derive shared secret
+            our_secret
+                .as_diffie_hellman(&their_public)
+                .ok_or_else(|| format_err!("invalid shared secret"))?
+                .as_bytes()
+                .to_vec()
+        }
+        #[cfg(feature = "p256")]
+        ECCCurve::P256 => derive_nist!(p256, &priv_key.secret, ephemeral_public_key),
+        #[cfg(feature = "p384")]
+        ECCCurve::P384 => derive_nist!(p384, &priv_key.secret, ephemeral_public_key),
+        #[cfg(feature = "p521")]
+        ECCCurve::P521 => derive_nist!(p521, &priv_key.secret, ephemeral_public_key),
+        _ => return Err(Error::UnsupportedAlgorithm(Algorithm::Curve(curve))),
     };
 
-    // derive shared secret
-    let shared_secret = our_secret.diffie_hellman(&their_public);
-
     // Perform key derivation
     let z = kdf(
         priv_key.hash,
-        shared_secret.as_bytes(),
+        &shared_secret,
         priv_key.alg_sym.key_size(),
         &param,
-    )?;
+    );
+    shared_secret.zeroize();
+    let z = z?;
 
     // Peform AES Key Unwrap
     let encrypted_key_len: usize = match mpis[1].first() {
@@ -140,7 +284,7 @@ pub fn decrypt(priv_key: &ECDHSecretKey, mpis: &[Mpi], fingerprint: &[u8]) -> Re
 
 /// Key Derivation Function for ECDH (as defined in RFC 6637).
 /// https://tools.ietf.org/html/rfc6637#section-7
-fn kdf(hash: HashAlgorithm, x: &[u8; 32], length: usize, param: &[u8]) -> Result<Vec<u8>> {
+fn kdf(hash: HashAlgorithm, x: &[u8], length: usize, param: &[u8]) -> Result<Vec<u8>> {
     let prefix = vec![0, 0, 0, 1];
 
     let values: Vec<&[u8]> = vec![&prefix, x, param];
@@ -158,34 +302,75 @@ pub fn encrypt<R: CryptoRng + Rng>(
     curve: &ECCCurve,
     alg_sym: SymmetricKeyAlgorithm,
     hash: HashAlgorithm,
-    fingerprint: &[u8],
+    fingerprint: &Fingerprint,
     q: &[u8],
     plain: &[u8],
 ) -> Result<Vec<Vec<u8>>> {
     debug!("ECDH encrypt");
 
-    let param = build_ecdh_param(&curve.oid(), alg_sym, hash, fingerprint);
-
-    ensure_eq!(q.len(), 33, "invalid public key");
-
-    let their_public = {
-        // public part of the ephemeral key (removes 0x40 prefix)
-        let public_key = &q[1..];
-
-        // create montgomery point
-        let mut public_key_arr = [0u8; 32];
-        public_key_arr[..].copy_from_slice(public_key);
-
-        x25519_dalek::PublicKey::from(public_key_arr)
+    let param = build_ecdh_param(&curve.oid(), alg_sym, hash, fingerprint.as_bytes());
+
+    let (mut shared_secret, encoded_public) = match *curve {
+        ECCCurve::Curve25519 => {
+            ensure_eq!(q.len(), 33, "invalid public key");
+
+            let their_public = {
+                // public part of the ephemeral key (removes 0x40 prefix)
+                let public_key = &q[1..];
+
+                // create montgomery point
+                let mut public_key_arr = [0u8; 32];
+                public_key_arr[..].copy_from_slice(public_key);
+
+                x25519_dalek::PublicKey::from(public_key_arr)
+            };
+
+            let our_secret = x25519_dalek::StaticSecret::new(rng);
+
+            // derive shared secret
+            let shared_secret = our_secret.diffie_hellman(&their_public);
+
+            // Encode public point: prefix with 0x40
+            let mut encoded_public = Vec::with_capacity(33);
+            encoded_public.push(0x40);
+            encoded_public.extend(x25519_dalek::PublicKey::from(&our_secret).as_bytes().iter());
+
+            (shared_secret.as_bytes().to_vec(), encoded_public)
+        }
+        #[cfg(feature = "curve448")]
+        ECCCurve::Curve448 => {
+            ensure_eq!(q.len(), 57, "invalid public key");
+            ensure_eq!(q[0], 0x40, "invalid public key");
+
+            let their_public = x448::PublicKey::from_bytes(&q[1..])
+                .ok_or_else(|| format_err!("invalid public key"))?;
+            let our_secret = generate_secret_scalar(rng, curve, x448::Secret::from_bytes);
+
+            // derive shared secret
+            let shared_secret = our_secret
+                .as_diffie_hellman(&their_public)
+                .ok_or_else(|| format_err!("invalid shared secret"))?;
+
+            // Encode public point: prefix with 0x40
+            let mut encoded_public = Vec::with_capacity(57);
+            encoded_public.push(0x40);
+            encoded_public.extend(x448::PublicKey::from(&our_secret).as_bytes().iter());
+
+            (shared_secret.as_bytes().to_vec(), encoded_public)
+        }
+        #[cfg(feature = "p256")]
+        ECCCurve::P256 => encrypt_nist!(rng, ECCCurve::P256, p256, q),
+        #[cfg(feature = "p384")]
+        ECCCurve::P384 => encrypt_nist!(rng, ECCCurve::P384, p384, q),
+        #[cfg(feature = "p521")]
+        ECCCurve::P521 => encrypt_nist!(rng, ECCCurve::P521, p521, q),
+        _ => return Err(Error::UnsupportedAlgorithm(Algorithm::Curve(*curve))),
     };
 
-    let our_secret = x25519_dalek::StaticSecret::new(rng);
-
-    // derive shared secret
-    let shared_secret = our_secret.diffie_hellman(&their_public);
-
     // Perform key derivation
-    let z = kdf(hash, shared_secret.as_bytes(), alg_sym.key_size(), &param)?;
+    let z = kdf(hash, &shared_secret, alg_sym.key_size(), &param);
+    shared_secret.zeroize();
+    let z = z?;
 
     // PKCS5 padding (PKCS5 is PKCS7 with a blocksize of 8)
     let len = plain.len();
@@ -196,11 +381,6 @@ pub fn encrypt<R: CryptoRng + Rng>(
     // Peform AES Key Wrap
     let encrypted_key = aes_kw::wrap(&z, plain_padded_ref)?;
 
-    // Encode public point: prefix with 0x40
-    let mut encoded_public = Vec::with_capacity(33);
-    encoded_public.push(0x40);
-    encoded_public.extend(x25519_dalek::PublicKey::from(&our_secret).as_bytes().iter());
-
     let encrypted_key_len = vec![encrypted_key.len() as u8];
 
     Ok(vec![encoded_public, encrypted_key_len, encrypted_key])
@@ -216,12 +396,41 @@ mod tests {
     use crate::types::{PublicParams, SecretKeyRepr};
 
     #[test]
-    fn test_encrypt_decrypt() {
+    fn test_encrypt_decrypt_curve25519() {
+        encrypt_decrypt(&ECCCurve::Curve25519);
+    }
+
+    #[test]
+    #[cfg(feature = "curve448")]
+    fn test_encrypt_decrypt_curve448() {
+        encrypt_decrypt(&ECCCurve::Curve448);
+    }
+
+    #[test]
+    #[cfg(feature = "p256")]
+    fn test_encrypt_decrypt_p256() {
+        encrypt_decrypt(&ECCCurve::P256);
+    }
+
+    #[test]
+    #[cfg(feature = "p384")]
+    fn test_encrypt_decrypt_p384() {
+        encrypt_decrypt(&ECCCurve::P384);
+    }
+
+    #[test]
+    #[cfg(feature = "p521")]
+    fn test_encrypt_decrypt_p521() {
+        encrypt_decrypt(&ECCCurve::P521);
+    }
+
+    fn encrypt_decrypt(curve: &ECCCurve) {
         let mut rng = ChaChaRng::from_seed([0u8; 32]);
 
-        let (pkey, skey) = generate_key(&mut rng);
-        let mut fingerprint = vec![0u8; 20];
+        let (pkey, skey) = generate_key(&mut rng, curve).unwrap();
+        let mut fingerprint = [0u8; 20];
         rng.fill_bytes(&mut fingerprint);
+        let fingerprint = Fingerprint::V4(fingerprint);
 
         let plain = b"hello world";
 
@@ -231,16 +440,19 @@ mod tests {
                 ref p,
                 hash,
                 alg_sym,
-            } => encrypt(
-                &mut rng,
-                curve,
-                alg_sym,
-                hash,
-                &fingerprint,
-                p.as_bytes(),
-                &plain[..],
-            )
-            .unwrap(),
+            } => {
+                assert_eq!((hash, alg_sym), default_kdf_params(curve));
+                encrypt(
+                    &mut rng,
+                    curve,
+                    alg_sym,
+                    hash,
+                    &fingerprint,
+                    p.as_bytes(),
+                    &plain[..],
+                )
+                .unwrap()
+            }
             _ => panic!("invalid key generated"),
         };
 
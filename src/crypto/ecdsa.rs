@@ -0,0 +1,227 @@
+use rand::{CryptoRng, Rng};
+
+use crate::crypto::ecc_curve::generate_secret_scalar;
+use crate::crypto::{ECCCurve, HashAlgorithm};
+use crate::errors::{Algorithm, Error, Result};
+use crate::types::{ECDSASecretKey, Mpi, PlainSecretParams, PublicParams};
+
+macro_rules! generate {
+    ($rng:expr, $curve:expr, $c:ident) => {{
+        let secret = generate_secret_scalar($rng, &$curve, |b| {
+            $c::ecdsa::SigningKey::from_slice(b).ok()
+        });
+
+        // public key, as uncompressed point
+        let p = secret.verifying_key().to_encoded_point(false);
+
+        (
+            PublicParams::ECDSA {
+                curve: $curve,
+                p: Mpi::from_raw_slice(p.as_bytes()),
+            },
+            PlainSecretParams::ECDSA(Mpi::from_raw_slice(&secret.to_bytes())),
+        )
+    }};
+}
+
+macro_rules! verify {
+    ($c:ident, $p:expr, $hashed:expr, $sig:expr) => {{
+        use $c::ecdsa::signature::hazmat::PrehashVerifier;
+
+        let pk = $c::ecdsa::VerifyingKey::from_sec1_bytes($p)
+            .map_err(|_| format_err!("invalid public key"))?;
+        let sig = $c::ecdsa::Signature::from_slice($sig)
+            .map_err(|_| format_err!("invalid signature"))?;
+
+        pk.verify_prehash($hashed, &sig)
+            .map_err(|_| format_err!("invalid signature"))?;
+    }};
+}
+
+macro_rules! sign {
+    ($c:ident, $secret:expr, $digest:expr) => {{
+        use $c::ecdsa::signature::hazmat::PrehashSigner;
+
+        let key = $c::ecdsa::SigningKey::from_slice($secret)
+            .map_err(|_| format_err!("invalid secret key"))?;
+        let signature: $c::ecdsa::Signature = key
+            .sign_prehash($digest)
+            .map_err(|_| format_err!("failed to sign"))?;
+        let (r, s) = signature.split_bytes();
+
+        vec![r.to_vec(), s.to_vec()]
+    }};
+}
+
+/// Generate an ECDSA KeyPair.
+pub fn generate_key<R: Rng + CryptoRng>(
+    rng: &mut R,
+    curve: &ECCCurve,
+) -> Result<(PublicParams, PlainSecretParams)> {
+    match *curve {
+        #[cfg(feature = "p256")]
+        ECCCurve::P256 => Ok(generate!(rng, ECCCurve::P256, p256)),
+        #[cfg(feature = "p384")]
+        ECCCurve::P384 => Ok(generate!(rng, ECCCurve::P384, p384)),
+        #[cfg(feature = "p521")]
+        ECCCurve::P521 => Ok(generate!(rng, ECCCurve::P521, p521)),
+        #[cfg(feature = "k256")]
+        ECCCurve::Secp256k1 => Ok(generate!(rng, ECCCurve::Secp256k1, k256)),
+        _ => Err(Error::UnsupportedAlgorithm(Algorithm::Curve(*curve))),
+    }
+}
+
+/// Left pads digests that are shorter than half the size of the curve.
+/// This is equivalent to how ECDSA interprets the digest as integer, but such digests are
+/// otherwise rejected by the `ecdsa` implementation, e.g. SHA2-256 on P-521.
+fn pad_digest(curve: &ECCCurve, digest: &[u8]) -> Vec<u8> {
+    let size = curve.secret_key_length();
+    if digest.len() * 2 < size {
+        let mut padded = vec![0u8; size];
+        padded[size - digest.len()..].copy_from_slice(digest);
+        padded
+    } else {
+        digest.to_vec()
+    }
+}
+
+/// Verify an ECDSA signature.
+pub fn verify(
+    curve: &ECCCurve,
+    p: &[u8],
+    _hash: HashAlgorithm,
+    hashed: &[u8],
+    sig: &[Mpi],
+) -> Result<()> {
+    ensure_eq!(sig.len(), 2);
+
+    let size = curve.secret_key_length();
+    let r = sig[0].as_bytes();
+    let s = sig[1].as_bytes();
+
+    ensure!(r.len() <= size, "invalid R (len)");
+    ensure!(s.len() <= size, "invalid S (len)");
+
+    // add padding if the values were encoded short
+    let mut sig_bytes = vec![0u8; 2 * size];
+    sig_bytes[(size - r.len())..size].copy_from_slice(r);
+    sig_bytes[size + (size - s.len())..].copy_from_slice(s);
+
+    let hashed = &pad_digest(curve, hashed);
+
+    match *curve {
+        #[cfg(feature = "p256")]
+        ECCCurve::P256 => verify!(p256, p, hashed, &sig_bytes),
+        #[cfg(feature = "p384")]
+        ECCCurve::P384 => verify!(p384, p, hashed, &sig_bytes),
+        #[cfg(feature = "p521")]
+        ECCCurve::P521 => verify!(p521, p, hashed, &sig_bytes),
+        #[cfg(feature = "k256")]
+        ECCCurve::Secp256k1 => {
+            use k256::ecdsa::signature::hazmat::PrehashVerifier;
+
+            let pk = k256::ecdsa::VerifyingKey::from_sec1_bytes(p)
+                .map_err(|_| format_err!("invalid public key"))?;
+            let sig = k256::ecdsa::Signature::from_slice(&sig_bytes)
+                .map_err(|_| format_err!("invalid signature"))?;
+            // `k256` rejects signatures with a high S value, which are valid in OpenPGP
+            let sig = sig.normalize_s().unwrap_or(sig);
+
+            pk.verify_prehash(hashed, &sig)
+                .map_err(|_| format_err!("invalid signature"))?;
+        }
+        _ => return Err(Error::UnsupportedAlgorithm(Algorithm::Curve(*curve))),
+    }
+
+    Ok(())
+}
+
+/// Sign using ECDSA.
+pub fn sign(
+    curve: &ECCCurve,
+    secret_key: &ECDSASecretKey,
+    _hash: HashAlgorithm,
+    digest: &[u8],
+) -> Result<Vec<Vec<u8>>> {
+    let secret = &secret_key.secret[..];
+    let digest = &pad_digest(curve, digest);
+
+    let sig = match *curve {
+        #[cfg(feature = "p256")]
+        ECCCurve::P256 => sign!(p256, secret, digest),
+        #[cfg(feature = "p384")]
+        ECCCurve::P384 => sign!(p384, secret, digest),
+        #[cfg(feature = "p521")]
+        ECCCurve::P521 => sign!(p521, secret, digest),
+        #[cfg(feature = "k256")]
+        ECCCurve::Secp256k1 => sign!(k256, secret, digest),
+        _ => return Err(Error::UnsupportedAlgorithm(Algorithm::Curve(*curve))),
+    };
+
+    Ok(sig)
+}
+
+// there is nothing to test without any of the ECDSA curves
+#[cfg(all(
+    test,
+    any(feature = "p256", feature = "p384", feature = "p521", feature = "k256")
+))]
+mod tests {
+    use super::*;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    use crate::types::SecretKeyRepr;
+
+    #[test]
+    #[cfg(feature = "p256")]
+    fn test_sign_verify_p256() {
+        sign_verify(&ECCCurve::P256, HashAlgorithm::SHA2_256);
+    }
+
+    #[test]
+    #[cfg(feature = "p384")]
+    fn test_sign_verify_p384() {
+        sign_verify(&ECCCurve::P384, HashAlgorithm::SHA2_384);
+    }
+
+    #[test]
+    #[cfg(feature = "p521")]
+    fn test_sign_verify_p521() {
+        sign_verify(&ECCCurve::P521, HashAlgorithm::SHA2_512);
+        sign_verify(&ECCCurve::P521, HashAlgorithm::SHA2_256);
+    }
+
+    #[test]
+    #[cfg(feature = "k256")]
+    fn test_sign_verify_secp256k1() {
+        sign_verify(&ECCCurve::Secp256k1, HashAlgorithm::SHA2_256);
+    }
+
+    fn sign_verify(curve: &ECCCurve, hash: HashAlgorithm) {
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+        let (pkey, skey) = generate_key(&mut rng, curve).unwrap();
+        let digest = hash.digest(&b"hello world"[..]).unwrap();
+
+        let sig = match skey.as_ref().as_repr(&pkey).unwrap() {
+            SecretKeyRepr::ECDSA(ref skey) => sign(curve, skey, hash, &digest).unwrap(),
+            _ => panic!("invalid key generated"),
+        };
+        let sig = sig
+            .iter()
+            .map(|v| Mpi::from_raw_slice(&v[..]))
+            .collect::<Vec<_>>();
+
+        match pkey {
+            PublicParams::ECDSA { ref p, .. } => {
+                verify(curve, p.as_bytes(), hash, &digest, &sig).unwrap();
+
+                let other = hash.digest(&b"hello world!"[..]).unwrap();
+                assert!(verify(curve, p.as_bytes(), hash, &other, &sig).is_err());
+            }
+            _ => panic!("invalid key generated"),
+        }
+    }
+}
@@ -1,33 +1,64 @@
+use std::convert::TryFrom;
+
 use ed25519_dalek::Keypair;
 use rand::{CryptoRng, Rng};
 use signature::{Signature, Signer, Verifier};
 use zeroize::Zeroize;
 
-use crate::crypto::{ECCCurve, HashAlgorithm};
-use crate::errors::Result;
+use crate::crypto::{ecc_curve_from_oid, ECCCurve, HashAlgorithm};
+use crate::errors::{Algorithm, Error, Result};
 use crate::types::{EdDSASecretKey, Mpi, PlainSecretParams, PublicParams};
 
 /// Generate an EdDSA KeyPair.
-pub fn generate_key<R: Rng + CryptoRng>(rng: &mut R) -> (PublicParams, PlainSecretParams) {
-    let keypair = Keypair::generate(rng);
-    let mut bytes = keypair.to_bytes();
+/// Supports Ed25519 and Ed448.
+pub fn generate_key<R: Rng + CryptoRng>(
+    rng: &mut R,
+    curve: &ECCCurve,
+) -> Result<(PublicParams, PlainSecretParams)> {
+    let (q, p) = match *curve {
+        ECCCurve::Ed25519 => {
+            let keypair = Keypair::generate(rng);
+            let mut bytes = keypair.to_bytes();
+
+            // public key
+            let mut q = Vec::with_capacity(33);
+            q.push(0x40);
+            q.extend_from_slice(&bytes[32..]);
 
-    // public key
-    let mut q = Vec::with_capacity(33);
-    q.push(0x40);
-    q.extend_from_slice(&bytes[32..]);
+            // secret key
+            let p = Mpi::from_raw_slice(&bytes[..32]);
+            bytes.zeroize();
 
-    // secret key
-    let p = Mpi::from_raw_slice(&bytes[..32]);
-    bytes.zeroize();
+            (q, p)
+        }
+        #[cfg(feature = "curve448")]
+        ECCCurve::Ed448 => {
+            let mut bytes = [0u8; 57];
+            rng.fill(&mut bytes[..]);
+            let secret = ed448_rust::PrivateKey::from(bytes);
+            let public = ed448_rust::PublicKey::from(&secret);
+
+            // public key
+            let mut q = Vec::with_capacity(58);
+            q.push(0x40);
+            q.extend_from_slice(&public.as_byte()[..]);
+
+            // secret key
+            let p = Mpi::from_raw_slice(&bytes[..]);
+            bytes.zeroize();
+
+            (q, p)
+        }
+        _ => return Err(Error::UnsupportedAlgorithm(Algorithm::Curve(*curve))),
+    };
 
-    (
+    Ok((
         PublicParams::EdDSA {
-            curve: ECCCurve::Ed25519,
+            curve: *curve,
             q: q.into(),
         },
         PlainSecretParams::EdDSA(p),
-    )
+    ))
 }
 
 /// Verify an EdDSA signature.
@@ -62,30 +93,84 @@ pub fn verify(
 
             Ok(())
         }
-        _ => unsupported_err!("curve {:?} for EdDSA", curve.to_string()),
+        #[cfg(feature = "curve448")]
+        ECCCurve::Ed448 => {
+            ensure_eq!(sig.len(), 2);
+
+            let r = sig[0].as_bytes();
+            let s = sig[1].as_bytes();
+
+            ensure!(r.len() < 58, "invalid R (len)");
+            ensure!(s.len() < 58, "invalid S (len)");
+            ensure_eq!(q.len(), 58, "invalid Q (len)");
+            ensure_eq!(q[0], 0x40, "invalid Q (prefix)");
+
+            let pk = ed448_rust::PublicKey::try_from(&q[1..])
+                .map_err(|err| format_err!("invalid Q: {:?}", err))?;
+            let mut sig_bytes = vec![0u8; 114];
+            // add padding if the values were encoded short
+            sig_bytes[(57 - r.len())..57].copy_from_slice(r);
+            sig_bytes[57 + (57 - s.len())..].copy_from_slice(s);
+
+            pk.verify(hashed, &sig_bytes, None)
+                .map_err(|err| format_err!("invalid signature: {:?}", err))?;
+
+            Ok(())
+        }
+        _ => Err(Error::UnsupportedAlgorithm(Algorithm::Curve(*curve))),
     }
 }
 
-/// Sign using RSA, with PKCS1v15 padding.
+/// Sign using EdDSA, on the curve of the given secret key.
 pub fn sign(
     q: &[u8],
     secret_key: &EdDSASecretKey,
     _hash: HashAlgorithm,
     digest: &[u8],
 ) -> Result<Vec<Vec<u8>>> {
-    ensure_eq!(q.len(), 33, "invalid Q (len)");
-    ensure_eq!(q[0], 0x40, "invalid Q (prefix)");
+    let curve =
+        ecc_curve_from_oid(&secret_key.oid).ok_or_else(|| format_err!("invalid curve"))?;
+
+    match curve {
+        ECCCurve::Ed25519 => {
+            ensure_eq!(q.len(), 33, "invalid Q (len)");
+            ensure_eq!(q[0], 0x40, "invalid Q (prefix)");
+            ensure_eq!(secret_key.secret.len(), 32, "invalid secret");
 
-    let mut kp_bytes = vec![0u8; 64];
-    kp_bytes[..32].copy_from_slice(&secret_key.secret);
-    kp_bytes[32..].copy_from_slice(&q[1..]);
-    let kp = ed25519_dalek::Keypair::from_bytes(&kp_bytes)?;
+            let mut kp_bytes = vec![0u8; 64];
+            kp_bytes[..32].copy_from_slice(&secret_key.secret);
+            kp_bytes[32..].copy_from_slice(&q[1..]);
+            let kp = ed25519_dalek::Keypair::from_bytes(&kp_bytes);
+            kp_bytes.zeroize();
 
-    let signature = kp.sign(digest);
-    let bytes = signature.to_bytes();
+            let signature = kp?.sign(digest);
+            let bytes = signature.to_bytes();
 
-    let r = bytes[..32].to_vec();
-    let s = bytes[32..].to_vec();
+            let r = bytes[..32].to_vec();
+            let s = bytes[32..].to_vec();
 
-    Ok(vec![r, s])
+            Ok(vec![r, s])
+        }
+        #[cfg(feature = "curve448")]
+        ECCCurve::Ed448 => {
+            ensure_eq!(q.len(), 58, "invalid Q (len)");
+            ensure_eq!(q[0], 0x40, "invalid Q (prefix)");
+            ensure_eq!(secret_key.secret.len(), 57, "invalid secret");
+
+            let secret = ed448_rust::PrivateKey::try_from(&secret_key.secret[..])
+                .map_err(|err| format_err!("invalid secret: {:?}", err))?;
+            let public = ed448_rust::PublicKey::from(&secret);
+            ensure_eq!(&public.as_byte()[..], &q[1..], "inconsistent Q");
+
+            let bytes = secret
+                .sign(digest, None)
+                .map_err(|err| format_err!("failed to sign: {:?}", err))?;
+
+            let r = bytes[..57].to_vec();
+            let s = bytes[57..].to_vec();
+
+            Ok(vec![r, s])
+        }
+        _ => Err(Error::UnsupportedAlgorithm(Algorithm::Curve(curve))),
+    }
 }
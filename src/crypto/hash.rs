@@ -6,10 +6,11 @@ use try_from::TryInto;
 use digest::{Digest, FixedOutput};
 use generic_array::typenum::Unsigned;
 use md5::Md5;
+#[cfg(feature = "ripemd160")]
 use ripemd160::Ripemd160;
 use sha1::Sha1;
 
-use crate::errors::{Error, Result};
+use crate::errors::{Algorithm, Error, Result};
 
 /// Available hash algorithms.
 /// Ref: https://tools.ietf.org/html/rfc4880.html#section-9.4
@@ -54,7 +55,7 @@ impl TryInto<Hash> for HashAlgorithm {
             HashAlgorithm::SHA2_224 => Ok(Hash::SHA2_224),
             HashAlgorithm::SHA3_256 => Ok(Hash::SHA3_256),
             HashAlgorithm::SHA3_512 => Ok(Hash::SHA3_512),
-            HashAlgorithm::Private10 => unsupported_err!("Private10 should not be used"),
+            HashAlgorithm::Private10 => Err(Error::UnsupportedAlgorithm(Algorithm::Hash(self))),
         }
     }
 }
@@ -100,12 +101,15 @@ macro_rules! derive_hasher {
 
 derive_hasher!(Md5Hasher, Md5);
 derive_hasher!(Sha1Hasher, Sha1);
+#[cfg(feature = "ripemd160")]
 derive_hasher!(Ripemd160Hasher, Ripemd160);
 derive_hasher!(Sha2_256Hasher, sha2::Sha256);
 derive_hasher!(Sha2_384Hasher, sha2::Sha384);
 derive_hasher!(Sha2_512Hasher, sha2::Sha512);
 derive_hasher!(Sha2_224Hasher, sha2::Sha224);
+#[cfg(feature = "sha3")]
 derive_hasher!(Sha3_256Hasher, sha3::Sha3_256);
+#[cfg(feature = "sha3")]
 derive_hasher!(Sha3_512Hasher, sha3::Sha3_512);
 
 impl HashAlgorithm {
@@ -114,15 +118,18 @@ impl HashAlgorithm {
         match self {
             HashAlgorithm::MD5 => Ok(Box::new(Md5Hasher::default())),
             HashAlgorithm::SHA1 => Ok(Box::new(Sha1Hasher::default())),
+            #[cfg(feature = "ripemd160")]
             HashAlgorithm::RIPEMD160 => Ok(Box::new(Ripemd160Hasher::default())),
             HashAlgorithm::SHA2_256 => Ok(Box::new(Sha2_256Hasher::default())),
             HashAlgorithm::SHA2_384 => Ok(Box::new(Sha2_384Hasher::default())),
             HashAlgorithm::SHA2_512 => Ok(Box::new(Sha2_512Hasher::default())),
             HashAlgorithm::SHA2_224 => Ok(Box::new(Sha2_224Hasher::default())),
+            #[cfg(feature = "sha3")]
             HashAlgorithm::SHA3_256 => Ok(Box::new(Sha3_256Hasher::default())),
+            #[cfg(feature = "sha3")]
             HashAlgorithm::SHA3_512 => Ok(Box::new(Sha3_512Hasher::default())),
 
-            _ => unimplemented_err!("hasher {:?}", self),
+            _ => Err(Error::UnsupportedAlgorithm(Algorithm::Hash(self))),
         }
     }
 
@@ -131,16 +138,18 @@ impl HashAlgorithm {
         Ok(match self {
             HashAlgorithm::MD5 => Md5::digest(data).to_vec(),
             HashAlgorithm::SHA1 => Sha1::digest(data).to_vec(),
+            #[cfg(feature = "ripemd160")]
             HashAlgorithm::RIPEMD160 => Ripemd160::digest(data).to_vec(),
             HashAlgorithm::SHA2_256 => sha2::Sha256::digest(data).to_vec(),
             HashAlgorithm::SHA2_384 => sha2::Sha384::digest(data).to_vec(),
             HashAlgorithm::SHA2_512 => sha2::Sha512::digest(data).to_vec(),
             HashAlgorithm::SHA2_224 => sha2::Sha224::digest(data).to_vec(),
+            #[cfg(feature = "sha3")]
             HashAlgorithm::SHA3_256 => sha3::Sha3_256::digest(data).to_vec(),
+            #[cfg(feature = "sha3")]
             HashAlgorithm::SHA3_512 => sha3::Sha3_512::digest(data).to_vec(),
 
-            HashAlgorithm::Private10 => unsupported_err!("Private10 should not be used"),
-            _ => unimplemented_err!("hasher: {:?}", self),
+            _ => return Err(Error::UnsupportedAlgorithm(Algorithm::Hash(self))),
         })
     }
 
@@ -149,13 +158,14 @@ impl HashAlgorithm {
         match self {
             HashAlgorithm::MD5 => <Md5 as FixedOutput>::OutputSize::to_usize(),
             HashAlgorithm::SHA1 => <Sha1 as FixedOutput>::OutputSize::to_usize(),
-            HashAlgorithm::RIPEMD160 => <Ripemd160 as FixedOutput>::OutputSize::to_usize(),
+            // the sizes of the optional algorithms are known, even when they are not compiled in
+            HashAlgorithm::RIPEMD160 => 20,
             HashAlgorithm::SHA2_256 => <sha2::Sha256 as FixedOutput>::OutputSize::to_usize(),
             HashAlgorithm::SHA2_384 => <sha2::Sha384 as FixedOutput>::OutputSize::to_usize(),
             HashAlgorithm::SHA2_512 => <sha2::Sha512 as FixedOutput>::OutputSize::to_usize(),
             HashAlgorithm::SHA2_224 => <sha2::Sha224 as FixedOutput>::OutputSize::to_usize(),
-            HashAlgorithm::SHA3_256 => <sha3::Sha3_256 as FixedOutput>::OutputSize::to_usize(),
-            HashAlgorithm::SHA3_512 => <sha3::Sha3_512 as FixedOutput>::OutputSize::to_usize(),
+            HashAlgorithm::SHA3_256 => 32,
+            HashAlgorithm::SHA3_512 => 64,
             _ => 0,
         }
     }
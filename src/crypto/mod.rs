@@ -5,6 +5,7 @@ pub mod aes_kw;
 pub mod checksum;
 pub mod ecc_curve;
 pub mod ecdh;
+pub mod ecdsa;
 pub mod eddsa;
 pub mod hash;
 pub mod public_key;
@@ -16,6 +17,7 @@ pub use self::aes_kw::*;
 pub use self::checksum::*;
 pub use self::ecc_curve::*;
 pub use self::ecdh::*;
+pub use self::ecdsa::*;
 pub use self::eddsa::*;
 pub use self::hash::*;
 pub use self::public_key::*;
@@ -1,4 +1,5 @@
 #[derive(Debug, PartialEq, Eq, Clone, Copy, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum PublicKeyAlgorithm {
     /// RSA (Encrypt and Sign)
@@ -1,21 +1,42 @@
 use num_bigint::traits::ModInverse;
-use num_bigint::BigUint;
-use rand::{CryptoRng, Rng};
+use num_bigint::{BigUint, RandPrime};
+use num_traits::One;
+use rand::{thread_rng, CryptoRng, Rng};
 use rsa::padding::PaddingScheme;
 use rsa::{PublicKey, PublicKeyParts, RSAPrivateKey, RSAPublicKey};
 use try_from::TryInto;
 
-use crate::crypto::HashAlgorithm;
+use crate::crypto::{HashAlgorithm, SymmetricKeyAlgorithm};
 use crate::errors::Result;
 use crate::types::{Mpi, PlainSecretParams, PublicParams};
 
+/// Length of the random session key returned for invalid padding: the algorithm, an AES256
+/// key and the checksum.
+const IMPLICIT_REJECTION_LEN: usize = 1 + 32 + 2;
+
 /// RSA decryption using PKCS1v15 padding.
-pub fn decrypt(priv_key: &RSAPrivateKey, mpis: &[Mpi], _fingerprint: &[u8]) -> Result<Vec<u8>> {
+///
+/// The decryption is blinded. Invalid padding is not reported, instead a random AES256
+/// session key is returned, which fails the session key checksum like any other invalid
+/// session key. This way neither errors nor timing act as a padding oracle.
+pub fn decrypt(priv_key: &RSAPrivateKey, mpis: &[Mpi]) -> Result<Vec<u8>> {
     // rsa consist of exactly one mpi
     ensure_eq!(mpis.len(), 1, "invalid input");
 
+    // generated before decrypting, so that valid and invalid padding take the same work
+    let mut rng = thread_rng();
+    let mut rejection = vec![0u8; IMPLICIT_REJECTION_LEN];
+    rng.fill(&mut rejection[..]);
+    rejection[0] = SymmetricKeyAlgorithm::AES256 as u8;
+
     let mpi = &mpis[0];
-    let m = priv_key.decrypt(PaddingScheme::new_pkcs1v15_encrypt(), mpi.as_bytes())?;
+    let m = priv_key
+        .decrypt_blinded(
+            &mut rng,
+            PaddingScheme::new_pkcs1v15_encrypt(),
+            mpi.as_bytes(),
+        )
+        .unwrap_or(rejection);
 
     Ok(m)
 }
@@ -40,6 +61,51 @@ pub fn generate_key<R: Rng + CryptoRng>(
 ) -> Result<(PublicParams, PlainSecretParams)> {
     let key = RSAPrivateKey::new(rng, bit_size)?;
 
+    Ok(key_params(&key))
+}
+
+/// Generate an RSA KeyPair, with the given public exponent.
+pub fn generate_key_with_exponent<R: Rng + CryptoRng>(
+    rng: &mut R,
+    bit_size: usize,
+    exponent: u32,
+) -> Result<(PublicParams, PlainSecretParams)> {
+    ensure!(
+        exponent >= 3 && exponent % 2 == 1,
+        "invalid RSA public exponent {}",
+        exponent
+    );
+
+    let e = BigUint::from(exponent);
+    let one = BigUint::one();
+    loop {
+        let p = rng.gen_prime(bit_size / 2);
+        let q = rng.gen_prime(bit_size - bit_size / 2);
+        if p == q {
+            continue;
+        }
+
+        let n = &p * &q;
+        if n.bits() != bit_size {
+            continue;
+        }
+
+        // the exponent needs to be invertible, otherwise try again with new primes
+        let totient = (&p - &one) * (&q - &one);
+        let d = match e.clone().mod_inverse(&totient).and_then(|d| d.to_biguint()) {
+            Some(d) => d,
+            None => continue,
+        };
+
+        let key = RSAPrivateKey::from_components(n, e, d, vec![p, q]);
+        key.validate()?;
+
+        return Ok(key_params(&key));
+    }
+}
+
+/// Converts an RSA private key into the OpenPGP public and secret parameters.
+pub(crate) fn key_params(key: &RSAPrivateKey) -> (PublicParams, PlainSecretParams) {
     let p = &key.primes()[0];
     let q = &key.primes()[1];
     let u = p
@@ -49,7 +115,7 @@ pub fn generate_key<R: Rng + CryptoRng>(
         .to_biguint()
         .expect("invalid prime");
 
-    Ok((
+    (
         PublicParams::RSA {
             n: key.n().into(),
             e: key.e().into(),
@@ -60,7 +126,7 @@ pub fn generate_key<R: Rng + CryptoRng>(
             q: q.into(),
             u: u.into(),
         },
-    ))
+    )
 }
 
 /// Verify a RSA, PKCS1v15 padded signature.
@@ -79,3 +145,67 @@ pub fn sign(key: &RSAPrivateKey, hash: HashAlgorithm, digest: &[u8]) -> Result<V
 
     Ok(vec![sig])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_generate_key_with_exponent() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+
+        let (public_params, secret_params) =
+            generate_key_with_exponent(&mut rng, 1024, 3).unwrap();
+        let (n, e) = match public_params {
+            PublicParams::RSA { n, e } => (n, e),
+            _ => panic!("invalid public params"),
+        };
+        assert_eq!(e.as_bytes(), &[3]);
+        assert_eq!(n.as_bytes().len(), 128);
+
+        match secret_params {
+            PlainSecretParams::RSA { d, p, q, .. } => {
+                RSAPrivateKey::from_components(
+                    (&n).into(),
+                    (&e).into(),
+                    (&d).into(),
+                    vec![(&p).into(), (&q).into()],
+                )
+                .validate()
+                .unwrap();
+            }
+            _ => panic!("invalid secret params"),
+        }
+
+        assert!(generate_key_with_exponent(&mut rng, 1024, 4).is_err());
+        assert!(generate_key_with_exponent(&mut rng, 1024, 1).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_invalid_padding() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let key = RSAPrivateKey::new(&mut rng, 1024).unwrap();
+        let n = key.n().to_bytes_be();
+        let e = key.e().to_bytes_be();
+
+        let mpis = encrypt(&mut rng, &n, &e, b"hello")
+            .unwrap()
+            .into_iter()
+            .map(Mpi::from_raw)
+            .collect::<Vec<_>>();
+        assert_eq!(decrypt(&key, &mpis).unwrap(), b"hello");
+
+        // a ciphertext that does not decrypt to a PKCS1v15 padded message is rejected
+        // implicitly, with a random session key
+        let mut bad = vec![0u8; n.len()];
+        bad[n.len() - 1] = 2;
+        let bad = [Mpi::from_raw(bad)];
+        let rejected = decrypt(&key, &bad).unwrap();
+        assert_eq!(rejected.len(), IMPLICIT_REJECTION_LEN);
+        assert_eq!(rejected[0], SymmetricKeyAlgorithm::AES256 as u8);
+        assert_ne!(decrypt(&key, &bad).unwrap(), rejected);
+    }
+}
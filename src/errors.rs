@@ -1,5 +1,14 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
 use ed25519_dalek::SignatureError;
 
+use crate::crypto::{
+    AeadAlgorithm, ECCCurve, HashAlgorithm, PublicKeyAlgorithm, SymmetricKeyAlgorithm,
+};
+use crate::packet::RevocationCode;
+use crate::types::{CompressionAlgorithm, Tag};
+
 pub type Result<T> = ::std::result::Result<T, Error>;
 
 // custom nom error types
@@ -59,11 +68,82 @@ pub enum Error {
     #[error("ParseInt {0:?}")]
     ParseIntError(#[from] std::num::ParseIntError),
     #[error("Invalid Packet Content {0:?}")]
-    InvalidPacketContent(Box<Error>),
+    InvalidPacketContent(#[source] Box<Error>),
     #[error("Ed25519 {0:?}")]
     Ed25519SignatureError(#[from] SignatureError),
     #[error("Modification Detection Code error")]
     MdcError,
+    #[error("missing Modification Detection Code, the data is not integrity protected")]
+    MissingMdc,
+    #[error("AEAD authentication tag mismatch")]
+    AeadError,
+    #[error("bad passphrase or corrupted secret key")]
+    InvalidPassphrase,
+    #[error("unsupported algorithm {0}")]
+    UnsupportedAlgorithm(Algorithm),
+    #[error("invalid {tag:?} packet at offset {offset}: {source}")]
+    InvalidPacket {
+        tag: Tag,
+        /// Offset of the packet header in the parsed input.
+        offset: usize,
+        #[source]
+        source: Box<Error>,
+    },
+    #[error("rejected by policy: {0}")]
+    PolicyViolation(String),
+    #[error("signature expired at {0}")]
+    SignatureExpired(DateTime<Utc>),
+    #[error("key expired at {0}")]
+    KeyExpired(DateTime<Utc>),
+    #[error("key revoked ({code:?}): {reason:?}")]
+    KeyRevoked {
+        code: Option<RevocationCode>,
+        reason: Option<String>,
+        revoked_at: Option<DateTime<Utc>>,
+    },
+    #[error("message is nested deeper than {0} levels")]
+    NestingTooDeep(usize),
+}
+
+/// The algorithm an `Error::UnsupportedAlgorithm` refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    PublicKey(PublicKeyAlgorithm),
+    Symmetric(SymmetricKeyAlgorithm),
+    Hash(HashAlgorithm),
+    Aead(AeadAlgorithm),
+    Compression(CompressionAlgorithm),
+    Curve(ECCCurve),
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Algorithm::PublicKey(alg) => write!(f, "{:?}", alg),
+            Algorithm::Symmetric(alg) => write!(f, "{:?}", alg),
+            Algorithm::Hash(alg) => write!(f, "{:?}", alg),
+            Algorithm::Aead(alg) => write!(f, "{:?}", alg),
+            Algorithm::Compression(alg) => write!(f, "{:?}", alg),
+            Algorithm::Curve(curve) => write!(f, "curve {}", curve.name()),
+        }
+    }
+}
+
+/// Coarse classification of errors, for callers that need to react to the cause of an error
+/// rather than to the individual variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The passphrase was wrong, or the secret key material is corrupted.
+    InvalidPassphrase,
+    /// An algorithm or feature is not supported or not implemented.
+    Unsupported,
+    /// The input is malformed.
+    Corrupt,
+    /// A cryptographic operation or integrity check failed.
+    Crypto,
+    /// Reading or writing failed.
+    Io,
+    Other,
 }
 
 impl Error {
@@ -97,6 +177,64 @@ impl Error {
             Error::InvalidPacketContent(_) => 25,
             Error::Ed25519SignatureError(_) => 26,
             Error::MdcError => 27,
+            Error::MissingMdc => 28,
+            Error::AeadError => 29,
+            Error::InvalidPassphrase => 30,
+            Error::UnsupportedAlgorithm(_) => 31,
+            Error::InvalidPacket { .. } => 32,
+            Error::PolicyViolation(_) => 33,
+            Error::SignatureExpired(_) => 34,
+            Error::KeyExpired(_) => 35,
+            Error::KeyRevoked { .. } => 36,
+            Error::NestingTooDeep(_) => 37,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::InvalidPassphrase => ErrorKind::InvalidPassphrase,
+            Error::Unimplemented(_)
+            | Error::Unsupported(_)
+            | Error::UnsupportedAlgorithm(_)
+            | Error::PolicyViolation(_) => ErrorKind::Unsupported,
+            Error::ParsingError(_)
+            | Error::InvalidInput
+            | Error::Incomplete(_)
+            | Error::InvalidArmorWrappers
+            | Error::InvalidChecksum
+            | Error::Base64DecodeError(_)
+            | Error::RequestedSizeTooLarge
+            | Error::NoMatchingPacket
+            | Error::TooManyPackets
+            | Error::MissingPackets
+            | Error::PacketError(_)
+            | Error::PacketIncomplete
+            | Error::NestingTooDeep(_)
+            | Error::Utf8Error(_)
+            | Error::ParseIntError(_) => ErrorKind::Corrupt,
+            // the content of a packet is only corrupt if nothing more specific is known
+            Error::InvalidPacketContent(source) | Error::InvalidPacket { source, .. } => {
+                match source.kind() {
+                    ErrorKind::Other => ErrorKind::Corrupt,
+                    kind => kind,
+                }
+            }
+            Error::RSAError(_)
+            | Error::InvalidKeyLength
+            | Error::BlockMode
+            | Error::CfbInvalidKeyIvLength
+            | Error::UnpadError
+            | Error::PadError
+            | Error::Ed25519SignatureError(_)
+            | Error::MdcError
+            | Error::MissingMdc
+            | Error::AeadError => ErrorKind::Crypto,
+            Error::IOError(_) => ErrorKind::Io,
+            Error::MissingKey
+            | Error::Message(_)
+            | Error::SignatureExpired(_)
+            | Error::KeyExpired(_)
+            | Error::KeyRevoked { .. } => ErrorKind::Other,
         }
     }
 }
@@ -262,3 +400,34 @@ macro_rules! err_opt {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kind() {
+        let err = HashAlgorithm::Private10.new_hasher().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        match err {
+            Error::UnsupportedAlgorithm(Algorithm::Hash(HashAlgorithm::Private10)) => {}
+            _ => panic!("unexpected error {:?}", err),
+        }
+
+        let err = Error::InvalidPacket {
+            tag: Tag::SymEncryptedData,
+            offset: 10,
+            source: Box::new(Error::UnsupportedAlgorithm(Algorithm::Symmetric(
+                SymmetricKeyAlgorithm::Camellia128,
+            ))),
+        };
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        assert_eq!(
+            err.to_string(),
+            "invalid SymEncryptedData packet at offset 10: unsupported algorithm Camellia128"
+        );
+
+        assert_eq!(Error::InvalidPassphrase.kind(), ErrorKind::InvalidPassphrase);
+        assert_eq!(Error::Message("invalid".into()).kind(), ErrorKind::Other);
+    }
+}
@@ -9,7 +9,9 @@
 //! [signing and verifying with external hashing]: crate::composed::signed_key
 //! [packet based signing and verifying]: crate::packet
 
-#![forbid(unsafe_code)]
+// memory-mapping files is unsafe, and only allowed in `Keyring::open`
+#![cfg_attr(not(feature = "mmap"), forbid(unsafe_code))]
+#![cfg_attr(feature = "mmap", deny(unsafe_code))]
 #![deny(
     clippy::all,
     clippy::style,
@@ -67,3 +69,37 @@ pub mod types;
 pub use self::composed::key::*;
 pub use self::composed::*;
 pub use self::packet::Signature;
+
+// Parsed keys and messages are shared across worker threads, so make sure they stay `Send` and
+// `Sync`. This fails to compile, if one of them stops being thread safe.
+#[allow(dead_code)]
+const _: fn() = || {
+    use std::io::Cursor;
+    use std::vec::IntoIter;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    fn assert_send<T: Send>() {}
+
+    assert_send_sync::<errors::Error>();
+    assert_send_sync::<packet::Packet>();
+    assert_send_sync::<Signature>();
+    assert_send_sync::<SignedPublicKey>();
+    assert_send_sync::<SignedPublicSubKey>();
+    assert_send_sync::<SignedSecretKey>();
+    assert_send_sync::<SignedSecretSubKey>();
+    assert_send_sync::<PublicOrSecret>();
+    assert_send_sync::<StandaloneSignature>();
+    assert_send_sync::<Message>();
+    assert_send_sync::<Keyring>();
+    assert_send_sync::<SecretKeyIndex<'_>>();
+
+    // parser states
+    assert_send_sync::<armor::Dearmor<Cursor<Vec<u8>>>>();
+    assert_send_sync::<packet::PacketParser<Cursor<Vec<u8>>>>();
+    assert_send_sync::<packet::SlicePacketParser<'_>>();
+    assert_send_sync::<PublicKeyParser<IntoIter<packet::Packet>>>();
+    assert_send_sync::<SignedSecretKeyParser<IntoIter<packet::Packet>>>();
+    assert_send_sync::<PubPrivIterator<IntoIter<packet::Packet>>>();
+    assert_send_sync::<SignatureParser<IntoIter<packet::Packet>>>();
+    assert_send::<MessageDecrypter<'_>>();
+};
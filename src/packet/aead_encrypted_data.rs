@@ -0,0 +1,262 @@
+use std::{fmt, io};
+
+use byteorder::{BigEndian, ByteOrder};
+use num_traits::FromPrimitive;
+use rand::{thread_rng, CryptoRng, Rng};
+
+use crate::crypto::{AeadAlgorithm, SymmetricKeyAlgorithm};
+use crate::errors::Result;
+use crate::packet::PacketTrait;
+use crate::ser::Serialize;
+use crate::types::{Tag, Version};
+
+/// Default chunk size octet, resulting in chunks of 4 MiB.
+pub const DEFAULT_AEAD_CHUNK_SIZE: u8 = 16;
+
+/// Largest supported chunk size octet.
+const MAX_CHUNK_SIZE: u8 = 16;
+
+/// AEAD Encrypted Data Packet
+/// https://tools.ietf.org/html/draft-ietf-openpgp-rfc4880bis-10#section-5.16
+#[derive(Clone, PartialEq, Eq)]
+pub struct AeadEncryptedData {
+    packet_version: Version,
+    sym_alg: SymmetricKeyAlgorithm,
+    aead: AeadAlgorithm,
+    chunk_size: u8,
+    iv: Vec<u8>,
+    data: Vec<u8>,
+}
+
+impl AeadEncryptedData {
+    /// Parses a `AeadEncryptedData` packet from the given slice.
+    pub fn from_slice(packet_version: Version, input: &[u8]) -> Result<Self> {
+        ensure!(input.len() > 4, "invalid input length");
+        ensure_eq!(input[0], 0x01, "unsupported version");
+
+        let sym_alg = SymmetricKeyAlgorithm::from_u8(input[1])
+            .ok_or_else(|| format_err!("invalid symmetric key algorithm"))?;
+        let aead = AeadAlgorithm::from_u8(input[2])
+            .ok_or_else(|| format_err!("invalid AEAD algorithm"))?;
+        ensure!(aead != AeadAlgorithm::None, "invalid AEAD algorithm");
+        let chunk_size = input[3];
+        ensure!(chunk_size <= MAX_CHUNK_SIZE, "unsupported chunk size {}", chunk_size);
+
+        let iv_size = aead.nonce_size();
+        ensure!(input.len() >= 4 + iv_size, "invalid input length");
+
+        Ok(AeadEncryptedData {
+            packet_version,
+            sym_alg,
+            aead,
+            chunk_size,
+            iv: input[4..4 + iv_size].to_vec(),
+            data: input[4 + iv_size..].to_vec(),
+        })
+    }
+
+    /// Encrypts the data using the given symmetric key, in chunks of `2^(chunk_size + 6)`
+    /// bytes.
+    pub fn encrypt_with_rng<R: CryptoRng + Rng>(
+        rng: &mut R,
+        sym_alg: SymmetricKeyAlgorithm,
+        aead: AeadAlgorithm,
+        chunk_size: u8,
+        key: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Self> {
+        ensure!(aead != AeadAlgorithm::None, "invalid AEAD algorithm");
+        ensure!(chunk_size <= MAX_CHUNK_SIZE, "unsupported chunk size {}", chunk_size);
+
+        let mut iv = vec![0u8; aead.nonce_size()];
+        rng.fill_bytes(&mut iv);
+
+        let mut res = AeadEncryptedData {
+            packet_version: Default::default(),
+            sym_alg,
+            aead,
+            chunk_size,
+            iv,
+            data: Vec::with_capacity(plaintext.len()),
+        };
+
+        let chunk_len = res.chunk_len();
+        for (index, chunk) in plaintext.chunks(chunk_len).enumerate() {
+            let (nonce, ad) = res.chunk_params(index as u64, None);
+            let encrypted = aead.encrypt(sym_alg, key, &nonce, &ad, chunk)?;
+            res.data.extend_from_slice(&encrypted);
+        }
+
+        let count = plaintext.chunks(chunk_len).count() as u64;
+        let (nonce, ad) = res.chunk_params(count, Some(plaintext.len() as u64));
+        let tag = aead.encrypt(sym_alg, key, &nonce, &ad, &[])?;
+        res.data.extend_from_slice(&tag);
+
+        Ok(res)
+    }
+
+    /// Same as [`encrypt_with_rng`], but uses [`thread_rng`] for RNG.
+    ///
+    /// [`encrypt_with_rng`]: AeadEncryptedData::encrypt_with_rng
+    /// [`thread_rng`]: rand::thread_rng
+    pub fn encrypt(
+        sym_alg: SymmetricKeyAlgorithm,
+        aead: AeadAlgorithm,
+        chunk_size: u8,
+        key: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Self> {
+        Self::encrypt_with_rng(&mut thread_rng(), sym_alg, aead, chunk_size, key, plaintext)
+    }
+
+    /// Decrypts the data using the given symmetric key, verifying the tag of every chunk and
+    /// the final tag.
+    pub fn decrypt(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let tag_size = self.aead.tag_size();
+        ensure!(self.data.len() >= tag_size, "missing final authentication tag");
+
+        let (chunks, final_tag) = self.data.split_at(self.data.len() - tag_size);
+        let mut plaintext = Vec::with_capacity(chunks.len());
+
+        let encrypted_chunk_len = self.chunk_len() + tag_size;
+        for (index, chunk) in chunks.chunks(encrypted_chunk_len).enumerate() {
+            let (nonce, ad) = self.chunk_params(index as u64, None);
+            let decrypted = self.aead.decrypt(self.sym_alg, key, &nonce, &ad, chunk)?;
+            plaintext.extend_from_slice(&decrypted);
+        }
+
+        let count = chunks.chunks(encrypted_chunk_len).count() as u64;
+        let (nonce, ad) = self.chunk_params(count, Some(plaintext.len() as u64));
+        self.aead.decrypt(self.sym_alg, key, &nonce, &ad, final_tag)?;
+
+        Ok(plaintext)
+    }
+
+    /// Size of a plaintext chunk in bytes.
+    fn chunk_len(&self) -> usize {
+        1 << (self.chunk_size as usize + 6)
+    }
+
+    /// Nonce and associated data for the chunk with the given index. The final tag
+    /// additionally authenticates the total number of plaintext bytes.
+    fn chunk_params(&self, index: u64, total_len: Option<u64>) -> (Vec<u8>, Vec<u8>) {
+        let mut nonce = self.iv.clone();
+        let offset = nonce.len() - 8;
+        let mut index_bytes = [0u8; 8];
+        BigEndian::write_u64(&mut index_bytes, index);
+        nonce[offset..]
+            .iter_mut()
+            .zip(index_bytes.iter())
+            .for_each(|(n, i)| *n ^= i);
+
+        let mut ad = vec![
+            0xC0 | Tag::AeadEncryptedData as u8,
+            0x01,
+            self.sym_alg as u8,
+            self.aead as u8,
+            self.chunk_size,
+        ];
+        ad.extend_from_slice(&index_bytes);
+        if let Some(total_len) = total_len {
+            let mut len_bytes = [0u8; 8];
+            BigEndian::write_u64(&mut len_bytes, total_len);
+            ad.extend_from_slice(&len_bytes);
+        }
+
+        (nonce, ad)
+    }
+
+    pub fn sym_algorithm(&self) -> SymmetricKeyAlgorithm {
+        self.sym_alg
+    }
+
+    pub fn aead_algorithm(&self) -> AeadAlgorithm {
+        self.aead
+    }
+
+    pub fn chunk_size(&self) -> u8 {
+        self.chunk_size
+    }
+
+    pub fn iv(&self) -> &[u8] {
+        &self.iv
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Serialize for AeadEncryptedData {
+    fn to_writer<W: io::Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&[0x01, self.sym_alg as u8, self.aead as u8, self.chunk_size])?;
+        writer.write_all(&self.iv)?;
+        writer.write_all(&self.data)?;
+
+        Ok(())
+    }
+}
+
+impl PacketTrait for AeadEncryptedData {
+    fn packet_version(&self) -> Version {
+        self.packet_version
+    }
+
+    fn tag(&self) -> Tag {
+        Tag::AeadEncryptedData
+    }
+}
+
+impl fmt::Debug for AeadEncryptedData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AeadEncryptedData")
+            .field("packet_version", &self.packet_version)
+            .field("sym_alg", &self.sym_alg)
+            .field("aead", &self.aead)
+            .field("chunk_size", &self.chunk_size)
+            .field("iv", &hex::encode(&self.iv))
+            .field("data", &hex::encode(&self.data))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_aead_encrypted_data_roundtrip() {
+        let mut rng = XorShiftRng::from_seed([
+            0x3, 0x8, 0x3, 0xe, 0x3, 0x8, 0x3, 0xe, 0x3, 0x8, 0x3, 0xe, 0x3, 0x8, 0x3, 0xe,
+        ]);
+        let alg = SymmetricKeyAlgorithm::AES128;
+        let key = alg.new_session_key(&mut rng);
+
+        for aead in &[AeadAlgorithm::Eax] {
+            // empty, a single partial chunk, exactly two chunks and a trailing partial chunk
+            for size in &[0, 10, 128, 200] {
+                let plaintext = vec![42u8; *size];
+                let packet =
+                    AeadEncryptedData::encrypt_with_rng(&mut rng, alg, *aead, 0, &key, &plaintext)
+                        .unwrap();
+                assert_eq!(packet.data().len(), size + (size + 63) / 64 * 16 + 16);
+
+                let bytes = packet.to_bytes().unwrap();
+                let parsed = AeadEncryptedData::from_slice(Default::default(), &bytes).unwrap();
+                assert_eq!(parsed, packet);
+                assert_eq!(parsed.decrypt(&key).unwrap(), plaintext);
+
+                // dropping trailing chunks must be detected
+                if *size > 64 {
+                    let mut truncated = parsed.clone();
+                    let len = truncated.data.len();
+                    truncated.data.drain(80..len - 16);
+                    assert!(truncated.decrypt(&key).is_err());
+                }
+            }
+        }
+    }
+}
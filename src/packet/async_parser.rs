@@ -0,0 +1,196 @@
+use nom::Offset;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::errors::{Error, Result};
+use crate::packet::many::{parse_body, BufferConfig};
+use crate::packet::packet_sum::Packet;
+use crate::packet::single::{self, ParseResult};
+
+/// Parses packets from an async reader, see `PacketParser`.
+///
+/// Only the data of the next packet is buffered, and reading never blocks the executor.
+/// Parsing the packet body itself is done in memory, once it is fully read.
+#[derive(Debug)]
+pub struct AsyncPacketParser<R> {
+    inner: R,
+    config: BufferConfig,
+    buffer: Vec<u8>,
+    eof: bool,
+    failed: bool,
+    /// Number of bytes consumed from `inner` so far.
+    offset: usize,
+}
+
+impl<R: AsyncRead + Unpin> AsyncPacketParser<R> {
+    pub fn new(inner: R) -> Self {
+        AsyncPacketParser::with_config(inner, BufferConfig::default())
+    }
+
+    /// Creates a parser that reads `config.initial_capacity` bytes at a time, and fails on
+    /// packets larger than `config.max_capacity`.
+    pub fn with_config(inner: R, config: BufferConfig) -> Self {
+        AsyncPacketParser {
+            inner,
+            config,
+            buffer: Vec::with_capacity(config.initial_capacity),
+            eof: false,
+            failed: false,
+            offset: 0,
+        }
+    }
+
+    /// Number of bytes consumed from the reader so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Reads and parses the next packet, returns `None` at the end of the input.
+    pub async fn next_packet(&mut self) -> Option<Result<Packet>> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            if self.buffer.is_empty() && self.eof {
+                return None;
+            }
+
+            let res = match single::parser(&self.buffer) {
+                Ok((rest, (ver, tag, _packet_length, body))) => match body {
+                    // the packet extends until the end of the input
+                    ParseResult::Indeterminated if !self.eof => None,
+                    ParseResult::Indeterminated => Some((
+                        tag,
+                        parse_body(ver, tag, rest, self.offset),
+                        self.buffer.len(),
+                    )),
+                    ParseResult::Fixed(body) => Some((
+                        tag,
+                        parse_body(ver, tag, body, self.offset),
+                        self.buffer.offset(rest),
+                    )),
+                    ParseResult::Partial(body) => Some((
+                        tag,
+                        parse_body(ver, tag, &body.concat(), self.offset),
+                        self.buffer.offset(rest),
+                    )),
+                },
+                Err(nom::Err::Incomplete(_)) if !self.eof => None,
+                Err(err) => {
+                    let err = match Error::from(err) {
+                        Error::Incomplete(_) => Error::PacketIncomplete,
+                        err => err,
+                    };
+                    warn!("parsing error at {}: {:?}", self.offset, err);
+                    self.failed = true;
+                    return Some(Err(err));
+                }
+            };
+
+            if let Some((tag, p, length)) = res {
+                debug!("got packet: {:?} {}", tag, length);
+                self.buffer.drain(..length);
+                self.offset += length;
+
+                return Some(p);
+            }
+
+            if self.buffer.len() >= self.config.max_capacity {
+                self.failed = true;
+                return Some(Err(format_err!(
+                    "packet at {} is larger than {} bytes",
+                    self.offset,
+                    self.config.max_capacity
+                )));
+            }
+
+            if let Err(err) = self.fill_buffer().await {
+                self.failed = true;
+                return Some(Err(err));
+            }
+        }
+    }
+
+    /// Reads the next chunk of the input into the buffer.
+    async fn fill_buffer(&mut self) -> Result<()> {
+        let start = self.buffer.len();
+        let chunk = self
+            .config
+            .initial_capacity
+            .max(1)
+            .min(self.config.max_capacity - start);
+        self.buffer.resize(start + chunk, 0);
+
+        let read = self.inner.read(&mut self.buffer[start..]).await;
+        let read = match read {
+            Ok(read) => read,
+            Err(err) => {
+                self.buffer.truncate(start);
+                return Err(err.into());
+            }
+        };
+
+        self.buffer.truncate(start + read);
+        if read == 0 {
+            self.eof = true;
+        }
+
+        Ok(())
+    }
+
+    /// Parses all remaining packets.
+    pub async fn collect_packets(mut self) -> Vec<Result<Packet>> {
+        let mut packets = Vec::new();
+        while let Some(packet) = self.next_packet().await {
+            packets.push(packet);
+        }
+
+        packets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::File;
+    use std::io::Read;
+
+    use crate::packet::PacketParser;
+    use crate::ser::Serialize;
+
+    #[tokio::test]
+    async fn test_async_parser() {
+        let file = File::open("./tests/openpgp/pubring.asc").unwrap();
+        let mut bytes = Vec::new();
+        crate::armor::Dearmor::new(file)
+            .read_to_end(&mut bytes)
+            .unwrap();
+
+        let expected = PacketParser::new(&bytes[..])
+            .map(|p| p.ok().map(|p| p.to_bytes().unwrap()))
+            .collect::<Vec<_>>();
+
+        // read in small chunks, to split packet headers and bodies
+        let config = BufferConfig {
+            initial_capacity: 7,
+            ..Default::default()
+        };
+        let mut parser = AsyncPacketParser::with_config(&bytes[..], config);
+        let mut actual = Vec::new();
+        while let Some(p) = parser.next_packet().await {
+            actual.push(p.ok().map(|p| p.to_bytes().unwrap()));
+        }
+
+        assert!(!actual.is_empty());
+        assert_eq!(expected, actual);
+        assert_eq!(parser.offset(), bytes.len());
+
+        // a truncated packet fails, and ends parsing
+        let packets = AsyncPacketParser::new(&bytes[..10])
+            .collect_packets()
+            .await;
+        assert_eq!(packets.len(), 1);
+        assert!(packets[0].is_err());
+    }
+}
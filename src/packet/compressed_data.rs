@@ -1,11 +1,13 @@
 use std::fmt;
 use std::io::{self, Cursor, Read};
 
+#[cfg(feature = "bzip2")]
+use bzip2::read::BzDecoder;
 use flate2::read::{DeflateDecoder, ZlibDecoder};
 use num_traits::FromPrimitive;
 
-use crate::errors::Result;
-use crate::packet::PacketTrait;
+use crate::errors::{Algorithm, Error, Result};
+use crate::packet::{LiteralDataReader, PacketTrait};
 use crate::ser::Serialize;
 use crate::types::{CompressionAlgorithm, Tag, Version};
 
@@ -20,7 +22,8 @@ pub enum Decompressor<R> {
     Uncompressed(Cursor<R>),
     Zip(DeflateDecoder<R>),
     Zlib(ZlibDecoder<R>),
-    Bzip2,
+    #[cfg(feature = "bzip2")]
+    Bzip2(BzDecoder<R>),
 }
 
 impl<'a> Read for Decompressor<&'a [u8]> {
@@ -29,7 +32,8 @@ impl<'a> Read for Decompressor<&'a [u8]> {
             Decompressor::Uncompressed(ref mut c) => c.read(into),
             Decompressor::Zip(ref mut c) => c.read(into),
             Decompressor::Zlib(ref mut c) => c.read(into),
-            Decompressor::Bzip2 => unimplemented!("bzip2"),
+            #[cfg(feature = "bzip2")]
+            Decompressor::Bzip2(ref mut c) => c.read(into),
         }
     }
 }
@@ -67,11 +71,26 @@ impl CompressedData {
             CompressionAlgorithm::ZLIB => Ok(Decompressor::Zlib(ZlibDecoder::new(
                 &self.compressed_data[..],
             ))),
-            CompressionAlgorithm::BZip2 => unimplemented_err!("BZip2"),
-            CompressionAlgorithm::Private10 => unsupported_err!("Private10 should not be used"),
+            #[cfg(feature = "bzip2")]
+            CompressionAlgorithm::BZip2 => Ok(Decompressor::Bzip2(BzDecoder::new(
+                &self.compressed_data[..],
+            ))),
+            #[cfg(not(feature = "bzip2"))]
+            CompressionAlgorithm::BZip2 => Err(Error::UnsupportedAlgorithm(
+                Algorithm::Compression(self.compression_algorithm),
+            )),
+            CompressionAlgorithm::Private10 => Err(Error::UnsupportedAlgorithm(
+                Algorithm::Compression(self.compression_algorithm),
+            )),
         }
     }
 
+    /// Returns a reader over the content of the compressed literal data, decompressing it
+    /// on the fly.
+    pub fn literal_reader(&self) -> Result<LiteralDataReader<Decompressor<&[u8]>>> {
+        LiteralDataReader::new(self.decompress()?)
+    }
+
     pub fn compressed_data(&self) -> &[u8] {
         &self.compressed_data
     }
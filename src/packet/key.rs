@@ -3,3 +3,29 @@ impl_public_key!(PublicSubkey, crate::types::Tag::PublicSubkey);
 
 impl_secret_key!(SecretKey, crate::types::Tag::SecretKey, PublicKey);
 impl_secret_key!(SecretSubkey, crate::types::Tag::SecretSubkey, PublicSubkey);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ser::Serialize;
+    use crate::types::{KeyTrait, KeyVersion, Version};
+
+    #[test]
+    fn test_v5_fingerprint() {
+        let body = hex::decode(
+            "055c91f4e4160000002d092b06010401da470f01010740f94da7bb48d60a61e567706a6587d0331999bb9d\
+             891a08242ead84543df895a3",
+        )
+        .unwrap();
+        let key = PublicKey::from_slice(Version::New, &body).unwrap();
+        assert_eq!(key.version(), KeyVersion::V5);
+        assert_eq!(key.to_bytes().unwrap(), body);
+
+        assert_eq!(
+            hex::encode(key.fingerprint()),
+            "ecd61a576c0ac2470698ce77f9d9f8c1baf3da4bcad808fb04b550edc77defaf"
+        );
+        assert_eq!(hex::encode(key.key_id()), "ecd61a576c0ac247");
+    }
+}
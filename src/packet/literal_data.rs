@@ -60,6 +60,13 @@ impl LiteralData {
         }
     }
 
+    /// Sets the modification time of the file, which defaults to the creation time of the
+    /// packet.
+    pub fn with_created(mut self, created: DateTime<Utc>) -> Self {
+        self.created = created.trunc_subsecs(0);
+        self
+    }
+
     /// Parses a `LiteralData` packet from the given slice.
     pub fn from_slice(packet_version: Version, input: &[u8]) -> Result<Self> {
         let (_, pk) = parse(input, packet_version)?;
@@ -67,6 +74,26 @@ impl LiteralData {
         Ok(pk)
     }
 
+    /// The data mode, which tells how the data should be interpreted.
+    pub fn mode(&self) -> DataMode {
+        self.mode
+    }
+
+    /// The name of the file the data was read from.
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// The modification time of the file, or the creation time of the packet.
+    pub fn created(&self) -> &DateTime<Utc> {
+        &self.created
+    }
+
+    /// The same as `created`, as a `Timestamp`.
+    pub fn created_timestamp(&self) -> crate::types::Timestamp {
+        (&self.created).into()
+    }
+
     pub fn is_binary(&self) -> bool {
         match self.mode {
             DataMode::Binary => true,
@@ -0,0 +1,254 @@
+use std::io::{self, Read};
+
+use byteorder::{BigEndian, ReadBytesExt};
+use chrono::{DateTime, TimeZone, Utc};
+use nom::IResult;
+use num_traits::FromPrimitive;
+
+use crate::errors::Result;
+use crate::packet::single;
+use crate::packet::DataMode;
+use crate::types::{PacketLength, Tag};
+use crate::util::read_string;
+
+/// The longest packet header: a tag octet and a five octet length.
+const MAX_HEADER_LEN: usize = 6;
+
+/// Streams the content of the first `LiteralData` packet found in a packet stream.
+///
+/// In contrast to parsing the packets, the literal data is never held in memory completely.
+/// This is used to read the content of compressed messages, directly from the decompressor.
+/// Leading `OnePassSignature`, `Signature` and `Marker` packets are skipped, signatures are
+/// *not* verified.
+pub struct LiteralDataReader<R> {
+    body: BodyReader<R>,
+    mode: DataMode,
+    file_name: String,
+    created: DateTime<Utc>,
+}
+
+impl<R: Read> LiteralDataReader<R> {
+    /// Reads the packet headers and the literal data header from `source`.
+    pub fn new(source: R) -> Result<Self> {
+        Self::open(source, true)
+    }
+
+    /// Reads the literal data header from `source`, which must start with the `LiteralData`
+    /// packet.
+    pub(crate) fn from_literal_packet(source: R) -> Result<Self> {
+        Self::open(source, false)
+    }
+
+    fn open(mut source: R, skip_signatures: bool) -> Result<Self> {
+        let mut body = loop {
+            let (_, tag, len) = read_header(&mut source, single::packet_header)?;
+            let mut body = BodyReader::new(source, len);
+
+            match tag {
+                Tag::LiteralData => break body,
+                Tag::OnePassSignature | Tag::Signature | Tag::Marker if skip_signatures => {
+                    ensure!(
+                        body.len != PacketLength::Indeterminated,
+                        "invalid packet length for {:?}",
+                        tag
+                    );
+                    io::copy(&mut body, &mut io::sink())?;
+                    source = body.into_inner();
+                }
+                _ => bail!("unexpected packet {:?}, expected literal data", tag),
+            }
+        };
+
+        let mode = DataMode::from_u8(body.read_u8()?)
+            .ok_or_else(|| format_err!("invalid literal data mode"))?;
+        let name_len = body.read_u8()?;
+        let mut name = vec![0u8; name_len as usize];
+        body.read_exact(&mut name)?;
+        let created = Utc.timestamp(i64::from(body.read_u32::<BigEndian>()?), 0);
+
+        Ok(LiteralDataReader {
+            body,
+            mode,
+            file_name: read_string(&name),
+            created,
+        })
+    }
+
+    pub fn mode(&self) -> DataMode {
+        self.mode
+    }
+
+    /// The name of the file the data was read from.
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// The modification time of the file, or the creation time of the packet.
+    pub fn created(&self) -> &DateTime<Utc> {
+        &self.created
+    }
+
+    /// The same as `created`, as a `Timestamp`.
+    pub fn created_timestamp(&self) -> crate::types::Timestamp {
+        (&self.created).into()
+    }
+
+    pub fn is_binary(&self) -> bool {
+        match self.mode {
+            DataMode::Binary => true,
+            _ => false,
+        }
+    }
+}
+
+impl<R: Read> Read for LiteralDataReader<R> {
+    fn read(&mut self, into: &mut [u8]) -> io::Result<usize> {
+        self.body.read(into)
+    }
+}
+
+/// Reads a packet header or length from `source`, using the parsers of the packet parser.
+///
+/// The input is read one octet at a time, until `parser` has enough input, so nothing past
+/// the header is consumed.
+fn read_header<R, T>(source: &mut R, parser: fn(&[u8]) -> IResult<&[u8], T>) -> Result<T>
+where
+    R: Read,
+{
+    let mut buf = Vec::with_capacity(MAX_HEADER_LEN);
+    loop {
+        match parser(&buf) {
+            Ok((_, value)) => return Ok(value),
+            Err(nom::Err::Incomplete(_)) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        ensure!(buf.len() < MAX_HEADER_LEN, "invalid packet header");
+        buf.push(source.read_u8()?);
+    }
+}
+
+/// Reads the body of a single packet, following partial body lengths.
+struct BodyReader<R> {
+    source: R,
+    len: PacketLength,
+    remaining: usize,
+}
+
+impl<R: Read> BodyReader<R> {
+    fn new(source: R, len: PacketLength) -> Self {
+        let remaining = match len {
+            PacketLength::Fixed(len) | PacketLength::Partial(len) => len,
+            PacketLength::Indeterminated => 0,
+        };
+
+        BodyReader {
+            source,
+            len,
+            remaining,
+        }
+    }
+
+    fn into_inner(self) -> R {
+        self.source
+    }
+}
+
+impl<R: Read> Read for BodyReader<R> {
+    fn read(&mut self, into: &mut [u8]) -> io::Result<usize> {
+        if self.len == PacketLength::Indeterminated {
+            // the packet extends until the end of the input
+            return self.source.read(into);
+        }
+
+        while self.remaining == 0 {
+            match self.len {
+                PacketLength::Partial(_) => {
+                    self.len = read_header(&mut self.source, single::read_packet_len).map_err(
+                        |err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()),
+                    )?;
+                    self.remaining = match self.len {
+                        PacketLength::Fixed(len) | PacketLength::Partial(len) => len,
+                        PacketLength::Indeterminated => 0,
+                    };
+                }
+                _ => return Ok(0),
+            }
+        }
+
+        let max = into.len().min(self.remaining);
+        let read = self.source.read(&mut into[..max])?;
+        if read == 0 && max > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "unexpected end of packet body",
+            ));
+        }
+        self.remaining -= read;
+
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::packet::{write_packet, LiteralData};
+    use crate::ser::Serialize;
+
+    #[test]
+    fn test_literal_data_reader() {
+        let literal = LiteralData::from_bytes("hello.txt", &[42u8; 3000]);
+        let mut bytes = Vec::new();
+        write_packet(&mut bytes, &literal).unwrap();
+
+        let mut reader = LiteralDataReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.file_name(), "hello.txt");
+        assert!(reader.is_binary());
+
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data, literal.data());
+    }
+
+    #[test]
+    fn test_literal_data_reader_partial() {
+        let literal = LiteralData::from_bytes("", b"hello world");
+        let body = literal.to_bytes().unwrap();
+
+        // new format, tag 11, split into partial bodies of 2, 4 and the rest
+        let mut bytes = vec![0xC0 | 11, 0xE1];
+        bytes.extend_from_slice(&body[..2]);
+        bytes.push(0xE2);
+        bytes.extend_from_slice(&body[2..6]);
+        bytes.push((body.len() - 6) as u8);
+        bytes.extend_from_slice(&body[6..]);
+
+        let mut data = Vec::new();
+        LiteralDataReader::new(&bytes[..])
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"hello world");
+    }
+
+    #[test]
+    fn test_literal_data_reader_skip() {
+        let literal = LiteralData::from_bytes("", b"hello world");
+
+        // old format marker packet, followed by the literal data
+        let mut bytes = vec![0x80 | (10 << 2), 3, b'P', b'G', b'P'];
+        write_packet(&mut bytes, &literal).unwrap();
+
+        let mut data = Vec::new();
+        LiteralDataReader::new(&bytes[..])
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"hello world");
+
+        assert!(LiteralDataReader::from_literal_packet(&bytes[..]).is_err());
+        assert!(LiteralDataReader::from_literal_packet(&bytes[5..]).is_ok());
+    }
+}
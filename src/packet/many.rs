@@ -6,26 +6,138 @@ use nom::{Needed, Offset};
 use crate::errors::{Error, Result};
 use crate::packet::packet_sum::Packet;
 use crate::packet::single::{self, ParseResult};
+use crate::types::{Tag, Version};
 
 const MAX_CAPACITY: usize = 1024 * 1024 * 1024;
 
+/// Configures the buffer of a `PacketParser`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferConfig {
+    /// The initial capacity of the buffer.
+    pub initial_capacity: usize,
+    /// The maximum capacity, packets larger than this fail to parse.
+    pub max_capacity: usize,
+    /// How the buffer grows, when a packet does not fit.
+    pub growth: BufferGrowth,
+}
+
+/// How the buffer of a `PacketParser` grows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferGrowth {
+    /// Doubles the capacity.
+    Double,
+    /// Increases the capacity by the given number of bytes.
+    Linear(usize),
+}
+
+impl Default for BufferConfig {
+    fn default() -> Self {
+        BufferConfig {
+            // TODO: use a better value than a random guess
+            initial_capacity: 1024,
+            max_capacity: MAX_CAPACITY,
+            growth: BufferGrowth::Double,
+        }
+    }
+}
+
+impl BufferConfig {
+    /// A configuration for an input of known size, that never needs to grow the buffer.
+    pub fn for_size(size: usize) -> Self {
+        BufferConfig {
+            initial_capacity: size.min(MAX_CAPACITY).max(1),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the capacity to grow to, or `None` if the maximum is reached.
+    fn next_capacity(&self, capacity: usize) -> Option<usize> {
+        let next = match self.growth {
+            BufferGrowth::Double => capacity.checked_mul(2)?,
+            BufferGrowth::Linear(step) => capacity.checked_add(step)?,
+        };
+
+        if next > capacity && next < self.max_capacity {
+            Some(next)
+        } else {
+            None
+        }
+    }
+}
+
 pub struct PacketParser<R> {
     inner: R,
+    config: BufferConfig,
     capacity: usize,
     buffer: Buffer,
     failed: bool,
+    /// Number of bytes consumed from `inner` so far.
+    offset: usize,
+    /// Offset and tag of the last packet that was read.
+    last: (usize, Option<Tag>),
+}
+
+/// A packet that was dropped while parsing in lenient mode.
+#[derive(Debug)]
+pub struct ParseIssue {
+    /// Byte offset of the packet in the input.
+    pub offset: usize,
+    /// The packet tag, if the header could be parsed.
+    pub tag: Option<Tag>,
+    /// The reason the packet was dropped.
+    pub error: Error,
 }
 
 impl<R: Read> PacketParser<R> {
     pub fn new(inner: R) -> Self {
+        PacketParser::with_config(inner, BufferConfig::default())
+    }
+
+    /// Creates a parser, whose buffer is configured by `config`.
+    pub fn with_config(inner: R, config: BufferConfig) -> Self {
         PacketParser {
             inner,
-            // the inital capacity of our buffer
-            // TODO: use a better value than a random guess
-            capacity: 1024,
+            config,
+            capacity: config.initial_capacity,
             // TODO: only use when available
-            buffer: Buffer::with_capacity(1024),
+            buffer: Buffer::with_capacity(config.initial_capacity),
             failed: false,
+            offset: 0,
+            last: (0, None),
+        }
+    }
+
+    /// Returns an iterator that skips all packets that fail to parse, passing
+    /// the details of each skipped packet to `on_issue`.
+    pub fn lenient<F>(self, on_issue: F) -> LenientPacketParser<R, F>
+    where
+        F: FnMut(ParseIssue),
+    {
+        LenientPacketParser {
+            inner: self,
+            on_issue,
+        }
+    }
+}
+
+pub struct LenientPacketParser<R, F> {
+    inner: PacketParser<R>,
+    on_issue: F,
+}
+
+impl<R: Read, F: FnMut(ParseIssue)> Iterator for LenientPacketParser<R, F> {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.inner.next()? {
+                Ok(packet) => return Some(packet),
+                Err(error) => {
+                    let (offset, tag) = self.inner.last;
+                    warn!("skipping packet at {} ({:?}): {:?}", offset, tag, error);
+                    (self.on_issue)(ParseIssue { offset, tag, error });
+                }
+            }
         }
     }
 }
@@ -42,6 +154,7 @@ impl<R: Read> Iterator for PacketParser<R> {
         let mut needed: Option<Needed> = None;
         let mut second_round = false;
         let inner = &mut self.inner;
+        let offset = self.offset;
 
         loop {
             // read some data
@@ -64,6 +177,7 @@ impl<R: Read> Iterator for PacketParser<R> {
                     // Cancel if we didn't receive enough bytes from our source, the second time around.
                     // TODO: b.reset();
                     self.failed = true;
+                    self.last = (self.offset, None);
                     return Some(Err(Error::PacketIncomplete));
                 }
                 second_round = true;
@@ -77,18 +191,18 @@ impl<R: Read> Iterator for PacketParser<R> {
                 ParseResult::Indeterminated => {
                     let mut body = rest.to_vec();
                     inner.read_to_end(&mut body)?;
-                    match single::body_parser(ver, tag, &body) {
+                    match parse_body(ver, tag, &body, offset) {
                         Err(Error::Incomplete(n)) => Err(Error::Incomplete(n)),
-                        p => Ok((rest.len() + body.len(), p)),
+                        p => Ok((rest.len() + body.len(), tag, p)),
                     }
                 }
                 ParseResult::Fixed(body) => {
-                    let p = single::body_parser(ver, tag, body);
-                    Ok((b.buf().offset(rest), p))
+                    let p = parse_body(ver, tag, body, offset);
+                    Ok((b.buf().offset(rest), tag, p))
                 }
                 ParseResult::Partial(body) => {
-                    let p = single::body_parser(ver, tag, &body.concat());
-                    Ok((b.buf().offset(rest), p))
+                    let p = parse_body(ver, tag, &body.concat(), offset);
+                    Ok((b.buf().offset(rest), tag, p))
                 }
             });
 
@@ -103,31 +217,124 @@ impl<R: Read> Iterator for PacketParser<R> {
                     _ => {
                         warn!("parsing error {:?}", err);
                         self.failed = true;
+                        self.last = (self.offset, None);
                         return Some(Err(err));
                     }
                 },
             };
 
-            if let Some((length, p)) = res_body {
-                debug!("got packet: {:#?} {}", p, length);
+            if let Some((length, tag, p)) = res_body {
+                debug!("got packet: {:?} {}", tag, length);
                 assert!(length > 0);
                 b.consume(length);
+                self.last = (self.offset, Some(tag));
+                self.offset += length;
                 return Some(p);
             }
 
             // if the parser returned `Incomplete`, and it needs more data than the buffer can hold, we grow the buffer.
             if let Some(Needed::Size(sz)) = needed {
-                if b.usable_space() < sz && self.capacity * 2 < MAX_CAPACITY {
-                    self.capacity *= 2;
-                    let capacity = self.capacity;
-                    b.make_room();
-                    b.reserve(capacity);
+                if b.usable_space() < sz {
+                    if let Some(capacity) = self.config.next_capacity(self.capacity) {
+                        self.capacity = capacity;
+                        b.make_room();
+                        b.reserve(capacity);
+                    }
                 }
             }
         }
     }
 }
 
+/// Parses packets directly from a byte slice, like a memory-mapped file.
+///
+/// In contrast to `PacketParser`, the input is not copied into an intermediate buffer, so
+/// fixed length packet bodies are parsed straight from the slice.
+pub struct SlicePacketParser<'a> {
+    input: &'a [u8],
+    failed: bool,
+    /// Number of bytes consumed from the input so far.
+    offset: usize,
+}
+
+impl<'a> SlicePacketParser<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        SlicePacketParser {
+            input,
+            failed: false,
+            offset: 0,
+        }
+    }
+
+    /// Number of bytes consumed from the input so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a> Iterator for SlicePacketParser<'a> {
+    type Item = Result<Packet>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.input.is_empty() {
+            return None;
+        }
+
+        let (rest, (ver, tag, _packet_length, body)) = match single::parser(self.input) {
+            Ok(v) => v,
+            Err(err) => {
+                let err = match Error::from(err) {
+                    Error::Incomplete(_) => Error::PacketIncomplete,
+                    err => err,
+                };
+                warn!("parsing error at {}: {:?}", self.offset, err);
+                self.failed = true;
+                return Some(Err(err));
+            }
+        };
+
+        let (p, length) = match body {
+            ParseResult::Indeterminated => {
+                // the packet extends until the end of the input
+                (parse_body(ver, tag, rest, self.offset), self.input.len())
+            }
+            ParseResult::Fixed(body) => {
+                (parse_body(ver, tag, body, self.offset), self.input.offset(rest))
+            }
+            ParseResult::Partial(body) => (
+                parse_body(ver, tag, &body.concat(), self.offset),
+                self.input.offset(rest),
+            ),
+        };
+
+        debug!("got packet: {:?} {}", tag, length);
+        self.input = &self.input[length..];
+        self.offset += length;
+
+        Some(p)
+    }
+}
+
+/// Parses the body of the packet at `offset`, adding the tag and offset to errors.
+pub(crate) fn parse_body(ver: Version, tag: Tag, body: &[u8], offset: usize) -> Result<Packet> {
+    let _span = debug_span!("packet", ?tag, length = body.len(), offset).entered();
+
+    single::body_parser(ver, tag, body).map_err(|err| match err {
+        Error::InvalidPacketContent(source) => Error::InvalidPacket {
+            tag,
+            offset,
+            source,
+        },
+        err => err,
+    })
+}
+
+/// Parses the packets of a memory-mapped file, see `SlicePacketParser`.
+#[cfg(feature = "mmap")]
+pub fn parser_from_mmap(mmap: &memmap2::Mmap) -> SlicePacketParser<'_> {
+    SlicePacketParser::new(&mmap[..])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -138,7 +345,6 @@ mod tests {
     use std::path::Path;
 
     use crate::ser::Serialize;
-    use crate::types::Tag;
 
     #[test]
     #[ignore]
@@ -279,6 +485,74 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_slice_parser() {
+        let file = File::open("./tests/openpgp/pubring.asc").unwrap();
+        let mut bytes = Vec::new();
+        crate::armor::Dearmor::new(file)
+            .read_to_end(&mut bytes)
+            .unwrap();
+
+        let expected = PacketParser::new(&bytes[..])
+            .map(|p| p.ok().map(|p| p.to_bytes().unwrap()))
+            .collect::<Vec<_>>();
+        let mut parser = SlicePacketParser::new(&bytes);
+        let actual = parser
+            .by_ref()
+            .map(|p| p.ok().map(|p| p.to_bytes().unwrap()))
+            .collect::<Vec<_>>();
+
+        assert!(!actual.is_empty());
+        assert_eq!(expected, actual);
+        assert_eq!(parser.offset(), bytes.len());
+
+        // the header of a truncated packet can be parsed, but not its body
+        let mut parser = SlicePacketParser::new(&bytes[..10]);
+        assert!(parser.next().unwrap().is_err());
+        assert!(parser.next().is_none());
+    }
+
+    #[test]
+    fn test_buffer_config() {
+        let file = File::open("./tests/openpgp/pubring.asc").unwrap();
+        let mut bytes = Vec::new();
+        crate::armor::Dearmor::new(file)
+            .read_to_end(&mut bytes)
+            .unwrap();
+
+        let parse = |config| {
+            PacketParser::with_config(&bytes[..], config)
+                .map(|p| p.ok().map(|p| p.to_bytes().unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        let expected = parse(BufferConfig::default());
+        assert!(!expected.is_empty());
+        assert_eq!(parse(BufferConfig::for_size(bytes.len())), expected);
+        assert_eq!(
+            parse(BufferConfig {
+                initial_capacity: 16,
+                max_capacity: 1024 * 1024,
+                growth: BufferGrowth::Linear(100),
+            }),
+            expected
+        );
+
+        // the packets do not fit into the buffer
+        let mut parser = PacketParser::with_config(
+            &bytes[..],
+            BufferConfig {
+                initial_capacity: 16,
+                max_capacity: 32,
+                growth: BufferGrowth::Double,
+            },
+        );
+        match parser.next() {
+            Some(Err(Error::PacketIncomplete)) => {}
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
     #[test]
     fn incomplete_packet_parser() {
         let _ = pretty_env_logger::try_init();
@@ -298,4 +572,56 @@ mod tests {
         });
         assert!(packets.next().is_none());
     }
+
+    #[test]
+    fn lenient_packet_parser() {
+        let _ = pretty_env_logger::try_init();
+        use std::io::Cursor;
+
+        // marker packet, marker packet with an invalid body, marker packet
+        let bytes: Vec<u8> = vec![
+            0xCA, 0x03, b'P', b'G', b'P', //
+            0xCA, 0x03, b'X', b'Y', b'Z', //
+            0xCA, 0x03, b'P', b'G', b'P',
+        ];
+
+        let mut issues = Vec::new();
+        let packets: Vec<_> = PacketParser::new(Cursor::new(bytes))
+            .lenient(|issue| issues.push(issue))
+            .collect();
+
+        assert_eq!(packets.len(), 2);
+        assert!(packets.iter().all(|p| p.tag() == Tag::Marker));
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].offset, 5);
+        assert_eq!(issues[0].tag, Some(Tag::Marker));
+    }
+
+    #[test]
+    fn test_invalid_packet_offset() {
+        use crate::errors::ErrorKind;
+
+        // a valid marker packet, followed by one with an invalid body
+        let bytes = [0xCA, 0x03, b'P', b'G', b'P', 0xCA, 0x03, b'X', b'Y', b'Z'];
+
+        let from_reader = PacketParser::new(&bytes[..]).collect::<Vec<_>>();
+        let from_slice = SlicePacketParser::new(&bytes[..]).collect::<Vec<_>>();
+
+        for packets in vec![from_reader, from_slice] {
+            assert_eq!(packets.len(), 2);
+            assert!(packets[0].is_ok());
+
+            let err = packets[1].as_ref().unwrap_err();
+            match err {
+                Error::InvalidPacket { tag, offset, .. } => {
+                    assert_eq!(*tag, Tag::Marker);
+                    assert_eq!(*offset, 5);
+                }
+                _ => panic!("unexpected error {:?}", err),
+            }
+            assert_eq!(err.kind(), ErrorKind::Corrupt);
+            assert!(std::error::Error::source(err).is_some());
+        }
+    }
 }
@@ -78,6 +78,8 @@
 //!
 //! ```
 
+#[cfg(feature = "async")]
+mod async_parser;
 mod many;
 mod packet_sum;
 mod single;
@@ -87,9 +89,11 @@ mod secret_key_macro;
 #[macro_use]
 mod public_key_macro;
 
+mod aead_encrypted_data;
 mod compressed_data;
 mod key;
 mod literal_data;
+mod literal_data_reader;
 mod marker;
 mod mod_detection_code;
 mod one_pass_signature;
@@ -105,9 +109,11 @@ mod user_id;
 mod public_key_parser;
 mod secret_key_parser;
 
+pub use self::aead_encrypted_data::*;
 pub use self::compressed_data::*;
 pub use self::key::*;
 pub use self::literal_data::*;
+pub use self::literal_data_reader::*;
 pub use self::marker::*;
 pub use self::mod_detection_code::*;
 pub use self::one_pass_signature::*;
@@ -120,5 +126,7 @@ pub use self::trust::*;
 pub use self::user_attribute::*;
 pub use self::user_id::*;
 
+#[cfg(feature = "async")]
+pub use self::async_parser::*;
 pub use self::many::*;
 pub use self::packet_sum::*;
@@ -52,6 +52,18 @@ impl OnePassSignature {
     pub fn packet_version(&self) -> Version {
         self.packet_version
     }
+
+    /// Marks whether this is the last one pass signature of a group, the default. Otherwise
+    /// the next packet is a one pass signature that covers the same data.
+    pub fn with_last(mut self, last: bool) -> Self {
+        self.last = u8::from(last);
+        self
+    }
+
+    /// Returns `false` if the next packet is a one pass signature over the same data.
+    pub fn is_last(&self) -> bool {
+        self.last != 0
+    }
 }
 
 #[rustfmt::skip]
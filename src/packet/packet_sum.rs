@@ -2,8 +2,8 @@ use std::io;
 
 use crate::errors::Result;
 use crate::packet::{
-    CompressedData, LiteralData, Marker, ModDetectionCode, OnePassSignature, PublicKey,
-    PublicKeyEncryptedSessionKey, PublicSubkey, SecretKey, SecretSubkey, Signature,
+    AeadEncryptedData, CompressedData, LiteralData, Marker, ModDetectionCode, OnePassSignature,
+    PublicKey, PublicKeyEncryptedSessionKey, PublicSubkey, SecretKey, SecretSubkey, Signature,
     SymEncryptedData, SymEncryptedProtectedData, SymKeyEncryptedSessionKey, Trust, UserAttribute,
     UserId,
 };
@@ -13,6 +13,7 @@ use crate::types::{Tag, Version};
 #[derive(Debug)]
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::large_enum_variant))] // TODO: fix me
 pub enum Packet {
+    AeadEncryptedData(AeadEncryptedData),
     CompressedData(CompressedData),
     PublicKey(PublicKey),
     PublicSubkey(PublicSubkey),
@@ -36,6 +37,7 @@ impl Packet {
     /// Returns the tag for this packet type.
     pub fn tag(&self) -> Tag {
         match self {
+            Packet::AeadEncryptedData(_) => Tag::AeadEncryptedData,
             Packet::CompressedData(_) => Tag::CompressedData,
             Packet::PublicKey(_) => Tag::PublicKey,
             Packet::PublicSubkey(_) => Tag::PublicSubkey,
@@ -58,6 +60,7 @@ impl Packet {
 
     pub fn packet_version(&self) -> Version {
         match self {
+            Packet::AeadEncryptedData(p) => p.packet_version(),
             Packet::CompressedData(p) => p.packet_version(),
             Packet::PublicKey(p) => p.packet_version(),
             Packet::PublicSubkey(p) => p.packet_version(),
@@ -81,6 +84,7 @@ impl Packet {
 
 impl_try_from_into!(
     Packet,
+    AeadEncryptedData => AeadEncryptedData,
     CompressedData => CompressedData,
     PublicKey => PublicKey,
     PublicSubkey => PublicSubkey,
@@ -104,6 +108,7 @@ impl_try_from_into!(
 impl Serialize for Packet {
     fn to_writer<W: io::Write>(&self, writer: &mut W) -> Result<()> {
         match self {
+            Packet::AeadEncryptedData(p) => write_packet(writer, &p),
             Packet::CompressedData(p) => write_packet(writer, &p),
             Packet::PublicKey(p) => write_packet(writer, &p),
             Packet::PublicSubkey(p) => write_packet(writer, &p),
@@ -141,7 +146,22 @@ impl<'a, T: 'a + PacketTrait> PacketTrait for &'a T {
 }
 
 pub fn write_packet(writer: &mut impl io::Write, packet: &impl PacketTrait) -> Result<()> {
-    let packet_version = packet.packet_version();
+    write_packet_with_version(writer, packet, packet.packet_version())
+}
+
+/// Writes the packet using the given header format, instead of the one stored in the packet.
+///
+/// Tags that can not be represented in the old format are always written with a new format header.
+pub fn write_packet_with_version(
+    writer: &mut impl io::Write,
+    packet: &impl PacketTrait,
+    packet_version: Version,
+) -> Result<()> {
+    let packet_version = if packet.tag().supports_old_format() {
+        packet_version
+    } else {
+        Version::New
+    };
     let mut buf = Vec::new();
     packet.to_writer(&mut buf)?;
     debug!(
@@ -159,3 +179,21 @@ pub fn write_packet(writer: &mut impl io::Write, packet: &impl PacketTrait) -> R
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_packet_with_version() {
+        let marker = Marker::from_slice(Version::New, &b"PGP"[..]).unwrap();
+
+        let mut buf = Vec::new();
+        write_packet(&mut buf, &marker).unwrap();
+        assert_eq!(hex::encode(buf), "ca03504750");
+
+        let mut buf = Vec::new();
+        write_packet_with_version(&mut buf, &marker, Version::Old).unwrap();
+        assert_eq!(hex::encode(buf), "a803504750");
+    }
+}
@@ -66,6 +66,10 @@ impl PublicKeyEncryptedSessionKey {
         &self.id
     }
 
+    pub fn algorithm(&self) -> PublicKeyAlgorithm {
+        self.algorithm
+    }
+
     pub fn mpis(&self) -> &[Mpi] {
         &self.mpis
     }
@@ -52,6 +52,11 @@ macro_rules! impl_public_key {
                 &self.created_at
             }
 
+            /// The key creation time, as a `Timestamp`.
+            pub fn created_timestamp(&self) -> $crate::types::Timestamp {
+                (&self.created_at).into()
+            }
+
             pub fn expiration(&self) -> Option<u16> {
                 self.expiration
             }
@@ -96,6 +101,23 @@ macro_rules! impl_public_key {
                 Ok(())
             }
 
+            fn to_writer_v5<W: std::io::Write>(
+                &self,
+                writer: &mut W,
+            ) -> $crate::errors::Result<()> {
+                use byteorder::{BigEndian, WriteBytesExt};
+                use $crate::ser::Serialize;
+
+                writer.write_u32::<BigEndian>(self.created_at.timestamp() as u32)?;
+                writer.write_all(&[self.algorithm as u8])?;
+                // the key material is prefixed with its length
+                let params = self.public_params.to_bytes()?;
+                writer.write_u32::<BigEndian>(params.len() as u32)?;
+                writer.write_all(&params)?;
+
+                Ok(())
+            }
+
             pub fn sign<F>(
                 &self,
                 key: &impl $crate::types::SecretKeyTrait,
@@ -137,7 +159,7 @@ macro_rules! impl_public_key {
                         self.to_writer_old(writer)
                     }
                     $crate::types::KeyVersion::V4 => self.to_writer_new(writer),
-                    $crate::types::KeyVersion::V5 => unimplemented_err!("V5 keys"),
+                    $crate::types::KeyVersion::V5 => self.to_writer_v5(writer),
                 }
             }
         }
@@ -154,16 +176,29 @@ macro_rules! impl_public_key {
 
         impl $crate::types::KeyTrait for $name {
             /// Returns the fingerprint of this key.
-            fn fingerprint(&self) -> Vec<u8> {
+            fn fingerprint(&self) -> $crate::types::Fingerprint {
                 use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
                 use md5::Md5;
                 use sha1::{Digest, Sha1};
+                use sha2::Sha256;
 
                 use $crate::ser::Serialize;
-                use $crate::types::KeyVersion;
+                use $crate::types::{Fingerprint, KeyVersion};
 
-                match self.version() {
-                    KeyVersion::V5 => unimplemented!("V5 keys"),
+                let fingerprint = match self.version() {
+                    KeyVersion::V5 => {
+                        // A one-octet version number (5).
+                        let mut packet = vec![5];
+                        self.to_writer_v5(&mut packet).expect("write to vec");
+
+                        let mut h = Sha256::new();
+                        h.update(&[0x9A]);
+                        h.write_u32::<BigEndian>(packet.len() as u32)
+                            .expect("write to hasher");
+                        h.update(&packet);
+
+                        h.finalize().to_vec()
+                    }
                     KeyVersion::V4 => {
                         // A one-octet version number (4).
                         let mut packet = vec![4, 0, 0, 0, 0];
@@ -195,21 +230,19 @@ macro_rules! impl_public_key {
                             .expect("write to hasher");
                         h.finalize().to_vec()
                     }
-                }
+                };
+
+                Fingerprint::new(self.version(), &fingerprint).expect("fixed size digest")
             }
 
             fn key_id(&self) -> $crate::types::KeyId {
                 use $crate::types::{KeyId, KeyVersion, PublicParams};
 
                 match self.version() {
-                    KeyVersion::V5 => unimplemented!("V5 keys"),
-                    KeyVersion::V4 => {
-                        // Lower 64 bits
-                        let f = self.fingerprint();
-                        let offset = f.len() - 8;
-
-                        KeyId::from_slice(&f[offset..]).expect("fixed size slice")
-                    }
+                    KeyVersion::V4 | KeyVersion::V5 => self
+                        .fingerprint()
+                        .key_id()
+                        .expect("v4 and v5 fingerprints contain the key id"),
                     KeyVersion::V2 | KeyVersion::V3 => match &self.public_params {
                         PublicParams::RSA { n, .. } => {
                             let offset = n.len() - 8;
@@ -249,8 +282,8 @@ macro_rules! impl_public_key {
                     PublicParams::EdDSA { ref curve, ref q } => {
                         $crate::crypto::eddsa::verify(curve, q.as_bytes(), hash, hashed, sig)
                     }
-                    PublicParams::ECDSA { ref curve, .. } => {
-                        unimplemented_err!("verify ECDSA: {:?}", curve);
+                    PublicParams::ECDSA { ref curve, ref p } => {
+                        $crate::crypto::ecdsa::verify(curve, p.as_bytes(), hash, hashed, sig)
                     }
                     PublicParams::ECDH {
                         ref curve,
@@ -116,6 +116,15 @@ named_args!(new_public_key_parser<'a>(key_ver: &'a KeyVersion) <(KeyVersion, Pub
     >> (*key_ver, alg, created_at, None, params)
 ));
 
+// Ref: https://tools.ietf.org/html/draft-ietf-openpgp-rfc4880bis-10#section-5.5.2
+named_args!(v5_public_key_parser<'a>(key_ver: &'a KeyVersion) <(KeyVersion, PublicKeyAlgorithm, DateTime<Utc>, Option<u16>, PublicParams)>, do_parse!(
+       created_at: map!(be_u32, |v| Utc.timestamp(i64::from(v), 0))
+    >>        alg: map_opt!(be_u8, |v| PublicKeyAlgorithm::from_u8(v))
+    >>        len: be_u32
+    >>     params: flat_map!(take!(len), call!(parse_pub_fields, alg))
+    >> (*key_ver, alg, created_at, None, params)
+));
+
 named_args!(old_public_key_parser<'a>(key_ver: &'a KeyVersion) <(KeyVersion, PublicKeyAlgorithm, DateTime<Utc>, Option<u16>, PublicParams)>, do_parse!(
         created_at: map!(be_u32, |v| Utc.timestamp(i64::from(v), 0))
     >>         exp: be_u16
@@ -138,6 +147,9 @@ named!(pub(crate) parse<(KeyVersion, PublicKeyAlgorithm, DateTime<Utc>, Option<u
                         ) |
                         &KeyVersion::V4 => call!(
                             new_public_key_parser, &key_ver
+                        ) |
+                        &KeyVersion::V5 => call!(
+                            v5_public_key_parser, &key_ver
                         )
         )
     >> (key)
@@ -1,12 +1,26 @@
 #[macro_export]
 macro_rules! impl_secret_key {
     ($name:ident, $tag:expr, $details:ident) => {
-        #[derive(Debug, PartialEq, Eq, Clone)]
+        #[derive(PartialEq, Eq, Clone)]
         pub struct $name {
             pub(crate) details: $crate::packet::$details,
             pub(crate) secret_params: $crate::types::SecretParams,
         }
 
+        /// Prints the key id and algorithm, the secret params are redacted.
+        impl std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                use $crate::types::KeyTrait;
+
+                f.debug_struct(stringify!($name))
+                    .field("key_id", &self.key_id())
+                    .field("algorithm", &self.algorithm())
+                    .field("created_at", self.created_at())
+                    .field("secret_params", &self.secret_params)
+                    .finish()
+            }
+        }
+
         impl zeroize::Zeroize for $name {
             fn zeroize(&mut self) {
                 // details are not zeroed as they are public knowledge.
@@ -52,6 +66,11 @@ macro_rules! impl_secret_key {
                 &self.details.created_at()
             }
 
+            /// The key creation time, as a `Timestamp`.
+            pub fn created_timestamp(&self) -> $crate::types::Timestamp {
+                self.details.created_timestamp()
+            }
+
             pub fn expiration(&self) -> Option<u16> {
                 self.details.expiration()
             }
@@ -87,6 +106,72 @@ macro_rules! impl_secret_key {
                 &self.secret_params
             }
 
+            /// Encrypts the secret parameters with the given passphrase and S2K parameters.
+            pub fn lock<R: rand::Rng + rand::CryptoRng>(
+                &mut self,
+                rng: &mut R,
+                passphrase: &str,
+                s2k_params: &$crate::types::S2kParams,
+            ) -> $crate::errors::Result<()> {
+                use $crate::types::SecretParams;
+
+                let plain = match self.secret_params {
+                    SecretParams::Plain(ref k) => k.clone(),
+                    SecretParams::Encrypted(_) => bail!("secret key is already locked"),
+                    SecretParams::Stub(_) => bail!("secret key material is not available"),
+                };
+
+                let encrypted = plain.encrypt_with_params(
+                    rng,
+                    passphrase,
+                    s2k_params,
+                    self.details.version,
+                )?;
+                self.secret_params = SecretParams::Encrypted(encrypted);
+
+                Ok(())
+            }
+
+            /// Replaces the passphrase protecting the secret parameters.
+            ///
+            /// The secret parameters are decrypted using `old_pw` and, if `new_pw` is set,
+            /// encrypted again using the given S2K parameters. Otherwise they are stored unprotected.
+            pub fn set_passphrase<R, F>(
+                &mut self,
+                rng: &mut R,
+                old_pw: F,
+                new_pw: Option<&str>,
+                s2k_params: &$crate::types::S2kParams,
+            ) -> $crate::errors::Result<()>
+            where
+                R: rand::Rng + rand::CryptoRng,
+                F: FnOnce() -> String,
+            {
+                use $crate::types::SecretParams;
+
+                let plain = match self.secret_params {
+                    SecretParams::Plain(ref k) => k.clone(),
+                    SecretParams::Encrypted(ref k) => k.unlock(old_pw, self.details.algorithm)?,
+                    // nothing to protect
+                    SecretParams::Stub(_) => return Ok(()),
+                };
+
+                // ensure the decrypted material is usable, before replacing it
+                self.repr_from_plaintext(&plain)?;
+
+                self.secret_params = match new_pw {
+                    Some(pw) => SecretParams::Encrypted(plain.encrypt_with_params(
+                        rng,
+                        pw,
+                        s2k_params,
+                        self.details.version,
+                    )?),
+                    None => SecretParams::Plain(plain),
+                };
+
+                Ok(())
+            }
+
             /// Checks if we should expect a SHA1 checksum in the encrypted part.
             pub fn has_sha1_checksum(&self) -> bool {
                 self.secret_params.string_to_key_id() == 254
@@ -161,6 +246,9 @@ macro_rules! impl_secret_key {
                 let decrypted = match self.secret_params {
                     SecretParams::Plain(ref k) => self.repr_from_plaintext(k),
                     SecretParams::Encrypted(ref k) => self.repr_from_ciphertext(pw, k),
+                    SecretParams::Stub(_) => {
+                        Err(format_err!("secret key material is not available"))
+                    }
                 }?;
 
                 work(&decrypted)
@@ -186,13 +274,18 @@ macro_rules! impl_secret_key {
                             $crate::crypto::rsa::sign(priv_key, hash, data)
                         }
                         SecretKeyRepr::DSA(_) => unimplemented_err!("sign DSA"),
-                        SecretKeyRepr::ECDSA => unimplemented_err!("sign ECDSA"),
+                        SecretKeyRepr::ECDSA(ref priv_key) => match self.public_params() {
+                            PublicParams::ECDSA { ref curve, .. } => {
+                                $crate::crypto::ecdsa::sign(curve, priv_key, hash, data)
+                            }
+                            _ => unreachable!("inconsistent key state"),
+                        },
                         SecretKeyRepr::ECDH(_) => {
                             bail!("ECDH can not be used to for signing operations")
                         }
                         SecretKeyRepr::EdDSA(ref priv_key) => match self.public_params() {
                             PublicParams::EdDSA { ref curve, ref q } => match *curve {
-                                ECCCurve::Ed25519 => {
+                                ECCCurve::Ed25519 | ECCCurve::Ed448 => {
                                     $crate::crypto::eddsa::sign(q.as_bytes(), priv_key, hash, data)
                                 }
                                 _ => unsupported_err!("curve {:?} for EdDSA", curve.to_string()),
@@ -244,7 +337,7 @@ macro_rules! impl_secret_key {
 
         impl $crate::types::KeyTrait for $name {
             /// Returns the fingerprint of this key.
-            fn fingerprint(&self) -> Vec<u8> {
+            fn fingerprint(&self) -> $crate::types::Fingerprint {
                 self.details.fingerprint()
             }
 
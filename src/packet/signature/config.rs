@@ -7,6 +7,8 @@ use chrono::{DateTime, Utc};
 use crate::crypto::hash::{HashAlgorithm, Hasher};
 use crate::crypto::public_key::PublicKeyAlgorithm;
 use crate::errors::Result;
+use crate::line_writer::LineBreak;
+use crate::normalize_lines::Normalized;
 use crate::packet::{Signature, SignatureType, SignatureVersion, Subpacket};
 use crate::ser::Serialize;
 use crate::types::{KeyId, PublicKeyTrait, SecretKeyTrait, Tag};
@@ -80,6 +82,21 @@ impl SignatureConfig {
         tag: Tag,
         id: &impl Serialize,
     ) -> Result<Signature>
+    where
+        F: FnOnce() -> String,
+    {
+        self.sign_certificate_third_party(key, key_pw, key, tag, id)
+    }
+
+    /// Create a certificate signature over `id` belonging to `key`, made by `signing_key`.
+    pub fn sign_certificate_third_party<F>(
+        self,
+        signing_key: &impl SecretKeyTrait,
+        key_pw: F,
+        key: &impl PublicKeyTrait,
+        tag: Tag,
+        id: &impl Serialize,
+    ) -> Result<Signature>
     where
         F: FnOnce() -> String,
     {
@@ -124,7 +141,7 @@ impl SignatureConfig {
         let hash = &hasher.finish()[..];
 
         let signed_hash_value = [hash[0], hash[1]];
-        let signature = key.create_signature(key_pw, self.hash_alg, hash)?;
+        let signature = signing_key.create_signature(key_pw, self.hash_alg, hash)?;
 
         Ok(Signature::from_config(self, signed_hash_value, signature))
     }
@@ -162,6 +179,39 @@ impl SignatureConfig {
         Ok(Signature::from_config(self, signed_hash_value, signature))
     }
 
+    /// Sign a primary key binding (back signature), made by a signing subkey.
+    pub fn sign_primary_key_binding<F>(
+        self,
+        signing_key: &impl SecretKeyTrait,
+        key_pw: F,
+        primary_key: &impl PublicKeyTrait,
+    ) -> Result<Signature>
+    where
+        F: FnOnce() -> String,
+    {
+        debug!(
+            "signing primary key binding: {:#?} - {:#?} - {:#?}",
+            self, signing_key, primary_key
+        );
+
+        let mut hasher = self.hash_alg.new_hasher()?;
+
+        // Primary Key
+        primary_key.to_writer_old(&mut hasher)?;
+
+        // Subkey, creating the signature
+        signing_key.to_writer_old(&mut hasher)?;
+
+        let len = self.hash_signature_data(&mut *hasher)?;
+        hasher.update(&self.trailer(len));
+
+        let hash = &hasher.finish()[..];
+        let signed_hash_value = [hash[0], hash[1]];
+        let signature = signing_key.create_signature(key_pw, self.hash_alg, hash)?;
+
+        Ok(Signature::from_config(self, signed_hash_value, signature))
+    }
+
     /// Signs a direct key signature or a revocation.
     pub fn sign_key<F>(
         self,
@@ -250,8 +300,16 @@ impl SignatureConfig {
         R: Read,
     {
         match self.typ {
-            SignatureType::Text |
-                // assumes that the passed in text was already valid utf8 and normalized
+            SignatureType::Text => {
+                // text is hashed in its canonical form, with `\r\n` line endings
+                let mut raw = Vec::new();
+                data.read_to_end(&mut raw)?;
+                let normalized: Vec<u8> =
+                    Normalized::new(raw.into_iter(), LineBreak::Crlf).collect();
+                hasher.update(&normalized);
+
+                Ok(normalized.len())
+            }
             SignatureType::Binary => {
                 Ok(std::io::copy(&mut data, hasher)? as usize)
             }
@@ -0,0 +1,203 @@
+use std::{fmt, io};
+
+use crate::crypto::hash::Hasher;
+use crate::errors::Result;
+use crate::packet::signature::SignatureConfig;
+use crate::packet::{Signature, SignatureType};
+use crate::types::{PublicKeyTrait, SecretKeyTrait};
+
+/// Hashes the data of a binary or text signature incrementally.
+///
+/// The data is passed in chunks using `update`, or by writing to the hasher, so large files
+/// can be signed and verified without holding them in memory. Text is hashed in its canonical
+/// form, with `\r\n` line endings, just like `SignatureConfig::sign` and `Signature::verify`
+/// do.
+pub struct DataHasher {
+    config: SignatureConfig,
+    hasher: Box<dyn Hasher>,
+    /// For text signatures, a `\r` was hashed, but its `\n` not yet.
+    pending_cr: bool,
+}
+
+impl DataHasher {
+    /// Creates a hasher for the data of a signature with the given config.
+    pub fn new(config: SignatureConfig) -> Result<Self> {
+        ensure!(
+            config.typ == SignatureType::Binary || config.typ == SignatureType::Text,
+            "not a document signature: {:?}",
+            config.typ
+        );
+        let hasher = config.hash_alg.new_hasher()?;
+
+        Ok(DataHasher {
+            config,
+            hasher,
+            pending_cr: false,
+        })
+    }
+
+    /// Hashes the next chunk of data.
+    pub fn update(&mut self, data: &[u8]) {
+        if self.config.typ != SignatureType::Text {
+            self.hasher.update(data);
+            return;
+        }
+
+        let mut normalized = Vec::with_capacity(data.len() + data.len() / 16);
+        for &b in data {
+            match b {
+                b'\r' => {
+                    if self.pending_cr {
+                        normalized.push(b'\n');
+                    }
+                    normalized.push(b'\r');
+                    self.pending_cr = true;
+                }
+                b'\n' => {
+                    if !self.pending_cr {
+                        normalized.push(b'\r');
+                    }
+                    normalized.push(b'\n');
+                    self.pending_cr = false;
+                }
+                _ => {
+                    if self.pending_cr {
+                        normalized.push(b'\n');
+                        self.pending_cr = false;
+                    }
+                    normalized.push(b);
+                }
+            }
+        }
+
+        self.hasher.update(&normalized);
+    }
+
+    /// Signs the hashed data.
+    pub fn sign<F>(self, key: &impl SecretKeyTrait, key_pw: F) -> Result<Signature>
+    where
+        F: FnOnce() -> String,
+    {
+        let (config, hash) = self.finish()?;
+
+        let signed_hash_value = [hash[0], hash[1]];
+        let signature = key.create_signature(key_pw, config.hash_alg, &hash)?;
+
+        Ok(Signature::from_config(config, signed_hash_value, signature))
+    }
+
+    /// Verifies `signature` over the hashed data.
+    ///
+    /// The hasher must have been created from the config of `signature`, see
+    /// `Signature::data_hasher`.
+    pub fn verify(self, key: &impl PublicKeyTrait, signature: &Signature) -> Result<()> {
+        ensure!(
+            self.config == signature.config,
+            "hasher was not created for this signature"
+        );
+        let (config, hash) = self.finish()?;
+
+        ensure_eq!(
+            &signature.signed_hash_value,
+            &hash[0..2],
+            "invalid signed hash value"
+        );
+
+        key.verify_signature(config.hash_alg, &hash, &signature.signature)
+    }
+
+    fn finish(mut self) -> Result<(SignatureConfig, Vec<u8>)> {
+        if self.pending_cr {
+            self.hasher.update(b"\n");
+        }
+
+        let len = self.config.hash_signature_data(&mut *self.hasher)?;
+        self.hasher.update(&self.config.trailer(len));
+
+        Ok((self.config, self.hasher.finish()))
+    }
+}
+
+impl io::Write for DataHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl fmt::Debug for DataHasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataHasher")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl Signature {
+    /// Creates a hasher for the data signed by this signature, see `DataHasher::verify`.
+    pub fn data_hasher(&self) -> Result<DataHasher> {
+        DataHasher::new(self.config.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::File;
+    use std::io::Write;
+
+    use chrono::{SubsecRound, Utc};
+
+    use crate::composed::{Deserializable, SignedSecretKey};
+    use crate::crypto::hash::HashAlgorithm;
+    use crate::packet::{SignatureConfigBuilder, Subpacket};
+    use crate::types::KeyTrait;
+
+    #[test]
+    fn test_data_hasher() {
+        let (key, _headers) = SignedSecretKey::from_armor_single(
+            File::open("./tests/autocrypt/alice@autocrypt.example.sec.asc").unwrap(),
+        )
+        .unwrap();
+        let data = b"hello\r\nworld\nwith\rmixed\r\rline endings\r";
+
+        for typ in &[SignatureType::Binary, SignatureType::Text] {
+            let config = SignatureConfigBuilder::default()
+                .typ(*typ)
+                .pub_alg(key.algorithm())
+                .hash_alg(HashAlgorithm::SHA2_256)
+                .hashed_subpackets(vec![Subpacket::SignatureCreationTime(
+                    Utc::now().trunc_subsecs(0),
+                )])
+                .unhashed_subpackets(vec![Subpacket::Issuer(key.key_id())])
+                .build()
+                .unwrap();
+
+            // sign in chunks, that split the line endings
+            let mut hasher = DataHasher::new(config.clone()).unwrap();
+            for chunk in data.chunks(3) {
+                hasher.write_all(chunk).unwrap();
+            }
+            let signature = hasher.sign(&key, || "".into()).unwrap();
+
+            // the signature is the same as when signing all the data at once
+            signature.verify(&key, &data[..]).unwrap();
+            let expected = config.sign(&key, || "".into(), &data[..]).unwrap();
+            assert_eq!(signature.signed_hash_value, expected.signed_hash_value);
+
+            let mut hasher = signature.data_hasher().unwrap();
+            hasher.update(&data[..10]);
+            hasher.update(&data[10..]);
+            hasher.verify(&key, &signature).unwrap();
+
+            let mut hasher = signature.data_hasher().unwrap();
+            hasher.update(b"other data");
+            assert!(hasher.verify(&key, &signature).is_err());
+        }
+    }
+}
@@ -28,6 +28,16 @@ impl Deserialize for Signature {
     }
 }
 
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Signature {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let bytes = crate::util::serde_hex::deserialize(deserializer)?;
+        Signature::from_slice(Default::default(), &bytes).map_err(serde::de::Error::custom)
+    }
+}
+
 /// Convert an epoch timestamp to a `DateTime`
 fn dt_from_timestamp(ts: u32) -> DateTime<Utc> {
     DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(i64::from(ts), 0), Utc)
@@ -254,6 +264,7 @@ named!(issuer_fingerprint<Subpacket>, do_parse!(
 ));
 
 /// Parse a preferred aead subpacket
+/// Ref: https://tools.ietf.org/html/draft-ietf-openpgp-rfc4880bis-10#section-5.2.3.8
 fn pref_aead_alg(body: &[u8]) -> IResult<&[u8], Subpacket> {
     let list: SmallVec<[AeadAlgorithm; 2]> = body
         .iter()
@@ -453,4 +464,15 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_subpacket_pref_aead_alg() {
+        let (_, res) = pref_aead_alg(&[2, 1]).unwrap();
+        assert_eq!(
+            res,
+            Subpacket::PreferredAeadAlgorithms(smallvec![AeadAlgorithm::Ocb, AeadAlgorithm::Eax])
+        );
+
+        assert!(pref_aead_alg(&[42]).is_err());
+    }
 }
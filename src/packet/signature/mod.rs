@@ -1,7 +1,9 @@
 pub mod config;
+pub mod data_hasher;
 pub mod de;
 pub mod ser;
 pub mod types;
 
 pub use self::config::*;
+pub use self::data_hasher::*;
 pub use self::types::*;
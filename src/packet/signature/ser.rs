@@ -19,6 +19,18 @@ impl Serialize for Signature {
     }
 }
 
+/// Serialized as hex string of the packet body.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Signature {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let bytes = self.to_bytes().map_err(serde::ser::Error::custom)?;
+        crate::util::serde_hex::serialize(&bytes, serializer)
+    }
+}
+
 impl Subpacket {
     fn body_to_writer(&self, writer: &mut impl io::Write) -> Result<()> {
         match self {
@@ -9,12 +9,13 @@ use crate::crypto::aead::AeadAlgorithm;
 use crate::crypto::hash::HashAlgorithm;
 use crate::crypto::public_key::PublicKeyAlgorithm;
 use crate::crypto::sym::SymmetricKeyAlgorithm;
-use crate::errors::Result;
+use crate::errors::{Error, Result};
 use crate::packet::signature::SignatureConfig;
 use crate::packet::PacketTrait;
 use crate::ser::Serialize;
 use crate::types::{
-    self, CompressionAlgorithm, KeyId, KeyVersion, Mpi, PublicKeyTrait, Tag, Version,
+    self, CompressionAlgorithm, KeyId, KeyVersion, Mpi, Policy, PublicKeyTrait, Tag, Timestamp,
+    Version,
 };
 use smallvec::SmallVec;
 
@@ -76,11 +77,18 @@ impl Signature {
         self.config.typ()
     }
 
+    /// Checks the hash algorithm of this signature against `policy`, based on its creation time.
+    pub fn check_policy(&self, policy: &Policy) -> Result<()> {
+        policy.check_hash(self.config.hash_alg, self.created_timestamp())
+    }
+
     /// Verify this signature.
     pub fn verify<R>(&self, key: &impl PublicKeyTrait, data: R) -> Result<()>
     where
         R: Read,
     {
+        let _span = debug_span!("verify", typ = ?self.typ(), issuer = ?self.issuer()).entered();
+
         if let Some(issuer) = self.issuer() {
             if &key.key_id() != issuer {
                 // TODO: should this be an actual error?
@@ -108,6 +116,20 @@ impl Signature {
         key.verify_signature(self.config.hash_alg, hash, &self.signature)
     }
 
+    /// Verify this signature over a document, e.g. as a detached signature.
+    ///
+    /// Only binary and text signatures are accepted. For text signatures the line endings
+    /// of `data` are converted to `\r\n` before hashing.
+    pub fn verify_data<R>(&self, key: &impl PublicKeyTrait, data: R) -> Result<()>
+    where
+        R: Read,
+    {
+        match self.typ() {
+            SignatureType::Binary | SignatureType::Text => self.verify(key, data),
+            typ => bail!("not a document signature: {:?}", typ),
+        }
+    }
+
     /// Verifies a certificate siganture type.
     pub fn verify_certificate(
         &self,
@@ -115,14 +137,25 @@ impl Signature {
         tag: Tag,
         id: &impl Serialize,
     ) -> Result<()> {
-        debug!("verifying certificate {:#?}", self);
+        self.verify_certificate_third_party(key, key, tag, id)
+    }
+
+    /// Verifies a certificate over `id` belonging to `key`, that was made by `signing_key`.
+    pub fn verify_certificate_third_party(
+        &self,
+        signing_key: &impl PublicKeyTrait,
+        key: &impl PublicKeyTrait,
+        tag: Tag,
+        id: &impl Serialize,
+    ) -> Result<()> {
+        let _span = debug_span!("verify_certificate", typ = ?self.typ(), ?tag).entered();
 
         if let Some(issuer) = self.issuer() {
-            if &key.key_id() != issuer {
+            if &signing_key.key_id() != issuer {
                 // TODO: should this be an actual error?
                 warn!(
                     "validating certificate with a non matching Key ID {:?} != {:?}",
-                    &key.key_id(),
+                    &signing_key.key_id(),
                     issuer
                 );
             }
@@ -172,7 +205,7 @@ impl Signature {
             "invalid signed hash value"
         );
 
-        key.verify_signature(self.config.hash_alg, hash, &self.signature)
+        signing_key.verify_signature(self.config.hash_alg, hash, &self.signature)
     }
 
     /// Verifies a key binding.
@@ -181,10 +214,8 @@ impl Signature {
         signing_key: &impl PublicKeyTrait,
         key: &impl PublicKeyTrait,
     ) -> Result<()> {
-        debug!(
-            "verifying key binding: {:#?} - {:#?} - {:#?}",
-            self, signing_key, key
-        );
+        let _span =
+            debug_span!("verify_key_binding", typ = ?self.typ(), key_id = ?key.key_id()).entered();
 
         let key_id = signing_key.key_id();
         if let Some(issuer) = self.issuer() {
@@ -227,11 +258,65 @@ impl Signature {
         signing_key.verify_signature(self.config.hash_alg, hash, &self.signature)
     }
 
+    /// Verifies a primary key binding (back signature), made by the signing subkey `key`.
+    pub fn verify_primary_key_binding(
+        &self,
+        primary_key: &impl PublicKeyTrait,
+        key: &impl PublicKeyTrait,
+    ) -> Result<()> {
+        let _span = debug_span!("verify_primary_key_binding", key_id = ?key.key_id()).entered();
+
+        ensure_eq!(
+            self.typ(),
+            SignatureType::KeyBinding,
+            "invalid primary key binding signature type"
+        );
+
+        let mut hasher = self.config.hash_alg.new_hasher()?;
+
+        // Primary Key
+        {
+            let mut key_buf = Vec::new();
+            primary_key.to_writer_old(&mut key_buf)?;
+
+            hasher.update(&key_buf);
+        }
+        // Subkey, that created the signature
+        {
+            let mut key_buf = Vec::new();
+            key.to_writer_old(&mut key_buf)?;
+
+            hasher.update(&key_buf);
+        }
+
+        let len = self.config.hash_signature_data(&mut *hasher)?;
+        hasher.update(&self.config.trailer(len));
+
+        let hash = &hasher.finish()[..];
+        ensure_eq!(
+            &self.signed_hash_value,
+            &hash[0..2],
+            "invalid signed hash value"
+        );
+
+        key.verify_signature(self.config.hash_alg, hash, &self.signature)
+    }
+
     /// Verifies a direct key signature or a revocation.
     pub fn verify_key(&self, key: &impl PublicKeyTrait) -> Result<()> {
-        debug!("verifying key (revocation): {:#?} - {:#?}", self, key);
+        self.verify_key_third_party(key, key)
+    }
 
-        let key_id = key.key_id();
+    /// Verifies a direct key signature or a revocation over `key`, that was made by
+    /// `signing_key`, e.g. a designated revoker.
+    pub fn verify_key_third_party(
+        &self,
+        signing_key: &impl PublicKeyTrait,
+        key: &impl PublicKeyTrait,
+    ) -> Result<()> {
+        let _span = debug_span!("verify_key", typ = ?self.typ(), key_id = ?key.key_id()).entered();
+
+        let key_id = signing_key.key_id();
         if let Some(issuer) = self.issuer() {
             if &key_id != issuer {
                 // TODO: should this be an actual error?
@@ -261,7 +346,7 @@ impl Signature {
             "invalid signed hash value"
         );
 
-        key.verify_signature(self.config.hash_alg, hash, &self.signature)
+        signing_key.verify_signature(self.config.hash_alg, hash, &self.signature)
     }
 
     /// Returns if the signature is a certificate or not.
@@ -269,11 +354,23 @@ impl Signature {
         self.config.is_certificate()
     }
 
-    /// Returns an iterator over all subpackets of this signature.
-    fn subpackets(&self) -> impl Iterator<Item = &Subpacket> {
+    /// Returns an iterator over all subpackets of this signature, the hashed ones first.
+    pub fn subpackets(&self) -> impl Iterator<Item = &Subpacket> {
         self.config.subpackets()
     }
 
+    /// Returns an iterator over the subpackets in the hashed area, in order.
+    /// These are covered by the signature.
+    pub fn hashed_subpackets(&self) -> impl Iterator<Item = &Subpacket> {
+        self.config.hashed_subpackets.iter()
+    }
+
+    /// Returns an iterator over the subpackets in the unhashed area, in order.
+    /// These are not covered by the signature, so anyone can add, change or remove them.
+    pub fn unhashed_subpackets(&self) -> impl Iterator<Item = &Subpacket> {
+        self.config.unhashed_subpackets.iter()
+    }
+
     pub fn key_expiration_time(&self) -> Option<&DateTime<Utc>> {
         self.subpackets().find_map(|p| match p {
             Subpacket::KeyExpirationTime(d) => Some(d),
@@ -292,6 +389,40 @@ impl Signature {
         self.config.created()
     }
 
+    /// The time this signature expires, based on its creation and signature expiration time.
+    /// Returns `None` if the signature never expires.
+    pub fn signature_expires_at(&self) -> Option<DateTime<Utc>> {
+        let created = self.created()?;
+        match self.signature_expiration_time()?.timestamp() {
+            // zero means the signature never expires
+            0 => None,
+            offset => Some(*created + chrono::Duration::seconds(offset)),
+        }
+    }
+
+    /// Checks that this signature is not expired at `time`.
+    pub fn check_expiration(&self, time: &DateTime<Utc>) -> Result<()> {
+        match self.signature_expires_at() {
+            Some(expires_at) if expires_at <= *time => Err(Error::SignatureExpired(expires_at)),
+            _ => Ok(()),
+        }
+    }
+
+    /// The signature creation time, as a `Timestamp`.
+    pub fn created_timestamp(&self) -> Option<Timestamp> {
+        self.created().map(Into::into)
+    }
+
+    /// The signature expiration time, as a `Timestamp`.
+    pub fn signature_expiration_timestamp(&self) -> Option<Timestamp> {
+        self.signature_expiration_time().map(Into::into)
+    }
+
+    /// The key expiration time, as a `Timestamp`.
+    pub fn key_expiration_timestamp(&self) -> Option<Timestamp> {
+        self.key_expiration_time().map(Into::into)
+    }
+
     pub fn issuer(&self) -> Option<&KeyId> {
         self.config.issuer()
     }
@@ -323,6 +454,15 @@ impl Signature {
             .unwrap_or_else(|| &[][..])
     }
 
+    pub fn preferred_aead_algs(&self) -> &[AeadAlgorithm] {
+        self.subpackets()
+            .find_map(|p| match p {
+                Subpacket::PreferredAeadAlgorithms(d) => Some(&d[..]),
+                _ => None,
+            })
+            .unwrap_or_else(|| &[][..])
+    }
+
     pub fn key_server_prefs(&self) -> &[u8] {
         self.subpackets()
             .find_map(|p| match p {
@@ -405,6 +545,13 @@ impl Signature {
             .collect()
     }
 
+    pub fn issuer_fingerprint(&self) -> Option<&[u8]> {
+        self.subpackets().find_map(|p| match p {
+            Subpacket::IssuerFingerprint(_, fp) => Some(&fp[..]),
+            _ => None,
+        })
+    }
+
     pub fn revocation_key(&self) -> Option<&types::RevocationKey> {
         self.subpackets().find_map(|p| match p {
             Subpacket::RevocationKey(d) => Some(d),
@@ -563,6 +710,31 @@ pub enum SignatureType {
     ThirdParty = 0x50,
 }
 
+/// The amount of checking a certifier did, before certifying a User ID.
+/// Ref: https://tools.ietf.org/html/rfc4880.html#section-5.2.1
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CertificationType {
+    /// No particular assertion about how well the key owner's identity was checked.
+    Generic,
+    /// No verification of the key owner's identity was done.
+    Persona,
+    /// Some casual verification of the key owner's identity was done.
+    Casual,
+    /// Substantial verification of the key owner's identity was done.
+    Positive,
+}
+
+impl From<CertificationType> for SignatureType {
+    fn from(typ: CertificationType) -> Self {
+        match typ {
+            CertificationType::Generic => SignatureType::CertGeneric,
+            CertificationType::Persona => SignatureType::CertPersona,
+            CertificationType::Casual => SignatureType::CertCasual,
+            CertificationType::Positive => SignatureType::CertPositive,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 /// Available signature subpacket types
 pub enum SubpacketType {
@@ -748,13 +920,27 @@ impl From<KeyFlags> for SmallVec<[u8; 1]> {
     }
 }
 
+/// Notation Data, a `name@domain` and value pair attached to a signature.
+/// Ref: https://tools.ietf.org/html/rfc4880.html#section-5.2.3.16
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Notation {
+    /// Set if the value is human readable text.
     pub readable: bool,
     pub name: String,
     pub value: String,
 }
 
+impl Notation {
+    /// Creates a human readable notation.
+    pub fn new(name: &str, value: &str) -> Self {
+        Notation {
+            readable: true,
+            name: name.to_owned(),
+            value: value.to_owned(),
+        }
+    }
+}
+
 /// Codes for revocation reasons
 #[derive(Debug, PartialEq, Eq, Copy, Clone, FromPrimitive)]
 #[repr(u8)]
@@ -836,4 +1022,66 @@ mod tests {
         flags.set_group(true);
         assert_eq!(flags.0, 0x80);
     }
+
+    #[test]
+    fn test_text_signature_line_endings() {
+        use crate::composed::{Deserializable, SignedSecretKey};
+        use crate::packet::SignatureConfig;
+        use crate::types::{KeyTrait, SecretKeyTrait};
+
+        let (skey, _headers) = SignedSecretKey::from_armor_single(
+            std::fs::File::open("./tests/autocrypt/alice@autocrypt.example.sec.asc").unwrap(),
+        )
+        .unwrap();
+        let pkey = skey.public_key();
+
+        let config = SignatureConfig::new_v4(
+            Default::default(),
+            SignatureType::Text,
+            skey.algorithm(),
+            HashAlgorithm::SHA2_256,
+            vec![Subpacket::SignatureCreationTime(Utc::now())],
+            vec![Subpacket::Issuer(skey.key_id())],
+        );
+        let signature = config
+            .sign(&skey, || "".into(), &b"hello\nworld\n"[..])
+            .unwrap();
+
+        signature.verify(&pkey, &b"hello\r\nworld\r\n"[..]).unwrap();
+        signature.verify(&pkey, &b"hello\nworld\r\n"[..]).unwrap();
+        assert!(signature.verify(&pkey, &b"hello world\n"[..]).is_err());
+    }
+
+    #[test]
+    fn test_subpacket_iterators() {
+        let created = chrono::TimeZone::timestamp(&Utc, 1_500_000_000, 0);
+        let issuer = KeyId::from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        let sig = Signature::new(
+            Version::New,
+            SignatureVersion::V4,
+            SignatureType::Binary,
+            PublicKeyAlgorithm::RSA,
+            HashAlgorithm::SHA2_256,
+            [0, 0],
+            vec![],
+            vec![
+                Subpacket::SignatureCreationTime(created),
+                Subpacket::Features(smallvec![1]),
+            ],
+            vec![Subpacket::Issuer(issuer)],
+        );
+
+        assert_eq!(
+            sig.hashed_subpackets().collect::<Vec<_>>(),
+            vec![
+                &Subpacket::SignatureCreationTime(created),
+                &Subpacket::Features(smallvec![1]),
+            ]
+        );
+        assert_eq!(
+            sig.unhashed_subpackets().collect::<Vec<_>>(),
+            vec![&Subpacket::Issuer(issuer)]
+        );
+        assert_eq!(sig.subpackets().count(), 3);
+    }
 }
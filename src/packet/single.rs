@@ -8,8 +8,8 @@ use crate::de::Deserialize;
 use crate::errors::{Error, Result};
 use crate::packet::packet_sum::Packet;
 use crate::packet::{
-    CompressedData, LiteralData, Marker, ModDetectionCode, OnePassSignature, PublicKey,
-    PublicKeyEncryptedSessionKey, PublicSubkey, SecretKey, SecretSubkey, Signature,
+    AeadEncryptedData, CompressedData, LiteralData, Marker, ModDetectionCode, OnePassSignature,
+    PublicKey, PublicKeyEncryptedSessionKey, PublicSubkey, SecretKey, SecretSubkey, Signature,
     SymEncryptedData, SymEncryptedProtectedData, SymKeyEncryptedSessionKey, Trust, UserAttribute,
     UserId,
 };
@@ -41,7 +41,7 @@ named!(old_packet_header(&[u8]) -> (Version, Tag, PacketLength), bits!(do_parse!
 )));
 
 #[rustfmt::skip]
-named!(read_packet_len(&[u8]) -> PacketLength, do_parse!(
+named!(pub read_packet_len(&[u8]) -> PacketLength, do_parse!(
        olen: be_u8
     >>  len: switch!(value!(olen),
                // One-Octet Lengths
@@ -117,6 +117,11 @@ named!(new_packet_header(&[u8]) -> (Version, Tag, PacketLength), bits!(do_parse!
     >> ((ver, tag, len))
 )));
 
+// Parses a packet header, in either format
+named!(pub packet_header(&[u8]) -> (Version, Tag, PacketLength),
+    alt!(new_packet_header | old_packet_header)
+);
+
 #[derive(Debug)]
 pub enum ParseResult<'a> {
     Fixed(&'a [u8]),
@@ -128,7 +133,7 @@ pub enum ParseResult<'a> {
 // https://tools.ietf.org/html/rfc4880.html#section-4.2
 #[rustfmt::skip]
 named!(pub parser<(Version, Tag, PacketLength, ParseResult<'_>)>, do_parse!(
-       head: alt!(new_packet_header | old_packet_header)
+       head: packet_header
     >> body: switch!(value!(&head.2),
         PacketLength::Fixed(length)   => map!(take!(*length), |v| ParseResult::Fixed(v)) |
         PacketLength::Indeterminated  => value!(ParseResult::Indeterminated) |
@@ -162,13 +167,15 @@ pub fn body_parser(ver: Version, tag: Tag, body: &[u8]) -> Result<Packet> {
             SymEncryptedProtectedData::from_slice(ver, body).map(Into::into)
         }
         Tag::ModDetectionCode => ModDetectionCode::from_slice(ver, body).map(Into::into),
+        Tag::AeadEncryptedData => AeadEncryptedData::from_slice(ver, body).map(Into::into),
     };
 
     match res {
         Ok(res) => Ok(res),
         Err(Error::Incomplete(n)) => Err(Error::Incomplete(n)),
         Err(err) => {
-            warn!("invalid packet: {:?} {:?}\n{}", err, tag, hex::encode(body));
+            // the body is not logged, it might contain secret key material
+            warn!("invalid packet: {:?} {:?} ({} bytes)", err, tag, body.len());
             Err(Error::InvalidPacketContent(Box::new(err)))
         }
     }
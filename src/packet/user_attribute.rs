@@ -1,6 +1,6 @@
 use std::{fmt, io};
 
-use chrono::{SubsecRound, Utc};
+use chrono::{DateTime, SubsecRound, Utc};
 
 use byteorder::{LittleEndian, WriteBytesExt};
 use nom::{be_u8, le_u16, rest};
@@ -11,6 +11,9 @@ use crate::ser::Serialize;
 use crate::types::{SecretKeyTrait, SignedUserAttribute, Tag, Version};
 use crate::util::{packet_length, write_packet_length};
 
+/// Start of image marker of a JPEG file.
+const JPEG_MAGIC: [u8; 3] = [0xFF, 0xD8, 0xFF];
+
 /// User Attribute Packet
 /// https://tools.ietf.org/html/rfc4880.html#section-5.12
 #[derive(Clone, PartialEq, Eq)]
@@ -35,6 +38,24 @@ impl UserAttribute {
         Ok(pk)
     }
 
+    /// Creates an image attribute, containing the given JPEG image.
+    ///
+    /// Ref: https://tools.ietf.org/html/rfc4880.html#section-5.12.1
+    pub fn new_image(packet_version: Version, data: Vec<u8>) -> Result<Self> {
+        ensure!(data.starts_with(&JPEG_MAGIC), "image is not a JPEG");
+
+        // version 1 of the image header, with the JPEG image format
+        let mut header = vec![0u8; 14];
+        header[0] = 0x01;
+        header[1] = 0x01;
+
+        Ok(UserAttribute::Image {
+            packet_version,
+            header,
+            data,
+        })
+    }
+
     pub fn to_u8(&self) -> u8 {
         match *self {
             UserAttribute::Image { .. } => 1,
@@ -56,6 +77,19 @@ impl UserAttribute {
     }
 
     pub fn sign<F>(&self, key: &impl SecretKeyTrait, key_pw: F) -> Result<SignedUserAttribute>
+    where
+        F: FnOnce() -> String,
+    {
+        self.sign_at(key, key_pw, Utc::now())
+    }
+
+    /// Certifies the user attribute, using `created_at` as signature creation time.
+    pub fn sign_at<F>(
+        &self,
+        key: &impl SecretKeyTrait,
+        key_pw: F,
+        created_at: DateTime<Utc>,
+    ) -> Result<SignedUserAttribute>
     where
         F: FnOnce() -> String,
     {
@@ -63,7 +97,7 @@ impl UserAttribute {
             .typ(SignatureType::CertGeneric)
             .pub_alg(key.algorithm())
             .hashed_subpackets(vec![Subpacket::SignatureCreationTime(
-                Utc::now().trunc_subsecs(0),
+                created_at.trunc_subsecs(0),
             )])
             .unhashed_subpackets(vec![Subpacket::Issuer(key.key_id())])
             .build()?;
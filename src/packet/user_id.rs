@@ -1,6 +1,6 @@
 use std::{fmt, io, str};
 
-use chrono::{SubsecRound, Utc};
+use chrono::{DateTime, SubsecRound, Utc};
 
 use crate::errors::Result;
 use crate::packet::{PacketTrait, Signature, SignatureConfigBuilder, SignatureType, Subpacket};
@@ -8,6 +8,58 @@ use crate::ser::Serialize;
 use crate::types::{SecretKeyTrait, SignedUser, Tag, Version};
 use crate::util::{read_string, write_string};
 
+/// The parts of a user id, following the RFC 2822 `name-addr` convention of
+/// `Name (Comment) <email@example.com>`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UserIdParts<'a> {
+    pub name: Option<&'a str>,
+    pub comment: Option<&'a str>,
+    pub email: Option<&'a str>,
+}
+
+impl<'a> UserIdParts<'a> {
+    /// Splits a user id string into its parts. Bare email addresses, without angle
+    /// brackets, are detected as well.
+    pub fn parse(id: &'a str) -> Self {
+        let mut rest = id.trim();
+        let mut parts = UserIdParts::default();
+
+        if rest.ends_with('>') {
+            if let Some(start) = rest.rfind('<') {
+                parts.email = non_empty(&rest[start + 1..rest.len() - 1]);
+                rest = rest[..start].trim_end();
+            }
+        } else if rest.contains('@') && !rest.contains(char::is_whitespace) {
+            parts.email = Some(rest);
+            rest = "";
+        }
+
+        if rest.ends_with(')') {
+            if let Some(start) = rest.rfind('(') {
+                parts.comment = non_empty(&rest[start + 1..rest.len() - 1]);
+                rest = rest[..start].trim_end();
+            }
+        }
+
+        // the name can be a quoted string
+        if rest.len() >= 2 && rest.starts_with('"') && rest.ends_with('"') {
+            rest = &rest[1..rest.len() - 1];
+        }
+        parts.name = non_empty(rest);
+
+        parts
+    }
+}
+
+fn non_empty(s: &str) -> Option<&str> {
+    let s = s.trim();
+    if s.is_empty() {
+        None
+    } else {
+        Some(s)
+    }
+}
+
 /// User ID Packet
 /// https://tools.ietf.org/html/rfc4880.html#section-5.11
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -35,7 +87,30 @@ impl UserId {
         self.id.as_str()
     }
 
+    /// Splits the user id into name, comment and email.
+    pub fn parts(&self) -> UserIdParts<'_> {
+        UserIdParts::parse(&self.id)
+    }
+
+    /// The email address of the user id, if it contains one.
+    pub fn email(&self) -> Option<&str> {
+        self.parts().email
+    }
+
     pub fn sign<F>(&self, key: &impl SecretKeyTrait, key_pw: F) -> Result<SignedUser>
+    where
+        F: FnOnce() -> String,
+    {
+        self.sign_at(key, key_pw, Utc::now())
+    }
+
+    /// Certifies the user id, using `created_at` as signature creation time.
+    pub fn sign_at<F>(
+        &self,
+        key: &impl SecretKeyTrait,
+        key_pw: F,
+        created_at: DateTime<Utc>,
+    ) -> Result<SignedUser>
     where
         F: FnOnce() -> String,
     {
@@ -43,7 +118,7 @@ impl UserId {
             .typ(SignatureType::CertGeneric)
             .pub_alg(key.algorithm())
             .hashed_subpackets(vec![Subpacket::SignatureCreationTime(
-                Utc::now().trunc_subsecs(0),
+                created_at.trunc_subsecs(0),
             )])
             .unhashed_subpackets(vec![Subpacket::Issuer(key.key_id())])
             .build()?;
@@ -58,6 +133,31 @@ impl UserId {
     }
 }
 
+/// Serialized as the user id string.
+#[cfg(feature = "serde")]
+impl serde::Serialize for UserId {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.id)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for UserId {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let id = <String as serde::Deserialize>::deserialize(deserializer)?;
+
+        Ok(UserId {
+            packet_version: Default::default(),
+            id,
+        })
+    }
+}
+
 impl Serialize for UserId {
     fn to_writer<W: io::Write>(&self, writer: &mut W) -> Result<()> {
         writer.write_all(&write_string(&self.id))?;
@@ -81,3 +181,57 @@ impl PacketTrait for UserId {
         Tag::UserId
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts<'a>(
+        name: Option<&'a str>,
+        comment: Option<&'a str>,
+        email: Option<&'a str>,
+    ) -> UserIdParts<'a> {
+        UserIdParts {
+            name,
+            comment,
+            email,
+        }
+    }
+
+    #[test]
+    fn test_user_id_parts() {
+        assert_eq!(
+            UserIdParts::parse("Alice Lovelace (work) <alice@example.com>"),
+            parts(Some("Alice Lovelace"), Some("work"), Some("alice@example.com"))
+        );
+        assert_eq!(
+            UserIdParts::parse("Alice <alice@example.com>"),
+            parts(Some("Alice"), None, Some("alice@example.com"))
+        );
+        assert_eq!(
+            UserIdParts::parse("\"Lovelace, Alice\" <alice@example.com>"),
+            parts(Some("Lovelace, Alice"), None, Some("alice@example.com"))
+        );
+        assert_eq!(
+            UserIdParts::parse("<alice@example.com>"),
+            parts(None, None, Some("alice@example.com"))
+        );
+        assert_eq!(
+            UserIdParts::parse(" alice@example.com "),
+            parts(None, None, Some("alice@example.com"))
+        );
+        assert_eq!(
+            UserIdParts::parse("Alice (demo key)"),
+            parts(Some("Alice"), Some("demo key"), None)
+        );
+        assert_eq!(
+            UserIdParts::parse("Alice at example.com"),
+            parts(Some("Alice at example.com"), None, None)
+        );
+        assert_eq!(UserIdParts::parse("Alice <>"), parts(Some("Alice"), None, None));
+        assert_eq!(UserIdParts::parse(""), parts(None, None, None));
+
+        let id = UserId::from_str(Version::New, "Me <me@mail.com>");
+        assert_eq!(id.email(), Some("me@mail.com"));
+    }
+}
@@ -0,0 +1,264 @@
+use std::{fmt, str};
+
+use crate::errors::Result;
+use crate::types::{KeyId, KeyVersion};
+
+/// The fingerprint of a key, whose length and hash algorithm depend on the key version.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Fingerprint {
+    /// MD5 fingerprint of a V2 or V3 key.
+    V3([u8; 16]),
+    /// SHA-1 fingerprint of a V4 key.
+    V4([u8; 20]),
+    /// SHA-256 fingerprint of a V5 key.
+    V5([u8; 32]),
+}
+
+impl Fingerprint {
+    /// Creates the fingerprint of a key with the given version, validating its length.
+    pub fn new(version: KeyVersion, input: &[u8]) -> Result<Self> {
+        let fingerprint = Fingerprint::from_slice(input)?;
+        match (version, &fingerprint) {
+            (KeyVersion::V2, Fingerprint::V3(_))
+            | (KeyVersion::V3, Fingerprint::V3(_))
+            | (KeyVersion::V4, Fingerprint::V4(_))
+            | (KeyVersion::V5, Fingerprint::V5(_)) => Ok(fingerprint),
+            _ => bail!(
+                "invalid fingerprint length for {:?} keys: {}",
+                version,
+                input.len()
+            ),
+        }
+    }
+
+    /// Creates a fingerprint, deriving the key version from the length of `input`.
+    pub fn from_slice(input: &[u8]) -> Result<Self> {
+        let fingerprint = match input.len() {
+            16 => {
+                let mut fp = [0u8; 16];
+                fp.copy_from_slice(input);
+                Fingerprint::V3(fp)
+            }
+            20 => {
+                let mut fp = [0u8; 20];
+                fp.copy_from_slice(input);
+                Fingerprint::V4(fp)
+            }
+            32 => {
+                let mut fp = [0u8; 32];
+                fp.copy_from_slice(input);
+                Fingerprint::V5(fp)
+            }
+            len => bail!("invalid fingerprint length: {}", len),
+        };
+
+        Ok(fingerprint)
+    }
+
+    /// The version of the key this fingerprint belongs to. V2 keys are reported as V3.
+    pub fn version(&self) -> KeyVersion {
+        match self {
+            Fingerprint::V3(_) => KeyVersion::V3,
+            Fingerprint::V4(_) => KeyVersion::V4,
+            Fingerprint::V5(_) => KeyVersion::V5,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Fingerprint::V3(fp) => &fp[..],
+            Fingerprint::V4(fp) => &fp[..],
+            Fingerprint::V5(fp) => &fp[..],
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    /// Derives the Key ID, which is not possible for V3 keys, as theirs is taken from the
+    /// RSA modulus instead.
+    pub fn key_id(&self) -> Option<KeyId> {
+        let key_id = match self {
+            Fingerprint::V3(_) => return None,
+            // Lower 64 bits
+            Fingerprint::V4(fp) => KeyId::from_slice(&fp[12..]),
+            // Upper 64 bits
+            Fingerprint::V5(fp) => KeyId::from_slice(&fp[..8]),
+        };
+
+        Some(key_id.expect("fixed size slice"))
+    }
+}
+
+impl AsRef<[u8]> for Fingerprint {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+/// Formatted with `format_fingerprint`.
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_fingerprint(self.as_bytes()))
+    }
+}
+
+impl fmt::Debug for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Fingerprint({})", hex::encode(self.as_bytes()))
+    }
+}
+
+/// Parsed with `parse_fingerprint`.
+impl str::FromStr for Fingerprint {
+    type Err = crate::errors::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        Fingerprint::from_slice(&parse_fingerprint(input)?)
+    }
+}
+
+/// Serialized as upper case hex string.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Fingerprint {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        crate::util::serde_hex::serialize(self.as_bytes(), serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Fingerprint {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let bytes = crate::util::serde_hex::deserialize(deserializer)?;
+        Fingerprint::from_slice(&bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Formats a fingerprint the way it is usually displayed: upper case hex, in groups of four
+/// characters, e.g. `ABCD 1234 ABCD 1234 ABCD  1234 ABCD 1234 ABCD 1234`.
+/// V4 fingerprints get an extra space in the middle, like GnuPG prints them.
+pub fn format_fingerprint(fingerprint: &[u8]) -> String {
+    let hex = hex::encode_upper(fingerprint);
+    let mut out = String::with_capacity(hex.len() + hex.len() / 4 + 1);
+
+    for (i, group) in hex.as_bytes().chunks(4).enumerate() {
+        if i > 0 {
+            out.push(' ');
+            if fingerprint.len() == 20 && i == 5 {
+                out.push(' ');
+            }
+        }
+        // only ascii hex characters
+        out.push_str(std::str::from_utf8(group).expect("hex is ascii"));
+    }
+
+    out
+}
+
+/// Parses a fingerprint, as pasted by users.
+///
+/// Whitespace and colons are ignored, as is an optional `0x` prefix, and both upper and
+/// lower case hex are accepted. Only the lengths of V3 (16 bytes), V4 (20 bytes) and
+/// V5 (32 bytes) fingerprints are valid.
+pub fn parse_fingerprint(input: &str) -> Result<Vec<u8>> {
+    let input = input.trim();
+    let input = if input.starts_with("0x") || input.starts_with("0X") {
+        &input[2..]
+    } else {
+        input
+    };
+    let hex: String = input
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ':')
+        .collect();
+
+    let fingerprint =
+        hex::decode(&hex).map_err(|err| format_err!("invalid fingerprint: {}", err))?;
+    ensure!(
+        [16, 20, 32].contains(&fingerprint.len()),
+        "invalid fingerprint length: {}",
+        fingerprint.len()
+    );
+
+    Ok(fingerprint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_fingerprint() {
+        let fp = hex::decode("289B0EF1D105E124B6F626020EF77096D74C5F22").unwrap();
+        assert_eq!(
+            format_fingerprint(&fp),
+            "289B 0EF1 D105 E124 B6F6  2602 0EF7 7096 D74C 5F22"
+        );
+        assert_eq!(format_fingerprint(&fp[..16]).len(), 39);
+    }
+
+    #[test]
+    fn test_parse_fingerprint() {
+        let fp = hex::decode("289B0EF1D105E124B6F626020EF77096D74C5F22").unwrap();
+
+        for input in &[
+            "289B 0EF1 D105 E124 B6F6  2602 0EF7 7096 D74C 5F22",
+            "289b0ef1d105e124b6f626020ef77096d74c5f22",
+            "28:9B:0E:F1:D1:05:E1:24:B6:F6:26:02:0E:F7:70:96:D7:4C:5F:22",
+            " 0x289B0EF1D105E124B6F626020EF77096D74C5F22\n",
+        ] {
+            assert_eq!(parse_fingerprint(input).unwrap(), fp, "{}", input);
+        }
+        assert_eq!(parse_fingerprint(&format_fingerprint(&fp)).unwrap(), fp);
+
+        assert!(parse_fingerprint("289B 0EF1").is_err());
+        assert!(parse_fingerprint("289B 0EF1 D105 E124 B6F6  2602 0EF7 7096 D74C 5F2").is_err());
+        assert!(parse_fingerprint("XY9B 0EF1 D105 E124 B6F6  2602 0EF7 7096 D74C 5F22").is_err());
+    }
+
+    #[test]
+    fn test_fingerprint_type() {
+        let bytes = hex::decode("289B0EF1D105E124B6F626020EF77096D74C5F22").unwrap();
+        let fp: Fingerprint = "289B 0EF1 D105 E124 B6F6  2602 0EF7 7096 D74C 5F22"
+            .parse()
+            .unwrap();
+
+        assert_eq!(fp, Fingerprint::new(KeyVersion::V4, &bytes).unwrap());
+        assert_eq!(fp.version(), KeyVersion::V4);
+        assert_eq!(fp.as_bytes(), &bytes[..]);
+        assert_eq!(fp.to_string(), format_fingerprint(&bytes));
+        assert_eq!(fp.key_id().unwrap(), KeyId::from_slice(&bytes[12..]).unwrap());
+
+        assert!(Fingerprint::new(KeyVersion::V5, &bytes).is_err());
+        assert!(Fingerprint::from_slice(&bytes[..19]).is_err());
+
+        let v3 = Fingerprint::from_slice(&bytes[..16]).unwrap();
+        assert_eq!(v3.version(), KeyVersion::V3);
+        assert!(Fingerprint::new(KeyVersion::V2, v3.as_bytes()).is_ok());
+        assert_eq!(v3.key_id(), None);
+    }
+
+    #[test]
+    fn test_map_keys() {
+        use std::collections::{BTreeMap, HashMap};
+
+        let bytes = hex::decode("289B0EF1D105E124B6F626020EF77096D74C5F22").unwrap();
+        let fp = Fingerprint::from_slice(&bytes).unwrap();
+        let key_id = fp.key_id().unwrap();
+
+        let mut by_fp = BTreeMap::new();
+        by_fp.insert(fp, "key");
+        let mut by_id = HashMap::new();
+        by_id.insert(key_id, "key");
+
+        assert_eq!(by_fp.get(&Fingerprint::from_slice(&bytes).unwrap()), Some(&"key"));
+        assert_eq!(by_id.get(&KeyId::from_slice(&bytes[12..]).unwrap()), Some(&"key"));
+        assert!(Fingerprint::from_slice(&bytes[..16]).unwrap() < fp);
+    }
+}
@@ -1,8 +1,8 @@
 use crate::crypto::public_key::PublicKeyAlgorithm;
-use crate::types::KeyId;
+use crate::types::{Fingerprint, KeyId};
 
 pub trait KeyTrait: ::std::fmt::Debug {
-    fn fingerprint(&self) -> Vec<u8>;
+    fn fingerprint(&self) -> Fingerprint;
 
     /// Returns the Key ID of the associated primary key.
     fn key_id(&self) -> KeyId;
@@ -29,7 +29,7 @@ pub trait KeyTrait: ::std::fmt::Debug {
 }
 
 impl<'a, T: KeyTrait> KeyTrait for &'a T {
-    fn fingerprint(&self) -> Vec<u8> {
+    fn fingerprint(&self) -> Fingerprint {
         (*self).fingerprint()
     }
 
@@ -3,7 +3,7 @@ use std::fmt;
 use crate::errors::Result;
 
 /// Represents a Key ID.
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct KeyId([u8; 8]);
 
 impl AsRef<[u8]> for KeyId {
@@ -24,6 +24,36 @@ impl KeyId {
     pub fn to_vec(&self) -> Vec<u8> {
         self.0.to_vec()
     }
+
+    /// The wildcard key id, that is used to hide the recipient of an encrypted message.
+    pub fn wildcard() -> KeyId {
+        KeyId([0u8; 8])
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        self.0 == [0u8; 8]
+    }
+}
+
+/// Serialized as upper case hex string.
+#[cfg(feature = "serde")]
+impl serde::Serialize for KeyId {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        crate::util::serde_hex::serialize(&self.0, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for KeyId {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let bytes = crate::util::serde_hex::deserialize(deserializer)?;
+        KeyId::from_slice(&bytes).map_err(serde::de::Error::custom)
+    }
 }
 
 impl fmt::Debug for KeyId {
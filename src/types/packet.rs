@@ -68,6 +68,16 @@ pub enum Tag {
     SymEncryptedProtectedData = 18,
     /// Modification Detection Code Packet
     ModDetectionCode = 19,
+    /// AEAD Encrypted Data Packet
+    AeadEncryptedData = 20,
+}
+
+impl Tag {
+    /// Old format packet headers only have room for four bit tags.
+    /// Ref: https://tools.ietf.org/html/rfc4880.html#section-4.2
+    pub fn supports_old_format(self) -> bool {
+        (self as u8) < 16
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, FromPrimitive)]
@@ -165,4 +175,29 @@ mod tests {
 
         assert_eq!(hex::encode(buf), "c2c06f");
     }
+
+    #[test]
+    fn test_write_header_old() {
+        let mut buf = Vec::new();
+        Version::Old
+            .write_header(&mut buf, Tag::Signature as u8, 156)
+            .unwrap();
+
+        assert_eq!(hex::encode(buf), "889c");
+
+        let mut buf = Vec::new();
+        Version::Old
+            .write_header(&mut buf, Tag::PublicKey as u8, 269)
+            .unwrap();
+
+        assert_eq!(hex::encode(buf), "99010d");
+    }
+
+    #[test]
+    fn test_supports_old_format() {
+        assert!(Tag::LiteralData.supports_old_format());
+        assert!(Tag::PublicSubkey.supports_old_format());
+        assert!(!Tag::UserAttribute.supports_old_format());
+        assert!(!Tag::SymEncryptedProtectedData.supports_old_format());
+    }
 }
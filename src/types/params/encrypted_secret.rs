@@ -1,11 +1,12 @@
 use std::{fmt, io};
 
 use byteorder::{BigEndian, ByteOrder};
+use zeroize::Zeroize;
 
 use crate::crypto::checksum;
 use crate::crypto::public_key::PublicKeyAlgorithm;
 use crate::crypto::sym::SymmetricKeyAlgorithm;
-use crate::errors::Result;
+use crate::errors::{Error, Result};
 use crate::ser::Serialize;
 use crate::types::*;
 
@@ -61,34 +62,16 @@ impl EncryptedSecretParams {
         self.string_to_key_id
     }
 
+    /// The checksum of encrypted secret params is part of the encrypted data,
+    /// and only verified on unlock.
     pub fn compare_checksum(&self, other: Option<&[u8]>) -> Result<()> {
-        if self.string_to_key_id < 254 {
-            if let Some(other) = other {
-                ensure_eq!(
-                    BigEndian::read_u16(other),
-                    checksum::calculate_simple(self.data()),
-                    "Invalid checksum"
-                );
-            } else {
-                bail!("Missing checksum");
-            }
-        } else {
-            ensure!(other.is_none(), "Expected no checksum, but found one");
-        }
+        ensure!(other.is_none(), "Expected no checksum, but found one");
 
         Ok(())
     }
 
     pub fn checksum(&self) -> Option<Vec<u8>> {
-        if self.string_to_key_id < 254 {
-            Some(
-                checksum::calculate_simple(self.data())
-                    .to_be_bytes()
-                    .to_vec(),
-            )
-        } else {
-            None
-        }
+        None
     }
 
     pub fn unlock<F>(&self, pw: F, alg: PublicKeyAlgorithm) -> Result<PlainSecretParams>
@@ -104,7 +87,36 @@ impl EncryptedSecretParams {
         self.encryption_algorithm
             .decrypt_with_iv_regular(&key, &self.iv, &mut plaintext)?;
 
-        PlainSecretParams::from_slice(&plaintext, alg)
+        let res = Self::check_plaintext(&plaintext, self.string_to_key_id)
+            .and_then(|data| PlainSecretParams::from_slice(data, alg));
+        plaintext.zeroize();
+
+        res
+    }
+
+    /// Verifies the checksum at the end of the decrypted data, and returns the data without it.
+    /// A mismatch means the passphrase was wrong, or the key is corrupted.
+    fn check_plaintext(plaintext: &[u8], string_to_key_id: u8) -> Result<&[u8]> {
+        let (data, valid) = match string_to_key_id {
+            254 => {
+                // 20 octet SHA1 hash
+                ensure!(plaintext.len() >= 20, "invalid secret key data");
+                let (data, hash) = plaintext.split_at(plaintext.len() - 20);
+                (data, checksum::calculate_sha1(data) == hash)
+            }
+            _ => {
+                // 2 octet checksum
+                ensure!(plaintext.len() >= 2, "invalid secret key data");
+                let (data, sum) = plaintext.split_at(plaintext.len() - 2);
+                (data, checksum::calculate_simple(data) == BigEndian::read_u16(sum))
+            }
+        };
+
+        if !valid {
+            return Err(Error::InvalidPassphrase);
+        }
+
+        Ok(data)
     }
 }
 
@@ -127,9 +139,6 @@ impl Serialize for EncryptedSecretParams {
         }
 
         writer.write_all(&self.data)?;
-        if let Some(cs) = self.checksum() {
-            writer.write_all(&cs)?;
-        }
 
         Ok(())
     }
@@ -138,8 +147,7 @@ impl Serialize for EncryptedSecretParams {
 impl fmt::Debug for EncryptedSecretParams {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("EncryptedSecretParams")
-            .field("data", &hex::encode(&self.data))
-            .field("checksum", &self.checksum().map(hex::encode))
+            .field("data", &format!("[.. {} bytes]", self.data.len()))
             .field("iv", &hex::encode(&self.iv))
             .field("encryption_algorithm", &self.encryption_algorithm)
             .field("string_to_key", &self.string_to_key)
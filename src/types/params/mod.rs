@@ -2,8 +2,10 @@ pub mod encrypted_secret;
 pub mod plain_secret;
 pub mod public;
 pub mod secret;
+pub mod stub_secret;
 
 pub use self::encrypted_secret::*;
 pub use self::plain_secret::*;
 pub use self::public::*;
 pub use self::secret::*;
+pub use self::stub_secret::*;
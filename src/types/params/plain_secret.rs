@@ -142,11 +142,16 @@ impl<'a> PlainSecretParamsRef<'a> {
                     ref alg_sym,
                     ..
                 } => match *curve {
-                    ECCCurve::Curve25519 => {
-                        ensure!(d.len() <= 32, "invalid secret");
+                    ECCCurve::Curve25519
+                    | ECCCurve::Curve448
+                    | ECCCurve::P256
+                    | ECCCurve::P384
+                    | ECCCurve::P521 => {
+                        let size = curve.secret_key_length();
+                        ensure!(d.len() <= size, "invalid secret");
 
-                        let mut secret = [0u8; 32];
-                        secret[32 - d.len()..].copy_from_slice(d.as_bytes());
+                        let mut secret = vec![0u8; size];
+                        secret[size - d.len()..].copy_from_slice(d.as_bytes());
 
                         Ok(SecretKeyRepr::ECDH(ECDHSecretKey {
                             oid: curve.oid(),
@@ -161,11 +166,12 @@ impl<'a> PlainSecretParamsRef<'a> {
             },
             PlainSecretParamsRef::EdDSA(d) => match public_params {
                 PublicParams::EdDSA { ref curve, .. } => match *curve {
-                    ECCCurve::Ed25519 => {
-                        ensure!(d.len() <= 32, "invalid secret");
+                    ECCCurve::Ed25519 | ECCCurve::Ed448 => {
+                        let size = curve.secret_key_length();
+                        ensure!(d.len() <= size, "invalid secret");
 
-                        let mut secret = [0u8; 32];
-                        secret[32 - d.len()..].copy_from_slice(d.as_bytes());
+                        let mut secret = vec![0u8; size];
+                        secret[size - d.len()..].copy_from_slice(d.as_bytes());
 
                         Ok(SecretKeyRepr::EdDSA(EdDSASecretKey {
                             oid: curve.oid(),
@@ -182,9 +188,24 @@ impl<'a> PlainSecretParamsRef<'a> {
             PlainSecretParamsRef::Elgamal(_) => {
                 unimplemented_err!("Elgamal");
             }
-            PlainSecretParamsRef::ECDSA(_) => {
-                unimplemented_err!("ECDSA");
-            }
+            PlainSecretParamsRef::ECDSA(d) => match public_params {
+                PublicParams::ECDSA { ref curve, .. } => match *curve {
+                    ECCCurve::P256 | ECCCurve::P384 | ECCCurve::P521 | ECCCurve::Secp256k1 => {
+                        let size = curve.secret_key_length();
+                        ensure!(d.len() <= size, "invalid secret");
+
+                        let mut secret = vec![0u8; size];
+                        secret[size - d.len()..].copy_from_slice(d.as_bytes());
+
+                        Ok(SecretKeyRepr::ECDSA(ECDSASecretKey {
+                            oid: curve.oid(),
+                            secret,
+                        }))
+                    }
+                    _ => unsupported_err!("curve {:?} for ECDSA", curve.to_string()),
+                },
+                _ => unreachable!("inconsistent key state"),
+            },
         }
     }
 }
@@ -232,6 +253,10 @@ impl PlainSecretParams {
         version: KeyVersion,
         id: u8,
     ) -> Result<EncryptedSecretParams> {
+        if s2k.typ() == StringToKeyType::Argon2 {
+            unsupported_err!("Argon2 requires AEAD key protection, which is not available");
+        }
+
         let key = s2k.derive_key(passphrase, alg.key_size())?;
         let mut iv = vec![0u8; alg.block_size()];
         rng.fill(&mut iv[..]);
@@ -260,6 +285,19 @@ impl PlainSecretParams {
 
         Ok(EncryptedSecretParams::new(enc_data, iv, alg, s2k, id))
     }
+
+    /// Encrypts the secret parameters using the given S2K parameters, with a SHA1 checksum.
+    pub fn encrypt_with_params<R: CryptoRng + Rng>(
+        self,
+        rng: &mut R,
+        passphrase: &str,
+        params: &S2kParams,
+        version: KeyVersion,
+    ) -> Result<EncryptedSecretParams> {
+        let s2k = params.to_string_to_key(rng);
+        // encrypted, sha1 checksum
+        self.encrypt(rng, passphrase, params.cipher, s2k, version, 254)
+    }
 }
 
 impl Serialize for PlainSecretParams {
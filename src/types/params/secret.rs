@@ -16,6 +16,8 @@ use crate::types::*;
 pub enum SecretParams {
     Plain(PlainSecretParams),
     Encrypted(EncryptedSecretParams),
+    /// The secret key material is not available.
+    Stub(StubSecretParams),
 }
 
 impl Zeroize for SecretParams {
@@ -23,6 +25,7 @@ impl Zeroize for SecretParams {
         match self {
             SecretParams::Plain(p) => p.zeroize(),
             SecretParams::Encrypted(_) => { /* encrypted params do not need zeroing */ }
+            SecretParams::Stub(_) => { /* stubs contain no secrets */ }
         }
     }
 }
@@ -32,10 +35,23 @@ impl SecretParams {
         match self {
             SecretParams::Plain(_) => false,
             SecretParams::Encrypted(_) => true,
+            SecretParams::Stub(_) => false,
+        }
+    }
+
+    /// Is the secret key material missing, e.g. for offline primary keys.
+    pub fn is_stub(&self) -> bool {
+        match self {
+            SecretParams::Stub(_) => true,
+            _ => false,
         }
     }
 
     pub fn from_slice(data: &[u8], alg: PublicKeyAlgorithm) -> Result<Self> {
+        if let Ok(params) = StubSecretParams::from_slice(data) {
+            return Ok(SecretParams::Stub(params));
+        }
+
         let (_, (params, cs)) = parse_secret_fields(data, alg)?;
 
         params.compare_checksum(cs)?;
@@ -47,6 +63,7 @@ impl SecretParams {
         match self {
             SecretParams::Plain(k) => k.string_to_key_id(),
             SecretParams::Encrypted(k) => k.string_to_key_id(),
+            SecretParams::Stub(k) => k.string_to_key_id(),
         }
     }
 
@@ -54,6 +71,10 @@ impl SecretParams {
         match self {
             SecretParams::Plain(k) => k.as_ref().compare_checksum_simple(other),
             SecretParams::Encrypted(k) => k.compare_checksum(other),
+            SecretParams::Stub(_) => {
+                ensure!(other.is_none(), "Expected no checksum, but found one");
+                Ok(())
+            }
         }
     }
 
@@ -61,6 +82,7 @@ impl SecretParams {
         match self {
             SecretParams::Plain(k) => Some(k.checksum_simple()),
             SecretParams::Encrypted(k) => k.checksum(),
+            SecretParams::Stub(_) => None,
         }
     }
 }
@@ -70,6 +92,7 @@ impl Serialize for SecretParams {
         match self {
             SecretParams::Plain(k) => k.to_writer(writer),
             SecretParams::Encrypted(k) => k.to_writer(writer),
+            SecretParams::Stub(k) => k.to_writer(writer),
         }
     }
 }
@@ -102,10 +125,10 @@ named_args!(parse_secret_fields(alg: PublicKeyAlgorithm) <(SecretParams, Option<
                    )
     )
     >> checksum_len: switch!(value!(s2k_typ),
-        // 20 octect hash at the end, but part of the encrypted part
-        254 => value!(0) |
         // 2 octet checksum at the end
-        _   => value!(2)
+        0 => value!(2) |
+        // 20 octet hash or 2 octet checksum at the end, but part of the encrypted part
+        _ => value!(0)
     )
     >> data_len: map!(rest_len, |r| r - checksum_len)
     >>     data: take!(data_len)
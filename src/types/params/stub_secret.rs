@@ -0,0 +1,130 @@
+use std::io;
+
+use nom::be_u8;
+use num_traits::FromPrimitive;
+
+use crate::crypto::hash::HashAlgorithm;
+use crate::crypto::sym::SymmetricKeyAlgorithm;
+use crate::errors::Result;
+use crate::ser::Serialize;
+
+/// Private S2K type, used by GnuPG for its extensions.
+const GNU_S2K_TYPE: u8 = 101;
+
+/// Placeholder for secret key material that is not available, using the
+/// `gnu-dummy` or `gnu-divert-to-card` S2K extensions of GnuPG.
+///
+/// Keys like this are created by `gpg --export-secret-subkeys`, to keep the primary key offline,
+/// or when the secret key material lives on a smartcard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StubSecretParams {
+    /// The identifier for how this data is stored.
+    string_to_key_id: u8,
+    /// The encryption algorithm, not used for anything.
+    encryption_algorithm: SymmetricKeyAlgorithm,
+    /// The hash algorithm, not used for anything.
+    hash: HashAlgorithm,
+    typ: StubType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StubType {
+    /// The secret key material was removed (`gnu-dummy`).
+    Dummy,
+    /// The secret key material is stored on the smartcard with the given serial number
+    /// (`gnu-divert-to-card`).
+    DivertToCard(Vec<u8>),
+}
+
+impl StubSecretParams {
+    pub fn new(typ: StubType) -> Self {
+        StubSecretParams {
+            string_to_key_id: 255,
+            encryption_algorithm: SymmetricKeyAlgorithm::Plaintext,
+            hash: HashAlgorithm::None,
+            typ,
+        }
+    }
+
+    pub fn from_slice(data: &[u8]) -> Result<Self> {
+        let (_, params) = parse_stub_secret_params(data)?;
+
+        Ok(params)
+    }
+
+    pub fn string_to_key_id(&self) -> u8 {
+        self.string_to_key_id
+    }
+
+    pub fn typ(&self) -> &StubType {
+        &self.typ
+    }
+}
+
+impl Serialize for StubSecretParams {
+    fn to_writer<W: io::Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&[
+            self.string_to_key_id,
+            self.encryption_algorithm as u8,
+            GNU_S2K_TYPE,
+            self.hash as u8,
+        ])?;
+        writer.write_all(b"GNU")?;
+
+        match self.typ {
+            StubType::Dummy => writer.write_all(&[1])?,
+            StubType::DivertToCard(ref serial) => {
+                writer.write_all(&[2, serial.len() as u8])?;
+                writer.write_all(serial)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[rustfmt::skip]
+named!(parse_stub_secret_params<StubSecretParams>, do_parse!(
+        string_to_key_id: map_opt!(be_u8, |v| if v >= 254 { Some(v) } else { None })
+    >> encryption_algorithm: map_opt!(be_u8, SymmetricKeyAlgorithm::from_u8)
+    >>                       tag!(&[GNU_S2K_TYPE][..])
+    >>                 hash: map_opt!(be_u8, HashAlgorithm::from_u8)
+    >>                       tag!("GNU")
+    >>                  typ: switch!(be_u8,
+                                 1 => value!(StubType::Dummy) |
+                                 2 => do_parse!(
+                                         len: be_u8
+                                     >> serial: take!(len)
+                                     >> (StubType::DivertToCard(serial.to_vec()))
+                                 )
+                             )
+    >> (StubSecretParams {
+        string_to_key_id,
+        encryption_algorithm,
+        hash,
+        typ,
+    })
+));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stub_secret_params() {
+        let dummy = hex::decode("ff006500474e5501").unwrap();
+        let params = StubSecretParams::from_slice(&dummy).unwrap();
+        assert_eq!(params, StubSecretParams::new(StubType::Dummy));
+        assert_eq!(params.to_bytes().unwrap(), dummy);
+
+        let card = hex::decode("fe076502474e550203010203").unwrap();
+        let params = StubSecretParams::from_slice(&card).unwrap();
+        assert_eq!(params.string_to_key_id(), 254);
+        assert_eq!(params.typ(), &StubType::DivertToCard(vec![1, 2, 3]));
+        assert_eq!(params.to_bytes().unwrap(), card);
+
+        // regular iterated and salted S2K
+        let regular = hex::decode("fe070308000000000000000060").unwrap();
+        assert!(StubSecretParams::from_slice(&regular).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use crate::crypto::hash::HashAlgorithm;
+use crate::crypto::sym::SymmetricKeyAlgorithm;
+use crate::errors::{Error, Result};
+use crate::types::{PublicParams, Timestamp};
+use crate::util::bit_size;
+
+/// MD5 is broken, signatures using it are rejected no matter when they were made.
+const MD5_CUTOFF: Timestamp = Timestamp::from_secs(0);
+
+/// 2020-01-01, when chosen-prefix collisions for SHA-1 became practical.
+const SHA1_CUTOFF: Timestamp = Timestamp::from_secs(1_577_836_800);
+
+/// Decides which algorithms and key sizes are acceptable, when verifying signatures and
+/// decrypting messages.
+///
+/// The default policy
+///
+/// - rejects MD5 signatures, and SHA-1 signatures made after 2020-01-01,
+/// - rejects RSA keys with fewer than 2048 bits,
+/// - only decrypts using AES, Twofish and Camellia, and
+/// - requires encrypted data to be integrity protected, using a MDC or AEAD.
+///
+/// `Policy::legacy()` accepts everything this crate can process, for working with old keys
+/// and messages.
+///
+/// ```rust
+/// use pgp::crypto::{HashAlgorithm, SymmetricKeyAlgorithm};
+/// use pgp::types::{Policy, Timestamp};
+///
+/// // additionally accept CAST5, and reject all SHA-1 signatures
+/// let policy = Policy::default()
+///     .allow_symmetric(SymmetricKeyAlgorithm::CAST5)
+///     .reject_hash_after(HashAlgorithm::SHA1, Timestamp::from_secs(0));
+///
+/// assert!(policy.check_symmetric(SymmetricKeyAlgorithm::CAST5).is_ok());
+/// assert!(policy.check_hash(HashAlgorithm::SHA1, Some(Timestamp::now())).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+    /// Hash algorithms, and the time from which on signatures using them are rejected.
+    hash_cutoffs: Vec<(HashAlgorithm, Timestamp)>,
+    min_rsa_bits: usize,
+    symmetric_algorithms: Vec<SymmetricKeyAlgorithm>,
+    require_mdc: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            hash_cutoffs: vec![
+                (HashAlgorithm::MD5, MD5_CUTOFF),
+                (HashAlgorithm::SHA1, SHA1_CUTOFF),
+            ],
+            min_rsa_bits: 2048,
+            symmetric_algorithms: vec![
+                SymmetricKeyAlgorithm::AES128,
+                SymmetricKeyAlgorithm::AES192,
+                SymmetricKeyAlgorithm::AES256,
+                SymmetricKeyAlgorithm::Twofish,
+                SymmetricKeyAlgorithm::Camellia128,
+                SymmetricKeyAlgorithm::Camellia192,
+                SymmetricKeyAlgorithm::Camellia256,
+            ],
+            require_mdc: true,
+        }
+    }
+}
+
+impl Policy {
+    /// A permissive policy, that accepts all hash and symmetric algorithms, any key size and
+    /// data without integrity protection.
+    pub fn legacy() -> Self {
+        Policy {
+            hash_cutoffs: Vec::new(),
+            min_rsa_bits: 0,
+            symmetric_algorithms: vec![
+                SymmetricKeyAlgorithm::IDEA,
+                SymmetricKeyAlgorithm::TripleDES,
+                SymmetricKeyAlgorithm::CAST5,
+                SymmetricKeyAlgorithm::Blowfish,
+                SymmetricKeyAlgorithm::AES128,
+                SymmetricKeyAlgorithm::AES192,
+                SymmetricKeyAlgorithm::AES256,
+                SymmetricKeyAlgorithm::Twofish,
+                SymmetricKeyAlgorithm::Camellia128,
+                SymmetricKeyAlgorithm::Camellia192,
+                SymmetricKeyAlgorithm::Camellia256,
+            ],
+            require_mdc: false,
+        }
+    }
+
+    /// Rejects signatures using `hash`, that were made at or after `time`.
+    pub fn reject_hash_after(mut self, hash: HashAlgorithm, time: Timestamp) -> Self {
+        self.hash_cutoffs.retain(|(alg, _)| *alg != hash);
+        self.hash_cutoffs.push((hash, time));
+        self
+    }
+
+    /// Accepts signatures using `hash`, regardless of when they were made.
+    pub fn accept_hash(mut self, hash: HashAlgorithm) -> Self {
+        self.hash_cutoffs.retain(|(alg, _)| *alg != hash);
+        self
+    }
+
+    /// Sets the minimum size of RSA keys, in bits.
+    pub fn min_rsa_bits(mut self, bits: usize) -> Self {
+        self.min_rsa_bits = bits;
+        self
+    }
+
+    pub fn allow_symmetric(mut self, alg: SymmetricKeyAlgorithm) -> Self {
+        if !self.symmetric_algorithms.contains(&alg) {
+            self.symmetric_algorithms.push(alg);
+        }
+        self
+    }
+
+    pub fn reject_symmetric(mut self, alg: SymmetricKeyAlgorithm) -> Self {
+        self.symmetric_algorithms.retain(|a| *a != alg);
+        self
+    }
+
+    /// Sets whether encrypted data without integrity protection is refused.
+    pub fn require_mdc(mut self, require: bool) -> Self {
+        self.require_mdc = require;
+        self
+    }
+
+    pub fn requires_mdc(&self) -> bool {
+        self.require_mdc
+    }
+
+    /// Checks a signature using `hash`, that was made at `created`.
+    ///
+    /// Signatures without a creation time are rejected, if `hash` has a cutoff at all.
+    pub fn check_hash(&self, hash: HashAlgorithm, created: Option<Timestamp>) -> Result<()> {
+        let cutoff = self.hash_cutoffs.iter().find(|(alg, _)| *alg == hash);
+        match (cutoff, created) {
+            (None, _) => Ok(()),
+            (Some((_, cutoff)), Some(created)) if created < *cutoff => Ok(()),
+            (Some((_, cutoff)), _) => Err(Error::PolicyViolation(format!(
+                "{:?} signatures made at or after {} are rejected",
+                hash, cutoff
+            ))),
+        }
+    }
+
+    /// Checks the size of a public key.
+    pub fn check_public_params(&self, params: &PublicParams) -> Result<()> {
+        if let PublicParams::RSA { ref n, .. } = params {
+            let bits = bit_size(n.as_bytes());
+            if bits < self.min_rsa_bits {
+                return Err(Error::PolicyViolation(format!(
+                    "RSA key with {} bits is smaller than {} bits",
+                    bits, self.min_rsa_bits
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn check_symmetric(&self, alg: SymmetricKeyAlgorithm) -> Result<()> {
+        if !self.symmetric_algorithms.contains(&alg) {
+            return Err(Error::PolicyViolation(format!(
+                "symmetric algorithm {:?} is rejected",
+                alg
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::types::Mpi;
+
+    #[test]
+    fn test_default_policy() {
+        let policy = Policy::default();
+        let before = Some(Timestamp::from_secs(1_500_000_000));
+        let after = Some(Timestamp::from_secs(1_600_000_000));
+
+        assert!(policy.check_hash(HashAlgorithm::SHA2_256, None).is_ok());
+        assert!(policy.check_hash(HashAlgorithm::MD5, before).is_err());
+        assert!(policy.check_hash(HashAlgorithm::SHA1, before).is_ok());
+        assert!(policy.check_hash(HashAlgorithm::SHA1, after).is_err());
+        assert!(policy.check_hash(HashAlgorithm::SHA1, None).is_err());
+
+        let small = PublicParams::RSA {
+            n: Mpi::from_raw(vec![0xFF; 128]),
+            e: Mpi::from_raw(vec![1, 0, 1]),
+        };
+        assert!(policy.check_public_params(&small).is_err());
+        assert!(policy.clone().min_rsa_bits(1024).check_public_params(&small).is_ok());
+
+        assert!(policy.check_symmetric(SymmetricKeyAlgorithm::AES256).is_ok());
+        assert!(policy.check_symmetric(SymmetricKeyAlgorithm::CAST5).is_err());
+        assert!(policy.check_symmetric(SymmetricKeyAlgorithm::Plaintext).is_err());
+        assert!(policy.requires_mdc());
+    }
+
+    #[test]
+    fn test_legacy_policy() {
+        let policy = Policy::legacy();
+
+        assert!(policy.check_hash(HashAlgorithm::MD5, None).is_ok());
+        assert!(policy.check_symmetric(SymmetricKeyAlgorithm::IDEA).is_ok());
+        assert!(!policy.requires_mdc());
+
+        let policy = policy.reject_hash_after(HashAlgorithm::MD5, Timestamp::from_secs(10));
+        assert!(policy
+            .check_hash(HashAlgorithm::MD5, Some(Timestamp::from_secs(9)))
+            .is_ok());
+        assert!(policy
+            .check_hash(HashAlgorithm::MD5, Some(Timestamp::from_secs(10)))
+            .is_err());
+        assert!(policy
+            .accept_hash(HashAlgorithm::MD5)
+            .check_hash(HashAlgorithm::MD5, None)
+            .is_ok());
+    }
+}
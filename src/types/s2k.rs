@@ -1,10 +1,12 @@
 use std::io;
 
+use argon2::{Algorithm, Argon2, Version};
 use nom::be_u8;
 use num_traits::FromPrimitive;
 use rand::{CryptoRng, Rng};
 
-use crate::crypto::hash::HashAlgorithm;
+use crate::crypto::hash::{HashAlgorithm, Hasher};
+use crate::crypto::sym::SymmetricKeyAlgorithm;
 use crate::errors::Result;
 use crate::ser::Serialize;
 
@@ -16,6 +18,7 @@ pub struct StringToKey {
     hash: HashAlgorithm,
     salt: Option<Vec<u8>>,
     count: Option<u8>,
+    argon2: Option<Argon2Params>,
 }
 
 impl StringToKey {
@@ -32,6 +35,137 @@ impl StringToKey {
             hash,
             salt: Some(salt),
             count: Some(count),
+            argon2: None,
+        }
+    }
+
+    /// Creates an Argon2 String-To-Key, with a random salt.
+    /// Ref: https://www.rfc-editor.org/rfc/rfc9580.html#section-3.7.1.4
+    pub fn new_argon2<R: CryptoRng + Rng>(rng: &mut R, params: Argon2Params) -> Self {
+        let mut salt = vec![0u8; 16];
+        rng.fill(&mut salt[..]);
+
+        StringToKey {
+            typ: StringToKeyType::Argon2,
+            hash: HashAlgorithm::None,
+            salt: Some(salt),
+            count: None,
+            argon2: Some(params),
+        }
+    }
+}
+
+/// Parameters of the Argon2 String-To-Key method, which always uses Argon2id.
+/// Ref: https://www.rfc-editor.org/rfc/rfc9580.html#section-3.7.1.4
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Params {
+    /// The number of passes `t`.
+    pub passes: u8,
+    /// The degree of parallelism `p`.
+    pub parallelism: u8,
+    /// The exponent of the memory size in KiB, the memory size is `2^memory_exp` KiB.
+    pub memory_exp: u8,
+}
+
+impl Default for Argon2Params {
+    /// The second recommended parameter set of RFC 9580, using 64 MiB of memory.
+    fn default() -> Self {
+        Argon2Params {
+            passes: 3,
+            parallelism: 4,
+            memory_exp: 16,
+        }
+    }
+}
+
+/// The largest supported memory size exponent, limiting Argon2 to 2 GiB of memory.
+const MAX_ARGON2_MEMORY_EXP: u8 = 21;
+
+impl Argon2Params {
+    /// Checks the parameters against the limits of RFC 9580 and the supported memory size.
+    fn validate(&self) -> Result<()> {
+        ensure!(self.passes > 0, "argon2 passes must not be zero");
+        ensure!(self.parallelism > 0, "argon2 parallelism must not be zero");
+
+        // RFC 9580 requires at least 8 * p KiB, i.e. 3 + ceil(log2(p)) as exponent
+        let min_exp = 3 + (32 - (u32::from(self.parallelism) - 1).leading_zeros()) as u8;
+        ensure!(
+            self.memory_exp >= min_exp && self.memory_exp <= MAX_ARGON2_MEMORY_EXP,
+            "invalid argon2 memory size exponent {}",
+            self.memory_exp
+        );
+
+        Ok(())
+    }
+
+    fn derive_key(&self, passphrase: &[u8], salt: &[u8], key_size: usize) -> Result<Vec<u8>> {
+        self.validate()?;
+
+        let argon2 = Argon2::new(
+            None,
+            u32::from(self.passes),
+            1 << self.memory_exp,
+            u32::from(self.parallelism),
+            Version::V0x13,
+        )
+        .map_err(|err| format_err!("invalid argon2 parameters: {:?}", err))?;
+
+        let mut key = vec![0u8; key_size];
+        argon2
+            .hash_password_into(Algorithm::Argon2id, passphrase, salt, &[], &mut key)
+            .map_err(|err| format_err!("argon2 failed: {:?}", err))?;
+
+        Ok(key)
+    }
+}
+
+/// Parameters used to protect secret key material with a passphrase, using the iterated and
+/// salted String-To-Key method.
+///
+/// RFC 9580 only allows Argon2 for AEAD protected keys, which are not supported yet, so Argon2
+/// is only available for message encryption, see `StringToKey::new_argon2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S2kParams {
+    /// The hash algorithm used to derive the key.
+    pub hash: HashAlgorithm,
+    /// The salt, randomly generated when not set.
+    pub salt: Option<[u8; 8]>,
+    /// The coded iteration count.
+    /// Ref: https://tools.ietf.org/html/rfc4880#section-3.7.1.3
+    pub count: u8,
+    /// The symmetric algorithm used to encrypt the secret key material.
+    pub cipher: SymmetricKeyAlgorithm,
+}
+
+impl Default for S2kParams {
+    fn default() -> Self {
+        S2kParams {
+            hash: HashAlgorithm::default(),
+            salt: None,
+            count: 224,
+            cipher: SymmetricKeyAlgorithm::AES256,
+        }
+    }
+}
+
+impl S2kParams {
+    /// Creates the matching `StringToKey`, generating a salt if none was configured.
+    pub fn to_string_to_key<R: CryptoRng + Rng>(&self, rng: &mut R) -> StringToKey {
+        let salt = match self.salt {
+            Some(salt) => salt,
+            None => {
+                let mut salt = [0u8; 8];
+                rng.fill(&mut salt[..]);
+                salt
+            }
+        };
+
+        StringToKey {
+            typ: StringToKeyType::IteratedAndSalted,
+            hash: self.hash,
+            salt: Some(salt.to_vec()),
+            count: Some(self.count),
+            argon2: None,
         }
     }
 }
@@ -61,9 +195,19 @@ impl StringToKey {
         self.typ
     }
 
+    /// The Argon2 parameters, for the Argon2 type.
+    pub fn argon2(&self) -> Option<&Argon2Params> {
+        self.argon2.as_ref()
+    }
+
     /// String-To-Key methods are used to convert a given password string into a key.
     /// Ref: https://tools.ietf.org/html/rfc4880#section-3.7
     pub fn derive_key(&self, passphrase: &str, key_size: usize) -> Result<Vec<u8>> {
+        if let Some(ref params) = self.argon2 {
+            let salt = self.salt.as_ref().expect("missing salt");
+            return params.derive_key(passphrase.as_bytes(), salt, key_size);
+        }
+
         let digest_size = self.hash.digest_size();
         let rounds = (key_size as f32 / digest_size as f32).ceil() as usize;
 
@@ -87,29 +231,8 @@ impl StringToKey {
                 }
                 StringToKeyType::IteratedAndSalted => {
                     let salt = self.salt.as_ref().expect("missing salt");
-                    let pw = passphrase.as_bytes();
-                    let data_size = salt.len() + pw.len();
-                    // how many bytes are supposed to be hashed
-                    let mut count = self.count().expect("missing count");
-
-                    if count < data_size {
-                        // if the count is less, hash one full set
-                        count = data_size;
-                    }
-
-                    while count > data_size {
-                        hasher.update(salt);
-                        hasher.update(pw);
-                        count -= data_size;
-                    }
-
-                    if count < salt.len() {
-                        hasher.update(&salt[..count]);
-                    } else {
-                        hasher.update(salt);
-                        count -= salt.len();
-                        hasher.update(&pw[..count]);
-                    }
+                    let count = self.count().expect("missing count");
+                    hash_iterated(&mut *hasher, salt, passphrase.as_bytes(), count);
                 }
                 _ => unimplemented_err!("S2K {:?} is not available", self.typ),
             }
@@ -126,6 +249,33 @@ impl StringToKey {
     }
 }
 
+/// Hashes the salt and passphrase repeatedly, until `count` bytes have been hashed.
+/// Ref: https://tools.ietf.org/html/rfc4880#section-3.7.1.3
+pub(crate) fn hash_iterated(hasher: &mut dyn Hasher, salt: &[u8], pw: &[u8], count: usize) {
+    let data_size = salt.len() + pw.len();
+    // how many bytes are supposed to be hashed
+    let mut count = count;
+
+    if count < data_size {
+        // if the count is less, hash one full set
+        count = data_size;
+    }
+
+    while count > data_size {
+        hasher.update(salt);
+        hasher.update(pw);
+        count -= data_size;
+    }
+
+    if count < salt.len() {
+        hasher.update(&salt[..count]);
+    } else {
+        hasher.update(salt);
+        count -= salt.len();
+        hasher.update(&pw[..count]);
+    }
+}
+
 /// Available String-To-Key types
 #[repr(u8)]
 #[derive(Debug, PartialEq, Eq, Copy, Clone, FromPrimitive)]
@@ -134,6 +284,7 @@ pub enum StringToKeyType {
     Salted = 1,
     Reserved = 2,
     IteratedAndSalted = 3,
+    Argon2 = 4,
     Private100 = 100,
     Private101 = 101,
     Private102 = 102,
@@ -162,16 +313,19 @@ impl StringToKeyType {
             StringToKeyType::Salted => 9,
             // Salted and iterated has 1 octet hash algorithm, 8 octets salt value and 1 octet count.
             StringToKeyType::IteratedAndSalted => 10,
+            // Argon2 has 16 octets salt value, and 1 octet each for passes, parallelism and memory.
+            StringToKeyType::Argon2 => 19,
             _ => 0,
         }
     }
 }
 
-/// Has the given s2k type a salt?
-fn has_salt(typ: StringToKeyType) -> bool {
+/// The size of the salt of the given s2k type.
+fn salt_len(typ: StringToKeyType) -> usize {
     match typ {
-        StringToKeyType::Salted | StringToKeyType::IteratedAndSalted => true,
-        _ => false,
+        StringToKeyType::Salted | StringToKeyType::IteratedAndSalted => 8,
+        StringToKeyType::Argon2 => 16,
+        _ => 0,
     }
 }
 
@@ -183,23 +337,40 @@ fn has_count(typ: StringToKeyType) -> bool {
     }
 }
 
+#[rustfmt::skip]
+named!(argon2_params<Argon2Params>, do_parse!(
+             passes: be_u8
+    >>  parallelism: be_u8
+    >>   memory_exp: be_u8
+    >> (Argon2Params {
+        passes,
+        parallelism,
+        memory_exp,
+    })
+));
+
 #[rustfmt::skip]
 named!(pub s2k_parser<StringToKey>, do_parse!(
-         typ: map_opt!(be_u8, StringToKeyType::from_u8)
-    >>  hash: map_opt!(be_u8, HashAlgorithm::from_u8)
-    >>  salt: cond!(has_salt(typ), map!(take!(8), |v| v.to_vec()))
-    >> count: cond!(has_count(typ), be_u8)
+          typ: map_opt!(be_u8, StringToKeyType::from_u8)
+    >>   hash: cond!(typ != StringToKeyType::Argon2, map_opt!(be_u8, HashAlgorithm::from_u8))
+    >>   salt: cond!(salt_len(typ) > 0, map!(take!(salt_len(typ)), |v| v.to_vec()))
+    >>  count: cond!(has_count(typ), be_u8)
+    >> argon2: cond!(typ == StringToKeyType::Argon2, argon2_params)
     >> (StringToKey {
         typ,
-        hash,
+        hash: hash.unwrap_or(HashAlgorithm::None),
         salt,
         count,
+        argon2,
     })
 ));
 
 impl Serialize for StringToKey {
     fn to_writer<W: io::Write>(&self, writer: &mut W) -> Result<()> {
-        writer.write_all(&[self.typ as u8, self.hash as u8])?;
+        writer.write_all(&[self.typ as u8])?;
+        if self.typ != StringToKeyType::Argon2 {
+            writer.write_all(&[self.hash as u8])?;
+        }
 
         if let Some(ref salt) = self.salt {
             writer.write_all(salt)?;
@@ -209,6 +380,107 @@ impl Serialize for StringToKey {
             writer.write_all(&[count])?;
         }
 
+        if let Some(ref params) = self.argon2 {
+            writer.write_all(&[params.passes, params.parallelism, params.memory_exp])?;
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_s2k_params() {
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+        let params = S2kParams {
+            hash: HashAlgorithm::SHA2_512,
+            salt: Some([1u8; 8]),
+            count: 255,
+            cipher: SymmetricKeyAlgorithm::AES128,
+        };
+        let s2k = params.to_string_to_key(&mut rng);
+
+        assert_eq!(s2k.typ(), StringToKeyType::IteratedAndSalted);
+        assert_eq!(s2k.hash(), HashAlgorithm::SHA2_512);
+        assert_eq!(s2k.salt(), Some(&[1u8; 8][..]));
+        assert_eq!(s2k.count(), Some(65_011_712));
+
+        let s2k = S2kParams::default().to_string_to_key(&mut rng);
+        assert_eq!(s2k.salt().map(|s| s.len()), Some(8));
+        assert_eq!(s2k.count(), Some(16_777_216));
+    }
+
+    #[test]
+    fn test_argon2() {
+        let params = Argon2Params {
+            passes: 1,
+            parallelism: 4,
+            memory_exp: 10,
+        };
+        let mut bytes = vec![0x04];
+        bytes.extend(0u8..16);
+        bytes.extend_from_slice(&[1, 4, 10]);
+
+        let (rest, s2k) = s2k_parser(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(s2k.typ(), StringToKeyType::Argon2);
+        assert_eq!(s2k.argon2(), Some(&params));
+        assert_eq!(s2k.salt(), Some(&bytes[1..17]));
+        assert_eq!(s2k.count(), None);
+        assert_eq!(s2k.to_bytes().unwrap(), bytes);
+
+        // the output length is part of the derivation, so shorter keys are not prefixes
+        assert_eq!(
+            hex::encode(s2k.derive_key("password", 32).unwrap()),
+            "56b7f50ed10015933ab3b7ad253fa70374a1083aecf2acaa1d076ecd8a017875"
+        );
+        assert_eq!(
+            hex::encode(s2k.derive_key("password", 16).unwrap()),
+            "a46f5d94507479af0ebbeba912c1bb69"
+        );
+
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+        let s2k = StringToKey::new_argon2(&mut rng, params);
+        assert_eq!(s2k.salt().map(|s| s.len()), Some(16));
+        let (_, parsed) = s2k_parser(&s2k.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed, s2k);
+    }
+
+    #[test]
+    fn test_argon2_invalid_params() {
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+        let invalid = [
+            (0, 1, 10),
+            (1, 0, 10),
+            // p = 4 needs at least 2^5 KiB
+            (1, 4, 4),
+            (1, 1, 2),
+            (1, 1, 22),
+            (1, 1, 255),
+        ];
+        for &(passes, parallelism, memory_exp) in &invalid {
+            let params = Argon2Params {
+                passes,
+                parallelism,
+                memory_exp,
+            };
+            let s2k = StringToKey::new_argon2(&mut rng, params);
+            assert!(s2k.derive_key("password", 32).is_err(), "{:?}", params);
+        }
+
+        let params = Argon2Params {
+            passes: 1,
+            parallelism: 4,
+            memory_exp: 5,
+        };
+        let s2k = StringToKey::new_argon2(&mut rng, params);
+        assert!(s2k.derive_key("password", 32).is_ok());
+    }
+}
@@ -9,21 +9,40 @@ use crate::crypto::sym::SymmetricKeyAlgorithm;
 
 /// The version of the secret key that is actually exposed to users to do crypto operations.
 #[allow(clippy::large_enum_variant)] // FIXME
-#[derive(Debug)]
 pub enum SecretKeyRepr {
     RSA(RSAPrivateKey),
     DSA(DSASecretKey),
-    ECDSA,
+    ECDSA(ECDSASecretKey),
     ECDH(ECDHSecretKey),
     EdDSA(EdDSASecretKey),
 }
 
-/// Secret key for ECDH with Curve25519, the only combination we currently support.
+/// Only the algorithm and public parameters are printed, never the secret values.
+impl fmt::Debug for SecretKeyRepr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretKeyRepr::RSA(key) => {
+                use rsa::PublicKeyParts;
+
+                f.debug_struct("RSASecretKey")
+                    .field("bits", &key.n().bits())
+                    .field("d", &"[..]".to_string())
+                    .finish()
+            }
+            SecretKeyRepr::DSA(key) => key.fmt(f),
+            SecretKeyRepr::ECDSA(key) => key.fmt(f),
+            SecretKeyRepr::ECDH(key) => key.fmt(f),
+            SecretKeyRepr::EdDSA(key) => key.fmt(f),
+        }
+    }
+}
+
+/// Secret key for ECDH with Curve25519, Curve448 or one of the NIST curves.
 #[derive(Clone, PartialEq, Eq, Zeroize)]
 #[zeroize(drop)]
 pub struct ECDHSecretKey {
-    /// The secret point.
-    pub secret: [u8; 32],
+    /// The secret point, padded to the size of the curve.
+    pub secret: Vec<u8>,
     pub hash: HashAlgorithm,
     pub oid: Vec<u8>,
     pub alg_sym: SymmetricKeyAlgorithm,
@@ -40,12 +59,30 @@ impl fmt::Debug for ECDHSecretKey {
     }
 }
 
-/// Secret key for EdDSA with Curve25519, the only combination we currently support.
+/// Secret key for ECDSA.
+#[derive(Clone, PartialEq, Eq, Zeroize)]
+#[zeroize(drop)]
+pub struct ECDSASecretKey {
+    /// The secret scalar, padded to the size of the curve.
+    pub secret: Vec<u8>,
+    pub oid: Vec<u8>,
+}
+
+impl fmt::Debug for ECDSASecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ECDSASecretKey")
+            .field("secret", &"[..]".to_string())
+            .field("oid", &hex::encode(&self.oid))
+            .finish()
+    }
+}
+
+/// Secret key for EdDSA with Ed25519 or Ed448.
 #[derive(Clone, PartialEq, Eq, Zeroize)]
 #[zeroize(drop)]
 pub struct EdDSASecretKey {
-    /// The secret point.
-    pub secret: [u8; 32],
+    /// The secret point, padded to the secret key length of the curve.
+    pub secret: Vec<u8>,
     pub oid: Vec<u8>,
 }
 
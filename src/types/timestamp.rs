@@ -0,0 +1,138 @@
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, TimeZone, Utc};
+
+/// A point in time, as stored in OpenPGP packets: seconds since the unix epoch, in UTC.
+///
+/// Allows working with key and signature times, without using the `chrono` types, which this
+/// crate still depends on internally. Conversions exist for `std::time::SystemTime`,
+/// `chrono::DateTime<Utc>` and, with the `time` feature, `time::OffsetDateTime`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Timestamp(u32);
+
+impl Timestamp {
+    pub const fn from_secs(secs: u32) -> Self {
+        Timestamp(secs)
+    }
+
+    /// Seconds since the unix epoch.
+    pub fn as_secs(self) -> u32 {
+        self.0
+    }
+
+    /// The current time, truncated to seconds.
+    pub fn now() -> Self {
+        Timestamp::from(SystemTime::now())
+    }
+
+    pub fn to_system_time(self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(u64::from(self.0))
+    }
+}
+
+impl From<u32> for Timestamp {
+    fn from(secs: u32) -> Self {
+        Timestamp(secs)
+    }
+}
+
+impl From<Timestamp> for u32 {
+    fn from(time: Timestamp) -> Self {
+        time.0
+    }
+}
+
+/// Times before the epoch, or after 2106, are clamped to the range of OpenPGP timestamps.
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Timestamp(secs.min(u64::from(u32::max_value())) as u32)
+    }
+}
+
+impl From<Timestamp> for SystemTime {
+    fn from(time: Timestamp) -> Self {
+        time.to_system_time()
+    }
+}
+
+/// Sub-second precision is dropped, the same as when writing packets.
+impl From<&DateTime<Utc>> for Timestamp {
+    fn from(time: &DateTime<Utc>) -> Self {
+        Timestamp(time.timestamp() as u32)
+    }
+}
+
+impl From<DateTime<Utc>> for Timestamp {
+    fn from(time: DateTime<Utc>) -> Self {
+        Timestamp::from(&time)
+    }
+}
+
+impl From<Timestamp> for DateTime<Utc> {
+    fn from(time: Timestamp) -> Self {
+        Utc.timestamp(i64::from(time.0), 0)
+    }
+}
+
+/// Times outside of the range of OpenPGP timestamps are clamped.
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Timestamp {
+    fn from(time: time::OffsetDateTime) -> Self {
+        let secs = time.unix_timestamp().max(0).min(i64::from(u32::max_value()));
+
+        Timestamp(secs as u32)
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<Timestamp> for time::OffsetDateTime {
+    fn from(time: Timestamp) -> Self {
+        time::OffsetDateTime::from_unix_timestamp(i64::from(time.0))
+            .expect("u32 timestamps are always in range")
+    }
+}
+
+/// Formats the time as RFC 3339, e.g. `2019-03-08T21:36:34+00:00`.
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", DateTime::<Utc>::from(*self).to_rfc3339())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        let ts = Timestamp::from_secs(1_552_081_000);
+
+        let dt: DateTime<Utc> = ts.into();
+        assert_eq!(dt, Utc.timestamp(1_552_081_000, 0));
+        assert_eq!(Timestamp::from(dt), ts);
+
+        let st: SystemTime = ts.into();
+        assert_eq!(Timestamp::from(st), ts);
+        assert_eq!(Timestamp::from(UNIX_EPOCH - Duration::from_secs(1)).as_secs(), 0);
+
+        assert_eq!(ts.to_string(), "2019-03-08T21:36:40+00:00");
+    }
+
+    #[test]
+    #[cfg(feature = "time")]
+    fn test_time_conversions() {
+        let ts = Timestamp::from_secs(1_552_081_000);
+
+        let dt: time::OffsetDateTime = ts.into();
+        assert_eq!(dt.unix_timestamp(), 1_552_081_000);
+        assert_eq!(Timestamp::from(dt), ts);
+    }
+}
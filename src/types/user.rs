@@ -1,10 +1,45 @@
 use std::io;
 
 use crate::errors::Result;
-use crate::packet::{write_packet, Signature, UserAttribute, UserId};
+use crate::packet::{write_packet, Packet, Signature, UserAttribute, UserId};
 use crate::ser::Serialize;
 use crate::types::{PublicKeyTrait, Tag};
 
+/// Filters out third party signatures, based on the issuer.
+fn filter_self_signatures<'a>(
+    signatures: &'a [Signature],
+    key: &impl PublicKeyTrait,
+) -> impl Iterator<Item = &'a Signature> {
+    let key_id = key.key_id();
+    signatures.iter().filter(move |sig| match sig.issuer() {
+        Some(issuer) if issuer != &key_id => {
+            debug!("skipping third party signature by {:?}", issuer);
+            false
+        }
+        _ => true,
+    })
+}
+
+/// Verifies the self signatures using `verify`, skipping third party certifications.
+/// Fails if there is no self signature.
+fn verify_self_signatures<F>(
+    signatures: &[Signature],
+    key: &impl PublicKeyTrait,
+    verify: F,
+) -> Result<()>
+where
+    F: Fn(&Signature) -> Result<()>,
+{
+    let mut verified = 0;
+    for signature in filter_self_signatures(signatures, key) {
+        verify(signature)?;
+        verified += 1;
+    }
+    ensure!(verified > 0, "no self signature found");
+
+    Ok(())
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct SignedUser {
     pub id: UserId,
@@ -31,14 +66,34 @@ impl SignedUser {
         SignedUser { id, signatures }
     }
 
-    /// Verify all signatures. If signatures is empty, this fails.
+    /// Verify all self signatures. If there is no self signature, this fails.
+    ///
+    /// Third party certifications are skipped, as they require the key of the certifier.
     pub fn verify(&self, key: &impl PublicKeyTrait) -> Result<()> {
         debug!("verify signed user {:#?}", self);
         ensure!(!self.signatures.is_empty(), "no signatures found");
 
+        verify_self_signatures(&self.signatures, key, |signature| {
+            signature.verify_certificate(key, Tag::UserId, &self.id)
+        })
+    }
+
+    /// Verify the certifications of this user id, that were made by `signer`.
+    /// If there are none, this fails.
+    pub fn verify_third_party(
+        &self,
+        key: &impl PublicKeyTrait,
+        signer: &impl PublicKeyTrait,
+    ) -> Result<()> {
+        let signer_id = signer.key_id();
+        let mut found = false;
         for signature in &self.signatures {
-            signature.verify_certificate(key, Tag::UserId, &self.id)?;
+            if signature.issuer() == Some(&signer_id) {
+                signature.verify_certificate_third_party(signer, key, Tag::UserId, &self.id)?;
+                found = true;
+            }
         }
+        ensure!(found, "no certifications by {:?} found", signer_id);
 
         Ok(())
     }
@@ -46,6 +101,13 @@ impl SignedUser {
     pub fn is_primary(&self) -> bool {
         self.signatures.iter().any(Signature::is_primary)
     }
+
+    /// Returns the User ID packet, followed by its signatures.
+    pub fn packets(&self) -> Vec<Packet> {
+        let mut packets = vec![Packet::UserId(self.id.clone())];
+        packets.extend(self.signatures.iter().cloned().map(Packet::Signature));
+        packets
+    }
 }
 
 impl Serialize for SignedUser {
@@ -85,16 +147,23 @@ impl SignedUserAttribute {
         SignedUserAttribute { attr, signatures }
     }
 
-    /// Verify all signatures. If signatures is empty, this fails.
+    /// Verify all self signatures. If there is no self signature, this fails.
+    ///
+    /// Third party certifications are skipped, as they require the key of the certifier.
     pub fn verify(&self, key: &impl PublicKeyTrait) -> Result<()> {
         debug!("verify signed attribute {:?}", self);
         ensure!(!self.signatures.is_empty(), "no signatures found");
 
-        for signature in &self.signatures {
-            signature.verify_certificate(key, Tag::UserAttribute, &self.attr)?;
-        }
+        verify_self_signatures(&self.signatures, key, |signature| {
+            signature.verify_certificate(key, Tag::UserAttribute, &self.attr)
+        })
+    }
 
-        Ok(())
+    /// Returns the User Attribute packet, followed by its signatures.
+    pub fn packets(&self) -> Vec<Packet> {
+        let mut packets = vec![Packet::UserAttribute(self.attr.clone())];
+        packets.extend(self.signatures.iter().cloned().map(Packet::Signature));
+        packets
     }
 }
 
@@ -231,6 +231,92 @@ pub fn write_all(writer: &mut impl io::Write, mut buf: &[u8]) -> io::Result<()>
     Ok(())
 }
 
+/// Creates a debug level `tracing` span, if the `tracing` feature is enabled.
+///
+/// Spans only carry metadata, like packet tags, lengths and offsets, never key material.
+#[cfg(feature = "tracing")]
+macro_rules! debug_span {
+    ($($args:tt)*) => {
+        tracing::debug_span!($($args)*)
+    };
+}
+
+/// Creates a debug level `tracing` span, if the `tracing` feature is enabled.
+#[cfg(not(feature = "tracing"))]
+macro_rules! debug_span {
+    ($($args:tt)*) => {
+        $crate::util::NoSpan
+    };
+}
+
+/// Stands in for `tracing::Span`, when the `tracing` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NoSpan;
+
+#[cfg(not(feature = "tracing"))]
+impl NoSpan {
+    #[inline]
+    pub(crate) fn entered(self) -> Self {
+        self
+    }
+}
+
+/// Serde helpers, encoding bytes as hex strings.
+#[cfg(feature = "serde")]
+pub mod serde_hex {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode_upper(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        hex::decode(&s).map_err(de::Error::custom)
+    }
+}
+
+/// Serde helpers, encoding timestamps as seconds since the unix epoch, like OpenPGP does.
+#[cfg(feature = "serde")]
+pub mod serde_timestamp {
+    use chrono::{DateTime, TimeZone, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        time: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(time.timestamp())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        Ok(Utc.timestamp(i64::deserialize(deserializer)?, 0))
+    }
+
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            time: &Option<DateTime<Utc>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match time {
+                Some(time) => serializer.serialize_some(&time.timestamp()),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<DateTime<Utc>>, D::Error> {
+            Ok(Option::<i64>::deserialize(deserializer)?.map(|t| Utc.timestamp(t, 0)))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
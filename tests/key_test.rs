@@ -17,8 +17,11 @@ use rsa::padding::PaddingScheme;
 use rsa::{PublicKey as PublicKeyTrait, PublicKeyParts, RSAPrivateKey, RSAPublicKey};
 use smallvec::SmallVec;
 
+use pgp::armor;
 use pgp::composed::signed_key::*;
-use pgp::composed::Deserializable;
+use pgp::composed::{
+    openpgpkey_from_record, ssh_public_key, AgentKey, Deserializable, ImportedKey, Keyring,
+};
 use pgp::crypto::{ECCCurve, HashAlgorithm, PublicKeyAlgorithm, SymmetricKeyAlgorithm};
 use pgp::errors::Error;
 use pgp::packet::{
@@ -26,8 +29,9 @@ use pgp::packet::{
 };
 use pgp::ser::Serialize;
 use pgp::types::{
-    CompressionAlgorithm, KeyId, KeyTrait, KeyVersion, Mpi, PublicParams, SecretKeyRepr,
-    SecretKeyTrait, SecretParams, SignedUser, StringToKeyType, Version,
+    CompressionAlgorithm, Fingerprint, KeyId, KeyTrait, KeyVersion, Mpi, PlainSecretParams,
+    Policy, PublicParams, S2kParams, SecretKeyRepr, SecretKeyTrait, SecretParams, SignedUser,
+    StringToKeyType, StubType, Tag, Timestamp, Version,
 };
 
 fn read_file<P: AsRef<Path> + ::std::fmt::Debug>(path: P) -> File {
@@ -250,6 +254,7 @@ fn test_parse_details() {
     }
 
     assert_eq!(pk.created_at().timestamp(), 14_0207_0261);
+    assert_eq!(pk.created_timestamp().as_secs(), 14_0207_0261);
     assert_eq!(pk.expiration(), None);
 
     // TODO: examine subkey details
@@ -511,7 +516,7 @@ fn encrypted_private_key() {
     let pp = key.primary_key.secret_params().clone();
 
     match pp {
-        SecretParams::Plain(_) => panic!("should be encrypted"),
+        SecretParams::Plain(_) | SecretParams::Stub(_) => panic!("should be encrypted"),
         SecretParams::Encrypted(pp) => {
             assert_eq!(
                 pp.iv(),
@@ -553,6 +558,438 @@ fn encrypted_private_key() {
     ).unwrap();
 }
 
+#[test]
+fn encrypted_private_key_set_passphrase() {
+    let p = Path::new("./tests/opengpg-interop/testcases/messages/gnupg-v1-001-decrypt.asc");
+    let mut file = read_file(p.to_path_buf());
+
+    let (mut key, _headers) =
+        SignedSecretKey::from_armor_single(&mut file).expect("failed to parse key");
+    key.verify().expect("invalid key");
+
+    let get_n = |key: &SignedSecretKey, pw: &'static str| {
+        let mut n = Vec::new();
+        key.unlock(
+            || pw.to_string(),
+            |k| {
+                match k {
+                    SecretKeyRepr::RSA(k) => n = k.n().to_bytes_be(),
+                    _ => panic!("wrong key format"),
+                }
+                Ok(())
+            },
+        )
+        .expect("failed to unlock");
+        n
+    };
+    let n = get_n(&key, "test");
+
+    // a failure on any subkey leaves the whole key unchanged
+    let mut broken = key.clone();
+    broken.secret_subkeys[0]
+        .key
+        .set_passphrase(
+            &mut thread_rng(),
+            || "test".to_string(),
+            Some("other"),
+            &S2kParams::default(),
+        )
+        .expect("failed to change subkey passphrase");
+    let expected = broken.clone();
+    assert!(broken
+        .set_passphrase(|| "test".into(), Some("hello"))
+        .is_err());
+    assert_eq!(broken, expected);
+
+    // change the passphrase
+    let s2k = S2kParams {
+        hash: HashAlgorithm::SHA2_512,
+        salt: None,
+        count: 255,
+        cipher: SymmetricKeyAlgorithm::AES256,
+    };
+    key.set_passphrase_with_rng(&mut thread_rng(), || "test".into(), Some("hello"), &s2k)
+        .expect("failed to change passphrase");
+
+    let armor = key.to_armored_string(None).expect("failed to serialize key");
+    let (mut key, _headers) = SignedSecretKey::from_string(&armor).expect("failed to parse key");
+    key.verify().expect("invalid key");
+
+    match key.primary_key.secret_params() {
+        SecretParams::Plain(_) | SecretParams::Stub(_) => panic!("should be encrypted"),
+        SecretParams::Encrypted(pp) => {
+            assert_eq!(pp.encryption_algorithm(), SymmetricKeyAlgorithm::AES256);
+            assert_eq!(pp.string_to_key().hash(), HashAlgorithm::SHA2_512);
+            assert_eq!(pp.string_to_key().count(), Some(65_011_712));
+        }
+    }
+    assert_eq!(get_n(&key, "hello"), n);
+
+    // remove the passphrase
+    key.set_passphrase(|| "hello".into(), None)
+        .expect("failed to remove passphrase");
+    assert!(!key.primary_key.secret_params().is_encrypted());
+    for subkey in &key.secret_subkeys {
+        assert!(!subkey.key.secret_params().is_encrypted());
+    }
+    key.verify().expect("invalid key");
+    assert_eq!(get_n(&key, ""), n);
+}
+
+#[test]
+fn test_keyring() {
+    let mut file = read_file(Path::new("./tests/openpgp/pubring.asc").to_path_buf());
+    let mut keyring = Keyring::from_armor(&mut file).expect("failed to parse keyring");
+    let count = keyring.len();
+    assert!(count > 1);
+
+    // lookup by subkey id
+    let key = keyring
+        .get_by_key_id(&KeyId::from_slice(&hex::decode("ABAB28A247BE2775").unwrap()).unwrap())
+        .expect("missing key");
+    let fingerprint: Fingerprint = "289B0EF1D105E124B6F626020EF77096D74C5F22".parse().unwrap();
+    assert_eq!(key.fingerprint(), fingerprint);
+
+    // lookup by fingerprint
+    let key = keyring
+        .get_by_fingerprint(&"CD3D0F5701CBFCACB2A4907305A37887B27907AA".parse().unwrap())
+        .expect("missing key");
+    assert_eq!(
+        key.key_id(),
+        KeyId::from_slice(&hex::decode("973D50E1C40FDECF").unwrap()).unwrap()
+    );
+
+    // lookup by user id
+    let found = keyring.find_by_email("TWO@example.com");
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].key_id(), key.key_id());
+    assert!(keyring.find_by_user_id("demo key").len() > 1);
+    assert!(keyring.find_by_email("demo key").is_empty());
+
+    // serialize and parse again
+    let armor = keyring
+        .to_armored_string(None)
+        .expect("failed to serialize keyring");
+    let keyring2 = Keyring::from_string(&armor).expect("failed to parse keyring");
+    assert_eq!(keyring, keyring2);
+
+    let bytes = keyring.to_bytes().expect("failed to serialize keyring");
+    assert_eq!(Keyring::from_slice(&bytes).unwrap(), keyring);
+
+    let removed = keyring.remove(&fingerprint).expect("failed to remove key");
+    assert_eq!(keyring.len(), count - 1);
+    assert!(keyring.get_by_fingerprint(&fingerprint).is_none());
+    assert!(keyring.remove(&fingerprint).is_none());
+
+    assert!(keyring.add(removed.clone()).is_none());
+    assert_eq!(keyring.len(), count);
+    assert_eq!(keyring.add(removed), keyring2.get_by_fingerprint(&fingerprint).cloned());
+    assert_eq!(keyring.len(), count);
+}
+
+#[test]
+#[cfg(feature = "mmap")]
+fn test_keyring_open() {
+    let mut file = read_file(Path::new("./tests/openpgp/pubring.asc").to_path_buf());
+    let keyring = Keyring::from_armor(&mut file).expect("failed to parse keyring");
+
+    let path = std::env::temp_dir().join("rpgp-test-keyring-open.gpg");
+    std::fs::write(&path, keyring.to_bytes().unwrap()).unwrap();
+    let opened = Keyring::open(&path).expect("failed to open keyring");
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(opened, keyring);
+}
+
+#[test]
+fn test_merge_public_keys() {
+    let (keys, _) =
+        SignedPublicKey::from_armor_many(read_file("./tests/openpgp/pubring.asc")).unwrap();
+    let keys = keys.collect::<Result<Vec<_>, _>>().unwrap();
+    let key_id = KeyId::from_slice(&hex::decode("2D727CC768697734").unwrap()).unwrap();
+    let key = keys.iter().find(|k| k.key_id() == key_id).unwrap();
+    assert_eq!(key.details.users.len(), 3);
+    assert_eq!(key.public_subkeys.len(), 1);
+
+    let mut part1 = key.clone();
+    part1.details.users.truncate(1);
+    part1.public_subkeys.clear();
+
+    let mut part2 = key.clone();
+    part2.details.users.remove(0);
+    part2.details.users[0].signatures.clear();
+
+    let merged = part1.merge(part2).expect("failed to merge");
+    assert_eq!(merged.details.users.len(), 3);
+    assert!(merged.details.users[1].signatures.is_empty());
+    assert_eq!(merged.public_subkeys, key.public_subkeys);
+
+    let merged = merged.merge(key.clone()).expect("failed to merge");
+    assert_eq!(&merged, key);
+
+    let other = keys.iter().find(|k| k.key_id() != key_id).unwrap();
+    assert!(merged.merge(other.clone()).is_err());
+}
+
+fn read_agent_key(path: &str) -> AgentKey {
+    let mut data = Vec::new();
+    if path.ends_with(".asc") {
+        armor::Dearmor::new(read_file(path))
+            .read_to_end(&mut data)
+            .unwrap();
+    } else {
+        read_file(path).read_to_end(&mut data).unwrap();
+    }
+
+    AgentKey::from_bytes(&data).expect("failed to parse agent key")
+}
+
+/// Finds the key with the given key id in `tests/openpgp/{pubring, secring}.asc`.
+fn read_ring_keys(key_id: &str) -> (SignedPublicKey, SignedSecretKey) {
+    let key_id = KeyId::from_slice(&hex::decode(key_id).unwrap()).unwrap();
+
+    let (public_keys, _) =
+        SignedPublicKey::from_armor_many(read_file("./tests/openpgp/pubring.asc")).unwrap();
+    let public_key = public_keys
+        .map(|k| k.unwrap())
+        .find(|k| k.key_id() == key_id)
+        .unwrap();
+
+    let (secret_keys, _) =
+        SignedSecretKey::from_armor_many(read_file("./tests/openpgp/secring.asc")).unwrap();
+    let secret_key = secret_keys
+        .map(|k| k.unwrap())
+        .find(|k| k.key_id() == key_id)
+        .unwrap();
+
+    (public_key, secret_key)
+}
+
+#[test]
+fn test_agent_keys_unprotected() {
+    let (public_key, expected) = read_ring_keys("973D50E1C40FDECF");
+    let agent_keys = vec![
+        read_agent_key("./tests/openpgp/privkeys/343D8AF79796EE107D645A2787A9D9252F924E6F.asc"),
+        read_agent_key("./tests/openpgp/privkeys/8B5ABF3EF9EB8D96B91A0B8C2C4401C91C834C34.asc"),
+    ];
+    assert!(!agent_keys[0].is_protected());
+
+    let key = SignedSecretKey::from_agent_keys(public_key, &agent_keys, || "".into())
+        .expect("failed to import agent keys");
+
+    assert_eq!(
+        key.primary_key.secret_params(),
+        expected.primary_key.secret_params()
+    );
+    assert!(key.public_subkeys.is_empty());
+    assert_eq!(key.secret_subkeys.len(), 1);
+    assert_eq!(
+        key.secret_subkeys[0].key.secret_params(),
+        expected.secret_subkeys[0].key.secret_params()
+    );
+}
+
+#[test]
+fn test_agent_keys_protected() {
+    let (public_key, expected) = read_ring_keys("0EF77096D74C5F22");
+    let unlock = |params: &SecretParams, alg| match params {
+        SecretParams::Encrypted(params) => {
+            SecretParams::Plain(params.unlock(|| "def".into(), alg).unwrap())
+        }
+        SecretParams::Plain(_) | SecretParams::Stub(_) => panic!("expected encrypted key"),
+    };
+    let expected_primary = unlock(
+        expected.primary_key.secret_params(),
+        PublicKeyAlgorithm::DSA,
+    );
+    let expected_subkey = unlock(
+        expected.secret_subkeys[0].key.secret_params(),
+        PublicKeyAlgorithm::ElgamalSign,
+    );
+
+    let primary =
+        read_agent_key("./tests/openpgp/privkeys/50B2D4FA4122C212611048BC5FC31BD44393626E.asc");
+    let subkey =
+        read_agent_key("./tests/openpgp/privkeys/7E201E28B6FEB2927B321F443205F4724EBE637E.asc");
+    assert!(primary.is_protected());
+    assert!(primary.matches(public_key.primary_key.public_params()));
+    assert!(!subkey.matches(public_key.primary_key.public_params()));
+
+    // wrong passphrase
+    assert!(SignedSecretKey::from_agent_keys(
+        public_key.clone(),
+        &[primary.clone(), subkey.clone()],
+        || "abc".into()
+    )
+    .is_err());
+
+    // missing primary key
+    assert!(
+        SignedSecretKey::from_agent_keys(public_key.clone(), &[subkey.clone()], || "def".into())
+            .is_err()
+    );
+
+    // only the primary key is available
+    let key = SignedSecretKey::from_agent_keys(public_key.clone(), &[primary.clone()], || {
+        "def".into()
+    })
+    .expect("failed to import agent keys");
+    assert_eq!(key.primary_key.secret_params(), &expected_primary);
+    assert_eq!(key.public_subkeys.len(), 1);
+    assert!(key.secret_subkeys.is_empty());
+
+    let key = SignedSecretKey::from_agent_keys(public_key, &[primary, subkey], || "def".into())
+        .expect("failed to import agent keys");
+    assert_eq!(key.primary_key.secret_params(), &expected_primary);
+    assert_eq!(key.secret_subkeys[0].key.secret_params(), &expected_subkey);
+}
+
+#[test]
+fn test_blowfish_protected_keys() {
+    // the demo keys of the GnuPG test suite are protected using Blowfish
+    let (keys, _) =
+        SignedSecretKey::from_armor_many(read_file("./tests/openpgp/secdemo.asc")).unwrap();
+
+    let mut count = 0;
+    for key in keys {
+        let key = key.expect("failed to parse key");
+        let params = match key.primary_key.secret_params() {
+            SecretParams::Encrypted(params) => params,
+            _ => panic!("expected encrypted key"),
+        };
+        assert_eq!(params.encryption_algorithm(), SymmetricKeyAlgorithm::Blowfish);
+
+        let plain = params
+            .unlock(|| "abc".into(), PublicKeyAlgorithm::DSA)
+            .expect("failed to unlock");
+        let x = match plain {
+            PlainSecretParams::DSA(ref x) => BigUint::from_bytes_be(x.as_bytes()),
+            _ => panic!("unexpected params {:?}", plain),
+        };
+
+        // the decrypted secret must match the public key
+        match key.primary_key.public_params() {
+            PublicParams::DSA { p, g, y, .. } => {
+                let p = BigUint::from_bytes_be(p.as_bytes());
+                let g = BigUint::from_bytes_be(g.as_bytes());
+                let y = BigUint::from_bytes_be(y.as_bytes());
+                assert_eq!(g.modpow(&x, &p), y);
+            }
+            _ => panic!("expected DSA key"),
+        }
+        count += 1;
+    }
+
+    assert_eq!(count, 26);
+}
+
+#[test]
+fn test_debug_redacts_secrets() {
+    let (key, _) = SignedSecretKey::from_armor_single(read_file(
+        "./tests/opengpg-interop/testcases/messages/gnupg-v1-001-decrypt.asc",
+    ))
+    .unwrap();
+    let encrypted = match key.primary_key.secret_params() {
+        SecretParams::Encrypted(params) => hex::encode(params.data()),
+        params => panic!("unexpected params {:?}", params),
+    };
+
+    let debug = format!("{:?}", key);
+    assert!(!debug.contains(&encrypted));
+    assert!(debug.contains(&format!("{:?}", key.key_id())));
+
+    key.unlock(
+        || "test".into(),
+        |repr| {
+            assert!(format!("{:?}", repr).contains("[..]"));
+            Ok(())
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_unlock_wrong_passphrase() {
+    // protected with a SHA1 hash
+    let (key, _) = SignedSecretKey::from_armor_single(read_file(
+        "./tests/opengpg-interop/testcases/messages/gnupg-v1-001-decrypt.asc",
+    ))
+    .unwrap();
+    assert_eq!(key.primary_key.secret_params().string_to_key_id(), 254);
+    key.unlock(|| "test".into(), |_| Ok(())).expect("failed to unlock");
+    match key.unlock(|| "wrong".into(), |_| Ok(())) {
+        Err(Error::InvalidPassphrase) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+
+    // protected with a two octet checksum
+    let (keys, _) =
+        SignedSecretKey::from_armor_many(read_file("./tests/openpgp/secdemo.asc")).unwrap();
+    let key = keys.into_iter().next().unwrap().unwrap();
+    assert_eq!(key.primary_key.secret_params().string_to_key_id(), 255);
+    match key.unlock(|| "wrong".into(), |_| Ok(())) {
+        Err(Error::InvalidPassphrase) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn test_agent_keys_extended_format() {
+    let (public_key, _) =
+        SignedPublicKey::from_armor_single(read_file("./tests/gpg-agent/ed25519.pub.asc")).unwrap();
+    let (expected, _) =
+        SignedSecretKey::from_armor_single(read_file("./tests/gpg-agent/ed25519.sec.asc")).unwrap();
+    let agent_keys = vec![
+        read_agent_key("./tests/gpg-agent/C17CB22C028CE9BA6ACDCFB91FE5174C0350F988.key"),
+        read_agent_key("./tests/gpg-agent/AE2E201B68FFBAF02C5796B178D8CEB9F1405DD4.key"),
+    ];
+
+    let key = SignedSecretKey::from_agent_keys(public_key, &agent_keys, || "".into())
+        .expect("failed to import agent keys");
+    key.verify().expect("invalid key");
+
+    assert_eq!(key.primary_key, expected.primary_key);
+    assert_eq!(key.secret_subkeys[0].key, expected.secret_subkeys[0].key);
+
+    // protect the imported key again
+    let mut key = key;
+    key.set_passphrase(|| "".into(), Some("test"))
+        .expect("failed to set passphrase");
+    key.primary_key
+        .unlock(|| "test".into(), |_| Ok(()))
+        .expect("failed to unlock");
+}
+
+#[test]
+fn test_gnu_dummy_primary_key() {
+    let mut file = read_file("./tests/gnu-dummy/ed25519.sec.asc");
+    let mut armor = String::new();
+    file.read_to_string(&mut armor).unwrap();
+
+    let (key, _) = SignedSecretKey::from_string(&armor).expect("failed to parse key");
+    key.verify().expect("invalid key");
+
+    match key.primary_key.secret_params() {
+        SecretParams::Stub(params) => assert_eq!(params.typ(), &StubType::Dummy),
+        _ => panic!("expected a stub"),
+    }
+    assert!(!key.secret_subkeys[0].key.secret_params().is_stub());
+
+    // no secret key material for the primary key
+    assert!(key.unlock(|| "".into(), |_| Ok(())).is_err());
+    let mut locked = key.clone();
+    locked
+        .set_passphrase(|| "".into(), Some("test"))
+        .expect("failed to set passphrase");
+    assert!(locked.primary_key.secret_params().is_stub());
+    assert!(locked.secret_subkeys[0].key.secret_params().is_encrypted());
+
+    // roundtrip
+    let bytes = key.to_bytes().expect("failed to serialize key");
+    let key2 = SignedSecretKey::from_bytes(&bytes[..]).expect("failed to parse key");
+    assert_eq!(key, key2);
+}
+
 fn get_test_fingerprint(filename: &str) -> (serde_json::Value, SignedPublicKey) {
     let mut asc = read_file(
         Path::new(&format!(
@@ -973,3 +1410,246 @@ fn test_handle_incomplete_packets_end() {
     let key = SignedSecretKey::from_bytes(Cursor::new(raw)).expect("failed");
     key.verify().expect("invalid key");
 }
+
+#[test]
+fn test_ssh_public_key() {
+    // expected values are from `gpg --export-ssh-key`
+    let (key, _) = SignedPublicKey::from_armor_single(
+        File::open("./tests/ssh/ed25519-auth.pub.asc").unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        key.to_ssh_public_key().unwrap(),
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINPibP0VfQAJ3MYa0iQN/GOD/6GuPlVWyFb4dB6EWvAk \
+         openpgp:0x3BB756D4"
+    );
+
+    let (key, _) =
+        SignedPublicKey::from_armor_single(File::open("./tests/ssh/rsa-auth.pub.asc").unwrap())
+            .unwrap();
+    assert_eq!(
+        key.to_ssh_public_key().unwrap(),
+        "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQDg+bYbX/hE/ujPaed4UJpBo5B8mh9/IFYF+Y1iiTf2lPl6rl\
+         yQvnEcetMSXnGVEaMlPrWmnFrz5NbhWvM9pVM1XtO/c0RvrsmQGyi3sGnSb7ZqxP+eLj097nnpNisiEQ+r8aRp\
+         yCK+TJOYFTbkorgwLUJYNCwZBLUbNsEgWy2QS3LnSbXd/kfbSQiIw5c6MiInDMpCv/H2UGA5Zdq4KZpcKlhSFB\
+         XuC6+MDKz7t3YGjFBLJdTJP8wTU69n0Asv9GGDYyB/UHOk2b4jnAtsRF99GMxV8WNN6kTP0d50W9YxcQQ1LjYC\
+         TLLWzI9Al0QAdwv8IvnN37mv1zRNSpwu5qmp openpgp:0x42CF6BC7"
+    );
+
+    // the key only has a signing primary key
+    let (key, _) = SignedPublicKey::from_armor_single(
+        File::open("./tests/secp256k1/pub.asc").unwrap(),
+    )
+    .unwrap();
+    assert!(key.to_ssh_public_key().is_err());
+}
+
+fn imported_key(name: &str) -> ImportedKey {
+    let input = std::fs::read_to_string(format!("./tests/import/{}", name)).unwrap();
+    ImportedKey::from_string(&input).unwrap()
+}
+
+#[test]
+fn test_import_keys() {
+    let rsa = imported_key("rsa.pkcs1.pem");
+    assert!(rsa.has_secret_key());
+    assert_eq!(rsa.algorithm(), PublicKeyAlgorithm::RSA);
+    for name in &["rsa.pkcs8.pem", "rsa.openssh"] {
+        assert_eq!(imported_key(name), rsa, "{}", name);
+    }
+    for name in &["rsa.pub.pkcs1.pem", "rsa.pub.pem", "rsa.openssh.pub"] {
+        let key = imported_key(name);
+        assert!(!key.has_secret_key());
+        assert_eq!(key.public_params(), rsa.public_params(), "{}", name);
+    }
+
+    let p256 = imported_key("p256.sec1.pem");
+    assert_eq!(p256.algorithm(), PublicKeyAlgorithm::ECDSA);
+    for name in &["p256.pkcs8.pem", "p256.openssh"] {
+        assert_eq!(imported_key(name), p256, "{}", name);
+    }
+    assert_eq!(imported_key("p256.openssh.pub").public_params(), p256.public_params());
+
+    let ed25519 = imported_key("ed25519.pkcs8.pem");
+    assert_eq!(ed25519.algorithm(), PublicKeyAlgorithm::EdDSA);
+    assert_eq!(imported_key("ed25519.pub.pem").public_params(), ed25519.public_params());
+
+    let ed25519 = imported_key("ed25519.openssh");
+    assert_eq!(imported_key("ed25519.openssh.pub").public_params(), ed25519.public_params());
+
+    // roundtrip the public keys through the OpenSSH format
+    for name in &["rsa", "p256", "ed25519"] {
+        let line = std::fs::read_to_string(format!("./tests/import/{}.openssh.pub", name)).unwrap();
+        let line = line.trim();
+        let key = imported_key(&format!("{}.openssh", name));
+        let comment = if *name == "ed25519" { "me@example.com" } else { "" };
+        assert_eq!(ssh_public_key(key.public_params(), comment).unwrap(), line);
+    }
+}
+
+#[test]
+fn test_import_keys_to_openpgp() {
+    let created_at = Utc::now();
+    for name in &["rsa.pkcs1.pem", "p256.pkcs8.pem", "ed25519.openssh"] {
+        let key = imported_key(name);
+        let signed_key = key
+            .to_signed_secret_key("Me <me@example.com>", created_at)
+            .unwrap();
+        signed_key.verify().expect("invalid key");
+        assert_eq!(signed_key.algorithm(), key.algorithm());
+        assert!(signed_key.is_signing_key());
+        assert_eq!(
+            signed_key.primary_key.public_params(),
+            key.public_params()
+        );
+
+        let armored = signed_key.to_armored_string(None).unwrap();
+        let (parsed, _) = SignedSecretKey::from_string(&armored).unwrap();
+        assert_eq!(parsed, signed_key);
+    }
+
+    // no secret key material available
+    assert!(imported_key("rsa.pub.pem")
+        .to_signed_secret_key("Me <me@example.com>", created_at)
+        .is_err());
+}
+
+#[test]
+fn test_emails() {
+    // the user id is a bare email address
+    let (key, _) = SignedPublicKey::from_armor_single(read_file(
+        "./tests/autocrypt/alice@autocrypt.example.pub.asc",
+    ))
+    .unwrap();
+    assert_eq!(key.emails(), vec!["alice@autocrypt.example"]);
+
+    let (mut keys, _) =
+        SignedPublicKey::from_armor_many(read_file("./tests/openpgp/pubdemo.asc")).unwrap();
+    let key = keys.next().unwrap().unwrap();
+    let parts = key.details.users[0].id.parts();
+    assert_eq!(parts.name, Some("Alpha Test"));
+    assert_eq!(parts.comment, Some("demo key"));
+    assert_eq!(parts.email, Some("alpha@example.net"));
+    // "Alice (demo key)" has no email address
+    assert_eq!(key.emails(), vec!["alpha@example.net", "alfa@example.net"]);
+}
+
+#[test]
+fn test_openpgpkey_record() {
+    let (key, _) = SignedPublicKey::from_armor_single(read_file(
+        "./tests/autocrypt/alice@autocrypt.example.pub.asc",
+    ))
+    .unwrap();
+
+    let (name, data) = key.to_openpgpkey_record("alice@autocrypt.example").unwrap();
+    assert_eq!(
+        name,
+        "2bd806c97f0e00af1a1fc3328fa763a9269723c8db8fac4f93af71db._openpgpkey.autocrypt.example."
+    );
+
+    let parsed = openpgpkey_from_record(&data, "alice@autocrypt.example").unwrap();
+    assert_eq!(parsed.primary_key, key.primary_key);
+    assert_eq!(parsed.emails(), vec!["alice@autocrypt.example"]);
+    assert!(openpgpkey_from_record(&data, "bob@autocrypt.example").is_err());
+
+    assert!(key.to_openpgpkey_record("bob@autocrypt.example").is_err());
+}
+
+#[test]
+fn test_key_packets() {
+    let (key, _) = SignedSecretKey::from_armor_single(read_file(
+        "./tests/opengpg-interop/testcases/messages/gnupg-v1-001-decrypt.asc",
+    ))
+    .unwrap();
+
+    let packets = key.packets();
+    assert_eq!(packets[0].tag(), Tag::SecretKey);
+    assert!(packets.iter().any(|p| p.tag() == Tag::UserId));
+    assert!(packets.iter().any(|p| p.tag() == Tag::SecretSubkey));
+
+    // the packets serialize to the same bytes as the key
+    let mut bytes = Vec::new();
+    for packet in &packets {
+        packet.to_writer(&mut bytes).unwrap();
+    }
+    assert_eq!(bytes, key.to_bytes().unwrap());
+
+    let (key, _) = SignedPublicKey::from_armor_single(read_file(
+        "./tests/autocrypt/alice@autocrypt.example.pub.asc",
+    ))
+    .unwrap();
+
+    let packets = key.packets();
+    assert_eq!(packets[0].tag(), Tag::PublicKey);
+    assert_eq!(packets.last().unwrap().tag(), Tag::Signature);
+
+    let mut bytes = Vec::new();
+    for packet in &packets {
+        packet.to_writer(&mut bytes).unwrap();
+    }
+    assert_eq!(bytes, key.to_bytes().unwrap());
+}
+
+#[test]
+fn test_key_summary() {
+    let (key, _) = SignedPublicKey::from_armor_single(read_file(
+        "./tests/autocrypt/alice@autocrypt.example.pub.asc",
+    ))
+    .unwrap();
+
+    let summary = key.summary();
+    assert_eq!(summary.fingerprint, key.fingerprint());
+    assert_eq!(summary.key_id, key.key_id());
+    assert_eq!(&summary.created_at, key.created_at());
+    assert_eq!(summary.user_ids, vec!["alice@autocrypt.example"]);
+    assert_eq!(summary.subkeys.len(), key.public_subkeys.len());
+    assert_eq!(summary.subkeys[0].key_id, key.public_subkeys[0].key_id());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_key_summary_serde() {
+    let (key, _) = SignedPublicKey::from_armor_single(read_file(
+        "./tests/autocrypt/alice@autocrypt.example.pub.asc",
+    ))
+    .unwrap();
+
+    let summary = key.summary();
+    let json = serde_json::to_value(&summary).unwrap();
+    assert_eq!(json["key_id"], hex::encode_upper(key.key_id()));
+    assert_eq!(json["user_ids"][0], "alice@autocrypt.example");
+    assert_eq!(serde_json::from_value::<pgp::composed::KeySummary>(json).unwrap(), summary);
+
+    let user = &key.details.users[0];
+    let json = serde_json::to_string(&user.id).unwrap();
+    assert_eq!(json, "\"alice@autocrypt.example\"");
+    assert_eq!(serde_json::from_str::<UserId>(&json).unwrap().id(), user.id.id());
+
+    let sig = &user.signatures[0];
+    let json = serde_json::to_string(sig).unwrap();
+    let parsed: Signature = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.config, sig.config);
+    assert_eq!(parsed.signature, sig.signature);
+}
+
+#[test]
+fn test_verify_with_policy() {
+    let (key, _) = SignedSecretKey::from_armor_single(read_file(
+        "./tests/openpgp/samplekeys/rsa-primary-auth-only.sec.asc",
+    ))
+    .unwrap();
+    key.verify_with_policy(&Policy::legacy()).expect("invalid key");
+
+    // the primary key has 2048 bits
+    match key.verify_with_policy(&Policy::legacy().min_rsa_bits(4096)) {
+        Err(Error::PolicyViolation(_)) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+
+    let hash = key.details.users[0].signatures[0].config.hash_alg;
+    let policy = Policy::legacy().reject_hash_after(hash, Timestamp::from_secs(0));
+    match key.verify_with_policy(&policy) {
+        Err(Error::PolicyViolation(_)) => {}
+        res => panic!("unexpected result {:?}", res),
+    }
+}
@@ -217,7 +217,8 @@ msg_test!(msg_pgp_10_0_004, "pgp-10-0-004", false);
 msg_test!(msg_pgp_10_0_005, "pgp-10-0-005", false);
 msg_test!(msg_pgp_10_0_006, "pgp-10-0-006", false);
 // IDEA
-// msg_test!(msg_pgp_10_0_007, "pgp-10-0-007", true);
+#[cfg(feature = "idea")]
+msg_test!(msg_pgp_10_0_007, "pgp-10-0-007", false);
 
 // ECDH
 // msg_test!(msg_openkeychain_001, "openkeychain-001", true);
@@ -356,3 +357,82 @@ fn msg_literal_signature() {
 
     msg.verify(&pkey).unwrap();
 }
+
+#[test]
+fn msg_secp256k1_signature() {
+    let (pkey, _) =
+        SignedPublicKey::from_armor_single(File::open("./tests/secp256k1/pub.asc").unwrap())
+            .unwrap();
+    pkey.verify().expect("invalid key");
+
+    let mut msg_file = File::open("./tests/secp256k1/message.asc").unwrap();
+    let (msg, _) = Message::from_armor_single(&mut msg_file).expect("failed to parse message");
+
+    msg.verify(&pkey).expect("invalid signature");
+}
+
+#[test]
+fn msg_decrypt_offline_primary_key() {
+    // the secret primary key is a gnu-dummy stub, only the encryption subkey is available
+    let (skey, _) = SignedSecretKey::from_armor_single(
+        File::open("./tests/gnu-dummy/ed25519.sec.asc").unwrap(),
+    )
+    .unwrap();
+    assert!(skey.primary_key.secret_params().is_stub());
+
+    let mut msg_file = File::open("./tests/gnu-dummy/message.asc").unwrap();
+    let (msg, _) = Message::from_armor_single(&mut msg_file).expect("failed to parse message");
+
+    let decrypted = msg
+        .decrypt(|| "".to_string(), || "".to_string(), &[&skey])
+        .expect("failed to decrypt message")
+        .0
+        .next()
+        .expect("no message")
+        .expect("message decryption failed");
+
+    let raw = match decrypted {
+        Message::Literal(data) => data,
+        Message::Compressed(data) => {
+            let m = Message::from_bytes(data.decompress().unwrap()).unwrap();
+
+            m.get_literal().unwrap().clone()
+        }
+        _ => panic!("unexpected message type: {:?}", decrypted),
+    };
+    assert_eq!(raw.data(), &b"hello offline world\n"[..]);
+}
+
+#[test]
+fn msg_strict_parsing() {
+    use pgp::ser::Serialize;
+
+    let (pkey, _) = SignedPublicKey::from_armor_single(
+        File::open("./tests/autocrypt/alice@autocrypt.example.pub.asc").unwrap(),
+    )
+    .unwrap();
+    let mut msg_file = File::open("./tests/literal-text-signed.asc").unwrap();
+    let (msg, _) = Message::from_armor_single(&mut msg_file).expect("failed to parse message");
+
+    let bytes = msg.to_bytes().unwrap();
+    Message::from_bytes_strict(&bytes[..])
+        .expect("failed to parse message")
+        .verify(&pkey)
+        .unwrap();
+
+    // a trailing marker packet with an invalid body
+    let mut malformed = bytes;
+    malformed.extend_from_slice(&[0xCA, 0x03, b'X', b'Y', b'Z']);
+
+    Message::from_bytes(&malformed[..])
+        .expect("lenient parsing skips the packet")
+        .verify(&pkey)
+        .unwrap();
+    assert!(Message::from_bytes_strict(&malformed[..]).is_err());
+
+    let mut key = pkey.to_bytes().unwrap();
+    key.extend_from_slice(&[0xCA, 0x03, b'X', b'Y', b'Z']);
+    assert_eq!(SignedPublicKey::from_bytes_many(&key[..]).count(), 1);
+    let res = SignedPublicKey::from_bytes_many_strict(&key[..]).collect::<Vec<_>>();
+    assert!(res.last().unwrap().is_err());
+}